@@ -0,0 +1,285 @@
+use crate::compose::{apply_key_color, apply_mask, fit_within, load_pna_mask, opaque_bounds};
+use crate::thumbnail::{AlphaMode, ThumbnailInfo};
+use image::codecs::png::PngDecoder;
+use image::imageops;
+use image::{AnimationDecoder, RgbaImage};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// アニメーションプレビューの上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnimatedPreviewLimits {
+    /// 長辺の最大ピクセル数
+    pub max_edge: u32,
+    /// 最大フレーム数。超えた分は捨てる
+    pub max_frames: usize,
+    /// 合計表示時間の上限（ミリ秒）。超えたフレーム以降は捨てる
+    pub max_duration_ms: u32,
+}
+
+impl Default for AnimatedPreviewLimits {
+    fn default() -> Self {
+        Self {
+            max_edge: 512,
+            max_frames: 120,
+            max_duration_ms: 10_000,
+        }
+    }
+}
+
+/// APNG（acTL チャンクを持つ PNG）かどうか。拡張子ではなく中身で判定する
+pub fn is_animated_png(path: &Path) -> bool {
+    open_decoder(path)
+        .and_then(|decoder| decoder.is_apng().ok())
+        .unwrap_or(false)
+}
+
+/// APNG ならアニメーションの最初のフレームを返す。APNG でなければ None
+pub(crate) fn first_apng_frame(path: &Path) -> Option<RgbaImage> {
+    let decoder = open_decoder(path)?;
+    if !decoder.is_apng().ok()? {
+        return None;
+    }
+    let frame = decoder.apng().ok()?.into_frames().next()?.ok()?;
+    Some(frame.into_buffer())
+}
+
+/// 詳細表示用のアニメーションプレビューを APNG として生成する。
+///
+/// - 各フレームに透過モードを適用し、全フレームを通して透明な余白を切り落とす
+/// - 長辺が `limits.max_edge` 以下になるよう全フレームを同じ倍率で縮小する
+/// - `limits.max_frames` / `limits.max_duration_ms` を超えるフレームは捨てる
+/// - ループ回数は無限
+/// - キャンバス大のフレームを全部は持たないよう 2 回デコードする。1 回目で切り落とす範囲を求め、
+///   2 回目でフレームごとに切り落として縮小し、元の大きさのフレームはその場で捨てる
+///
+/// 元画像が APNG でない・2 フレーム未満・読み込めない場合は None（静止画サムネイルを使う）。
+pub fn render_animated_preview(
+    info: &ThumbnailInfo,
+    limits: &AnimatedPreviewLimits,
+) -> Option<Vec<u8>> {
+    let mask = match &info.alpha {
+        AlphaMode::PnaMask(mask) => Some(load_pna_mask(mask)?),
+        _ => None,
+    };
+    let apply_alpha = |image: &mut RgbaImage| match (&info.alpha, &mask) {
+        (AlphaMode::KeyColor, _) => apply_key_color(image),
+        (AlphaMode::PnaMask(_), Some(mask)) => apply_mask(image, mask),
+        _ => {}
+    };
+
+    // 1 回目: フレームごとに切り落とすと位置がずれるので、全フレームの不透明範囲の和を求める
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    let mut delays = Vec::new();
+    for_each_frame(&info.path, limits, |mut image, delay_ms| {
+        apply_alpha(&mut image);
+        if let Some(frame_bounds) = opaque_bounds(&image) {
+            bounds = Some(bounds.map_or(frame_bounds, |bounds| union_bounds(bounds, frame_bounds)));
+        }
+        delays.push(delay_ms);
+    })?;
+    if delays.len() < 2 {
+        return None;
+    }
+
+    // 2 回目: 切り落として縮小したフレームだけを残す
+    let mut images = Vec::with_capacity(delays.len());
+    for_each_frame(&info.path, limits, |mut image, _| {
+        apply_alpha(&mut image);
+        let image = match bounds {
+            Some((left, top, width, height)) => {
+                imageops::crop_imm(&image, left, top, width, height).to_image()
+            }
+            None => image,
+        };
+        images.push(fit_within(image, limits.max_edge));
+    })?;
+    // 2 回の読み込みの間にファイルが置き換わった場合
+    if images.len() != delays.len() {
+        return None;
+    }
+    encode_apng(&images, &delays)
+}
+
+/// 2 つの (left, top, width, height) を両方含む範囲
+fn union_bounds(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> (u32, u32, u32, u32) {
+    let (left, top) = (a.0.min(b.0), a.1.min(b.1));
+    let right = (a.0 + a.2).max(b.0 + b.2);
+    let bottom = (a.1 + a.3).max(b.1 + b.3);
+    (left, top, right - left, bottom - top)
+}
+
+fn open_decoder(path: &Path) -> Option<PngDecoder<BufReader<File>>> {
+    PngDecoder::new(BufReader::new(File::open(path).ok()?)).ok()
+}
+
+/// APNG のフレーム（キャンバス全体に合成済み）と表示時間（ミリ秒）を 1 枚ずつ f に渡す。
+/// 上限を超えたフレームは読まない。APNG でない・デコードに失敗した場合は None
+fn for_each_frame(
+    path: &Path,
+    limits: &AnimatedPreviewLimits,
+    mut f: impl FnMut(RgbaImage, u32),
+) -> Option<()> {
+    let decoder = open_decoder(path)?;
+    if !decoder.is_apng().ok()? {
+        return None;
+    }
+    let mut total_ms: u32 = 0;
+    for (count, frame) in decoder.apng().ok()?.into_frames().enumerate() {
+        if count >= limits.max_frames || total_ms >= limits.max_duration_ms {
+            break;
+        }
+        let frame = frame.ok()?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let delay_ms = numer.checked_div(denom).unwrap_or(0);
+        total_ms = total_ms.saturating_add(delay_ms);
+        f(frame.into_buffer(), delay_ms);
+    }
+    Some(())
+}
+
+/// 同じ大きさの RGBA フレーム列を APNG にエンコードする
+fn encode_apng(images: &[RgbaImage], delays_ms: &[u32]) -> Option<Vec<u8>> {
+    let (width, height) = images.first()?.dimensions();
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(images.len() as u32, 0).ok()?;
+    let mut writer = encoder.write_header().ok()?;
+    for (image, delay_ms) in images.iter().zip(delays_ms) {
+        writer
+            .set_frame_delay((*delay_ms).min(u16::MAX as u32) as u16, 1000)
+            .ok()?;
+        writer.write_image_data(image.as_raw()).ok()?;
+    }
+    writer.finish().ok()?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+    use image::{Rgba, RgbaImage};
+
+    /// 単色フレームを並べた APNG を書き出す
+    fn write_apng(path: &Path, width: u32, height: u32, colors: &[Rgba<u8>], delay_ms: u16) {
+        let images: Vec<RgbaImage> = colors
+            .iter()
+            .map(|color| RgbaImage::from_pixel(width, height, *color))
+            .collect();
+        let delays = vec![delay_ms as u32; images.len()];
+        std::fs::write(path, encode_apng(&images, &delays).unwrap()).unwrap();
+    }
+
+    fn decode_all(bytes: Vec<u8>) -> Vec<image::Frame> {
+        PngDecoder::new(std::io::Cursor::new(bytes))
+            .unwrap()
+            .apng()
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap()
+    }
+
+    #[test]
+    fn apng_は最初のフレームを静止画として読み込む() {
+        let tmp = TempDirGuard::new("ghost_meta_apng_first_frame");
+        let path = tmp.path().join("surface0.apng");
+        let red = Rgba([255, 0, 0, 255]);
+        write_apng(&path, 4, 2, &[red, Rgba([0, 0, 255, 255])], 100);
+
+        assert!(is_animated_png(&path));
+        let frame = first_apng_frame(&path).unwrap();
+        assert_eq!(frame.dimensions(), (4, 2));
+        assert_eq!(*frame.get_pixel(3, 1), red);
+
+        let still = tmp.path().join("surface0.png");
+        RgbaImage::from_pixel(4, 2, red).save(&still).unwrap();
+        assert!(!is_animated_png(&still));
+        assert!(first_apng_frame(&still).is_none());
+    }
+
+    #[test]
+    fn アニメーションプレビューは縮小しフレーム数の上限で打ち切る() {
+        let tmp = TempDirGuard::new("ghost_meta_apng_preview");
+        let path = tmp.path().join("surface0.apng");
+        let colors: Vec<Rgba<u8>> = (0..5).map(|i| Rgba([i * 40, 0, 0, 255])).collect();
+        write_apng(&path, 40, 20, &colors, 50);
+        let info = ThumbnailInfo {
+            path,
+            alpha: AlphaMode::SelfAlpha,
+            kind: crate::ThumbnailKind::Surface,
+        };
+
+        let limits = AnimatedPreviewLimits {
+            max_edge: 10,
+            max_frames: 3,
+            ..AnimatedPreviewLimits::default()
+        };
+        let frames = decode_all(render_animated_preview(&info, &limits).unwrap());
+        assert_eq!(frames.len(), 3);
+        for (frame, color) in frames.iter().zip(&colors) {
+            assert_eq!(frame.buffer().dimensions(), (10, 5));
+            assert_eq!(frame.delay().numer_denom_ms(), (50, 1));
+            assert_eq!(*frame.buffer().get_pixel(5, 2), *color);
+        }
+
+        // 合計表示時間の上限でも打ち切る（50ms × 2 で 100ms に達する）
+        let limits = AnimatedPreviewLimits {
+            max_duration_ms: 100,
+            ..AnimatedPreviewLimits::default()
+        };
+        assert_eq!(
+            decode_all(render_animated_preview(&info, &limits).unwrap()).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn アニメーションプレビューは全フレームの不透明範囲の和で切り落とす() {
+        let tmp = TempDirGuard::new("ghost_meta_apng_preview_bounds");
+        let path = tmp.path().join("surface0.apng");
+        let (red, blue) = (Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]));
+        let mut first = RgbaImage::new(8, 4);
+        first.put_pixel(1, 1, red);
+        let mut second = RgbaImage::new(8, 4);
+        second.put_pixel(5, 2, blue);
+        std::fs::write(&path, encode_apng(&[first, second], &[100, 100]).unwrap()).unwrap();
+        let info = ThumbnailInfo {
+            path,
+            alpha: AlphaMode::SelfAlpha,
+            kind: crate::ThumbnailKind::Surface,
+        };
+
+        let frames =
+            decode_all(render_animated_preview(&info, &AnimatedPreviewLimits::default()).unwrap());
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].buffer().dimensions(), (5, 2));
+        assert_eq!(*frames[0].buffer().get_pixel(0, 0), red);
+        assert_eq!(frames[1].buffer().dimensions(), (5, 2));
+        assert_eq!(*frames[1].buffer().get_pixel(4, 1), blue);
+    }
+
+    #[test]
+    fn 静止画や_1_フレームの_apng_はアニメーションプレビューを作らない() {
+        let tmp = TempDirGuard::new("ghost_meta_apng_preview_still");
+        let single = tmp.path().join("single.apng");
+        write_apng(&single, 4, 4, &[Rgba([0, 255, 0, 255])], 100);
+        let still = tmp.path().join("still.png");
+        RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 255]))
+            .save(&still)
+            .unwrap();
+
+        for path in [single, still] {
+            let info = ThumbnailInfo {
+                path,
+                alpha: AlphaMode::KeyColor,
+                kind: crate::ThumbnailKind::Surface,
+            };
+            assert!(render_animated_preview(&info, &AnimatedPreviewLimits::default()).is_none());
+        }
+    }
+}
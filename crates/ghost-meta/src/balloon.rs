@@ -0,0 +1,193 @@
+use crate::descript::{read_descript, Descript};
+use crate::thumbnail::{
+    find_pna_mask, resolve_root_thumbnail, AlphaMode, ThumbnailInfo, ThumbnailKind,
+};
+use crate::GhostMetaError;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// バルーン（`balloon/<name>/`）のメタデータ
+#[derive(Debug, Clone)]
+pub struct BalloonMeta {
+    /// descript.txt の name フィールド（表示名）。未設定の場合はディレクトリ名
+    pub name: String,
+    /// descript.txt の id フィールド。未設定の場合は None
+    pub id: Option<String>,
+    /// descript.txt の craftman フィールド（作者名）。未設定の場合は None
+    pub craftman: Option<String>,
+    /// descript.txt の craftmanw フィールド（作者名2）。未設定の場合は None
+    pub craftmanw: Option<String>,
+    /// descript.txt の craftmanurl フィールド（作者 URL）。未設定の場合は None
+    pub craftmanurl: Option<String>,
+    /// descript.txt の homeurl フィールド（ネットワーク更新 URL）。未設定の場合は None
+    pub homeurl: Option<String>,
+    /// 上記以外の descript.txt フィールド（charset を含む）
+    pub extra: HashMap<String, String>,
+    /// バルーンのディレクトリ名
+    pub directory_name: String,
+    /// バルーンディレクトリの絶対パス
+    pub path: PathBuf,
+    /// プレビュー画像。thumbnail.png → balloons0.png の順に探し、どちらもなければ None
+    pub thumbnail: Option<ThumbnailInfo>,
+}
+
+/// バルーンディレクトリのメタデータを取得する。
+/// balloon_root は `SSP/balloon/{balloon_name}` に相当するディレクトリ。
+/// descript.txt のパスは `balloon_root/descript.txt`。
+pub fn read_balloon(balloon_root: &Path) -> Result<BalloonMeta, GhostMetaError> {
+    let directory_name = balloon_root
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_string();
+
+    let Descript {
+        charset,
+        name,
+        id,
+        craftman,
+        craftmanw,
+        craftmanurl,
+        homeurl,
+        mut extra,
+        ..
+    } = read_descript(&balloon_root.join("descript.txt"))?;
+
+    let name = name.unwrap_or_else(|| directory_name.clone());
+    if let Some(charset) = charset {
+        extra.insert("charset".to_string(), charset);
+    }
+    let thumbnail = resolve_root_thumbnail(balloon_root)
+        .or_else(|| resolve_balloon_surface(balloon_root, &extra));
+
+    Ok(BalloonMeta {
+        name,
+        id,
+        craftman,
+        craftmanw,
+        craftmanurl,
+        homeurl,
+        extra,
+        directory_name,
+        path: balloon_root.to_path_buf(),
+        thumbnail,
+    })
+}
+
+/// parent_dir（`SSP/balloon`）配下のバルーンを走査して全メタデータを返す。
+/// descript.txt が存在しないエントリはスキップし、ディレクトリ名の辞書順（大文字小文字不問）で返す。
+/// parent_dir の read_dir に失敗した場合はエラーを返す。
+pub fn scan_balloons(parent_dir: &Path) -> Result<Vec<BalloonMeta>, GhostMetaError> {
+    let mut balloons: Vec<BalloonMeta> = fs::read_dir(parent_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| read_balloon(&path).ok())
+        .collect();
+    // OS 依存の列挙順を回避するためソートする
+    balloons.sort_by_key(|balloon| balloon.directory_name.to_ascii_lowercase());
+    Ok(balloons)
+}
+
+/// バルーンディレクトリ直下の balloons0.png（\0 側の標準バルーン画像）を ThumbnailInfo として返す。
+/// descript.txt の use_self_alpha が "1" なら PNG のアルファチャンネル、
+/// そうでなければ同名の .pna、.pna もなければ左上ピクセルのキーカラーで透過する。
+fn resolve_balloon_surface(
+    balloon_root: &Path,
+    fields: &HashMap<String, String>,
+) -> Option<ThumbnailInfo> {
+    let mut candidates: Vec<String> = fs::read_dir(balloon_root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|filename| filename.eq_ignore_ascii_case("balloons0.png"))
+        .collect();
+    // OS 依存の列挙順を回避するためソートして最初を選ぶ
+    candidates.sort();
+    let path = balloon_root.join(candidates.first()?);
+
+    let alpha = if fields.get("use_self_alpha").map(|v| v.as_str()) == Some("1") {
+        AlphaMode::SelfAlpha
+    } else {
+        find_pna_mask(&path).map_or(AlphaMode::KeyColor, AlphaMode::PnaMask)
+    };
+    Some(ThumbnailInfo {
+        path,
+        alpha,
+        kind: ThumbnailKind::Balloon,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+
+    fn create_balloon(root: &Path, dir_name: &str, descript: &str) -> PathBuf {
+        let balloon_root = root.join(dir_name);
+        fs::create_dir_all(&balloon_root).unwrap();
+        fs::write(balloon_root.join("descript.txt"), descript).unwrap();
+        balloon_root
+    }
+
+    #[test]
+    fn read_balloon_がnameとcraftmanとプレビュー画像を読み取る() {
+        let tmp = TempDirGuard::new("ghost_meta_read_balloon");
+        let root = create_balloon(
+            tmp.path(),
+            "sample",
+            "charset,UTF-8\ntype,balloon\nname,標準バルーン\ncraftman,作者\nx.custom,value\n",
+        );
+        fs::write(root.join("Balloons0.png"), "").unwrap();
+        fs::write(root.join("balloons0.pna"), "").unwrap();
+
+        let meta = read_balloon(&root).unwrap();
+        assert_eq!(meta.name, "標準バルーン");
+        assert_eq!(meta.craftman.as_deref(), Some("作者"));
+        assert_eq!(meta.directory_name, "sample");
+        assert_eq!(meta.extra.get("x.custom").map(String::as_str), Some("value"));
+
+        let thumbnail = meta.thumbnail.unwrap();
+        assert_eq!(thumbnail.path, root.join("Balloons0.png"));
+        assert_eq!(thumbnail.alpha, AlphaMode::PnaMask(root.join("balloons0.pna")));
+        assert_eq!(thumbnail.kind, ThumbnailKind::Balloon);
+    }
+
+    #[test]
+    fn read_balloon_は_thumbnail_png_を_balloons0_より優先する() {
+        let tmp = TempDirGuard::new("ghost_meta_read_balloon_thumbnail");
+        let root = create_balloon(tmp.path(), "thumb", "charset,UTF-8\nuse_self_alpha,1\n");
+        fs::write(root.join("balloons0.png"), "").unwrap();
+
+        let meta = read_balloon(&root).unwrap();
+        assert_eq!(meta.name, "thumb");
+        assert_eq!(meta.thumbnail.as_ref().unwrap().alpha, AlphaMode::SelfAlpha);
+
+        fs::write(root.join("thumbnail.png"), "").unwrap();
+        let meta = read_balloon(&root).unwrap();
+        let thumbnail = meta.thumbnail.unwrap();
+        assert_eq!(thumbnail.path, root.join("thumbnail.png"));
+        assert_eq!(thumbnail.kind, ThumbnailKind::Thumbnail);
+    }
+
+    #[test]
+    fn scan_balloons_は_descript_のあるディレクトリだけをディレクトリ名順に返す() {
+        let tmp = TempDirGuard::new("ghost_meta_scan_balloons");
+        create_balloon(tmp.path(), "Zeta", "charset,UTF-8\nname,Z\n");
+        create_balloon(tmp.path(), "alpha", "charset,UTF-8\nname,A\n");
+        fs::create_dir_all(tmp.path().join("broken")).unwrap();
+        fs::write(tmp.path().join("readme.txt"), "").unwrap();
+
+        let balloons = scan_balloons(tmp.path()).unwrap();
+        let names: Vec<&str> = balloons.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, vec!["A", "Z"]);
+        assert!(balloons[0].thumbnail.is_none());
+    }
+
+    #[test]
+    fn scan_balloons_が存在しないディレクトリでioエラーを返す() {
+        let result = scan_balloons(Path::new("/nonexistent/balloon"));
+        assert!(matches!(result, Err(GhostMetaError::Io(_))));
+    }
+}
@@ -0,0 +1,538 @@
+use crate::apng::first_apng_frame;
+use crate::surfaces::{read_surfaces, surfaces_files, DrawMethod, SurfacesFile};
+use crate::thumbnail::{find_pna_mask, AlphaMode, ThumbnailInfo, ThumbnailKind};
+use image::imageops::{self, FilterType};
+use image::{GrayImage, ImageFormat, ImageReader, Rgba, RgbaImage};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// 合成キャンバスの辺の上限（surfaces.txt の座標は信用しない）
+const MAX_CANVAS_EDGE: u64 = 4096;
+/// 合成キャンバスの画素数の上限（RGBA で 32 MiB）
+const MAX_CANVAS_PIXELS: u64 = 8 * 1024 * 1024;
+
+/// 合成する 1 枚分の画像
+struct Layer {
+    method: DrawMethod,
+    image: RgbaImage,
+    x: i32,
+    y: i32,
+}
+
+/// サーフェスを合成して RGBA 画像を返す。
+///
+/// - element 定義があるサーフェスは element を ID 順に合成する（`surfaceN.png` 自体は使わない）
+/// - element 定義がなければ `surfaceN.png`（`surface0000.png` のようなゼロ埋めも可）をそのまま使う
+/// - use_self_alpha が false の場合は各画像の同名 .pna をアルファマスクとして使い、
+///   .pna がなければ左上ピクセルをキーカラーとして透過する
+/// - キャンバスは (0, 0) から全 element を含む大きさ。負の座標にはみ出した部分は切り捨てる
+///
+/// 読み込めない element は飛ばし、描画できる画像が 1 枚もなければ None を返す。
+/// キャンバスの辺が `MAX_CANVAS_EDGE` か画素数が `MAX_CANVAS_PIXELS` を超える場合も None を返す。
+/// アニメーション（bind を含む）は適用しないため、SSP の表示とは完全には一致しない（サムネイル用途の近似）。
+pub fn render_surface(
+    shell_dir: &Path,
+    surfaces: &SurfacesFile,
+    surface_id: u32,
+    use_self_alpha: bool,
+) -> Option<RgbaImage> {
+    let elements = surfaces.elements(surface_id);
+
+    let layers: Vec<Layer> = if elements.is_empty() {
+        let path = find_surface_file(shell_dir, surface_id)?;
+        vec![Layer {
+            method: DrawMethod::Overlay,
+            image: load_layer(&path, use_self_alpha)?,
+            x: 0,
+            y: 0,
+        }]
+    } else {
+        elements
+            .iter()
+            .filter_map(|element| {
+                Some(Layer {
+                    method: element.method.clone(),
+                    image: load_layer(&shell_dir.join(&element.filename), use_self_alpha)?,
+                    x: element.x,
+                    y: element.y,
+                })
+            })
+            .collect()
+    };
+
+    // 負の座標にはみ出した分は数えない（全体が負の側にある element は幅 0）
+    let extent = |offset: i32, size: u32| (offset as i64 + size as i64).max(0) as u64;
+    let width = layers
+        .iter()
+        .map(|layer| extent(layer.x, layer.image.width()))
+        .max()?;
+    let height = layers
+        .iter()
+        .map(|layer| extent(layer.y, layer.image.height()))
+        .max()?;
+    if width == 0
+        || height == 0
+        || width > MAX_CANVAS_EDGE
+        || height > MAX_CANVAS_EDGE
+        || width * height > MAX_CANVAS_PIXELS
+    {
+        return None;
+    }
+
+    let mut canvas = RgbaImage::new(width as u32, height as u32);
+    for layer in &layers {
+        draw_layer(&mut canvas, layer);
+    }
+    Some(canvas)
+}
+
+/// シェルディレクトリから `surface{id}.png` を探す（ゼロ埋め・大文字小文字不問、名前順の最初）
+fn find_surface_file(shell_dir: &Path, surface_id: u32) -> Option<PathBuf> {
+    let mut candidates: Vec<String> = fs::read_dir(shell_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|filename| {
+            let lower = filename.to_ascii_lowercase();
+            lower
+                .strip_prefix("surface")
+                .and_then(|rest| rest.strip_suffix(".png"))
+                .filter(|digits| !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+                .and_then(|digits| digits.parse::<u32>().ok())
+                == Some(surface_id)
+        })
+        .collect();
+    candidates.sort_by_key(|filename| filename.to_ascii_lowercase());
+    candidates.first().map(|filename| shell_dir.join(filename))
+}
+
+/// サーフェスの描画結果を左右するファイルを返す（キャッシュキーの計算用）。
+/// シェルディレクトリの surfaces*.txt と、element 定義があれば存在する element の画像・同名の .pna
+pub fn surface_source_files(shell_dir: &Path, surface_id: u32) -> Vec<PathBuf> {
+    let mut files = surfaces_files(shell_dir).unwrap_or_default();
+    let Ok(surfaces) = read_surfaces(shell_dir) else {
+        return files;
+    };
+    for element in surfaces.elements(surface_id) {
+        let path = shell_dir.join(&element.filename);
+        if path.is_file() {
+            let mask = find_pna_mask(&path);
+            files.push(path);
+            files.extend(mask);
+        }
+    }
+    files
+}
+
+/// サーフェスのサムネイルで、シェルディレクトリ（画像の親）の surfaces*.txt に surface0 の
+/// element 定義があれば `render_surface` で合成する。element 定義がなければ None
+fn render_composed_surface0(info: &ThumbnailInfo) -> Option<RgbaImage> {
+    if info.kind != ThumbnailKind::Surface {
+        return None;
+    }
+    let shell_dir = info.path.parent()?;
+    let surfaces = read_surfaces(shell_dir).ok()?;
+    if surfaces.elements(0).is_empty() {
+        return None;
+    }
+    render_surface(shell_dir, &surfaces, 0, info.alpha == AlphaMode::SelfAlpha)
+}
+
+/// サムネイル画像を透過モードに従って透過済みの RGBA 画像として読み込む。
+/// surface0 が element で合成されるシェルは、`surface0.png` ではなく合成した画像を使う
+pub fn load_thumbnail(info: &ThumbnailInfo) -> Option<RgbaImage> {
+    if let Some(image) = render_composed_surface0(info) {
+        return Some(image);
+    }
+    let mut image = decode_rgba(&info.path)?;
+    match &info.alpha {
+        AlphaMode::SelfAlpha => {}
+        AlphaMode::KeyColor => apply_key_color(&mut image),
+        AlphaMode::PnaMask(mask) => apply_pna_mask(&mut image, mask)?,
+    }
+    Some(image)
+}
+
+/// `load_thumbnail` の結果を RGBA PNG にエンコードして返す
+pub fn render_thumbnail_png(info: &ThumbnailInfo) -> Option<Vec<u8>> {
+    encode_png(&load_thumbnail(info)?)
+}
+
+/// 一覧表示用のサムネイルを生成する。
+/// 透過済みの画像から完全に透明な余白を切り落とし、長辺が max_edge 以下になるよう縦横比を保って縮小する
+/// （max_edge より小さい画像は拡大しない）。
+pub fn render_thumbnail(info: &ThumbnailInfo, max_edge: u32) -> Option<RgbaImage> {
+    let image = trim_transparent(load_thumbnail(info)?);
+    Some(fit_within(image, max_edge))
+}
+
+/// RGBA 画像を PNG にエンコードする
+pub fn encode_png(image: &RgbaImage) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .ok()?;
+    Some(bytes)
+}
+
+/// アルファ値が 0 のピクセルだけの外周を切り落とす。全ピクセルが透明なら元の画像を返す
+fn trim_transparent(image: RgbaImage) -> RgbaImage {
+    match opaque_bounds(&image) {
+        Some((left, top, width, height)) if (width, height) != image.dimensions() => {
+            imageops::crop_imm(&image, left, top, width, height).to_image()
+        }
+        _ => image,
+    }
+}
+
+/// アルファ値が 0 でないピクセルを含む最小の矩形を (left, top, width, height) で返す。
+/// 全ピクセルが透明なら None
+pub(crate) fn opaque_bounds(image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for (x, y, pixel) in image.enumerate_pixels() {
+        if pixel[3] == 0 {
+            continue;
+        }
+        bounds = Some(match bounds {
+            None => (x, y, x, y),
+            Some((left, top, right, bottom)) => {
+                (left.min(x), top.min(y), right.max(x), bottom.max(y))
+            }
+        });
+    }
+    bounds.map(|(left, top, right, bottom)| (left, top, right - left + 1, bottom - top + 1))
+}
+
+/// 長辺が max_edge を超える場合だけ縦横比を保って縮小する
+pub(crate) fn fit_within(image: RgbaImage, max_edge: u32) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let long_edge = width.max(height);
+    if long_edge <= max_edge || max_edge == 0 {
+        return image;
+    }
+    let scale = |edge: u32| ((edge as u64 * max_edge as u64) / long_edge as u64).max(1) as u32;
+    imageops::resize(&image, scale(width), scale(height), FilterType::Triangle)
+}
+
+/// 画像を RGBA で読み込む。use_self_alpha が false なら .pna マスクかキーカラーで透過する
+fn load_layer(path: &Path, use_self_alpha: bool) -> Option<RgbaImage> {
+    let mut image = decode_rgba(path)?;
+    if !use_self_alpha {
+        match find_pna_mask(path) {
+            Some(mask) => apply_pna_mask(&mut image, &mask)?,
+            None => apply_key_color(&mut image),
+        }
+    }
+    Some(image)
+}
+
+/// 画像を RGBA で読み込む。APNG はアニメーションの最初のフレームを静止画として返す
+fn decode_rgba(path: &Path) -> Option<RgbaImage> {
+    if let Some(frame) = first_apng_frame(path) {
+        return Some(frame);
+    }
+    Some(
+        ImageReader::open(path)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .decode()
+            .ok()?
+            .into_rgba8(),
+    )
+}
+
+/// 左上ピクセルをキーカラーとして透過する（PNG のアルファチャンネルは使わない）
+pub(crate) fn apply_key_color(image: &mut RgbaImage) {
+    if image.width() == 0 || image.height() == 0 {
+        return;
+    }
+    let key = *image.get_pixel(0, 0);
+    for pixel in image.pixels_mut() {
+        pixel[3] = if pixel.0[..3] == key.0[..3] { 0 } else { 255 };
+    }
+}
+
+/// .pna の輝度をアルファ値として画像に書き込む。
+/// .pna の範囲外のピクセルは透明にする。.pna を読み込めなければ None
+pub(crate) fn apply_pna_mask(image: &mut RgbaImage, mask_path: &Path) -> Option<()> {
+    apply_mask(image, &load_pna_mask(mask_path)?);
+    Some(())
+}
+
+/// .pna を輝度画像として読み込む
+pub(crate) fn load_pna_mask(mask_path: &Path) -> Option<GrayImage> {
+    Some(
+        ImageReader::open(mask_path)
+            .ok()?
+            .with_guessed_format()
+            .ok()?
+            .decode()
+            .ok()?
+            .into_luma8(),
+    )
+}
+
+/// 読み込み済みの .pna の輝度をアルファ値として画像に書き込む（範囲外は透明）
+pub(crate) fn apply_mask(image: &mut RgbaImage, mask: &GrayImage) {
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        pixel[3] = if x < mask.width() && y < mask.height() {
+            mask.get_pixel(x, y)[0]
+        } else {
+            0
+        };
+    }
+}
+
+fn draw_layer(canvas: &mut RgbaImage, layer: &Layer) {
+    let (width, height) = (canvas.width() as i64, canvas.height() as i64);
+    for (sx, sy, src) in layer.image.enumerate_pixels() {
+        let dx = layer.x as i64 + sx as i64;
+        let dy = layer.y as i64 + sy as i64;
+        if dx < 0 || dy < 0 || dx >= width || dy >= height {
+            continue;
+        }
+        let dst = canvas.get_pixel_mut(dx as u32, dy as u32);
+        *dst = blend(&layer.method, *dst, *src);
+    }
+}
+
+/// 描画メソッドごとの 1 ピクセル合成
+fn blend(method: &DrawMethod, dst: Rgba<u8>, src: Rgba<u8>) -> Rgba<u8> {
+    match method {
+        // 透明部分も含めて置き換える
+        DrawMethod::Replace => src,
+        // 透過せずそのまま描く
+        DrawMethod::Asis => Rgba([src[0], src[1], src[2], 255]),
+        // 下地の透明部分にだけ描く（下に潜り込ませる）
+        DrawMethod::Interpolate => over(dst, src),
+        // 下地が透明な部分には描かない
+        DrawMethod::OverlayFast if dst[3] == 0 => dst,
+        DrawMethod::OverlayMultiply => multiply(dst, src),
+        // 下地のアルファを src のアルファで削る
+        DrawMethod::Reduce => Rgba([
+            dst[0],
+            dst[1],
+            dst[2],
+            (dst[3] as u32 * src[3] as u32 / 255) as u8,
+        ]),
+        _ => over(src, dst),
+    }
+}
+
+/// top を bottom の上にアルファ合成する（ストレートアルファ）
+fn over(top: Rgba<u8>, bottom: Rgba<u8>) -> Rgba<u8> {
+    let top_alpha = top[3] as u32;
+    let bottom_weight = bottom[3] as u32 * (255 - top_alpha);
+    // 255 倍したアルファ
+    let out_alpha = top_alpha * 255 + bottom_weight;
+    if out_alpha == 0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    let channel = |i: usize| {
+        ((top[i] as u32 * top_alpha * 255 + bottom[i] as u32 * bottom_weight) / out_alpha) as u8
+    };
+    Rgba([channel(0), channel(1), channel(2), (out_alpha / 255) as u8])
+}
+
+/// 下地の色に src の色を乗算する（src のアルファで効き具合を調整し、下地のアルファは保つ）
+fn multiply(dst: Rgba<u8>, src: Rgba<u8>) -> Rgba<u8> {
+    let alpha = src[3] as u32;
+    let channel = |i: usize| {
+        let d = dst[i] as u32;
+        let multiplied = d * src[i] as u32 / 255;
+        ((d * (255 - alpha) + multiplied * alpha) / 255) as u8
+    };
+    Rgba([channel(0), channel(1), channel(2), dst[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surfaces::parse_surfaces;
+    use crate::testutil::TempDirGuard;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+    const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+    const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+    fn write_png(path: &Path, width: u32, height: u32, pixel: Rgba<u8>) {
+        RgbaImage::from_pixel(width, height, pixel)
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn element_を_id_順に合成しキャンバスを広げる() {
+        let tmp = TempDirGuard::new("ghost_meta_compose_elements");
+        fs::create_dir_all(tmp.path().join("parts")).unwrap();
+        write_png(&tmp.path().join("base.png"), 4, 4, RED);
+        write_png(&tmp.path().join("parts").join("face.png"), 2, 2, BLUE);
+        // element 定義があるときは surface0.png を使わない
+        write_png(&tmp.path().join("surface0.png"), 8, 8, BLUE);
+        let surfaces = parse_surfaces(
+            "surface0\n{\nelement1,overlay,parts\\face.png,3,3\nelement0,base,base.png,0,0\n}\n",
+        );
+
+        let image = render_surface(tmp.path(), &surfaces, 0, true).unwrap();
+        assert_eq!(image.dimensions(), (5, 5));
+        assert_eq!(*image.get_pixel(0, 0), RED);
+        assert_eq!(*image.get_pixel(3, 3), BLUE);
+        assert_eq!(*image.get_pixel(4, 4), BLUE);
+        assert_eq!(*image.get_pixel(4, 0), CLEAR);
+    }
+
+    #[test]
+    fn 座標が大きすぎる_element_はキャンバスを確保せず_none_を返す() {
+        let tmp = TempDirGuard::new("ghost_meta_compose_oversized");
+        write_png(&tmp.path().join("a.png"), 2, 2, RED);
+
+        let far = parse_surfaces("surface0\n{\nelement0,overlay,a.png,100000,100000\n}\n");
+        assert!(render_surface(tmp.path(), &far, 0, true).is_none());
+        // 辺は上限内でも画素数が上限を超える
+        let wide = parse_surfaces(
+            "surface0\n{\nelement0,overlay,a.png,0,0\nelement1,overlay,a.png,4000,4000\n}\n",
+        );
+        assert!(render_surface(tmp.path(), &wide, 0, true).is_none());
+        // 負の側に大きくはみ出した element はキャンバスを広げない
+        let negative = parse_surfaces(
+            "surface0\n{\nelement0,overlay,a.png,0,0\nelement1,overlay,a.png,-100000,-100000\n}\n",
+        );
+        let image = render_surface(tmp.path(), &negative, 0, true).unwrap();
+        assert_eq!(image.dimensions(), (2, 2));
+    }
+
+    #[test]
+    fn surface0_に_element_定義があればサムネイルも合成した画像を使う() {
+        let tmp = TempDirGuard::new("ghost_meta_compose_thumbnail");
+        write_png(&tmp.path().join("surface0.png"), 8, 8, BLUE);
+        write_png(&tmp.path().join("body.png"), 3, 2, RED);
+        fs::write(
+            tmp.path().join("surfaces.txt"),
+            "surface0\n{\nelement0,base,body.png,1,0\n}\n",
+        )
+        .unwrap();
+        let info = ThumbnailInfo {
+            path: tmp.path().join("surface0.png"),
+            alpha: AlphaMode::SelfAlpha,
+            kind: crate::ThumbnailKind::Surface,
+        };
+
+        let image = render_thumbnail(&info, 100).unwrap();
+        assert_eq!(image.dimensions(), (3, 2));
+        assert!(image.pixels().all(|pixel| *pixel == RED));
+
+        assert_eq!(
+            surface_source_files(tmp.path(), 0),
+            vec![tmp.path().join("surfaces.txt"), tmp.path().join("body.png")]
+        );
+        assert_eq!(
+            surface_source_files(tmp.path(), 1),
+            vec![tmp.path().join("surfaces.txt")]
+        );
+
+        // サーフェス以外の種別は合成しない
+        let info = ThumbnailInfo {
+            kind: crate::ThumbnailKind::Thumbnail,
+            ..info
+        };
+        assert_eq!(render_thumbnail(&info, 100).unwrap().dimensions(), (8, 8));
+    }
+
+    #[test]
+    fn element_がなければ_surface_png_をキーカラー透過で使う() {
+        let tmp = TempDirGuard::new("ghost_meta_compose_fallback");
+        let mut image = RgbaImage::from_pixel(2, 1, Rgba([0, 255, 0, 255]));
+        image.put_pixel(1, 0, Rgba([10, 20, 30, 128]));
+        image.save(tmp.path().join("surface0000.png")).unwrap();
+
+        let rendered = render_surface(tmp.path(), &SurfacesFile::default(), 0, false).unwrap();
+        assert_eq!(*rendered.get_pixel(0, 0), CLEAR);
+        assert_eq!(*rendered.get_pixel(1, 0), Rgba([10, 20, 30, 255]));
+
+        assert!(render_surface(tmp.path(), &SurfacesFile::default(), 1, false).is_none());
+    }
+
+    #[test]
+    fn pna_があればマスクの輝度をアルファとして使う() {
+        let tmp = TempDirGuard::new("ghost_meta_compose_pna");
+        let path = tmp.path().join("surface0.png");
+        write_png(&path, 3, 1, RED);
+        let mut mask = image::GrayImage::new(2, 1);
+        mask.put_pixel(0, 0, image::Luma([255]));
+        mask.put_pixel(1, 0, image::Luma([64]));
+        mask.save_with_format(tmp.path().join("surface0.pna"), ImageFormat::Png)
+            .unwrap();
+
+        let info = ThumbnailInfo {
+            path: path.clone(),
+            alpha: AlphaMode::PnaMask(tmp.path().join("surface0.pna")),
+            kind: crate::ThumbnailKind::Surface,
+        };
+        let bytes = render_thumbnail_png(&info).unwrap();
+        let image = image::load_from_memory(&bytes).unwrap().into_rgba8();
+        assert_eq!(*image.get_pixel(0, 0), RED);
+        assert_eq!(*image.get_pixel(1, 0), Rgba([255, 0, 0, 64]));
+        // マスクの範囲外は透明
+        assert_eq!(image.get_pixel(2, 0)[3], 0);
+
+        // element 合成でも use_self_alpha でなければ .pna を使う
+        let rendered = render_surface(tmp.path(), &SurfacesFile::default(), 0, false).unwrap();
+        assert_eq!(*rendered.get_pixel(1, 0), Rgba([255, 0, 0, 64]));
+        let rendered = render_surface(tmp.path(), &SurfacesFile::default(), 0, true).unwrap();
+        assert_eq!(*rendered.get_pixel(1, 0), RED);
+    }
+
+    #[test]
+    fn render_thumbnail_は透明な余白を切り落として縮小する() {
+        let tmp = TempDirGuard::new("ghost_meta_compose_render_thumbnail");
+        let path = tmp.path().join("surface0.png");
+        let mut image = RgbaImage::from_pixel(100, 60, CLEAR);
+        for x in 10..50 {
+            for y in 20..40 {
+                image.put_pixel(x, y, RED);
+            }
+        }
+        image.save(&path).unwrap();
+        let info = ThumbnailInfo {
+            path,
+            alpha: AlphaMode::SelfAlpha,
+            kind: crate::ThumbnailKind::Surface,
+        };
+
+        let trimmed = render_thumbnail(&info, 100).unwrap();
+        assert_eq!(trimmed.dimensions(), (40, 20));
+        assert!(trimmed.pixels().all(|pixel| *pixel == RED));
+
+        let resized = render_thumbnail(&info, 10).unwrap();
+        assert_eq!(resized.dimensions(), (10, 5));
+    }
+
+    #[test]
+    fn 描画メソッドごとに合成方法を切り替える() {
+        let half_blue = Rgba([0, 0, 255, 128]);
+        assert_eq!(blend(&DrawMethod::Replace, RED, CLEAR), CLEAR);
+        assert_eq!(blend(&DrawMethod::Interpolate, RED, BLUE), RED);
+        assert_eq!(blend(&DrawMethod::Interpolate, CLEAR, BLUE), BLUE);
+        assert_eq!(blend(&DrawMethod::OverlayFast, CLEAR, BLUE), CLEAR);
+        assert_eq!(blend(&DrawMethod::Overlay, CLEAR, BLUE), BLUE);
+        assert_eq!(
+            blend(&DrawMethod::Overlay, RED, half_blue),
+            Rgba([127, 0, 128, 255])
+        );
+        assert_eq!(blend(&DrawMethod::Asis, RED, half_blue), BLUE);
+        assert_eq!(
+            blend(
+                &DrawMethod::OverlayMultiply,
+                Rgba([200, 100, 50, 255]),
+                Rgba([128, 255, 0, 255])
+            ),
+            Rgba([100, 100, 0, 255])
+        );
+        assert_eq!(
+            blend(&DrawMethod::Reduce, RED, half_blue),
+            Rgba([255, 0, 0, 128])
+        );
+    }
+}
@@ -1,30 +1,117 @@
 use crate::GhostMetaError;
-use encoding_rs::{SHIFT_JIS, UTF_8};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
 const CHARSET_SCAN_LIMIT: usize = 4096;
+pub(crate) const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
 
-enum Charset {
-    Utf8,
-    ShiftJis,
+/// descript.txt の標準キーを型付きで保持するモデル。
+/// 標準キー以外のフィールドは `extra` にそのまま保持し、情報を落とさない。
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Descript {
+    /// charset フィールド（宣言された文字コード名）
+    pub charset: Option<String>,
+    /// name フィールド（表示名）
+    pub name: Option<String>,
+    /// id フィールド（SSP がゴーストを識別する ID）
+    pub id: Option<String>,
+    /// type フィールド（"ghost" / "shell" / "balloon" など）
+    pub kind: Option<String>,
+    /// sakura.name フィールド（\0 キャラ名）
+    pub sakura_name: Option<String>,
+    /// kero.name フィールド（\1 キャラ名）
+    pub kero_name: Option<String>,
+    /// craftman フィールド（作者名）
+    pub craftman: Option<String>,
+    /// craftmanw フィールド（作者名2）
+    pub craftmanw: Option<String>,
+    /// craftmanurl フィールド（作者 URL）
+    pub craftmanurl: Option<String>,
+    /// homeurl フィールド（ネットワーク更新 URL）
+    pub homeurl: Option<String>,
+    /// shiori フィールド（SHIORI DLL 名）
+    pub shiori: Option<String>,
+    /// readme フィールド（readme ファイル名）
+    pub readme: Option<String>,
+    /// icon フィールド（アイコンファイル名）
+    pub icon: Option<String>,
+    /// balloon フィールド（デフォルトバルーンのディレクトリ名）
+    pub balloon: Option<String>,
+    /// sakura.seriko.defaultsurface フィールド。数値でない場合は `extra` に残る
+    pub sakura_default_surface: Option<u32>,
+    /// kero.seriko.defaultsurface フィールド。数値でない場合は `extra` に残る
+    pub kero_default_surface: Option<u32>,
+    /// install.accept フィールド（受け入れるインストール元ゴースト）
+    pub install_accept: Option<String>,
+    /// 上記以外のすべてのフィールド
+    pub extra: HashMap<String, String>,
+}
+
+impl From<HashMap<String, String>> for Descript {
+    fn from(mut fields: HashMap<String, String>) -> Self {
+        let sakura_default_surface = take_surface(&mut fields, "sakura.seriko.defaultsurface");
+        let kero_default_surface = take_surface(&mut fields, "kero.seriko.defaultsurface");
+        Descript {
+            charset: fields.remove("charset"),
+            name: fields.remove("name"),
+            id: fields.remove("id"),
+            kind: fields.remove("type"),
+            sakura_name: fields.remove("sakura.name"),
+            kero_name: fields.remove("kero.name"),
+            craftman: fields.remove("craftman"),
+            craftmanw: fields.remove("craftmanw"),
+            craftmanurl: fields.remove("craftmanurl"),
+            homeurl: fields.remove("homeurl"),
+            shiori: fields.remove("shiori"),
+            readme: fields.remove("readme"),
+            icon: fields.remove("icon"),
+            balloon: fields.remove("balloon"),
+            sakura_default_surface,
+            kero_default_surface,
+            install_accept: fields.remove("install.accept"),
+            extra: fields,
+        }
+    }
+}
+
+/// サーフェス番号フィールドを数値として取り出す。数値でなければ HashMap に残す。
+fn take_surface(fields: &mut HashMap<String, String>, key: &str) -> Option<u32> {
+    let surface = fields.get(key)?.parse().ok()?;
+    fields.remove(key);
+    Some(surface)
+}
+
+/// descript.txt を読み込み、型付きの `Descript` として返す。
+pub fn read_descript(path: &Path) -> Result<Descript, GhostMetaError> {
+    parse_descript(path).map(Descript::from)
 }
 
 /// descript.txt をパースしてキー・バリューの HashMap を返す。
-/// charset フィールドに応じて Shift_JIS または UTF-8 でデコードする。
+/// charset フィールドのラベルで文字コードを解決してデコードする（未宣言時はバイト列から推定）。
 pub fn parse_descript(path: &Path) -> Result<HashMap<String, String>, GhostMetaError> {
-    let bytes = fs::read(path)?;
+    Ok(parse_descript_bytes(&fs::read(path)?))
+}
 
-    // まず UTF-8 BOM チェック
-    let content = if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
-        String::from_utf8_lossy(&bytes[3..]).into_owned()
-    } else {
-        // 先頭だけで charset を探して全体デコードに使う
-        let charset = detect_charset(&bytes);
-        decode_with_charset(&bytes, charset)
-    };
+/// read_descript と同じだが、デコード中に不正なバイト列を置換文字へ変換したか（had_errors）も返す
+pub(crate) fn read_descript_checked(path: &Path) -> Result<(Descript, bool), GhostMetaError> {
+    let decoded = decode_descript_bytes(&fs::read(path)?);
+    Ok((
+        Descript::from(parse_descript_text(&decoded.text)),
+        decoded.had_errors,
+    ))
+}
 
+/// descript.txt 形式（install.txt 等も同じ）のバイト列をパースする。文字コードの判定規則は `parse_descript` と同じ
+pub(crate) fn parse_descript_bytes(bytes: &[u8]) -> HashMap<String, String> {
+    parse_descript_text(&decode_descript_bytes(bytes).text)
+}
+
+/// デコード済みの descript.txt 形式のテキストをキー・バリューに分解する
+fn parse_descript_text(content: &str) -> HashMap<String, String> {
     let mut fields = HashMap::new();
     for line in content.lines() {
         let line = line.trim();
@@ -35,40 +122,80 @@ pub fn parse_descript(path: &Path) -> Result<HashMap<String, String>, GhostMetaE
             fields.insert(key.trim().to_string(), value.trim().to_string());
         }
     }
-    Ok(fields)
+    fields
+}
+
+/// descript.txt のバイト列をデコードした結果。lint 用に判定の根拠も保持する。
+pub(crate) struct DecodedText {
+    pub(crate) text: String,
+    /// デコードに使った文字コード
+    pub(crate) encoding: &'static Encoding,
+    /// charset フィールドに宣言された値（未宣言なら None）
+    pub(crate) declared_charset: Option<String>,
+    /// UTF-8 BOM が付いていたか
+    pub(crate) has_bom: bool,
+    /// デコード中に不正なバイト列を置換文字へ変換したか（encoding_rs の had_errors）
+    pub(crate) had_errors: bool,
+}
+
+/// バイト列を descript.txt の文字コード判定規則に従ってデコードする。
+/// 判定順序: UTF-8 BOM → charset フィールド → バイト列からの推定
+pub(crate) fn decode_descript_bytes(bytes: &[u8]) -> DecodedText {
+    let (body, has_bom) = match bytes.strip_prefix(&UTF8_BOM) {
+        Some(rest) => (rest, true),
+        None => (bytes, false),
+    };
+    // 先頭だけで charset を探して全体デコードに使う
+    let declared_charset = find_declared_charset(body);
+
+    let encoding = if has_bom {
+        UTF_8
+    } else {
+        resolve_charset(declared_charset.as_deref(), body)
+    };
+    let (cow, _, had_errors) = encoding.decode(body);
+
+    DecodedText {
+        text: cow.into_owned(),
+        encoding,
+        declared_charset,
+        has_bom,
+        had_errors,
+    }
 }
 
-/// バイト列先頭から charset フィールドを検出する（ASCII 互換部分のみで判定）
-fn detect_charset(bytes: &[u8]) -> Charset {
+/// バイト列先頭から charset フィールドの値を探す（ASCII 互換部分のみで判定）
+fn find_declared_charset(bytes: &[u8]) -> Option<String> {
     let scan_len = bytes.len().min(CHARSET_SCAN_LIMIT);
     let ascii_content = String::from_utf8_lossy(&bytes[..scan_len]);
     for line in ascii_content.lines() {
         let line = line.trim();
-        if let Some((key, value)) = line.split_once(',') {
-            if key.trim().eq_ignore_ascii_case("charset") {
-                if value.trim().eq_ignore_ascii_case("UTF-8") {
-                    return Charset::Utf8;
-                }
-                return Charset::ShiftJis;
-            }
+        if let Some((key, value)) = line.split_once(',')
+            && key.trim().eq_ignore_ascii_case("charset")
+        {
+            return Some(value.trim().to_string());
         }
     }
-    // デフォルトは Shift_JIS
-    Charset::ShiftJis
+    None
 }
 
-/// 指定された文字コードでバイト列をデコードする
-fn decode_with_charset(bytes: &[u8], charset: Charset) -> String {
-    match charset {
-        Charset::Utf8 => {
-            let (cow, _, _) = UTF_8.decode(bytes);
-            cow.into_owned()
-        }
-        Charset::ShiftJis => {
-            let (cow, _, _) = SHIFT_JIS.decode(bytes);
-            cow.into_owned()
-        }
-    }
+/// charset フィールドの値から文字コードを解決する。
+/// ラベルは encoding_rs のラベル解決（WHATWG Encoding Standard）で引くため、
+/// `Shift_JIS` / `GB2312` / `Big5` / `EUC-KR` / `ISO-8859-1` などの別名も受け付ける。
+/// 未宣言・未知のラベル・ASCII 非互換の文字コード（UTF-16 等）はバイト列からの推定にフォールバックする。
+pub(crate) fn resolve_charset(label: Option<&str>, bytes: &[u8]) -> &'static Encoding {
+    label
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()))
+        .filter(|encoding| encoding.is_ascii_compatible())
+        .unwrap_or_else(|| guess_encoding(bytes))
+}
+
+/// charset 未宣言のバイト列の文字コードを推定する。
+/// 伺かのゴーストは日本語圏が中心のため .jp のヒントを与え、判別しにくい場合は Shift_JIS 寄りに倒す。
+pub(crate) fn guess_encoding(bytes: &[u8]) -> &'static Encoding {
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(Some(b"jp"), true)
 }
 
 #[cfg(test)]
@@ -124,7 +251,7 @@ mod tests {
     }
 
     #[test]
-    fn charset_sjis以外のフィールドはshift_jisフォールバックになる() {
+    fn charset_shift_jis宣言のフィールドはshift_jisでデコードされる() {
         let tmp = TempDirGuard::new("ghost_meta_descript_sjis");
         let file = tmp.path().join("descript.txt");
         // Shift_JIS エンコードの「charset,Shift_JIS\nname,てすと\n」
@@ -141,10 +268,10 @@ mod tests {
     }
 
     #[test]
-    fn charsetフィールドがない場合shift_jisデフォルトになる() {
+    fn charset未宣言の_ascii_のみのファイルは推定でそのまま読める() {
         let tmp = TempDirGuard::new("ghost_meta_descript_default");
         let file = tmp.path().join("descript.txt");
-        // ASCII のみ（Shift_JIS でも UTF-8 でも同じ結果になる）
+        // ASCII のみ（推定が Shift_JIS でも UTF-8 でも同じ結果になる）
         fs::write(&file, "name,ASCII Ghost\n").unwrap();
 
         let result = parse_descript(&file).unwrap();
@@ -161,4 +288,126 @@ mod tests {
         // split_once(',') なので最初のカンマで分割される
         assert_eq!(result.get("description"), Some(&"a,b,c".to_string()));
     }
+
+    #[test]
+    fn read_descript_が標準キーを型付きフィールドに格納する() {
+        let tmp = TempDirGuard::new("ghost_meta_descript_typed");
+        let file = tmp.path().join("descript.txt");
+        fs::write(
+            &file,
+            "charset,UTF-8\ntype,ghost\nname,テスト\nid,Test\nshiori,yaya.dll\n\
+             homeurl,https://example.com/test/\ncraftmanurl,https://example.com/\n\
+             readme,readme.txt\nicon,icon.ico\nballoon,sample_balloon\n\
+             sakura.seriko.defaultsurface,3\nkero.seriko.defaultsurface,10\n\
+             install.accept,Other\n",
+        )
+        .unwrap();
+
+        let descript = read_descript(&file).unwrap();
+        assert_eq!(descript.charset.as_deref(), Some("UTF-8"));
+        assert_eq!(descript.kind.as_deref(), Some("ghost"));
+        assert_eq!(descript.name.as_deref(), Some("テスト"));
+        assert_eq!(descript.id.as_deref(), Some("Test"));
+        assert_eq!(descript.shiori.as_deref(), Some("yaya.dll"));
+        assert_eq!(descript.homeurl.as_deref(), Some("https://example.com/test/"));
+        assert_eq!(descript.craftmanurl.as_deref(), Some("https://example.com/"));
+        assert_eq!(descript.readme.as_deref(), Some("readme.txt"));
+        assert_eq!(descript.icon.as_deref(), Some("icon.ico"));
+        assert_eq!(descript.balloon.as_deref(), Some("sample_balloon"));
+        assert_eq!(descript.sakura_default_surface, Some(3));
+        assert_eq!(descript.kero_default_surface, Some(10));
+        assert_eq!(descript.install_accept.as_deref(), Some("Other"));
+        assert!(descript.extra.is_empty());
+    }
+
+    #[test]
+    fn read_descript_が未知のキーをextraに保持する() {
+        let tmp = TempDirGuard::new("ghost_meta_descript_extra");
+        let file = tmp.path().join("descript.txt");
+        fs::write(
+            &file,
+            "charset,UTF-8\nname,テスト\nsakura.seriko.defaultsurface,abc\nseriko.alignmenttodesktop,free\n",
+        )
+        .unwrap();
+
+        let descript = read_descript(&file).unwrap();
+        // 数値でないサーフェス番号は落とさず extra に残す
+        assert_eq!(descript.sakura_default_surface, None);
+        assert_eq!(
+            descript.extra.get("sakura.seriko.defaultsurface"),
+            Some(&"abc".to_string())
+        );
+        assert_eq!(
+            descript.extra.get("seriko.alignmenttodesktop"),
+            Some(&"free".to_string())
+        );
+        assert_eq!(descript.extra.len(), 2);
+    }
+
+    /// charset ラベルごとのフィクスチャ: (宣言ラベル, name の値のバイト列, 期待するデコード結果)
+    /// バイト列は encoding_rs 以外（Python の codecs）で生成した値を埋め込んでいる。
+    const CHARSET_FIXTURES: &[(&str, &[u8], &str)] = &[
+        ("GB2312", &[0xC3, 0xFB, 0xD7, 0xD6, 0xB2, 0xE2, 0xCA, 0xD4], "名字测试"),
+        ("GBK", &[0xC3, 0xFB, 0xD7, 0xD6, 0xB2, 0xE2, 0xCA, 0xD4], "名字测试"),
+        ("Big5", &[0xA6, 0x57, 0xA6, 0x72, 0xB4, 0xFA, 0xB8, 0xD5], "名字測試"),
+        ("EUC-KR", &[0xC5, 0xD7, 0xBD, 0xBA, 0xC6, 0xAE], "테스트"),
+        ("EUC-JP", &[0xA4, 0xC6, 0xA4, 0xB9, 0xA4, 0xC8], "てすと"),
+        ("Shift_JIS", &[0x82, 0xC4, 0x82, 0xB7, 0x82, 0xC6], "てすと"),
+        ("ISO-8859-1", &[0x43, 0x61, 0x66, 0xE9], "Café"),
+        ("Windows-1252", &[0x43, 0x61, 0x66, 0xE9, 0x20, 0x80], "Café €"),
+        ("utf-8", "テスト".as_bytes(), "テスト"),
+    ];
+
+    #[test]
+    fn charset_ラベルごとのフィクスチャを正しくデコードする() {
+        let tmp = TempDirGuard::new("ghost_meta_descript_charset_fixtures");
+        for (index, (label, name_bytes, expected)) in CHARSET_FIXTURES.iter().enumerate() {
+            let file = tmp.path().join(format!("descript_{index}.txt"));
+            let mut bytes = format!("charset,{label}\nname,").into_bytes();
+            bytes.extend_from_slice(name_bytes);
+            bytes.push(b'\n');
+            fs::write(&file, &bytes).unwrap();
+
+            let result = parse_descript(&file).unwrap();
+            assert_eq!(
+                result.get("name").map(String::as_str),
+                Some(*expected),
+                "charset={label}"
+            );
+        }
+    }
+
+    #[test]
+    fn 未知のcharsetラベルは推定にフォールバックする() {
+        let tmp = TempDirGuard::new("ghost_meta_descript_unknown_label");
+        let file = tmp.path().join("descript.txt");
+        fs::write(&file, "charset,default\nname,ゴースト名\n").unwrap();
+
+        let result = parse_descript(&file).unwrap();
+        assert_eq!(result.get("name"), Some(&"ゴースト名".to_string()));
+    }
+
+    #[test]
+    fn charset未宣言のutf8は推定でutf8デコードされる() {
+        let tmp = TempDirGuard::new("ghost_meta_descript_guess_utf8");
+        let file = tmp.path().join("descript.txt");
+        fs::write(&file, "name,推定テスト用のゴースト\ncraftman,作者\n").unwrap();
+
+        let result = parse_descript(&file).unwrap();
+        assert_eq!(result.get("name"), Some(&"推定テスト用のゴースト".to_string()));
+    }
+
+    #[test]
+    fn charset未宣言のshift_jisは推定でshift_jisデコードされる() {
+        let tmp = TempDirGuard::new("ghost_meta_descript_guess_sjis");
+        let file = tmp.path().join("descript.txt");
+        let mut bytes = b"name,".to_vec();
+        // "てすと" の Shift_JIS
+        bytes.extend_from_slice(&[0x82, 0xC4, 0x82, 0xB7, 0x82, 0xC6]);
+        bytes.push(b'\n');
+        fs::write(&file, &bytes).unwrap();
+
+        let result = parse_descript(&file).unwrap();
+        assert_eq!(result.get("name"), Some(&"てすと".to_string()));
+    }
 }
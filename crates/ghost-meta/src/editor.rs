@@ -0,0 +1,334 @@
+use crate::descript::{decode_descript_bytes, UTF8_BOM};
+use crate::GhostMetaError;
+use encoding_rs::Encoding;
+use std::fs;
+use std::path::Path;
+
+const CRLF: &[u8] = b"\r\n";
+const LF: &[u8] = b"\n";
+
+/// 編集用に読み込んだ descript.txt。
+/// 行ごとに元のバイト列を保持し、編集していない行・コメント・空行・改行コード・BOM・文字コードは
+/// 保存時にそのまま書き戻す。
+#[derive(Debug, Clone)]
+pub struct DescriptDocument {
+    lines: Vec<Line>,
+    encoding: &'static Encoding,
+    has_bom: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    /// 改行コードを除いた元のバイト列
+    raw: Vec<u8>,
+    /// 行末の改行コード（改行で終わらない最終行は空）
+    ending: &'static [u8],
+    /// `key,value` 形式の行ならトリム済みのキー（コメント・空行・不正行は None）
+    key: Option<String>,
+}
+
+impl DescriptDocument {
+    /// descript.txt を編集用に読み込む。
+    pub fn open(path: &Path) -> Result<Self, GhostMetaError> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    /// バイト列から編集用ドキュメントを作る。文字コードは `parse_descript` と同じ規則で判定する。
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let decoded = decode_descript_bytes(bytes);
+        let encoding = decoded.encoding;
+        let mut rest = if decoded.has_bom {
+            &bytes[UTF8_BOM.len()..]
+        } else {
+            bytes
+        };
+
+        let mut lines = Vec::new();
+        while !rest.is_empty() {
+            let (raw, ending, next) = match rest.iter().position(|&b| b == b'\n') {
+                Some(i) => match rest[..i].strip_suffix(b"\r") {
+                    Some(raw) => (raw, CRLF, &rest[i + 1..]),
+                    None => (&rest[..i], LF, &rest[i + 1..]),
+                },
+                None => (rest, &b""[..], &b""[..]),
+            };
+            lines.push(Line {
+                raw: raw.to_vec(),
+                ending,
+                key: line_key(encoding, raw),
+            });
+            rest = next;
+        }
+
+        Self {
+            lines,
+            encoding,
+            has_bom: decoded.has_bom,
+        }
+    }
+
+    /// 保存時に使う文字コード名（encoding_rs の正規名）
+    pub fn encoding_name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// UTF-8 BOM 付きで保存されるか
+    pub fn has_bom(&self) -> bool {
+        self.has_bom
+    }
+
+    /// キーの値を返す。重複している場合は `parse_descript` と同じく最後の定義を返す。
+    pub fn get(&self, key: &str) -> Option<String> {
+        let line = self.find_line(key)?;
+        let (text, _) = self
+            .encoding
+            .decode_without_bom_handling(&self.lines[line].raw);
+        text.split_once(',')
+            .map(|(_, value)| value.trim().to_string())
+    }
+
+    /// キーの値を設定する。既存のキーは最後の定義の行だけを書き換え、新しいキーは末尾に追記する。
+    /// 値が元の文字コードで表現できない場合や、改行を含む場合はエラーを返す。
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), GhostMetaError> {
+        validate_entry(key, value)?;
+        if key.eq_ignore_ascii_case("charset")
+            && Encoding::for_label(value.trim().as_bytes()) != Some(self.encoding)
+        {
+            return Err(invalid_value(
+                key,
+                format!(
+                    "文字コードを {} から変更することはできません",
+                    self.encoding.name()
+                ),
+            ));
+        }
+        let encoded_value = self.encode(key, value)?;
+
+        match self.find_line(key) {
+            Some(index) => {
+                let line = &mut self.lines[index];
+                // キー部分とカンマ直後の空白は元のバイト列を残す
+                let comma = line
+                    .raw
+                    .iter()
+                    .position(|&b| b == b',')
+                    .unwrap_or(line.raw.len());
+                let value_start = comma
+                    + 1
+                    + line.raw[comma + 1..]
+                        .iter()
+                        .take_while(|&&b| b == b' ' || b == b'\t')
+                        .count();
+                line.raw.truncate(value_start);
+                line.raw.extend_from_slice(&encoded_value);
+            }
+            None => {
+                let newline = self.newline();
+                if let Some(last) = self.lines.last_mut()
+                    && last.ending.is_empty()
+                {
+                    last.ending = newline;
+                }
+                let mut raw = self.encode(key, key)?;
+                raw.push(b',');
+                raw.extend_from_slice(&encoded_value);
+                self.lines.push(Line {
+                    raw,
+                    ending: newline,
+                    key: Some(key.to_string()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 保存用のバイト列を返す。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        if self.has_bom {
+            bytes.extend_from_slice(&UTF8_BOM);
+        }
+        for line in &self.lines {
+            bytes.extend_from_slice(&line.raw);
+            bytes.extend_from_slice(line.ending);
+        }
+        bytes
+    }
+
+    /// descript.txt として書き出す。
+    pub fn save(&self, path: &Path) -> Result<(), GhostMetaError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    fn find_line(&self, key: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .rposition(|line| line.key.as_deref() == Some(key))
+    }
+
+    /// 追記する行に使う改行コード。既存の最初の改行に合わせ、改行がなければ CRLF にする。
+    fn newline(&self) -> &'static [u8] {
+        self.lines
+            .iter()
+            .map(|line| line.ending)
+            .find(|ending| !ending.is_empty())
+            .unwrap_or(CRLF)
+    }
+
+    fn encode(&self, key: &str, text: &str) -> Result<Vec<u8>, GhostMetaError> {
+        let (bytes, _, had_errors) = self.encoding.encode(text);
+        if had_errors {
+            return Err(invalid_value(
+                key,
+                format!(
+                    "{} で表現できない文字が含まれています",
+                    self.encoding.name()
+                ),
+            ));
+        }
+        Ok(bytes.into_owned())
+    }
+}
+
+/// 1 行分のバイト列から `parse_descript` と同じ規則でキーを取り出す
+fn line_key(encoding: &'static Encoding, raw: &[u8]) -> Option<String> {
+    let (text, _) = encoding.decode_without_bom_handling(raw);
+    let line = text.trim();
+    if line.is_empty() || line.starts_with("//") {
+        return None;
+    }
+    line.split_once(',').map(|(key, _)| key.trim().to_string())
+}
+
+/// 書き込むとファイルの行構造が変わってしまうキー・値を弾く
+fn validate_entry(key: &str, value: &str) -> Result<(), GhostMetaError> {
+    if key.trim() != key || key.is_empty() || key.starts_with("//") || key.contains(',') {
+        return Err(invalid_value(
+            key,
+            "キーとして使えない文字列です".to_string(),
+        ));
+    }
+    if [key, value].iter().any(|s| s.contains(['\r', '\n'])) {
+        return Err(invalid_value(
+            key,
+            "改行を含めることはできません".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn invalid_value(key: &str, reason: String) -> GhostMetaError {
+    GhostMetaError::InvalidDescriptEntry {
+        key: key.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_descript;
+    use crate::testutil::TempDirGuard;
+
+    /// "てすと" の Shift_JIS
+    const SJIS_TESUTO: [u8; 6] = [0x82, 0xC4, 0x82, 0xB7, 0x82, 0xC6];
+
+    fn sjis_descript() -> Vec<u8> {
+        let mut bytes = b"// comment\r\ncharset,Shift_JIS\r\n\r\nname, ".to_vec();
+        bytes.extend_from_slice(&SJIS_TESUTO);
+        bytes.extend_from_slice(b"\r\ncraftman,author\r\n");
+        bytes
+    }
+
+    #[test]
+    fn 編集しなければ元のバイト列をそのまま返す() {
+        let mut bom = UTF8_BOM.to_vec();
+        bom.extend_from_slice("charset,UTF-8\nname,ゴースト\n\n// 末尾コメント".as_bytes());
+
+        for bytes in [
+            sjis_descript(),
+            bom,
+            b"name,no newline".to_vec(),
+            Vec::new(),
+        ] {
+            assert_eq!(DescriptDocument::from_bytes(&bytes).to_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    fn 既存キーの値だけを書き換え文字コードを維持する() {
+        let mut doc = DescriptDocument::from_bytes(&sjis_descript());
+        assert_eq!(doc.encoding_name(), "Shift_JIS");
+        assert_eq!(doc.get("name").as_deref(), Some("てすと"));
+
+        doc.set("craftman", "さくら").unwrap();
+
+        let mut expected = b"// comment\r\ncharset,Shift_JIS\r\n\r\nname, ".to_vec();
+        expected.extend_from_slice(&SJIS_TESUTO);
+        expected.extend_from_slice(b"\r\ncraftman,");
+        // "さくら" の Shift_JIS
+        expected.extend_from_slice(&[0x82, 0xB3, 0x82, 0xAD, 0x82, 0xE7]);
+        expected.extend_from_slice(b"\r\n");
+        assert_eq!(doc.to_bytes(), expected);
+    }
+
+    #[test]
+    fn 新しいキーは既存の改行コードで末尾に追記する() {
+        let mut doc = DescriptDocument::from_bytes(b"charset,UTF-8\r\nname,test");
+        doc.set("homeurl", "https://example.com/").unwrap();
+
+        assert_eq!(
+            doc.to_bytes(),
+            b"charset,UTF-8\r\nname,test\r\nhomeurl,https://example.com/\r\n"
+        );
+    }
+
+    #[test]
+    fn 重複キーは最後の定義を編集する() {
+        let mut doc = DescriptDocument::from_bytes(b"name,first\nname,second\n");
+        assert_eq!(doc.get("name").as_deref(), Some("second"));
+
+        doc.set("name", "third").unwrap();
+        assert_eq!(doc.to_bytes(), b"name,first\nname,third\n");
+    }
+
+    #[test]
+    fn 書き込めない値はエラーになり内容を変更しない() {
+        let original = sjis_descript();
+        let mut doc = DescriptDocument::from_bytes(&original);
+
+        for (key, value) in [
+            ("name", "😀"),
+            ("name", "a\r\nb"),
+            ("na,me", "x"),
+            ("charset", "UTF-8"),
+        ] {
+            let result = doc.set(key, value);
+            assert!(
+                matches!(result, Err(GhostMetaError::InvalidDescriptEntry { .. })),
+                "{key}={value}"
+            );
+        }
+        assert_eq!(doc.to_bytes(), original);
+        // 同じ文字コードの別名への変更は許可する
+        doc.set("charset", "sjis").unwrap();
+    }
+
+    #[test]
+    fn 保存したファイルを_parse_descript_で読み直せる() {
+        let tmp = TempDirGuard::new("ghost_meta_editor_save");
+        let file = tmp.path().join("descript.txt");
+        fs::write(&file, sjis_descript()).unwrap();
+
+        let mut doc = DescriptDocument::open(&file).unwrap();
+        doc.set("name", "ゴースト").unwrap();
+        doc.set("id", "Ghost").unwrap();
+        doc.save(&file).unwrap();
+
+        let fields = parse_descript(&file).unwrap();
+        assert_eq!(fields.get("name"), Some(&"ゴースト".to_string()));
+        assert_eq!(fields.get("id"), Some(&"Ghost".to_string()));
+        assert_eq!(fields.get("craftman"), Some(&"author".to_string()));
+    }
+}
@@ -1,7 +1,10 @@
-use crate::descript::parse_descript;
-use crate::thumbnail::{resolve_thumbnail, ThumbnailInfo};
+use crate::descript::{read_descript_checked, Descript};
+use crate::scan_report::scan_ghosts_with_report;
+use crate::shell::{scan_shells, ShellMeta};
+use crate::shiori::{detect_shiori, ShioriEngine};
+use crate::thumbnail::{resolve_root_thumbnail, resolve_thumbnail, ThumbnailInfo};
 use crate::GhostMetaError;
-use std::fs;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 /// ゴーストのメタデータ
@@ -16,87 +19,139 @@ pub struct GhostMeta {
     pub craftman: Option<String>,
     /// descript.txt の craftmanw フィールド（作者名2）。未設定の場合は None
     pub craftmanw: Option<String>,
+    /// descript.txt の id フィールド。未設定の場合は None
+    pub id: Option<String>,
+    /// descript.txt の type フィールド。未設定の場合は None
+    pub ghost_type: Option<String>,
+    /// descript.txt の shiori フィールド（SHIORI DLL 名）。未設定の場合は None
+    pub shiori: Option<String>,
+    /// shiori フィールドと `ghost/master` のファイルから判定した SHIORI
+    pub shiori_engine: ShioriEngine,
+    /// descript.txt の homeurl フィールド（ネットワーク更新 URL）。未設定の場合は None
+    pub homeurl: Option<String>,
+    /// descript.txt の craftmanurl フィールド（作者 URL）。未設定の場合は None
+    pub craftmanurl: Option<String>,
+    /// descript.txt の readme フィールド。未設定の場合は None
+    pub readme: Option<String>,
+    /// descript.txt の icon フィールド。未設定の場合は None
+    pub icon: Option<String>,
+    /// descript.txt の balloon フィールド（デフォルトバルーン）。未設定の場合は None
+    pub balloon: Option<String>,
+    /// descript.txt の sakura.seriko.defaultsurface フィールド。未設定または数値でない場合は None
+    pub sakura_default_surface: Option<u32>,
+    /// descript.txt の kero.seriko.defaultsurface フィールド。未設定または数値でない場合は None
+    pub kero_default_surface: Option<u32>,
+    /// descript.txt の install.accept フィールド。未設定の場合は None
+    pub install_accept: Option<String>,
+    /// 上記以外の descript.txt フィールド（charset を含む）
+    pub extra: HashMap<String, String>,
     /// ゴーストのディレクトリ名
     pub directory_name: String,
     /// ゴーストルートディレクトリの絶対パス
     pub path: PathBuf,
     /// 解決済みサムネイル情報。thumbnail feature の有無に関わらず常に存在するフィールド
     pub thumbnail: Option<ThumbnailInfo>,
+    /// `shell/` 配下の全シェル（ディレクトリ名順）
+    pub shells: Vec<ShellMeta>,
 }
 
 /// ゴーストルートディレクトリのメタデータを取得する。
 /// ghost_root は `{parent}/{ghost_name}` に相当するディレクトリ。
 /// descript.txt のパスは `ghost_root/ghost/master/descript.txt`。
 pub fn read_ghost(ghost_root: &Path) -> Result<GhostMeta, GhostMetaError> {
+    read_ghost_checked(ghost_root).map(|(meta, _)| meta)
+}
+
+/// read_ghost と同じだが、descript.txt のデコード中に不正なバイト列を置換したか（スキャンレポート用）も返す
+pub(crate) fn read_ghost_checked(ghost_root: &Path) -> Result<(GhostMeta, bool), GhostMetaError> {
     let directory_name = ghost_root
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("")
         .to_string();
 
-    let descript_path = ghost_root.join("ghost").join("master").join("descript.txt");
-    let fields = parse_descript(&descript_path)?;
-
-    let name = fields
-        .get("name")
-        .cloned()
-        .unwrap_or_else(|| directory_name.clone());
-    let sakura_name = fields.get("sakura.name").cloned();
-    let kero_name = fields.get("kero.name").cloned();
-    let craftman = fields.get("craftman").cloned();
-    let craftmanw = fields.get("craftmanw").cloned();
-
-    // shell/master/descript.txt を 1 回だけパースし、resolve_thumbnail に渡す。
-    // resolve_thumbnail 内での 2 回目のパースを省略する（I/O 削減）。
-    let shell_descript_path = ghost_root
-        .join("shell")
-        .join("master")
-        .join("descript.txt");
-    let shell_fields = parse_descript(&shell_descript_path).ok();
-    let thumbnail = resolve_thumbnail(ghost_root, shell_fields.as_ref());
-
-    Ok(GhostMeta {
+    let master_dir = ghost_root.join("ghost").join("master");
+    let descript_path = master_dir.join("descript.txt");
+    let (descript, had_decode_errors) = read_descript_checked(&descript_path)?;
+    let Descript {
+        charset,
         name,
+        id,
+        kind,
         sakura_name,
         kero_name,
         craftman,
         craftmanw,
+        craftmanurl,
+        homeurl,
+        shiori,
+        readme,
+        icon,
+        balloon,
+        sakura_default_surface,
+        kero_default_surface,
+        install_accept,
+        mut extra,
+    } = descript;
+
+    let name = name.unwrap_or_else(|| directory_name.clone());
+    let shiori_engine = detect_shiori(&master_dir, shiori.as_deref());
+    if let Some(charset) = charset {
+        extra.insert("charset".to_string(), charset);
+    }
+
+    // shell/master は scan_shells で解析済みのため、その surface0* を再利用する。
+    // resolve_thumbnail 内での descript.txt の 2 回目のパースを省略する（I/O 削減）。
+    let shells = scan_shells(ghost_root);
+    let thumbnail = match shells.iter().find(|shell| shell.directory_name == "master") {
+        Some(master) => master
+            .thumbnail
+            .clone()
+            .or_else(|| resolve_root_thumbnail(ghost_root)),
+        None => resolve_thumbnail(ghost_root, None),
+    };
+
+    let meta = GhostMeta {
+        name,
+        sakura_name,
+        kero_name,
+        craftman,
+        craftmanw,
+        id,
+        ghost_type: kind,
+        shiori,
+        shiori_engine,
+        homeurl,
+        craftmanurl,
+        readme,
+        icon,
+        balloon,
+        sakura_default_surface,
+        kero_default_surface,
+        install_accept,
+        extra,
         directory_name,
         path: ghost_root.to_path_buf(),
         thumbnail,
-    })
+        shells,
+    };
+    Ok((meta, had_decode_errors))
 }
 
 /// parent_dir 配下のゴーストを走査して全メタデータを返す。
-/// descript.txt が存在しないエントリはスキップする。
+/// descript.txt が存在しないエントリはスキップする（理由が必要なら `scan_ghosts_with_report`）。
 /// parent_dir の read_dir に失敗した場合はエラーを返す。
 pub fn scan_ghosts(parent_dir: &Path) -> Result<Vec<GhostMeta>, GhostMetaError> {
-    let mut ghosts = Vec::new();
-
-    for entry in fs::read_dir(parent_dir)? {
-        let entry = match entry {
-            Ok(e) => e,
-            Err(_) => continue,
-        };
-        let path = entry.path();
-        if !path.is_dir() {
-            continue;
-        }
-        // descript.txt が存在するエントリのみ Ghost として扱う
-        if let Ok(meta) = read_ghost(&path) {
-            ghosts.push(meta);
-        }
-    }
-
-    Ok(ghosts)
+    scan_ghosts_with_report(parent_dir).map(|report| report.ghosts)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::testutil::TempDirGuard;
+    use std::fs;
 
-    fn create_ghost(root: &PathBuf, dir_name: &str, descript: &str) {
+    fn create_ghost(root: &Path, dir_name: &str, descript: &str) {
         let base = root.join(dir_name).join("ghost").join("master");
         fs::create_dir_all(&base).unwrap();
         fs::write(base.join("descript.txt"), descript).unwrap();
@@ -164,6 +219,27 @@ mod tests {
         assert_eq!(meta.craftmanw, None);
     }
 
+    #[test]
+    fn read_ghost_がdescriptの標準キーと未知キーを読み取る() {
+        let tmp = TempDirGuard::new("ghost_meta_read_ghost_typed");
+        create_ghost(
+            tmp.path(),
+            "typed",
+            "charset,UTF-8\ntype,ghost\nname,型付き\nid,Typed\nshiori,satori.dll\nballoon,sample\nsakura.seriko.defaultsurface,5\nx.custom,value\n",
+        );
+
+        let meta = read_ghost(&tmp.path().join("typed")).unwrap();
+        assert_eq!(meta.id, Some("Typed".to_string()));
+        assert_eq!(meta.ghost_type, Some("ghost".to_string()));
+        assert_eq!(meta.shiori, Some("satori.dll".to_string()));
+        assert_eq!(meta.shiori_engine, ShioriEngine::Satori);
+        assert_eq!(meta.balloon, Some("sample".to_string()));
+        assert_eq!(meta.sakura_default_surface, Some(5));
+        assert_eq!(meta.kero_default_surface, None);
+        assert_eq!(meta.extra.get("x.custom"), Some(&"value".to_string()));
+        assert_eq!(meta.extra.get("charset"), Some(&"UTF-8".to_string()));
+    }
+
     #[test]
     fn read_ghost_がdescripttxtなしのときioエラーを返す() {
         let tmp = TempDirGuard::new("ghost_meta_read_ghost_missing");
@@ -253,4 +329,27 @@ mod tests {
         let meta = read_ghost(&tmp.path().join("no_shell")).unwrap();
         assert!(meta.thumbnail.is_none());
     }
+
+    #[test]
+    fn read_ghost_が全シェルを列挙しmasterのsurface0をthumbnailに使う() {
+        let tmp = TempDirGuard::new("ghost_meta_read_ghost_shells");
+        create_ghost(tmp.path(), "multi_shell", "charset,UTF-8\nname,Multi\n");
+        let shell_root = tmp.path().join("multi_shell").join("shell");
+        for (dir, name) in [("master", "通常"), ("winter", "冬服")] {
+            fs::create_dir_all(shell_root.join(dir)).unwrap();
+            fs::write(
+                shell_root.join(dir).join("descript.txt"),
+                format!("charset,UTF-8\nname,{name}\nseriko.use_self_alpha,1\n"),
+            )
+            .unwrap();
+            fs::write(shell_root.join(dir).join("surface0.png"), "").unwrap();
+        }
+
+        let meta = read_ghost(&tmp.path().join("multi_shell")).unwrap();
+        let names: Vec<&str> = meta.shells.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["通常", "冬服"]);
+        let thumbnail = meta.thumbnail.unwrap();
+        assert_eq!(thumbnail.path, shell_root.join("master").join("surface0.png"));
+        assert_eq!(thumbnail.alpha, crate::AlphaMode::SelfAlpha);
+    }
 }
@@ -0,0 +1,521 @@
+use crate::ghost::read_ghost;
+use crate::nar::{is_valid_directory_name, strip_prefix_ignore_case, InstallTxt, NarArchive};
+use crate::GhostMetaError;
+use std::fs;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+/// install.txt の type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NarKind {
+    /// ゴースト本体（`ghost_root/<directory>`）
+    Ghost,
+    /// 追加シェル（`<accept のゴースト>/shell/<directory>`）
+    Shell,
+    /// バルーン（`balloon_root/<directory>`）
+    Balloon,
+    /// サプリメント（`<accept のゴースト>/` に上書き展開）
+    Supplement,
+}
+
+impl NarKind {
+    /// install.txt の type の値から変換する（大文字小文字不問）。未対応の type は None
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ghost" => Some(NarKind::Ghost),
+            "shell" => Some(NarKind::Shell),
+            "balloon" => Some(NarKind::Balloon),
+            "supplement" => Some(NarKind::Supplement),
+            _ => None,
+        }
+    }
+
+    /// install.txt の type の表記
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NarKind::Ghost => "ghost",
+            NarKind::Shell => "shell",
+            NarKind::Balloon => "balloon",
+            NarKind::Supplement => "supplement",
+        }
+    }
+}
+
+/// インストール先のルートディレクトリ
+#[derive(Debug, Clone)]
+pub struct InstallRoots {
+    /// ゴーストを置くディレクトリ（`SSP/ghost` または追加フォルダ）
+    pub ghost_root: PathBuf,
+    /// バルーンを置くディレクトリ（`SSP/balloon`）
+    pub balloon_root: PathBuf,
+}
+
+/// 展開先 1 か所分
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallTarget {
+    /// アーカイブ内の接頭辞（`/` 終わり。ルートなら ""）
+    pub source_prefix: String,
+    /// 展開先ディレクトリ
+    pub path: PathBuf,
+    /// refresh が指定されていれば true（展開前に既存ファイルを削除する）
+    pub refresh: bool,
+    /// refresh で削除しない相対パス（refreshundeletemask を `:` で分割したもの）
+    pub undelete_mask: Vec<String>,
+}
+
+/// install.txt から決めたインストール計画
+#[derive(Debug, Clone)]
+pub struct InstallPlan {
+    /// install.txt の内容
+    pub install: InstallTxt,
+    /// install.txt の type
+    pub kind: NarKind,
+    /// 主な展開先
+    pub target: InstallTarget,
+    /// ゴーストに同梱されたバルーン（install.txt の balloon.directory）の展開先
+    pub bundled_balloon: Option<InstallTarget>,
+    /// 影響を受けるゴーストのディレクトリ（インストール後に再スキャンする）。balloon の場合は None
+    pub ghost_dir: Option<PathBuf>,
+    /// 上書きになる既存ディレクトリ（supplement の展開先は含めない）
+    pub existing: Vec<PathBuf>,
+}
+
+/// `install_nar` の結果
+#[derive(Debug, Clone)]
+pub enum InstallOutcome {
+    /// 既存ディレクトリを上書きするため確認が必要（何も書き込んでいない）
+    NeedsConfirmation(InstallPlan),
+    /// インストール済み。files は展開したファイル数
+    Installed { plan: InstallPlan, files: usize },
+}
+
+/// .nar をインストールする。
+///
+/// - install.txt の type / directory / accept に従って展開先を決める（`plan_install`）
+/// - overwrite が false で展開先が既に存在する場合は何も書き込まずに `NeedsConfirmation` を返す
+/// - アーカイブ外を指すエントリ（zip-slip）が 1 件でもあれば何も書き込まずにエラーを返す
+/// - refresh,1 の場合は refreshundeletemask に含まれないファイルを削除してから展開する
+pub fn install_nar(
+    nar_path: &Path,
+    roots: &InstallRoots,
+    overwrite: bool,
+) -> Result<InstallOutcome, GhostMetaError> {
+    let mut archive = NarArchive::open(nar_path)?;
+    let plan = plan_install(&mut archive, roots)?;
+    if !overwrite && !plan.existing.is_empty() {
+        return Ok(InstallOutcome::NeedsConfirmation(plan));
+    }
+    let files = apply_install(&mut archive, &plan)?;
+    Ok(InstallOutcome::Installed { plan, files })
+}
+
+/// install.txt を読んで展開先を決める。ファイルシステムには書き込まない
+pub fn plan_install<R: Read + Seek>(
+    archive: &mut NarArchive<R>,
+    roots: &InstallRoots,
+) -> Result<InstallPlan, GhostMetaError> {
+    let install = archive.read_install_txt()?;
+    archive.validate_entry_paths()?;
+
+    let kind_value = install.kind.as_deref().unwrap_or("");
+    let kind = NarKind::parse(kind_value).ok_or_else(|| GhostMetaError::InvalidNar {
+        reason: format!("未対応の type です: {kind_value}"),
+    })?;
+    let directory = match kind {
+        NarKind::Supplement => None,
+        _ => Some(valid_directory(install.directory.as_deref(), "directory")?),
+    };
+    let root_target = |path: PathBuf| InstallTarget {
+        source_prefix: String::new(),
+        path,
+        refresh: install.extra.get("refresh").map(|v| v.trim()) == Some("1"),
+        undelete_mask: parse_undelete_mask(install.extra.get("refreshundeletemask")),
+    };
+
+    let (target, ghost_dir) = match kind {
+        NarKind::Ghost => {
+            let path = roots.ghost_root.join(directory.unwrap_or_default());
+            (root_target(path.clone()), Some(path))
+        }
+        NarKind::Balloon => (
+            root_target(roots.balloon_root.join(directory.unwrap_or_default())),
+            None,
+        ),
+        NarKind::Shell | NarKind::Supplement => {
+            let accept = install
+                .accept
+                .as_deref()
+                .filter(|accept| !accept.is_empty())
+                .ok_or_else(|| GhostMetaError::InvalidNar {
+                    reason: format!("type が {} の install.txt に accept がありません", kind.as_str()),
+                })?;
+            let ghost_dir = find_accepting_ghost(&roots.ghost_root, accept).ok_or_else(|| {
+                GhostMetaError::InvalidNar {
+                    reason: format!("accept に一致するゴーストがありません: {accept}"),
+                }
+            })?;
+            let target = match directory {
+                Some(directory) => root_target(ghost_dir.join("shell").join(directory)),
+                // サプリメントはゴーストのファイルに追加・上書きするだけで、既存ファイルは消さない
+                None => InstallTarget {
+                    source_prefix: String::new(),
+                    path: ghost_dir.clone(),
+                    refresh: false,
+                    undelete_mask: Vec::new(),
+                },
+            };
+            (target, Some(ghost_dir))
+        }
+    };
+
+    // ゴーストに同梱されたバルーン: balloon.source.directory（省略時は balloon.directory）を
+    // balloon_root/<balloon.directory> に展開し、ゴースト側には展開しない
+    let bundled_balloon = match (kind, install.balloon_directory.as_deref()) {
+        (NarKind::Ghost, Some(balloon_directory)) if !balloon_directory.is_empty() => {
+            let balloon_directory = valid_directory(Some(balloon_directory), "balloon.directory")?;
+            let source = install
+                .extra
+                .get("balloon.source.directory")
+                .map(String::as_str)
+                .unwrap_or(balloon_directory);
+            let source = valid_directory(Some(source), "balloon.source.directory")?;
+            let source_prefix = format!("{source}/");
+            archive
+                .entry_names()
+                .iter()
+                .any(|name| strip_prefix_ignore_case(name, &source_prefix).is_some())
+                .then(|| InstallTarget {
+                    source_prefix,
+                    path: roots.balloon_root.join(balloon_directory),
+                    refresh: install.extra.get("balloon.refresh").map(|v| v.trim()) == Some("1"),
+                    undelete_mask: parse_undelete_mask(
+                        install.extra.get("balloon.refreshundeletemask"),
+                    ),
+                })
+        }
+        _ => None,
+    };
+
+    let mut existing = Vec::new();
+    if kind != NarKind::Supplement && target.path.exists() {
+        existing.push(target.path.clone());
+    }
+    if let Some(balloon) = &bundled_balloon
+        && balloon.path.exists()
+    {
+        existing.push(balloon.path.clone());
+    }
+
+    Ok(InstallPlan {
+        install,
+        kind,
+        target,
+        bundled_balloon,
+        ghost_dir,
+        existing,
+    })
+}
+
+/// 計画どおりに展開する（refresh → 展開）。展開したファイル数を返す
+pub fn apply_install<R: Read + Seek>(
+    archive: &mut NarArchive<R>,
+    plan: &InstallPlan,
+) -> Result<usize, GhostMetaError> {
+    // 書き込みを始める前にもう一度検査する（plan_install を経由しない呼び出しに備える）
+    archive.validate_entry_paths()?;
+
+    let mut files = 0;
+    if let Some(balloon) = &plan.bundled_balloon {
+        refresh_target(balloon)?;
+        files += archive.extract(&balloon.source_prefix, &balloon.path, &[])?;
+    }
+    refresh_target(&plan.target)?;
+    fs::create_dir_all(&plan.target.path)?;
+    let mut exclude: Vec<&str> = plan
+        .bundled_balloon
+        .iter()
+        .map(|balloon| balloon.source_prefix.as_str())
+        .collect();
+    // サプリメントの install.txt でゴースト本体の install.txt を上書きしない
+    if plan.kind == NarKind::Supplement {
+        exclude.push("install.txt");
+    }
+    files += archive.extract(&plan.target.source_prefix, &plan.target.path, &exclude)?;
+    Ok(files)
+}
+
+/// ghost_root 直下から accept を受け入れるゴーストを探す。
+/// descript.txt の install.accept が accept と一致するゴーストを優先し、なければ name が一致するゴーストを返す
+fn find_accepting_ghost(ghost_root: &Path, accept: &str) -> Option<PathBuf> {
+    let mut directories: Vec<PathBuf> = fs::read_dir(ghost_root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    // OS 依存の列挙順を回避するためソートする
+    directories.sort();
+
+    let ghosts: Vec<_> = directories
+        .into_iter()
+        .filter_map(|path| read_ghost(&path).ok())
+        .collect();
+    ghosts
+        .iter()
+        .find(|ghost| ghost.install_accept.as_deref() == Some(accept))
+        .or_else(|| ghosts.iter().find(|ghost| ghost.name == accept))
+        .map(|ghost| ghost.path.clone())
+}
+
+/// directory 系のフィールドが 1 階層のディレクトリ名であることを検査する
+fn valid_directory<'a>(value: Option<&'a str>, key: &str) -> Result<&'a str, GhostMetaError> {
+    let value = value.map(str::trim).unwrap_or("");
+    if !is_valid_directory_name(value) {
+        return Err(GhostMetaError::InvalidNar {
+            reason: format!("install.txt の {key} が不正です: {value}"),
+        });
+    }
+    Ok(value)
+}
+
+/// refreshundeletemask（`:` 区切り）を `/` 区切りの相対パスのリストにする
+fn parse_undelete_mask(value: Option<&String>) -> Vec<String> {
+    value
+        .map(|value| {
+            value
+                .split(':')
+                .map(|mask| mask.trim().replace('\\', "/").trim_matches('/').to_string())
+                .filter(|mask| !mask.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// refresh が指定されていれば、展開先の既存ファイルを undelete_mask を除いて削除する
+fn refresh_target(target: &InstallTarget) -> Result<(), GhostMetaError> {
+    if target.refresh && target.path.is_dir() {
+        remove_unmasked(&target.path, "", &target.undelete_mask)?;
+    }
+    Ok(())
+}
+
+/// dir 配下を再帰的に削除する。masks に一致するパスと、その親ディレクトリは残す
+fn remove_unmasked(dir: &Path, relative_dir: &str, masks: &[String]) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if relative_dir.is_empty() {
+            name
+        } else {
+            format!("{relative_dir}/{name}")
+        };
+        if masks.iter().any(|mask| mask.eq_ignore_ascii_case(&relative)) {
+            continue;
+        }
+        let path = entry.path();
+        // シンボリックリンクは辿らずにリンク自体を削除する
+        if entry.file_type()?.is_dir() {
+            let child_prefix = format!("{relative}/");
+            if masks
+                .iter()
+                .any(|mask| strip_prefix_ignore_case(mask, &child_prefix).is_some())
+            {
+                remove_unmasked(&path, &relative, masks)?;
+            } else {
+                fs::remove_dir_all(&path)?;
+            }
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nar::tests::build_zip;
+    use crate::testutil::TempDirGuard;
+
+    struct Fixture {
+        _tmp: TempDirGuard,
+        roots: InstallRoots,
+        nar_dir: PathBuf,
+    }
+
+    fn fixture(label: &str) -> Fixture {
+        let tmp = TempDirGuard::new(label);
+        let roots = InstallRoots {
+            ghost_root: tmp.path().join("ghost"),
+            balloon_root: tmp.path().join("balloon"),
+        };
+        fs::create_dir_all(&roots.ghost_root).unwrap();
+        fs::create_dir_all(&roots.balloon_root).unwrap();
+        let nar_dir = tmp.path().join("nar");
+        fs::create_dir_all(&nar_dir).unwrap();
+        Fixture {
+            _tmp: tmp,
+            roots,
+            nar_dir,
+        }
+    }
+
+    fn write_nar(fixture: &Fixture, file_name: &str, entries: &[(&str, &[u8])]) -> PathBuf {
+        let path = fixture.nar_dir.join(file_name);
+        fs::write(&path, build_zip(entries)).unwrap();
+        path
+    }
+
+    fn installed(outcome: InstallOutcome) -> (InstallPlan, usize) {
+        match outcome {
+            InstallOutcome::Installed { plan, files } => (plan, files),
+            InstallOutcome::NeedsConfirmation(plan) => {
+                panic!("確認が必要になった: {:?}", plan.existing)
+            }
+        }
+    }
+
+    #[test]
+    fn ghost_は_directory_に展開し同梱バルーンを_balloon_に展開する() {
+        let fixture = fixture("ghost_meta_install_ghost");
+        let nar = write_nar(
+            &fixture,
+            "sakura.nar",
+            &[
+                (
+                    "install.txt",
+                    b"type,ghost\ndirectory,sakura\nballoon.directory,sakura_balloon\nballoon.source.directory,bln\n",
+                ),
+                ("ghost/master/descript.txt", b"name,Sakura\n"),
+                ("shell/master/descript.txt", b"name,Default\n"),
+                ("bln/descript.txt", b"name,Balloon\n"),
+            ],
+        );
+
+        let (plan, files) = installed(install_nar(&nar, &fixture.roots, false).unwrap());
+        assert_eq!(plan.kind, NarKind::Ghost);
+        assert_eq!(files, 4);
+        let ghost_dir = fixture.roots.ghost_root.join("sakura");
+        assert_eq!(plan.ghost_dir.as_deref(), Some(ghost_dir.as_path()));
+        assert!(ghost_dir.join("ghost/master/descript.txt").is_file());
+        assert!(ghost_dir.join("install.txt").is_file());
+        assert!(!ghost_dir.join("bln").exists());
+        assert!(fixture
+            .roots
+            .balloon_root
+            .join("sakura_balloon/descript.txt")
+            .is_file());
+    }
+
+    #[test]
+    fn 既存ゴーストの上書きは確認が必要で_refresh_は_mask_以外を削除する() {
+        let fixture = fixture("ghost_meta_install_overwrite");
+        let ghost_dir = fixture.roots.ghost_root.join("sakura");
+        fs::create_dir_all(ghost_dir.join("ghost/master/profile")).unwrap();
+        fs::write(ghost_dir.join("ghost/master/old.dic"), "old").unwrap();
+        fs::write(ghost_dir.join("ghost/master/profile/save.dat"), "save").unwrap();
+        fs::write(ghost_dir.join("ghost/master/profile/tmp.dat"), "tmp").unwrap();
+        let nar = write_nar(
+            &fixture,
+            "sakura.nar",
+            &[
+                (
+                    "install.txt",
+                    b"type,ghost\ndirectory,sakura\nrefresh,1\nrefreshundeletemask,ghost/master/profile/save.dat\n",
+                ),
+                ("ghost/master/descript.txt", b"name,Sakura\n"),
+            ],
+        );
+
+        let outcome = install_nar(&nar, &fixture.roots, false).unwrap();
+        let InstallOutcome::NeedsConfirmation(plan) = outcome else {
+            panic!("確認なしでインストールされた");
+        };
+        assert_eq!(plan.existing, vec![ghost_dir.clone()]);
+        assert!(ghost_dir.join("ghost/master/old.dic").is_file());
+
+        installed(install_nar(&nar, &fixture.roots, true).unwrap());
+        assert!(!ghost_dir.join("ghost/master/old.dic").exists());
+        assert!(!ghost_dir.join("ghost/master/profile/tmp.dat").exists());
+        assert!(ghost_dir.join("ghost/master/profile/save.dat").is_file());
+        assert!(ghost_dir.join("ghost/master/descript.txt").is_file());
+    }
+
+    #[test]
+    fn shell_と_supplement_は_accept_のゴーストに展開する() {
+        let fixture = fixture("ghost_meta_install_accept");
+        let ghost_dir = fixture.roots.ghost_root.join("sakura_dir");
+        fs::create_dir_all(ghost_dir.join("ghost/master")).unwrap();
+        fs::write(
+            ghost_dir.join("ghost/master/descript.txt"),
+            "charset,UTF-8\nname,Sakura\n",
+        )
+        .unwrap();
+
+        let shell = write_nar(
+            &fixture,
+            "shell.nar",
+            &[
+                ("install.txt", b"type,shell\ndirectory,winter\naccept,Sakura\n"),
+                ("descript.txt", b"name,Winter\n"),
+            ],
+        );
+        let (plan, _) = installed(install_nar(&shell, &fixture.roots, false).unwrap());
+        assert_eq!(plan.ghost_dir.as_deref(), Some(ghost_dir.as_path()));
+        assert!(ghost_dir.join("shell/winter/descript.txt").is_file());
+
+        let supplement = write_nar(
+            &fixture,
+            "supplement.nar",
+            &[
+                ("install.txt", b"type,supplement\naccept,Sakura\n"),
+                ("ghost/master/extra.dic", b"extra"),
+            ],
+        );
+        let (plan, files) = installed(install_nar(&supplement, &fixture.roots, false).unwrap());
+        assert_eq!(plan.kind, NarKind::Supplement);
+        assert_eq!(files, 1);
+        assert!(!ghost_dir.join("install.txt").exists());
+        assert!(ghost_dir.join("ghost/master/extra.dic").is_file());
+        assert!(ghost_dir.join("ghost/master/descript.txt").is_file());
+
+        let unknown = write_nar(
+            &fixture,
+            "unknown.nar",
+            &[("install.txt", b"type,shell\ndirectory,x\naccept,Nobody\n")],
+        );
+        assert!(matches!(
+            install_nar(&unknown, &fixture.roots, false),
+            Err(GhostMetaError::InvalidNar { .. })
+        ));
+    }
+
+    #[test]
+    fn zip_slip_エントリや不正な_directory_があれば何も書き込まない() {
+        let fixture = fixture("ghost_meta_install_zip_slip");
+        let nar = write_nar(
+            &fixture,
+            "evil.nar",
+            &[
+                ("install.txt", b"type,ghost\ndirectory,evil\n"),
+                ("ghost/master/descript.txt", b"name,Evil\n"),
+                ("ghost/../../../escaped.txt", b"x"),
+            ],
+        );
+        assert!(matches!(
+            install_nar(&nar, &fixture.roots, true),
+            Err(GhostMetaError::InvalidNar { .. })
+        ));
+        assert!(!fixture.roots.ghost_root.join("evil").exists());
+
+        let nar = write_nar(
+            &fixture,
+            "parent.nar",
+            &[("install.txt", b"type,balloon\ndirectory,..\n")],
+        );
+        assert!(matches!(
+            install_nar(&nar, &fixture.roots, true),
+            Err(GhostMetaError::InvalidNar { .. })
+        ));
+    }
+}
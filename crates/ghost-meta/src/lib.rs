@@ -4,15 +4,84 @@ use thiserror::Error;
 pub enum GhostMetaError {
     #[error("I/O エラー: {0}")]
     Io(#[from] std::io::Error),
+    #[error("descript.txt に書き込めないエントリです（{key}）: {reason}")]
+    InvalidDescriptEntry { key: String, reason: String },
+    #[cfg(feature = "nar")]
+    #[error("zip エラー: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[cfg(feature = "nar")]
+    #[error(".nar ファイルが不正です: {reason}")]
+    InvalidNar { reason: String },
+    #[cfg(feature = "nar")]
+    #[error(".nar を作成できません: {reason}")]
+    InvalidPackage { reason: String },
 }
 
+#[cfg(feature = "thumbnail")]
+pub mod apng;
+pub mod balloon;
+#[cfg(feature = "thumbnail")]
+pub mod compose;
 pub mod descript;
+pub mod editor;
 pub mod ghost;
+#[cfg(feature = "nar")]
+pub mod install;
+pub mod lint;
+#[cfg(feature = "manifest")]
+pub mod manifest;
+#[cfg(feature = "nar")]
+pub mod nar;
+#[cfg(feature = "nar")]
+pub mod package;
+mod path_utils;
+pub mod readme;
+pub mod scan_report;
+pub mod shell;
+pub mod shiori;
+pub mod surfaces;
 pub mod thumbnail;
 
 #[cfg(test)]
 pub(crate) mod testutil;
 
-pub use descript::parse_descript;
+#[cfg(feature = "thumbnail")]
+pub use apng::{is_animated_png, render_animated_preview, AnimatedPreviewLimits};
+#[cfg(feature = "thumbnail")]
+pub use compose::{
+    encode_png, load_thumbnail, render_surface, render_thumbnail, render_thumbnail_png,
+    surface_source_files,
+};
+pub use balloon::{read_balloon, scan_balloons, BalloonMeta};
+pub use descript::{parse_descript, read_descript, Descript};
+pub use editor::DescriptDocument;
 pub use ghost::{read_ghost, scan_ghosts, GhostMeta};
-pub use thumbnail::{resolve_thumbnail, AlphaMode, ThumbnailInfo, ThumbnailKind};
+#[cfg(feature = "nar")]
+pub use install::{
+    apply_install, install_nar, plan_install, InstallOutcome, InstallPlan, InstallRoots,
+    InstallTarget, NarKind,
+};
+#[cfg(feature = "nar")]
+pub use nar::{read_nar, InstallTxt, NarArchive, NarGhostMeta};
+#[cfg(feature = "nar")]
+pub use package::{plan_package, write_package, PackageIssue, PackagePlan};
+pub use readme::{detect_urls, find_readme, parse_readme, Readme};
+pub use lint::{lint_descript, DescriptLint, Diagnostic, LintKind, Severity};
+#[cfg(feature = "manifest")]
+pub use manifest::{
+    build_update_entries, diff_manifests, format_updates2_dau, format_updates_txt, md5_file,
+    parse_manifest, read_manifest, verify_ghost, DeveloperOptions, GhostVerification,
+    UpdateEntry,
+};
+pub use scan_report::{
+    inspect_ghost, scan_ghosts_with_report, GhostScanReport, ScanSkip, ScanSkipReason,
+};
+pub use shell::{scan_shells, ShellMeta};
+pub use shiori::{detect_shiori, ShioriEngine};
+pub use surfaces::{
+    parse_surfaces, read_surfaces, surfaces_files, AnimationPattern, DrawMethod, SurfaceAlias,
+    SurfaceAnimation, SurfaceCollision, SurfaceDefinition, SurfaceElement, SurfacesFile,
+};
+pub use thumbnail::{
+    resolve_shell_thumbnail, resolve_thumbnail, AlphaMode, ThumbnailInfo, ThumbnailKind,
+};
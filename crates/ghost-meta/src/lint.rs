@@ -0,0 +1,368 @@
+use crate::descript::{decode_descript_bytes, guess_encoding, resolve_charset, DecodedText};
+use crate::GhostMetaError;
+use encoding_rs::UTF_8;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// 存在しなければ診断を出すキーと、その重大度
+const REQUIRED_KEYS: [(&str, Severity); 2] =
+    [("name", Severity::Error), ("charset", Severity::Warning)];
+
+/// descript.txt の 1 行分のエントリ（出現順・重複を含めてそのまま保持する）
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DescriptEntry {
+    /// 1 始まりの行番号
+    pub line: usize,
+    pub key: String,
+    pub value: String,
+}
+
+/// 診断の重大度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// SSP 上で表示・動作が壊れる可能性が高い
+    Error,
+    /// 動作はするが意図と異なる可能性がある
+    Warning,
+}
+
+/// 診断の種別
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LintKind {
+    /// 同じキーが複数回定義されている（parse_descript では後勝ちになる）
+    DuplicateKey { key: String, first_line: usize },
+    /// カンマを含まない行（parse_descript では無視される）
+    MalformedLine,
+    /// デコードで置換文字（U+FFFD）が発生した行
+    ReplacementCharacter,
+    /// 宣言された charset と実際のバイト列が一致しない
+    CharsetMismatch {
+        /// 宣言された charset。未宣言なら None（バイト列から推定した文字コードで扱われる）
+        declared: Option<String>,
+        /// バイト列から推定した文字コード。推定できなければ None
+        detected: Option<String>,
+    },
+    /// 必須キーが存在しない
+    MissingRequiredKey { key: String },
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::DuplicateKey { key, first_line } => {
+                write!(
+                    f,
+                    "キー {key} が重複しています（最初の定義: {first_line} 行目）"
+                )
+            }
+            LintKind::MalformedLine => write!(f, "カンマ区切りになっていない行です"),
+            LintKind::ReplacementCharacter => {
+                write!(f, "デコードできない文字が含まれています")
+            }
+            LintKind::CharsetMismatch { declared, detected } => write!(
+                f,
+                "charset の宣言（{}）と実際の文字コード（{}）が一致しません",
+                declared.as_deref().unwrap_or("未宣言"),
+                detected.as_deref().unwrap_or("不明")
+            ),
+            LintKind::MissingRequiredKey { key } => write!(f, "必須キー {key} がありません"),
+        }
+    }
+}
+
+/// lint の診断 1 件
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    /// 1 始まりの行番号。ファイル全体に対する診断では None
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub kind: LintKind,
+}
+
+/// lint 結果
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DescriptLint {
+    /// 出現順のエントリ
+    pub entries: Vec<DescriptEntry>,
+    /// 行番号順の診断（ファイル全体の診断は末尾）
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl DescriptLint {
+    /// Error 重大度の診断が 1 件以上あるか
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == Severity::Error)
+    }
+}
+
+/// descript.txt を lint する。
+/// parse_descript と同じ規則でデコード・分割し、黙って捨てられる行や後勝ちで消えるキーを診断として返す。
+pub fn lint_descript(path: &Path) -> Result<DescriptLint, GhostMetaError> {
+    let bytes = fs::read(path)?;
+    Ok(lint_descript_bytes(&bytes))
+}
+
+/// バイト列に対して lint を実行する（ファイル I/O を伴わない）
+pub fn lint_descript_bytes(bytes: &[u8]) -> DescriptLint {
+    let decoded = decode_descript_bytes(bytes);
+    let mut entries = Vec::new();
+    let mut diagnostics = Vec::new();
+    let mut first_lines: HashMap<String, usize> = HashMap::new();
+
+    for (index, raw_line) in decoded.text.lines().enumerate() {
+        let line_no = index + 1;
+        if raw_line.contains('\u{FFFD}') {
+            diagnostics.push(Diagnostic {
+                line: Some(line_no),
+                severity: Severity::Error,
+                kind: LintKind::ReplacementCharacter,
+            });
+        }
+
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(',') else {
+            diagnostics.push(Diagnostic {
+                line: Some(line_no),
+                severity: Severity::Warning,
+                kind: LintKind::MalformedLine,
+            });
+            continue;
+        };
+
+        let key = key.trim().to_string();
+        if let Some(&first_line) = first_lines.get(&key) {
+            diagnostics.push(Diagnostic {
+                line: Some(line_no),
+                severity: Severity::Warning,
+                kind: LintKind::DuplicateKey {
+                    key: key.clone(),
+                    first_line,
+                },
+            });
+        } else {
+            first_lines.insert(key.clone(), line_no);
+        }
+        entries.push(DescriptEntry {
+            line: line_no,
+            key,
+            value: value.trim().to_string(),
+        });
+    }
+
+    if let Some(kind) = check_charset(bytes, &decoded) {
+        diagnostics.push(Diagnostic {
+            line: first_lines.get("charset").copied(),
+            severity: Severity::Error,
+            kind,
+        });
+    }
+
+    for (key, severity) in REQUIRED_KEYS {
+        if !first_lines.contains_key(key) {
+            diagnostics.push(Diagnostic {
+                line: None,
+                severity,
+                kind: LintKind::MissingRequiredKey {
+                    key: key.to_string(),
+                },
+            });
+        }
+    }
+
+    // charset の不一致は charset 行の診断なので、ループ後に足した分も行番号順に並べ直す（安定ソートで同じ行の順序は保つ）
+    diagnostics.sort_by_key(|d| (d.line.is_none(), d.line));
+
+    DescriptLint {
+        entries,
+        diagnostics,
+    }
+}
+
+/// 宣言された charset（または BOM）と実際のバイト列の整合性を検査する
+fn check_charset(bytes: &[u8], decoded: &DecodedText) -> Option<LintKind> {
+    let declared = decoded.declared_charset.clone();
+    let is_valid_utf8 = std::str::from_utf8(bytes).is_ok();
+
+    if decoded.has_bom
+        && let Some(label) = declared.as_deref()
+        && resolve_charset(Some(label), bytes) != UTF_8
+    {
+        // BOM は UTF-8 を示しているが charset は別の文字コードを宣言している
+        return Some(LintKind::CharsetMismatch {
+            declared,
+            detected: Some(UTF_8.name().to_string()),
+        });
+    }
+    if decoded.had_errors {
+        let detected = if is_valid_utf8 {
+            UTF_8
+        } else {
+            guess_encoding(bytes)
+        };
+        return Some(LintKind::CharsetMismatch {
+            declared,
+            detected: (detected != decoded.encoding).then(|| detected.name().to_string()),
+        });
+    }
+    if decoded.encoding != UTF_8 && is_valid_utf8 && !bytes.is_ascii() {
+        // 他の文字コードとしても読めてしまうが、UTF-8 として妥当な非 ASCII バイト列は UTF-8 の可能性が高い
+        return Some(LintKind::CharsetMismatch {
+            declared,
+            detected: Some(UTF_8.name().to_string()),
+        });
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+
+    fn kinds(lint: &DescriptLint) -> Vec<&LintKind> {
+        lint.diagnostics.iter().map(|d| &d.kind).collect()
+    }
+
+    #[test]
+    fn 正常なファイルは診断なしでエントリを出現順に返す() {
+        let lint =
+            lint_descript_bytes(b"charset,UTF-8\n// comment\n\nname,Test\ncraftman,Author\n");
+        assert!(lint.diagnostics.is_empty());
+        let entries: Vec<(usize, &str, &str)> = lint
+            .entries
+            .iter()
+            .map(|e| (e.line, e.key.as_str(), e.value.as_str()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (1, "charset", "UTF-8"),
+                (4, "name", "Test"),
+                (5, "craftman", "Author")
+            ]
+        );
+    }
+
+    #[test]
+    fn 重複キーを後の行番号と最初の行番号付きで報告する() {
+        let lint = lint_descript_bytes(b"charset,UTF-8\nname,A\nname,B\n");
+        assert_eq!(lint.entries.len(), 3);
+        assert_eq!(lint.diagnostics.len(), 1);
+        assert_eq!(lint.diagnostics[0].line, Some(3));
+        assert_eq!(lint.diagnostics[0].severity, Severity::Warning);
+        assert_eq!(
+            lint.diagnostics[0].kind,
+            LintKind::DuplicateKey {
+                key: "name".to_string(),
+                first_line: 2
+            }
+        );
+    }
+
+    #[test]
+    fn カンマのない行をmalformedとして報告する() {
+        let lint = lint_descript_bytes(b"charset,UTF-8\nname,A\nbroken line\n");
+        assert_eq!(lint.diagnostics.len(), 1);
+        assert_eq!(lint.diagnostics[0].line, Some(3));
+        assert_eq!(lint.diagnostics[0].kind, LintKind::MalformedLine);
+    }
+
+    #[test]
+    fn 必須キーがない場合に報告する() {
+        let lint = lint_descript_bytes(b"craftman,A\n");
+        assert_eq!(
+            kinds(&lint),
+            vec![
+                &LintKind::MissingRequiredKey {
+                    key: "name".to_string()
+                },
+                &LintKind::MissingRequiredKey {
+                    key: "charset".to_string()
+                },
+            ]
+        );
+        assert!(lint.has_errors());
+    }
+
+    #[test]
+    fn utf8宣言で不正なバイト列は置換文字とcharset不一致を報告する() {
+        // "name,てすと" の Shift_JIS バイト列を UTF-8 宣言で書いたケース
+        let mut bytes = b"charset,UTF-8\nname,".to_vec();
+        bytes.extend_from_slice(&[0x82, 0xC4, 0x82, 0xB7, 0x82, 0xC6]);
+        bytes.push(b'\n');
+
+        let lint = lint_descript_bytes(&bytes);
+        assert!(lint.diagnostics.contains(&Diagnostic {
+            line: Some(2),
+            severity: Severity::Error,
+            kind: LintKind::ReplacementCharacter,
+        }));
+        assert!(lint.diagnostics.contains(&Diagnostic {
+            line: Some(1),
+            severity: Severity::Error,
+            kind: LintKind::CharsetMismatch {
+                declared: Some("UTF-8".to_string()),
+                detected: Some("Shift_JIS".to_string()),
+            },
+        }));
+    }
+
+    #[test]
+    fn 診断は行番号順に並びファイル全体の診断は末尾になる() {
+        let mut bytes = b"charset,UTF-8\ncraftman,".to_vec();
+        bytes.extend_from_slice(&[0x82, 0xC4, 0x82, 0xB7, 0x82, 0xC6]);
+        bytes.push(b'\n');
+
+        let lint = lint_descript_bytes(&bytes);
+        let lines: Vec<Option<usize>> = lint.diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![Some(1), Some(2), None]);
+        assert!(matches!(
+            lint.diagnostics[0].kind,
+            LintKind::CharsetMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn shift_jis宣言でutf8バイト列はcharset不一致を報告する() {
+        let lint = lint_descript_bytes("charset,Shift_JIS\nname,ゴースト\n".as_bytes());
+        assert!(kinds(&lint).contains(&&LintKind::CharsetMismatch {
+            declared: Some("Shift_JIS".to_string()),
+            detected: Some("UTF-8".to_string()),
+        }));
+    }
+
+    #[test]
+    fn bom付きでshift_jis宣言はcharset不一致を報告する() {
+        let mut bytes = vec![0xEF, 0xBB, 0xBF];
+        bytes.extend_from_slice(b"charset,Shift_JIS\nname,A\n");
+        let lint = lint_descript_bytes(&bytes);
+        assert_eq!(lint.diagnostics.len(), 1);
+        assert!(matches!(
+            lint.diagnostics[0].kind,
+            LintKind::CharsetMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn lint_descript_はファイルを読み込んで検査する() {
+        let tmp = TempDirGuard::new("ghost_meta_lint_file");
+        let file = tmp.path().join("descript.txt");
+        fs::write(&file, "charset,UTF-8\nname,Test\n").unwrap();
+
+        let lint = lint_descript(&file).unwrap();
+        assert_eq!(lint.entries.len(), 2);
+        assert!(!lint.has_errors());
+    }
+}
@@ -0,0 +1,488 @@
+use crate::descript::decode_descript_bytes;
+use crate::path_utils::safe_relative_path;
+use crate::GhostMetaError;
+use md5::{Digest, Md5};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// ネットワーク更新用マニフェスト（SSP 2.x 以降）のファイル名
+pub const UPDATES2_DAU: &str = "updates2.dau";
+/// ネットワーク更新用マニフェスト（旧形式）のファイル名
+pub const UPDATES_TXT: &str = "updates.txt";
+/// 開発者用の配布設定ファイル名
+pub const DEVELOPER_OPTIONS: &str = "developer_options.txt";
+
+/// マニフェストの 1 エントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UpdateEntry {
+    /// ゴーストのルートからの相対パス（`/` 区切り）
+    pub path: String,
+    /// ファイル内容の MD5（小文字の 16 進表記）
+    pub md5: String,
+    /// ファイルサイズ（バイト）。マニフェストに size がない場合は None
+    pub size: Option<u64>,
+}
+
+impl UpdateEntry {
+    /// ゴーストのルートからの相対パス。ルートの外を指すパス（絶対パス・`..` など）や空のパスは None
+    pub fn relative_path(&self) -> Option<PathBuf> {
+        safe_relative_path(&self.path).filter(|path| path.components().next().is_some())
+    }
+}
+
+/// files（root からの相対パス）の MD5 とサイズを計算してマニフェストのエントリにする（files の順序を保つ）
+pub fn build_update_entries(
+    root: &Path,
+    files: &[String],
+) -> Result<Vec<UpdateEntry>, GhostMetaError> {
+    files
+        .iter()
+        .map(|path| {
+            let (md5, size) = md5_file(&root.join(path))?;
+            Ok(UpdateEntry {
+                path: path.clone(),
+                md5,
+                size: Some(size),
+            })
+        })
+        .collect()
+}
+
+/// updates2.dau の内容を作る。1 行 1 ファイルで `パス\x01MD5\x01size=バイト数\x01`（改行は CRLF）
+pub fn format_updates2_dau(entries: &[UpdateEntry]) -> String {
+    format_manifest(entries, "")
+}
+
+/// updates.txt の内容を作る。updates2.dau の各行の先頭に `file,` を付けた形式
+pub fn format_updates_txt(entries: &[UpdateEntry]) -> String {
+    format_manifest(entries, "file,")
+}
+
+fn format_manifest(entries: &[UpdateEntry], line_prefix: &str) -> String {
+    let mut content = String::from("charset,UTF-8\r\n");
+    for entry in entries {
+        content.push_str(&format!("{line_prefix}{}\x01{}\x01", entry.path, entry.md5));
+        if let Some(size) = entry.size {
+            content.push_str(&format!("size={size}\x01"));
+        }
+        content.push_str("\r\n");
+    }
+    content
+}
+
+/// updates2.dau / updates.txt をパースする（どちらの形式も受け付ける）。
+///
+/// - 文字コードの判定規則は descript.txt と同じ（charset 行・推定）
+/// - `\x01` を含まない行（charset 行・コメント）は読み飛ばす。行頭の `file,` は取り除く
+/// - パスの `\` は `/` に正規化する。MD5 は小文字にそろえる。MD5 が 32 桁の 16 進でない行は読み飛ばす
+pub fn parse_manifest(bytes: &[u8]) -> Vec<UpdateEntry> {
+    let text = decode_descript_bytes(bytes).text;
+    text.lines()
+        .filter(|line| line.contains('\x01'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("file,").unwrap_or(line);
+            let mut fields = line.split('\x01');
+            let path = fields.next()?.trim().replace('\\', "/");
+            let md5 = fields.next()?.trim().to_ascii_lowercase();
+            if path.is_empty() || md5.len() != 32 || !md5.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            let size = fields
+                .filter_map(|field| field.trim().strip_prefix("size="))
+                .find_map(|size| size.parse().ok());
+            Some(UpdateEntry { path, md5, size })
+        })
+        .collect()
+}
+
+/// ghost_root のマニフェストを読む。updates2.dau を優先し、なければ updates.txt を読む。
+/// どちらもなければ None。戻り値の 1 つ目は読んだマニフェストのファイル名
+pub fn read_manifest(
+    ghost_root: &Path,
+) -> Result<Option<(&'static str, Vec<UpdateEntry>)>, GhostMetaError> {
+    for name in [UPDATES2_DAU, UPDATES_TXT] {
+        match fs::read(ghost_root.join(name)) {
+            Ok(bytes) => return Ok(Some((name, parse_manifest(&bytes)))),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(None)
+}
+
+/// ネットワーク更新で取得が必要なエントリを返す（remote の順序を保つ）。
+///
+/// remote（サーバーの updates2.dau）のうち、local（インストール済みのマニフェスト）にないか MD5 が異なるもの。
+/// パスは大文字小文字を区別せずに照合する。ゴーストのルートの外を指すパスは除く
+pub fn diff_manifests(local: &[UpdateEntry], remote: &[UpdateEntry]) -> Vec<UpdateEntry> {
+    let local: HashMap<String, &str> = local
+        .iter()
+        .map(|entry| (entry.path.to_lowercase(), entry.md5.as_str()))
+        .collect();
+    let mut seen = HashSet::new();
+    remote
+        .iter()
+        .filter(|entry| entry.relative_path().is_some())
+        .filter(|entry| seen.insert(entry.path.to_lowercase()))
+        .filter(|entry| local.get(&entry.path.to_lowercase()) != Some(&entry.md5.as_str()))
+        .cloned()
+        .collect()
+}
+
+/// インストール済みゴーストとマニフェストの照合結果
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GhostVerification {
+    /// 照合に使ったマニフェストのファイル名。マニフェストがなければ None（各リストは空）
+    pub manifest: Option<String>,
+    /// MD5 が一致したファイル数
+    pub verified: usize,
+    /// MD5（または size）がマニフェストと異なるファイル（手で編集された可能性がある）
+    pub modified: Vec<String>,
+    /// マニフェストにあるが存在しないファイル（インストールが不完全な可能性がある）
+    pub missing: Vec<String>,
+    /// マニフェストにないファイル
+    pub extra: Vec<String>,
+}
+
+/// インストール済みゴーストのファイルをハッシュしてマニフェストと照合する。
+///
+/// パスは大文字小文字を区別せずに照合する（Windows の挙動に合わせる）。
+/// extra からは、実行時に作られるファイル（`profile` ディレクトリ配下）・マニフェスト自身・install.txt・
+/// developer_options.txt と、developer_options.txt で noupdate を指定したファイルを除く
+pub fn verify_ghost(ghost_root: &Path) -> Result<GhostVerification, GhostMetaError> {
+    let Some((manifest_name, entries)) = read_manifest(ghost_root)? else {
+        return Ok(GhostVerification::default());
+    };
+    let options = DeveloperOptions::read(ghost_root)?;
+    let mut verification = GhostVerification {
+        manifest: Some(manifest_name.to_string()),
+        ..GhostVerification::default()
+    };
+
+    let mut files = Vec::new();
+    list_files(ghost_root, "", &mut files)?;
+    let on_disk: HashMap<String, &String> =
+        files.iter().map(|path| (path.to_lowercase(), path)).collect();
+
+    let mut listed = HashSet::new();
+    for entry in &entries {
+        let key = entry.path.to_lowercase();
+        if !listed.insert(key.clone()) {
+            continue;
+        }
+        let Some(actual) = on_disk.get(&key) else {
+            verification.missing.push(entry.path.clone());
+            continue;
+        };
+        let (md5, size) = md5_file(&ghost_root.join(actual))?;
+        if md5 == entry.md5 && entry.size.is_none_or(|expected| expected == size) {
+            verification.verified += 1;
+        } else {
+            verification.modified.push(entry.path.clone());
+        }
+    }
+
+    verification.extra = files
+        .iter()
+        .filter(|path| !listed.contains(&path.to_lowercase()))
+        .filter(|path| {
+            ![UPDATES2_DAU, UPDATES_TXT, DEVELOPER_OPTIONS, "install.txt"]
+                .iter()
+                .any(|name| path.eq_ignore_ascii_case(name))
+        })
+        .filter(|path| {
+            !path
+                .split('/')
+                .rev()
+                .skip(1)
+                .any(|dir| dir.eq_ignore_ascii_case("profile"))
+        })
+        .filter(|path| !options.is_noupdate(path))
+        .cloned()
+        .collect();
+    Ok(verification)
+}
+
+/// dir 配下のファイルを再帰的に集める（ルートからの相対パス、`/` 区切り、パス順）。シンボリックリンクは辿らない
+fn list_files(dir: &Path, relative_dir: &str, files: &mut Vec<String>) -> io::Result<()> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    // OS 依存の列挙順を回避するためソートする
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if relative_dir.is_empty() {
+            name
+        } else {
+            format!("{relative_dir}/{name}")
+        };
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_files(&entry.path(), &relative, files)?;
+        } else if file_type.is_file() {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// ファイルの MD5（小文字の 16 進表記）とサイズを返す
+pub fn md5_file(path: &Path) -> io::Result<(String, u64)> {
+    let mut file = File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buffer = [0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    let md5 = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok((md5, size))
+}
+
+/// developer_options.txt の 1 行分（`パス,オプション,...`）
+#[derive(Debug, Clone, PartialEq)]
+struct DeveloperRule {
+    /// 小文字化・`/` 区切りに正規化したパターン。`/` 終わりはディレクトリ
+    pattern: String,
+    nonar: bool,
+    noupdate: bool,
+}
+
+/// developer_options.txt（.nar・ネットワーク更新から除外するファイルの指定）
+///
+/// 各行は `パス,オプション,...`。オプションは `nonar`（.nar に入れない）と `noupdate`（マニフェストに載せない）。
+/// パスはゴーストのルートからの相対パスで、`*` / `?` のワイルドカードを使える。
+/// `/` で終わるパス、またはディレクトリに一致するパスはその配下すべてに適用する
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeveloperOptions {
+    rules: Vec<DeveloperRule>,
+}
+
+impl DeveloperOptions {
+    /// ghost_root 直下の developer_options.txt を読む。存在しない場合は空
+    pub fn read(ghost_root: &Path) -> Result<Self, GhostMetaError> {
+        match fs::read(ghost_root.join(DEVELOPER_OPTIONS)) {
+            Ok(bytes) => Ok(Self::parse(&bytes)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// developer_options.txt のバイト列をパースする。文字コードの判定規則は descript.txt と同じ
+    pub fn parse(bytes: &[u8]) -> Self {
+        let text = decode_descript_bytes(bytes).text;
+        let rules = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with("//"))
+            .filter_map(|line| {
+                let mut parts = line.split(',').map(str::trim);
+                let pattern = parts.next()?.replace('\\', "/");
+                let pattern = pattern.trim_start_matches("./").trim_start_matches('/');
+                if pattern.is_empty() || pattern.eq_ignore_ascii_case("charset") {
+                    return None;
+                }
+                let (mut nonar, mut noupdate) = (false, false);
+                for option in parts {
+                    nonar |= option.eq_ignore_ascii_case("nonar");
+                    noupdate |= option.eq_ignore_ascii_case("noupdate");
+                }
+                Some(DeveloperRule {
+                    pattern: pattern.to_lowercase(),
+                    nonar,
+                    noupdate,
+                })
+            })
+            .collect();
+        DeveloperOptions { rules }
+    }
+
+    /// path（ルートからの相対パス、`/` 区切り）が nonar 指定されているか
+    pub fn is_nonar(&self, path: &str) -> bool {
+        self.matches(path, |rule| rule.nonar)
+    }
+
+    /// path（ルートからの相対パス、`/` 区切り）が noupdate 指定されているか
+    pub fn is_noupdate(&self, path: &str) -> bool {
+        self.matches(path, |rule| rule.noupdate)
+    }
+
+    fn matches(&self, path: &str, option: impl Fn(&DeveloperRule) -> bool) -> bool {
+        let path = path.to_lowercase();
+        self.rules
+            .iter()
+            .filter(|rule| option(rule))
+            .any(|rule| match rule.pattern.strip_suffix('/') {
+                Some(directory) => path_within(&path, directory),
+                None => path_within(&path, &rule.pattern),
+            })
+    }
+}
+
+/// path が pattern そのもの、または pattern に一致するディレクトリの配下か
+fn path_within(path: &str, pattern: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    if wildcard_match(&pattern, &path) {
+        return true;
+    }
+    // 親ディレクトリのいずれかが pattern に一致すれば配下とみなす
+    path.iter()
+        .enumerate()
+        .filter(|(_, c)| **c == '/')
+        .any(|(index, _)| wildcard_match(&pattern, &path[..index]))
+}
+
+/// `*`（0 文字以上）と `?`（1 文字）のワイルドカード照合
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && wildcard_match(rest, &text[1..]),
+        Some((head, rest)) => text.first() == Some(head) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+
+    #[test]
+    fn マニフェストに_md5_とサイズを書き出す() {
+        let tmp = TempDirGuard::new("ghost_meta_manifest_build");
+        fs::create_dir_all(tmp.path().join("ghost/master")).unwrap();
+        fs::write(tmp.path().join("ghost/master/descript.txt"), "abc").unwrap();
+        fs::write(tmp.path().join("empty.txt"), "").unwrap();
+
+        let files = vec!["ghost/master/descript.txt".to_string(), "empty.txt".to_string()];
+        let entries = build_update_entries(tmp.path(), &files).unwrap();
+        assert_eq!(entries[0].md5, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(entries[0].size, Some(3));
+        assert_eq!(entries[1].md5, "d41d8cd98f00b204e9800998ecf8427e");
+
+        assert_eq!(
+            format_updates2_dau(&entries[..1]),
+            "charset,UTF-8\r\nghost/master/descript.txt\x01900150983cd24fb0d6963f7d28e17f72\x01size=3\x01\r\n"
+        );
+        assert!(format_updates_txt(&entries)
+            .contains("\r\nfile,empty.txt\x01d41d8cd98f00b204e9800998ecf8427e\x01size=0\x01\r\n"));
+    }
+
+    #[test]
+    fn developer_options_の_nonar_と_noupdate_を判定する() {
+        let options = DeveloperOptions::parse(
+            b"// comment\r\ncharset,UTF-8\r\nprofile/,nonar,noupdate\r\n*.bak,nonar\r\n.\\ghost\\master\\log.txt,noupdate\r\nghost/master/var,nonar\r\n",
+        );
+
+        assert!(options.is_nonar("profile/save.dat"));
+        assert!(options.is_noupdate("Profile/save.dat"));
+        assert!(options.is_nonar("ghost/master/old.BAK"));
+        assert!(!options.is_noupdate("ghost/master/old.bak"));
+        assert!(options.is_noupdate("ghost/master/log.txt"));
+        assert!(!options.is_nonar("ghost/master/log.txt"));
+        assert!(options.is_nonar("ghost/master/var/cache.dat"));
+        assert!(!options.is_nonar("ghost/master/variable.dic"));
+        assert!(!options.is_nonar("descript.txt"));
+    }
+
+    #[test]
+    fn developer_options_がなければ何も除外しない() {
+        let tmp = TempDirGuard::new("ghost_meta_developer_options_missing");
+        let options = DeveloperOptions::read(tmp.path()).unwrap();
+        assert_eq!(options, DeveloperOptions::default());
+        assert!(!options.is_nonar("ghost/master/descript.txt"));
+    }
+
+    #[test]
+    fn parse_manifest_は_updates2_dau_と_updates_txt_を読む() {
+        let entries = parse_manifest(
+            b"charset,UTF-8\r\nghost\\master\\descript.txt\x01900150983CD24FB0D6963F7D28E17F72\x01size=3\x01\r\nbroken\x01xyz\x01\r\nreadme.txt\x01d41d8cd98f00b204e9800998ecf8427e\x01\r\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                UpdateEntry {
+                    path: "ghost/master/descript.txt".to_string(),
+                    md5: "900150983cd24fb0d6963f7d28e17f72".to_string(),
+                    size: Some(3),
+                },
+                UpdateEntry {
+                    path: "readme.txt".to_string(),
+                    md5: "d41d8cd98f00b204e9800998ecf8427e".to_string(),
+                    size: None,
+                },
+            ]
+        );
+
+        let roundtrip = parse_manifest(format_updates_txt(&entries).as_bytes());
+        assert_eq!(roundtrip, entries);
+    }
+
+    #[test]
+    fn verify_ghost_は変更_欠落_余分なファイルを報告する() {
+        let tmp = TempDirGuard::new("ghost_meta_verify_ghost");
+        let root = tmp.path();
+        fs::create_dir_all(root.join("ghost/master/profile")).unwrap();
+        fs::write(root.join("ghost/master/descript.txt"), "abc").unwrap();
+        fs::write(root.join("ghost/master/edited.dic"), "edited").unwrap();
+        fs::write(root.join("ghost/master/new.dic"), "new").unwrap();
+        fs::write(root.join("ghost/master/profile/save.dat"), "save").unwrap();
+        fs::write(root.join("install.txt"), "type,ghost\r\n").unwrap();
+        fs::write(root.join(DEVELOPER_OPTIONS), "ghost/master/*.log,noupdate\r\n").unwrap();
+        fs::write(root.join("ghost/master/debug.log"), "log").unwrap();
+        fs::write(
+            root.join(UPDATES2_DAU),
+            "GHOST/master/descript.txt\x01900150983cd24fb0d6963f7d28e17f72\x01size=3\x01\r\n\
+             ghost/master/edited.dic\x01900150983cd24fb0d6963f7d28e17f72\x01size=3\x01\r\n\
+             shell/master/surface0.png\x01d41d8cd98f00b204e9800998ecf8427e\x01size=0\x01\r\n",
+        )
+        .unwrap();
+
+        let verification = verify_ghost(root).unwrap();
+        assert_eq!(verification.manifest.as_deref(), Some(UPDATES2_DAU));
+        assert_eq!(verification.verified, 1);
+        assert_eq!(verification.modified, vec!["ghost/master/edited.dic"]);
+        assert_eq!(verification.missing, vec!["shell/master/surface0.png"]);
+        assert_eq!(verification.extra, vec!["ghost/master/new.dic"]);
+    }
+
+    #[test]
+    fn verify_ghost_はマニフェストがなければ空の結果を返す() {
+        let tmp = TempDirGuard::new("ghost_meta_verify_ghost_no_manifest");
+        fs::write(tmp.path().join("updates.txt.bak"), "").unwrap();
+        assert_eq!(verify_ghost(tmp.path()).unwrap(), GhostVerification::default());
+    }
+
+    #[test]
+    fn diff_manifests_は追加と変更のあったエントリだけを返す() {
+        let entry = |path: &str, md5: &str| UpdateEntry {
+            path: path.to_string(),
+            md5: md5.repeat(32),
+            size: Some(1),
+        };
+        let local = vec![entry("ghost/master/descript.txt", "a"), entry("readme.txt", "b")];
+        let remote = vec![
+            entry("Ghost/Master/descript.txt", "a"),
+            entry("readme.txt", "c"),
+            entry("shell/master/surface0.png", "d"),
+            entry("../evil.txt", "e"),
+        ];
+
+        let changed = diff_manifests(&local, &remote);
+        let paths: Vec<_> = changed.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, vec!["readme.txt", "shell/master/surface0.png"]);
+    }
+}
@@ -0,0 +1,535 @@
+use crate::descript::{parse_descript_bytes, Descript};
+use crate::path_utils::safe_relative_path;
+use crate::shell::ShellMeta;
+use crate::thumbnail::{
+    detect_thumbnail_alpha_bytes, pick_surface0, seriko_alpha_from_fields, AlphaMode,
+    ThumbnailInfo, ThumbnailKind,
+};
+use crate::GhostMetaError;
+use encoding_rs::SHIFT_JIS;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+/// テキストエントリ（install.txt / descript.txt）として読み込む最大バイト数
+const TEXT_ENTRY_LIMIT: u64 = 1024 * 1024;
+
+/// install.txt の内容
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InstallTxt {
+    /// type フィールド（"ghost" / "balloon" / "shell" / "supplement" など）
+    pub kind: Option<String>,
+    /// name フィールド
+    pub name: Option<String>,
+    /// directory フィールド（インストール先のディレクトリ名）
+    pub directory: Option<String>,
+    /// accept フィールド（type が supplement / shell の場合の導入先ゴースト）
+    pub accept: Option<String>,
+    /// balloon.directory フィールド（同梱バルーンのディレクトリ名）
+    pub balloon_directory: Option<String>,
+    /// 上記以外のすべてのフィールド（charset を含む）
+    pub extra: HashMap<String, String>,
+}
+
+impl From<HashMap<String, String>> for InstallTxt {
+    fn from(mut fields: HashMap<String, String>) -> Self {
+        InstallTxt {
+            kind: fields.remove("type"),
+            name: fields.remove("name"),
+            directory: fields.remove("directory"),
+            accept: fields.remove("accept"),
+            balloon_directory: fields.remove("balloon.directory"),
+            extra: fields,
+        }
+    }
+}
+
+/// .nar から読み取ったゴーストのメタデータ（`GhostMeta` のアーカイブ版）。
+///
+/// パスはすべてアーカイブ内の相対パス（区切りは `/`）。
+/// `thumbnail` / `shells[].thumbnail` の画像は `NarArchive::read_entry` で取り出す。
+#[derive(Debug, Clone)]
+pub struct NarGhostMeta {
+    /// install.txt の内容
+    pub install: InstallTxt,
+    /// `ghost/master/descript.txt` の内容
+    pub descript: Descript,
+    /// descript.txt の name。未設定の場合は install.txt の directory
+    pub name: String,
+    /// install.txt の directory（インストール先のディレクトリ名）。未設定の場合は空文字列
+    pub directory_name: String,
+    /// 解決済みサムネイル情報（パスはアーカイブ内の相対パス）
+    pub thumbnail: Option<ThumbnailInfo>,
+    /// `shell/` 配下の全シェル（ディレクトリ名順。パスはアーカイブ内の相対パス）
+    pub shells: Vec<ShellMeta>,
+}
+
+/// .nar（install.txt をルートに持つ zip）を展開せずに読むためのラッパー
+pub struct NarArchive<R: Read + Seek> {
+    zip: ZipArchive<R>,
+    /// エントリ名（`\` を `/` に正規化）。zip のインデックス順
+    names: Vec<String>,
+}
+
+impl NarArchive<BufReader<File>> {
+    /// .nar ファイルを開く
+    pub fn open(path: &Path) -> Result<Self, GhostMetaError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> NarArchive<R> {
+    /// zip として読み込めるリーダーから NarArchive を作る
+    pub fn new(reader: R) -> Result<Self, GhostMetaError> {
+        let mut zip = ZipArchive::new(reader)?;
+        let names = (0..zip.len())
+            .map(|index| {
+                let entry = zip.by_index_raw(index)?;
+                Ok(decode_entry_name(entry.name_raw()))
+            })
+            .collect::<Result<Vec<_>, zip::result::ZipError>>()?;
+        Ok(NarArchive { zip, names })
+    }
+
+    /// アーカイブ内の全エントリ名（`/` 区切り。ディレクトリエントリは末尾が `/`）
+    pub fn entry_names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// エントリ名を大文字小文字を区別せずに探す。見つかれば実際のエントリ名を返す
+    pub fn find_entry(&self, name: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|entry| entry.eq_ignore_ascii_case(name))
+            .map(String::as_str)
+    }
+
+    /// エントリを読み込む（名前は大文字小文字不問）。limit バイトを超える場合はエラー
+    pub fn read_entry(&mut self, name: &str, limit: u64) -> Result<Vec<u8>, GhostMetaError> {
+        let index = self
+            .names
+            .iter()
+            .position(|entry| entry.eq_ignore_ascii_case(name))
+            .ok_or_else(|| GhostMetaError::InvalidNar {
+                reason: format!("{name} がありません"),
+            })?;
+        let entry = self.zip.by_index(index)?;
+        if entry.size() > limit {
+            return Err(GhostMetaError::InvalidNar {
+                reason: format!("{name} が大きすぎます（{} バイト）", entry.size()),
+            });
+        }
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        // ヘッダーのサイズを偽装したエントリでも limit までしか展開しない
+        entry.take(limit).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// ルートの install.txt を読む。descript.txt と同じ文字コード判定規則でデコードする
+    pub fn read_install_txt(&mut self) -> Result<InstallTxt, GhostMetaError> {
+        let bytes = self.read_entry("install.txt", TEXT_ENTRY_LIMIT)?;
+        Ok(InstallTxt::from(parse_descript_bytes(&bytes)))
+    }
+
+    /// 全エントリ名がアーカイブ外（絶対パス・ドライブ指定・`..`）を指していないか検査する（zip-slip 対策）。
+    /// 展開の前に呼び、1 件でも不正なら何も書き込まずにエラーにする
+    pub fn validate_entry_paths(&self) -> Result<(), GhostMetaError> {
+        match self
+            .names
+            .iter()
+            .find(|name| safe_relative_path(name).is_none())
+        {
+            Some(name) => Err(GhostMetaError::InvalidNar {
+                reason: format!("アーカイブ外を指すエントリがあります: {name}"),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// prefix 配下（大文字小文字不問。"" ならすべて）のエントリを、prefix を取り除いて dest に展開する。
+    /// exclude に一致するエントリ（`/` 終わりならその配下、それ以外は同名のエントリ）は展開しない。
+    /// 展開したファイル数を返す
+    pub fn extract(
+        &mut self,
+        prefix: &str,
+        dest: &Path,
+        exclude: &[&str],
+    ) -> Result<usize, GhostMetaError> {
+        let mut count = 0;
+        for index in 0..self.names.len() {
+            let name = self.names[index].clone();
+            let Some(rest) = strip_prefix_ignore_case(&name, prefix) else {
+                continue;
+            };
+            if exclude.iter().any(|exclude| {
+                if exclude.ends_with('/') {
+                    strip_prefix_ignore_case(&name, exclude).is_some()
+                } else {
+                    name.eq_ignore_ascii_case(exclude)
+                }
+            }) {
+                continue;
+            }
+            let relative = safe_relative_path(rest).ok_or_else(|| GhostMetaError::InvalidNar {
+                reason: format!("アーカイブ外を指すエントリがあります: {name}"),
+            })?;
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+            let target = dest.join(&relative);
+            if name.ends_with('/') {
+                fs::create_dir_all(&target)?;
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut entry = self.zip.by_index(index)?;
+            io::copy(&mut entry, &mut File::create(&target)?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// ゴーストの .nar として `GhostMeta` 相当のメタデータを読む。
+    ///
+    /// - install.txt・`ghost/master/descript.txt` がなければエラー（type は検査しない）
+    /// - サムネイルはディレクトリ版（`resolve_thumbnail`）と同じフォールバックチェーンで解決する
+    pub fn read_ghost(&mut self) -> Result<NarGhostMeta, GhostMetaError> {
+        let install = self.read_install_txt()?;
+        let directory_name = install.directory.clone().unwrap_or_default();
+
+        let bytes = self.read_entry("ghost/master/descript.txt", TEXT_ENTRY_LIMIT)?;
+        let descript = Descript::from(parse_descript_bytes(&bytes));
+        let name = descript
+            .name
+            .clone()
+            .unwrap_or_else(|| directory_name.clone());
+
+        let shells = self.read_shells();
+        // descript.txt のない shell/master でも surface0 があれば使う（ディレクトリ版と同じ）
+        let thumbnail = match shells
+            .iter()
+            .find(|shell| shell.directory_name.eq_ignore_ascii_case("master"))
+        {
+            Some(master) => master.thumbnail.clone(),
+            None => self.resolve_shell_thumbnail("shell/master", &HashMap::new()),
+        }
+        .or_else(|| self.resolve_root_thumbnail());
+
+        Ok(NarGhostMeta {
+            install,
+            descript,
+            name,
+            directory_name,
+            thumbnail,
+            shells,
+        })
+    }
+
+    /// `shell/<name>/descript.txt` を持つシェルを列挙する（ディレクトリ名順、大文字小文字不問）
+    fn read_shells(&mut self) -> Vec<ShellMeta> {
+        let mut directories: Vec<String> = self
+            .names
+            .iter()
+            .filter_map(|entry| {
+                let rest = strip_prefix_ignore_case(entry, "shell/")?;
+                let (directory, file) = rest.split_once('/')?;
+                file.eq_ignore_ascii_case("descript.txt")
+                    .then(|| directory.to_string())
+            })
+            .collect();
+        directories.sort_by_key(|directory| directory.to_ascii_lowercase());
+        directories.dedup();
+
+        let mut shells = Vec::new();
+        for directory_name in directories {
+            let shell_dir = format!("shell/{directory_name}");
+            let Ok(bytes) = self.read_entry(&format!("{shell_dir}/descript.txt"), TEXT_ENTRY_LIMIT)
+            else {
+                continue;
+            };
+            let mut fields = parse_descript_bytes(&bytes);
+            let thumbnail = self.resolve_shell_thumbnail(&shell_dir, &fields);
+            shells.push(ShellMeta {
+                name: fields
+                    .remove("name")
+                    .unwrap_or_else(|| directory_name.clone()),
+                craftman: fields.remove("craftman"),
+                directory_name,
+                path: PathBuf::from(shell_dir),
+                thumbnail,
+            });
+        }
+        shells
+    }
+
+    /// シェルディレクトリ直下の surface0* を探す（`resolve_shell_thumbnail` のアーカイブ版）
+    fn resolve_shell_thumbnail(
+        &self,
+        shell_dir: &str,
+        fields: &HashMap<String, String>,
+    ) -> Option<ThumbnailInfo> {
+        let prefix = format!("{shell_dir}/");
+        let filenames = self.names.iter().filter_map(|entry| {
+            let filename = strip_prefix_ignore_case(entry, &prefix)?;
+            (!filename.is_empty() && !filename.contains('/')).then(|| filename.to_string())
+        });
+        let (filename, is_apng) = pick_surface0(filenames)?;
+
+        let alpha = seriko_alpha_from_fields(fields);
+        let path = format!("{shell_dir}/{filename}");
+        let mask = (!is_apng && alpha == AlphaMode::KeyColor)
+            .then(|| {
+                let stem = &path[..path.len() - ".png".len()];
+                self.find_entry(&format!("{stem}.pna"))
+            })
+            .flatten();
+        Some(ThumbnailInfo {
+            path: PathBuf::from(&path),
+            alpha: mask.map_or(alpha, |mask| AlphaMode::PnaMask(PathBuf::from(mask))),
+            kind: ThumbnailKind::Surface,
+        })
+    }
+
+    /// ルート直下の thumbnail.png（`resolve_root_thumbnail` のアーカイブ版）
+    fn resolve_root_thumbnail(&mut self) -> Option<ThumbnailInfo> {
+        let name = self.find_entry("thumbnail.png")?.to_string();
+        // アルファチャンネルの検査には先頭のヘッダーだけあればよいが、zip は部分展開できないので全体を読む
+        let alpha = self
+            .read_entry(&name, TEXT_ENTRY_LIMIT * 16)
+            .map_or(AlphaMode::KeyColor, |bytes| {
+                detect_thumbnail_alpha_bytes(&bytes)
+            });
+        Some(ThumbnailInfo {
+            path: PathBuf::from(name),
+            alpha,
+            kind: ThumbnailKind::Thumbnail,
+        })
+    }
+}
+
+/// .nar ファイルを開いてゴーストのメタデータを読む（`NarArchive::open` + `read_ghost`）
+pub fn read_nar(path: &Path) -> Result<NarGhostMeta, GhostMetaError> {
+    NarArchive::open(path)?.read_ghost()
+}
+
+/// zip のエントリ名をデコードする。UTF-8 として不正なら Shift_JIS（日本語環境で作られた .nar の既定）とみなす
+fn decode_entry_name(raw: &[u8]) -> String {
+    let name = match std::str::from_utf8(raw) {
+        Ok(name) => name.to_string(),
+        Err(_) => SHIFT_JIS.decode(raw).0.into_owned(),
+    };
+    name.replace('\\', "/")
+}
+
+/// install.txt の directory 系フィールドとして使える 1 階層のディレクトリ名か
+pub(crate) fn is_valid_directory_name(value: &str) -> bool {
+    !value.is_empty()
+        && value != "."
+        && value != ".."
+        && !value.contains(['/', '\\', ':', '\0'])
+}
+
+/// ASCII の大文字小文字を区別せずに接頭辞を取り除く
+pub(crate) fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = value.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &value[prefix.len()..])
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    /// (エントリ名, 内容) の組から zip のバイト列を作る
+    pub(crate) fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            if name.ends_with('/') {
+                writer
+                    .add_directory(*name, SimpleFileOptions::default())
+                    .unwrap();
+            } else {
+                writer
+                    .start_file(*name, SimpleFileOptions::default())
+                    .unwrap();
+                writer.write_all(content).unwrap();
+            }
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn open(entries: &[(&str, &[u8])]) -> NarArchive<Cursor<Vec<u8>>> {
+        NarArchive::new(Cursor::new(build_zip(entries))).unwrap()
+    }
+
+    #[test]
+    fn install_txt_の_type_directory_accept_を読み取る() {
+        let mut nar = open(&[(
+            "install.txt",
+            b"charset,UTF-8\ntype,supplement\ndirectory,sample\naccept,Sample\nx.custom,1\n",
+        )]);
+
+        let install = nar.read_install_txt().unwrap();
+        assert_eq!(install.kind.as_deref(), Some("supplement"));
+        assert_eq!(install.directory.as_deref(), Some("sample"));
+        assert_eq!(install.accept.as_deref(), Some("Sample"));
+        assert_eq!(install.extra.get("x.custom").map(String::as_str), Some("1"));
+        assert_eq!(
+            install.extra.get("charset").map(String::as_str),
+            Some("UTF-8")
+        );
+    }
+
+    #[test]
+    fn read_ghost_はアーカイブ内の_descript_とシェルとサムネイルを読む() {
+        // Shift_JIS の descript.txt（charset 宣言あり）
+        let (descript, _, _) =
+            SHIFT_JIS.encode("charset,Shift_JIS\r\nname,さくら\r\ncraftman,作者\r\n");
+        let mut nar = open(&[
+            ("install.txt", b"type,ghost\ndirectory,sakura\n"),
+            ("ghost/", b""),
+            ("ghost/master/descript.txt", &descript),
+            (
+                "shell/master/descript.txt",
+                "charset,UTF-8\nname,通常\n".as_bytes(),
+            ),
+            ("shell/master/surface0.png", b""),
+            ("shell/master/SURFACE0.pna", b""),
+            (
+                "shell/winter/descript.txt",
+                b"charset,UTF-8\nseriko.use_self_alpha,1\n",
+            ),
+            ("shell/winter/surface00.png", b""),
+            ("shell/winter/surface0.pna", b""),
+            ("shell/profile/readme.txt", b""),
+        ]);
+
+        let meta = nar.read_ghost().unwrap();
+        assert_eq!(meta.name, "さくら");
+        assert_eq!(meta.directory_name, "sakura");
+        assert_eq!(meta.install.kind.as_deref(), Some("ghost"));
+        assert_eq!(meta.descript.craftman.as_deref(), Some("作者"));
+        assert_eq!(meta.descript.charset.as_deref(), Some("Shift_JIS"));
+
+        let names: Vec<&str> = meta.shells.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["通常", "winter"]);
+        assert_eq!(meta.shells[1].path, PathBuf::from("shell/winter"));
+        let winter = meta.shells[1].thumbnail.as_ref().unwrap();
+        assert_eq!(winter.path, PathBuf::from("shell/winter/surface00.png"));
+        assert_eq!(winter.alpha, AlphaMode::SelfAlpha);
+
+        let thumbnail = meta.thumbnail.unwrap();
+        assert_eq!(thumbnail.path, PathBuf::from("shell/master/surface0.png"));
+        assert_eq!(
+            thumbnail.alpha,
+            AlphaMode::PnaMask(PathBuf::from("shell/master/SURFACE0.pna"))
+        );
+        assert_eq!(thumbnail.kind, ThumbnailKind::Surface);
+    }
+
+    #[test]
+    fn read_ghost_はシェルがなければ_thumbnail_png_を使う() {
+        let mut nar = open(&[
+            ("install.txt", b"type,ghost\ndirectory,plain\n"),
+            ("ghost/master/descript.txt", b"charset,UTF-8\n"),
+            ("Thumbnail.png", b""),
+        ]);
+
+        let meta = nar.read_ghost().unwrap();
+        assert_eq!(meta.name, "plain");
+        assert!(meta.shells.is_empty());
+        let thumbnail = meta.thumbnail.unwrap();
+        assert_eq!(thumbnail.path, PathBuf::from("Thumbnail.png"));
+        assert_eq!(thumbnail.kind, ThumbnailKind::Thumbnail);
+    }
+
+    #[test]
+    fn install_txt_や_descript_がなければ_invalid_nar_を返す() {
+        let mut nar = open(&[("ghost/master/descript.txt", b"name,a\n")]);
+        assert!(matches!(
+            nar.read_ghost(),
+            Err(GhostMetaError::InvalidNar { .. })
+        ));
+
+        let mut nar = open(&[("install.txt", b"type,balloon\ndirectory,b\n")]);
+        assert!(matches!(
+            nar.read_ghost(),
+            Err(GhostMetaError::InvalidNar { .. })
+        ));
+    }
+
+    #[test]
+    fn zip_でなければ_zip_エラーを返す() {
+        let result = NarArchive::new(Cursor::new(b"not a zip".to_vec()));
+        assert!(matches!(result, Err(GhostMetaError::Zip(_))));
+    }
+
+    #[test]
+    fn read_entry_は上限を超えるエントリを拒否する() {
+        let mut nar = open(&[("install.txt", &[b'a'; 64])]);
+        assert!(matches!(
+            nar.read_entry("install.txt", 16),
+            Err(GhostMetaError::InvalidNar { .. })
+        ));
+        assert_eq!(nar.read_entry("INSTALL.TXT", 64).unwrap().len(), 64);
+    }
+
+    #[test]
+    fn safe_relative_path_はアーカイブ外を指す名前を拒否する() {
+        assert_eq!(
+            safe_relative_path("ghost/./master/descript.txt"),
+            Some(PathBuf::from("ghost/master/descript.txt"))
+        );
+        assert!(safe_relative_path("../evil.txt").is_none());
+        assert!(safe_relative_path("ghost/../../evil.txt").is_none());
+        assert!(safe_relative_path("/etc/passwd").is_none());
+        assert!(safe_relative_path("C:/Windows/evil.dll").is_none());
+    }
+
+    #[test]
+    fn extract_は接頭辞を取り除いて展開し_exclude_を除く() {
+        let tmp = crate::testutil::TempDirGuard::new("ghost_meta_nar_extract");
+        let mut nar = open(&[
+            ("install.txt", b"type,ghost\n"),
+            ("ghost/master/descript.txt", b"name,a\n"),
+            ("Balloon/descript.txt", b"name,b\n"),
+        ]);
+
+        assert!(nar.validate_entry_paths().is_ok());
+        let count = nar.extract("", tmp.path(), &["balloon/", "INSTALL.TXT"]).unwrap();
+        assert_eq!(count, 1);
+        assert!(tmp.path().join("ghost/master/descript.txt").is_file());
+        assert!(!tmp.path().join("install.txt").exists());
+        assert!(!tmp.path().join("Balloon").exists());
+
+        let balloon = tmp.path().join("balloon_out");
+        assert_eq!(nar.extract("balloon/", &balloon, &[]).unwrap(), 1);
+        assert!(balloon.join("descript.txt").is_file());
+    }
+
+    #[test]
+    fn validate_entry_paths_は_zip_slip_エントリを検出する() {
+        let nar = open(&[("install.txt", b""), ("../outside.txt", b"x")]);
+        assert!(matches!(
+            nar.validate_entry_paths(),
+            Err(GhostMetaError::InvalidNar { .. })
+        ));
+    }
+
+    #[test]
+    fn shift_jis_のエントリ名をデコードする() {
+        let (raw, _, _) = SHIFT_JIS.encode("shell\\春\\descript.txt");
+        assert_eq!(decode_entry_name(&raw), "shell/春/descript.txt");
+        assert_eq!(
+            decode_entry_name("ghost/master/".as_bytes()),
+            "ghost/master/"
+        );
+    }
+}
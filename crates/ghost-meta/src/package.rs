@@ -0,0 +1,483 @@
+use crate::descript::{parse_descript_bytes, read_descript};
+use crate::lint::Severity;
+use crate::manifest::{
+    build_update_entries, format_updates2_dau, format_updates_txt, DeveloperOptions,
+    UpdateEntry, DEVELOPER_OPTIONS, UPDATES2_DAU, UPDATES_TXT,
+};
+use crate::nar::{is_valid_directory_name, InstallTxt};
+use crate::GhostMetaError;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// パッケージ前の検査で見つかった問題
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PackageIssue {
+    /// Error があると .nar を書き出さない
+    pub severity: Severity,
+    /// 問題のあるファイル（ルートからの相対パス）。ゴースト全体に対する問題では None
+    pub path: Option<String>,
+    pub message: String,
+}
+
+/// ゴーストフォルダから .nar を作る計画（`plan_package` の結果）
+#[derive(Debug, Clone)]
+pub struct PackagePlan {
+    /// ゴーストのルートディレクトリ
+    pub ghost_root: PathBuf,
+    /// アーカイブに入れるファイル（ルートからの相対パス、`/` 区切り、パス順）。マニフェストは含まない
+    pub files: Vec<String>,
+    /// developer_options.txt の nonar で除外したファイル
+    pub excluded: Vec<String>,
+    /// install.txt がないときに生成した内容。既存の install.txt を使う場合は None
+    pub generated_install_txt: Option<String>,
+    /// updates2.dau / updates.txt に載せるエントリ（noupdate・install.txt・マニフェスト自身を除く）
+    pub manifest: Vec<UpdateEntry>,
+    /// 検査で見つかった問題
+    pub issues: Vec<PackageIssue>,
+}
+
+impl PackagePlan {
+    /// Error の問題があれば true（.nar を書き出せない）
+    pub fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error)
+    }
+}
+
+/// ゴーストフォルダを検査して .nar の中身を決める。ファイルシステムには書き込まない。
+///
+/// - developer_options.txt の nonar 指定と developer_options.txt 自身はアーカイブから除外する
+/// - `ghost/master/descript.txt` の name がなければ Error
+/// - install.txt があれば type が ghost であること・directory が 1 階層のディレクトリ名であることを検査し、
+///   なければ descript.txt の name とフォルダ名から生成する
+/// - アーカイブに入れられないパス（`:` を含む名前・フォルダ外を指すシンボリックリンク）は Error
+pub fn plan_package(ghost_root: &Path) -> Result<PackagePlan, GhostMetaError> {
+    let options = DeveloperOptions::read(ghost_root)?;
+    let mut issues = Vec::new();
+    let mut all_files = Vec::new();
+    collect_files(ghost_root, ghost_root, "", &mut all_files, &mut issues)?;
+
+    let (excluded, files): (Vec<String>, Vec<String>) = all_files
+        .into_iter()
+        .filter(|path| !is_root_file(path, DEVELOPER_OPTIONS))
+        .partition(|path| options.is_nonar(path));
+
+    let name = check_descript(ghost_root, &mut issues);
+    let generated_install_txt = check_install_txt(ghost_root, &files, name, &mut issues);
+
+    let manifest_files: Vec<String> = files
+        .iter()
+        .filter(|path| {
+            !is_root_file(path, "install.txt")
+                && !is_root_file(path, UPDATES2_DAU)
+                && !is_root_file(path, UPDATES_TXT)
+                && !options.is_noupdate(path)
+        })
+        .cloned()
+        .collect();
+    let manifest = build_update_entries(ghost_root, &manifest_files)?;
+
+    Ok(PackagePlan {
+        ghost_root: ghost_root.to_path_buf(),
+        files: files
+            .into_iter()
+            .filter(|path| !is_root_file(path, UPDATES2_DAU) && !is_root_file(path, UPDATES_TXT))
+            .collect(),
+        excluded,
+        generated_install_txt,
+        manifest,
+        issues,
+    })
+}
+
+/// 計画どおりに .nar を書き出す。書き出したエントリ数を返す。
+///
+/// updates2.dau / updates.txt をゴーストフォルダに書き直してからアーカイブに入れる（nonar 指定なら入れない）。
+/// 計画に Error があるとき、output がゴーストフォルダの中にあるときは何も書き込まずにエラーを返す。
+/// output は一時ファイルに書いてから置き換えるため、失敗しても既存の .nar は壊れない
+pub fn write_package(plan: &PackagePlan, output: &Path) -> Result<usize, GhostMetaError> {
+    if let Some(issue) = plan
+        .issues
+        .iter()
+        .find(|issue| issue.severity == Severity::Error)
+    {
+        return Err(GhostMetaError::InvalidPackage {
+            reason: match &issue.path {
+                Some(path) => format!("{path}: {}", issue.message),
+                None => issue.message.clone(),
+            },
+        });
+    }
+    let output_dir = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if fs::canonicalize(output_dir)?.starts_with(fs::canonicalize(&plan.ghost_root)?) {
+        return Err(GhostMetaError::InvalidPackage {
+            reason: format!(
+                "出力先がゴーストフォルダの中にあります: {}",
+                output.display()
+            ),
+        });
+    }
+
+    fs::write(
+        plan.ghost_root.join(UPDATES2_DAU),
+        format_updates2_dau(&plan.manifest),
+    )?;
+    fs::write(
+        plan.ghost_root.join(UPDATES_TXT),
+        format_updates_txt(&plan.manifest),
+    )?;
+    let options = DeveloperOptions::read(&plan.ghost_root)?;
+
+    let mut temp_name = output.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+    let result = write_archive(plan, &options, &temp_path);
+    match result {
+        Ok(count) => {
+            fs::rename(&temp_path, output)?;
+            Ok(count)
+        }
+        Err(error) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(error)
+        }
+    }
+}
+
+fn write_archive(
+    plan: &PackagePlan,
+    options: &DeveloperOptions,
+    path: &Path,
+) -> Result<usize, GhostMetaError> {
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(path)?));
+    let file_options = SimpleFileOptions::default();
+    let mut count = 0;
+
+    if let Some(install_txt) = &plan.generated_install_txt {
+        writer.start_file("install.txt", file_options)?;
+        writer.write_all(install_txt.as_bytes())?;
+        count += 1;
+    }
+    let manifests = [UPDATES2_DAU, UPDATES_TXT]
+        .into_iter()
+        .filter(|manifest| !options.is_nonar(manifest))
+        .map(str::to_string);
+    for relative in plan.files.iter().cloned().chain(manifests) {
+        writer.start_file(relative.as_str(), file_options)?;
+        io::copy(
+            &mut File::open(plan.ghost_root.join(&relative))?,
+            &mut writer,
+        )?;
+        count += 1;
+    }
+    writer.finish()?.flush()?;
+    Ok(count)
+}
+
+/// ルート直下の name（大文字小文字不問）か
+fn is_root_file(path: &str, name: &str) -> bool {
+    path.eq_ignore_ascii_case(name)
+}
+
+/// dir 配下のファイルを再帰的に集める（ルートからの相対パス、`/` 区切り、パス順）
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    relative_dir: &str,
+    files: &mut Vec<String>,
+    issues: &mut Vec<PackageIssue>,
+) -> Result<(), GhostMetaError> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.collect::<Result<_, _>>()?;
+    // OS 依存の列挙順を回避するためソートする
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let file_name = entry.file_name();
+        let display_name = file_name.to_string_lossy();
+        let relative = if relative_dir.is_empty() {
+            display_name.to_string()
+        } else {
+            format!("{relative_dir}/{display_name}")
+        };
+        let error = |message: &str| PackageIssue {
+            severity: Severity::Error,
+            path: Some(relative.clone()),
+            message: message.to_string(),
+        };
+
+        let Some(name) = file_name.to_str() else {
+            issues.push(error("ファイル名を UTF-8 で表せません"));
+            continue;
+        };
+        if name.contains([':', '\\']) {
+            issues.push(error("アーカイブに入れられないファイル名です（`:` や `\\` を含む）"));
+            continue;
+        }
+
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() {
+            let inside = fs::canonicalize(&path)
+                .and_then(|target| Ok(target.starts_with(fs::canonicalize(root)?)))
+                .unwrap_or(false);
+            if !inside {
+                issues.push(error("ゴーストフォルダの外を指すシンボリックリンクです"));
+                continue;
+            }
+            if path.is_dir() {
+                // 循環を避けるため、フォルダ内を指すディレクトリのリンクは辿らない
+                issues.push(PackageIssue {
+                    severity: Severity::Warning,
+                    path: Some(relative),
+                    message: "ディレクトリへのシンボリックリンクは含めません".to_string(),
+                });
+                continue;
+            }
+        }
+
+        if file_type.is_dir() {
+            collect_files(root, &path, &relative, files, issues)?;
+        } else {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// `ghost/master/descript.txt` を検査して name を返す
+fn check_descript(ghost_root: &Path, issues: &mut Vec<PackageIssue>) -> Option<String> {
+    let descript_path = "ghost/master/descript.txt";
+    let name = match read_descript(&ghost_root.join(descript_path)) {
+        Ok(descript) => descript.name.filter(|name| !name.is_empty()),
+        Err(_) => {
+            issues.push(PackageIssue {
+                severity: Severity::Error,
+                path: Some(descript_path.to_string()),
+                message: "descript.txt を読み込めません".to_string(),
+            });
+            return None;
+        }
+    };
+    if name.is_none() {
+        issues.push(PackageIssue {
+            severity: Severity::Error,
+            path: Some(descript_path.to_string()),
+            message: "descript.txt に name がありません".to_string(),
+        });
+    }
+    name
+}
+
+/// install.txt を検査する。ルートになければ生成した内容を返す
+fn check_install_txt(
+    ghost_root: &Path,
+    files: &[String],
+    name: Option<String>,
+    issues: &mut Vec<PackageIssue>,
+) -> Option<String> {
+    let existing = files.iter().find(|path| is_root_file(path, "install.txt"));
+    let Some(existing) = existing else {
+        let directory = ghost_root
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+            .to_string();
+        if !is_valid_directory_name(&directory) {
+            issues.push(PackageIssue {
+                severity: Severity::Error,
+                path: None,
+                message: format!(
+                    "install.txt の directory をフォルダ名から決められません: {directory}"
+                ),
+            });
+            return None;
+        }
+        issues.push(PackageIssue {
+            severity: Severity::Warning,
+            path: Some("install.txt".to_string()),
+            message: format!("install.txt がないため生成します（directory,{directory}）"),
+        });
+        let mut content = String::from("charset,UTF-8\r\ntype,ghost\r\n");
+        if let Some(name) = name {
+            content.push_str(&format!("name,{name}\r\n"));
+        }
+        content.push_str(&format!("directory,{directory}\r\n"));
+        return Some(content);
+    };
+
+    let mut issue = |severity: Severity, message: String| {
+        issues.push(PackageIssue {
+            severity,
+            path: Some(existing.clone()),
+            message,
+        })
+    };
+    let install = match fs::read(ghost_root.join(existing)) {
+        Ok(bytes) => InstallTxt::from(parse_descript_bytes(&bytes)),
+        Err(error) => {
+            issue(
+                Severity::Error,
+                format!("install.txt を読み込めません: {error}"),
+            );
+            return None;
+        }
+    };
+    match install.kind.as_deref() {
+        Some(kind) if kind.eq_ignore_ascii_case("ghost") => {}
+        Some(kind) => issue(
+            Severity::Error,
+            format!("install.txt の type が ghost ではありません: {kind}"),
+        ),
+        None => issue(
+            Severity::Error,
+            "install.txt に type がありません".to_string(),
+        ),
+    }
+    match install.directory.as_deref().filter(|d| !d.is_empty()) {
+        Some(directory) if is_valid_directory_name(directory) => {}
+        Some(directory) => issue(
+            Severity::Error,
+            format!("install.txt の directory が不正です（絶対パス・`..`・区切り文字を含む）: {directory}"),
+        ),
+        None => issue(
+            Severity::Error,
+            "install.txt に directory がありません".to_string(),
+        ),
+    }
+    if install.name.as_deref().is_none_or(str::is_empty) {
+        issue(
+            Severity::Warning,
+            "install.txt に name がありません".to_string(),
+        );
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nar::NarArchive;
+    use crate::testutil::TempDirGuard;
+
+    fn create_ghost(root: &Path, install_txt: Option<&str>) -> PathBuf {
+        let ghost_root = root.join("sakura");
+        fs::create_dir_all(ghost_root.join("ghost/master/profile")).unwrap();
+        fs::create_dir_all(ghost_root.join("shell/master")).unwrap();
+        fs::write(
+            ghost_root.join("ghost/master/descript.txt"),
+            "charset,UTF-8\r\nname,さくら\r\n",
+        )
+        .unwrap();
+        fs::write(ghost_root.join("ghost/master/profile/save.dat"), "save").unwrap();
+        fs::write(ghost_root.join("ghost/master/debug.log"), "log").unwrap();
+        fs::write(ghost_root.join("shell/master/surface0.png"), "png").unwrap();
+        fs::write(
+            ghost_root.join(DEVELOPER_OPTIONS),
+            "ghost/master/profile/,nonar,noupdate\r\n*.log,nonar\r\nshell/master/surface0.png,noupdate\r\n",
+        )
+        .unwrap();
+        if let Some(install_txt) = install_txt {
+            fs::write(ghost_root.join("install.txt"), install_txt).unwrap();
+        }
+        ghost_root
+    }
+
+    #[test]
+    fn nonar_を除外し_install_txt_を生成してマニフェストを書き直す() {
+        let tmp = TempDirGuard::new("ghost_meta_package_generate");
+        let ghost_root = create_ghost(tmp.path(), None);
+
+        let plan = plan_package(&ghost_root).unwrap();
+        assert!(!plan.has_errors());
+        assert_eq!(
+            plan.files,
+            vec!["ghost/master/descript.txt", "shell/master/surface0.png"]
+        );
+        assert_eq!(
+            plan.excluded,
+            vec!["ghost/master/debug.log", "ghost/master/profile/save.dat"]
+        );
+        let install_txt = plan.generated_install_txt.as_deref().unwrap();
+        assert!(install_txt.contains("name,さくら\r\n"));
+        assert!(install_txt.contains("directory,sakura\r\n"));
+        let manifest: Vec<&str> = plan.manifest.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(manifest, vec!["ghost/master/descript.txt"]);
+
+        let output = tmp.path().join("sakura.nar");
+        assert_eq!(write_package(&plan, &output).unwrap(), 5);
+        let dau = fs::read_to_string(ghost_root.join(UPDATES2_DAU)).unwrap();
+        assert!(dau.contains("ghost/master/descript.txt\x01"));
+        assert!(!dau.contains("surface0.png"));
+
+        let mut archive = NarArchive::open(&output).unwrap();
+        let mut names = archive.entry_names().to_vec();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "ghost/master/descript.txt",
+                "install.txt",
+                "shell/master/surface0.png",
+                "updates.txt",
+                "updates2.dau",
+            ]
+        );
+        let meta = archive.read_ghost().unwrap();
+        assert_eq!(meta.name, "さくら");
+        assert_eq!(meta.directory_name, "sakura");
+    }
+
+    #[test]
+    fn name_や_directory_がなければ書き出す前にエラーを報告する() {
+        let tmp = TempDirGuard::new("ghost_meta_package_errors");
+        let ghost_root = create_ghost(tmp.path(), Some("charset,UTF-8\r\ntype,ghost\r\nname,さくら\r\n"));
+        fs::write(ghost_root.join("ghost/master/descript.txt"), "charset,UTF-8\r\n").unwrap();
+
+        let plan = plan_package(&ghost_root).unwrap();
+        assert!(plan.has_errors());
+        let messages: Vec<&str> = plan.issues.iter().map(|i| i.message.as_str()).collect();
+        assert!(messages.contains(&"descript.txt に name がありません"));
+        assert!(messages.contains(&"install.txt に directory がありません"));
+
+        let output = tmp.path().join("sakura.nar");
+        assert!(matches!(
+            write_package(&plan, &output),
+            Err(GhostMetaError::InvalidPackage { .. })
+        ));
+        assert!(!output.exists());
+        assert!(!ghost_root.join(UPDATES2_DAU).exists());
+    }
+
+    #[test]
+    fn 既存の_install_txt_の不正な_directory_と出力先の位置を検査する() {
+        let tmp = TempDirGuard::new("ghost_meta_package_install_txt");
+        let ghost_root = create_ghost(
+            tmp.path(),
+            Some("charset,UTF-8\r\ntype,ghost\r\nname,さくら\r\ndirectory,C:\\ghost\\sakura\r\n"),
+        );
+        let plan = plan_package(&ghost_root).unwrap();
+        assert!(plan.has_errors());
+        assert!(plan.generated_install_txt.is_none());
+
+        fs::write(
+            ghost_root.join("install.txt"),
+            "charset,UTF-8\r\ntype,ghost\r\nname,さくら\r\ndirectory,sakura\r\n",
+        )
+        .unwrap();
+        let plan = plan_package(&ghost_root).unwrap();
+        assert!(!plan.has_errors());
+        assert!(plan.files.contains(&"install.txt".to_string()));
+        assert!(matches!(
+            write_package(&plan, &ghost_root.join("sakura.nar")),
+            Err(GhostMetaError::InvalidPackage { .. })
+        ));
+    }
+}
@@ -0,0 +1,18 @@
+use std::path::PathBuf;
+
+/// `/` 区切りの相対パス（.nar のエントリ名・マニフェストのパスなど）を基準ディレクトリからの相対パスに変換する。
+/// 絶対パス・ドライブ指定（`:` を含む）・`..` を含む名前は None（zip-slip 対策）
+pub(crate) fn safe_relative_path(name: &str) -> Option<PathBuf> {
+    if name.starts_with('/') || name.contains(':') || name.contains('\0') {
+        return None;
+    }
+    let mut path = PathBuf::new();
+    for component in name.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => return None,
+            component => path.push(component),
+        }
+    }
+    Some(path)
+}
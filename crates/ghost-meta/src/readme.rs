@@ -0,0 +1,164 @@
+use crate::descript::{decode_descript_bytes, parse_descript};
+use crate::path_utils::safe_relative_path;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// descript.txt に readme の指定がない場合に探すファイル名（大文字小文字は区別しない）
+const CONVENTIONAL_NAMES: [&str; 4] = ["readme.txt", "read me.txt", "readme.md", "readme"];
+
+/// ゴーストの readme の内容
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Readme {
+    /// readme ファイルの絶対パス
+    pub path: PathBuf,
+    /// デコードしたテキスト
+    pub text: String,
+    /// デコードに使った文字コード名（"Shift_JIS" / "UTF-8" など）
+    pub encoding: String,
+    /// text に含まれる http / https の URL（出現順、重複なし）
+    pub urls: Vec<String>,
+}
+
+/// ゴーストの readme ファイルを探す。
+///
+/// 1. `ghost/master/descript.txt` の readme フィールド（ゴーストのルートからの相対パス。ルートの外を指す指定は無視する）
+/// 2. ゴーストのルート直下の readme.txt など（大文字小文字を区別しない）
+///
+/// 見つからなければ None
+pub fn find_readme(ghost_root: &Path) -> Option<PathBuf> {
+    let declared = parse_descript(&ghost_root.join("ghost").join("master").join("descript.txt"))
+        .ok()
+        .and_then(|mut fields| fields.remove("readme"))
+        .and_then(|readme| relative_readme_path(&readme))
+        .map(|relative| ghost_root.join(relative))
+        .filter(|path| path.is_file());
+    if declared.is_some() {
+        return declared;
+    }
+
+    let entries: Vec<PathBuf> = fs::read_dir(ghost_root)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    CONVENTIONAL_NAMES.iter().find_map(|name| {
+        entries
+            .iter()
+            .find(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.eq_ignore_ascii_case(name))
+            })
+            .cloned()
+    })
+}
+
+/// readme のバイト列をデコードする。文字コードの判定規則は descript.txt と同じ（BOM → charset 行 → 推定）
+pub fn parse_readme(path: &Path, bytes: &[u8]) -> Readme {
+    let decoded = decode_descript_bytes(bytes);
+    let urls = detect_urls(&decoded.text);
+    Readme {
+        path: path.to_path_buf(),
+        text: decoded.text,
+        encoding: decoded.encoding.name().to_string(),
+        urls,
+    }
+}
+
+/// テキストから http / https の URL を抜き出す（出現順、重複なし）。
+/// 空白・全角文字・括弧や引用符で URL の終わりとみなし、末尾の句読点は含めない
+pub fn detect_urls(text: &str) -> Vec<String> {
+    // ASCII の小文字化はバイト位置を変えないため、lower で探した位置を text にそのまま使える
+    let lower = text.to_ascii_lowercase();
+    let mut urls: Vec<String> = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = find_url_start(&lower[offset..]) {
+        let candidate = &text[offset + start..];
+        let end = candidate
+            .find(|c: char| {
+                !c.is_ascii_graphic() || matches!(c, '<' | '>' | '"' | '\'' | '(' | ')' | '[' | ']')
+            })
+            .unwrap_or(candidate.len());
+        let url = candidate[..end].trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        if url.len() > "https://".len() && !urls.iter().any(|known| known == url) {
+            urls.push(url.to_string());
+        }
+        offset += start + end;
+    }
+    urls
+}
+
+/// "http://" / "https://" の開始位置（lower は小文字化済み）
+fn find_url_start(lower: &str) -> Option<usize> {
+    [lower.find("http://"), lower.find("https://")]
+        .into_iter()
+        .flatten()
+        .min()
+}
+
+/// readme フィールドの値をゴーストのルートからの相対パスにする。絶対パス・`..` を含む指定は None
+fn relative_readme_path(value: &str) -> Option<PathBuf> {
+    safe_relative_path(&value.trim().replace('\\', "/"))
+        .filter(|path| path.components().next().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+
+    fn create_ghost(root: &Path, descript: &str) -> PathBuf {
+        let ghost_root = root.join("sakura");
+        fs::create_dir_all(ghost_root.join("ghost").join("master")).unwrap();
+        fs::write(
+            ghost_root.join("ghost").join("master").join("descript.txt"),
+            descript,
+        )
+        .unwrap();
+        ghost_root
+    }
+
+    #[test]
+    fn descript_の_readme_指定を優先する() {
+        let tmp = TempDirGuard::new("ghost_meta_readme_declared");
+        let ghost_root = create_ghost(tmp.path(), "name,test\r\nreadme,doc\\terms.txt\r\n");
+        fs::create_dir_all(ghost_root.join("doc")).unwrap();
+        fs::write(ghost_root.join("doc").join("terms.txt"), "terms").unwrap();
+        fs::write(ghost_root.join("readme.txt"), "readme").unwrap();
+
+        assert_eq!(
+            find_readme(&ghost_root),
+            Some(ghost_root.join("doc").join("terms.txt"))
+        );
+    }
+
+    #[test]
+    fn readme_指定がなければ慣例のファイル名を探す() {
+        let tmp = TempDirGuard::new("ghost_meta_readme_conventional");
+        let ghost_root = create_ghost(tmp.path(), "name,test\r\nreadme,../outside.txt\r\n");
+        fs::write(tmp.path().join("outside.txt"), "outside").unwrap();
+        assert_eq!(find_readme(&ghost_root), None);
+
+        fs::write(ghost_root.join("ReadMe.TXT"), "readme").unwrap();
+        assert_eq!(
+            find_readme(&ghost_root),
+            Some(ghost_root.join("ReadMe.TXT"))
+        );
+    }
+
+    #[test]
+    fn shift_jis_の_readme_をデコードして_url_を抜き出す() {
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS
+            .encode("利用規約はこちら（https://example.com/terms.html）。\r\n配布元: http://example.jp/ghost/, https://example.com/terms.html\r\n");
+        let readme = parse_readme(Path::new("readme.txt"), &bytes);
+
+        assert_eq!(readme.encoding, "Shift_JIS");
+        assert!(readme.text.starts_with("利用規約はこちら"));
+        assert_eq!(
+            readme.urls,
+            vec!["https://example.com/terms.html", "http://example.jp/ghost/"]
+        );
+    }
+}
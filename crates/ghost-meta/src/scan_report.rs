@@ -0,0 +1,214 @@
+use crate::ghost::{read_ghost_checked, GhostMeta};
+use crate::GhostMetaError;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// ゴーストとして一覧に載らなかった（または壊れていた）理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ScanSkipReason {
+    /// `ghost/master/descript.txt` がない
+    NoDescript,
+    /// フォルダ・descript.txt を読み取れない（権限以外の I/O エラー）
+    Unreadable,
+    /// フォルダ名が UTF-8 として解釈できない
+    NonUtf8Name,
+    /// descript.txt に文字コードとして不正なバイト列がある（ゴースト自体は置換文字入りで一覧に載る）
+    DecodeFailure,
+    /// アクセス権がない
+    PermissionDenied,
+}
+
+impl ScanSkipReason {
+    /// DB・レポートで使う名前（"no_descript" / "unreadable" / "non_utf8_name" / "decode_failure" / "permission_denied"）
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanSkipReason::NoDescript => "no_descript",
+            ScanSkipReason::Unreadable => "unreadable",
+            ScanSkipReason::NonUtf8Name => "non_utf8_name",
+            ScanSkipReason::DecodeFailure => "decode_failure",
+            ScanSkipReason::PermissionDenied => "permission_denied",
+        }
+    }
+
+    /// I/O エラーの種類から理由を決める（PermissionDenied 以外は Unreadable）
+    pub fn from_io_error(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => ScanSkipReason::PermissionDenied,
+            _ => ScanSkipReason::Unreadable,
+        }
+    }
+}
+
+/// スキャンレポートの 1 件
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanSkip {
+    /// 対象フォルダの絶対パス
+    pub path: PathBuf,
+    pub reason: ScanSkipReason,
+    /// 元のエラーメッセージなどの補足（表示用）
+    pub detail: String,
+}
+
+impl ScanSkip {
+    pub fn new(path: &Path, reason: ScanSkipReason, detail: impl Into<String>) -> Self {
+        ScanSkip {
+            path: path.to_path_buf(),
+            reason,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// `scan_ghosts_with_report` の結果
+pub struct GhostScanReport {
+    pub ghosts: Vec<GhostMeta>,
+    /// 読み込めなかった・壊れていたフォルダ（走査順）
+    pub skipped: Vec<ScanSkip>,
+}
+
+/// ゴーストフォルダ 1 件を読み込み、一覧に載らない・壊れている場合はその理由を返す。
+///
+/// - フォルダ名が UTF-8 でない → `NonUtf8Name`（読み込まない）
+/// - descript.txt がない → `NoDescript`、読めない → `PermissionDenied` / `Unreadable`
+/// - descript.txt に不正なバイト列がある → ゴーストと `DecodeFailure` の両方を返す
+pub fn inspect_ghost(ghost_root: &Path) -> (Option<GhostMeta>, Option<ScanSkip>) {
+    if ghost_root.file_name().and_then(|n| n.to_str()).is_none() {
+        return (
+            None,
+            Some(ScanSkip::new(
+                ghost_root,
+                ScanSkipReason::NonUtf8Name,
+                "フォルダ名を UTF-8 として解釈できません",
+            )),
+        );
+    }
+
+    match read_ghost_checked(ghost_root) {
+        Ok((meta, false)) => (Some(meta), None),
+        Ok((meta, true)) => (
+            Some(meta),
+            Some(ScanSkip::new(
+                ghost_root,
+                ScanSkipReason::DecodeFailure,
+                "descript.txt に文字コードとして不正なバイト列があります（置換文字で読み込みました）",
+            )),
+        ),
+        Err(GhostMetaError::Io(error)) if error.kind() == io::ErrorKind::NotFound => (
+            None,
+            Some(ScanSkip::new(
+                ghost_root,
+                ScanSkipReason::NoDescript,
+                "ghost/master/descript.txt がありません",
+            )),
+        ),
+        Err(GhostMetaError::Io(error)) => (
+            None,
+            Some(ScanSkip::new(
+                ghost_root,
+                ScanSkipReason::from_io_error(&error),
+                format!("descript.txt を読み込めません: {error}"),
+            )),
+        ),
+        Err(error) => (
+            None,
+            Some(ScanSkip::new(
+                ghost_root,
+                ScanSkipReason::Unreadable,
+                error.to_string(),
+            )),
+        ),
+    }
+}
+
+/// parent_dir 直下のゴーストを走査し、読み込めなかったフォルダの理由とともに返す。
+/// parent_dir の read_dir に失敗した場合はエラーを返す。
+pub fn scan_ghosts_with_report(parent_dir: &Path) -> Result<GhostScanReport, GhostMetaError> {
+    let mut report = GhostScanReport {
+        ghosts: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    for entry in fs::read_dir(parent_dir)? {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(error) => {
+                report.skipped.push(ScanSkip::new(
+                    parent_dir,
+                    ScanSkipReason::from_io_error(&error),
+                    format!("フォルダの一覧を読み取れません: {error}"),
+                ));
+                continue;
+            }
+        };
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let (meta, skip) = inspect_ghost(&path);
+        report.ghosts.extend(meta);
+        report.skipped.extend(skip);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+
+    fn create_ghost(root: &Path, dir_name: &str, descript: &[u8]) {
+        let base = root.join(dir_name).join("ghost").join("master");
+        fs::create_dir_all(&base).unwrap();
+        fs::write(base.join("descript.txt"), descript).unwrap();
+    }
+
+    #[test]
+    fn 読み込めなかったフォルダを理由つきで返す() {
+        let tmp = TempDirGuard::new("ghost_meta_scan_report");
+        create_ghost(tmp.path(), "ok", b"charset,UTF-8\nname,ok\n");
+        // UTF-8 宣言なのに Shift_JIS のバイト列
+        create_ghost(tmp.path(), "broken", b"charset,UTF-8\nname,\x82\xa0\x82\xa2\n");
+        fs::create_dir_all(tmp.path().join("no_descript").join("ghost")).unwrap();
+        fs::write(tmp.path().join("file.txt"), "").unwrap();
+
+        let mut report = scan_ghosts_with_report(tmp.path()).unwrap();
+        report.ghosts.sort_by(|a, b| a.directory_name.cmp(&b.directory_name));
+        report.skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let names: Vec<&str> = report.ghosts.iter().map(|g| g.directory_name.as_str()).collect();
+        assert_eq!(names, vec!["broken", "ok"]);
+        let reasons: Vec<(PathBuf, ScanSkipReason)> = report
+            .skipped
+            .iter()
+            .map(|skip| (skip.path.clone(), skip.reason))
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                (tmp.path().join("broken"), ScanSkipReason::DecodeFailure),
+                (tmp.path().join("no_descript"), ScanSkipReason::NoDescript),
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn utf8_でないフォルダ名は読み込まない() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let tmp = TempDirGuard::new("ghost_meta_scan_report_non_utf8");
+        let name = OsStr::from_bytes(b"ghost_\xff");
+        create_ghost(tmp.path(), "placeholder", b"name,x\n");
+        fs::rename(tmp.path().join("placeholder"), tmp.path().join(name)).unwrap();
+
+        let (meta, skip) = inspect_ghost(&tmp.path().join(name));
+        assert!(meta.is_none());
+        assert_eq!(skip.unwrap().reason, ScanSkipReason::NonUtf8Name);
+    }
+}
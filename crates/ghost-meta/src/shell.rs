@@ -0,0 +1,121 @@
+use crate::descript::parse_descript;
+use crate::thumbnail::{resolve_shell_thumbnail, ThumbnailInfo};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// シェル（`shell/<name>/`）のメタデータ
+#[derive(Debug, Clone)]
+pub struct ShellMeta {
+    /// シェルの descript.txt の name フィールド。未設定の場合はディレクトリ名
+    pub name: String,
+    /// シェルの descript.txt の craftman フィールド。未設定の場合は None
+    pub craftman: Option<String>,
+    /// シェルのディレクトリ名（"master" など）
+    pub directory_name: String,
+    /// シェルディレクトリの絶対パス
+    pub path: PathBuf,
+    /// シェルの surface0* 画像。見つからない場合は None
+    pub thumbnail: Option<ThumbnailInfo>,
+}
+
+/// ゴーストの `shell/` 配下にあるシェルをすべて列挙する。
+/// descript.txt を持つディレクトリのみをシェルとして扱い、ディレクトリ名の辞書順（大文字小文字不問）で返す。
+/// `shell/` が存在しない・読めない場合は空の Vec を返す。
+pub fn scan_shells(ghost_root: &Path) -> Vec<ShellMeta> {
+    let Ok(entries) = fs::read_dir(ghost_root.join("shell")) else {
+        return Vec::new();
+    };
+
+    let mut shells: Vec<ShellMeta> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|path| read_shell(&path))
+        .collect();
+    // OS 依存の列挙順を回避するためソートする
+    shells.sort_by_key(|shell| shell.directory_name.to_ascii_lowercase());
+    shells
+}
+
+/// シェルディレクトリ 1 件のメタデータを読む。descript.txt が読めなければ None
+fn read_shell(shell_dir: &Path) -> Option<ShellMeta> {
+    let mut fields = parse_descript(&shell_dir.join("descript.txt")).ok()?;
+    let directory_name = shell_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_string();
+    let thumbnail = resolve_shell_thumbnail(shell_dir, Some(&fields));
+
+    Some(ShellMeta {
+        name: fields
+            .remove("name")
+            .unwrap_or_else(|| directory_name.clone()),
+        craftman: fields.remove("craftman"),
+        directory_name,
+        path: shell_dir.to_path_buf(),
+        thumbnail,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+    use crate::AlphaMode;
+
+    fn create_shell(ghost_root: &Path, dir_name: &str, descript: Option<&str>) -> PathBuf {
+        let shell_dir = ghost_root.join("shell").join(dir_name);
+        fs::create_dir_all(&shell_dir).unwrap();
+        if let Some(descript) = descript {
+            fs::write(shell_dir.join("descript.txt"), descript).unwrap();
+        }
+        shell_dir
+    }
+
+    #[test]
+    fn scan_shells_が全シェルをディレクトリ名順に返す() {
+        let tmp = TempDirGuard::new("ghost_meta_scan_shells");
+        create_shell(
+            tmp.path(),
+            "master",
+            Some("charset,UTF-8\nname,通常シェル\ncraftman,作者\n"),
+        );
+        let summer = create_shell(
+            tmp.path(),
+            "Summer",
+            Some("charset,UTF-8\nname,夏服\nseriko.use_self_alpha,1\n"),
+        );
+        fs::write(summer.join("surface0.png"), "").unwrap();
+        create_shell(tmp.path(), "autumn", Some("charset,UTF-8\n"));
+
+        let shells = scan_shells(tmp.path());
+        let names: Vec<&str> = shells.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["autumn", "通常シェル", "夏服"]);
+
+        assert_eq!(shells[1].craftman.as_deref(), Some("作者"));
+        assert!(shells[1].thumbnail.is_none());
+
+        let thumbnail = shells[2].thumbnail.as_ref().unwrap();
+        assert_eq!(thumbnail.path, summer.join("surface0.png"));
+        assert_eq!(thumbnail.alpha, AlphaMode::SelfAlpha);
+    }
+
+    #[test]
+    fn scan_shells_は_descript_のないディレクトリとファイルを無視する() {
+        let tmp = TempDirGuard::new("ghost_meta_scan_shells_skip");
+        create_shell(tmp.path(), "master", Some("charset,UTF-8\nname,master\n"));
+        create_shell(tmp.path(), "profile", None);
+        fs::write(tmp.path().join("shell").join("readme.txt"), "").unwrap();
+
+        let shells = scan_shells(tmp.path());
+        assert_eq!(shells.len(), 1);
+        assert_eq!(shells[0].directory_name, "master");
+    }
+
+    #[test]
+    fn scan_shells_は_shell_ディレクトリがなければ空を返す() {
+        let tmp = TempDirGuard::new("ghost_meta_scan_shells_none");
+        assert!(scan_shells(tmp.path()).is_empty());
+    }
+}
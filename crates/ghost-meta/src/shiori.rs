@@ -0,0 +1,147 @@
+use std::fs;
+use std::path::Path;
+
+/// ゴーストが使う SHIORI（栞）の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ShioriEngine {
+    /// YAYA（文 互換の後継）
+    Yaya,
+    /// 文（AYA）
+    Aya,
+    /// 里々（Satori）
+    Satori,
+    /// 華和梨（Kawari）
+    Kawari,
+    /// 美坂（Misaka）
+    Misaka,
+    /// 篝火（Kagari）
+    Kagari,
+    /// 判定できない
+    Unknown,
+}
+
+impl ShioriEngine {
+    /// DB・フィルタで使う名前（"yaya" / "aya" / "satori" / "kawari" / "misaka" / "kagari" / "unknown"）
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShioriEngine::Yaya => "yaya",
+            ShioriEngine::Aya => "aya",
+            ShioriEngine::Satori => "satori",
+            ShioriEngine::Kawari => "kawari",
+            ShioriEngine::Misaka => "misaka",
+            ShioriEngine::Kagari => "kagari",
+            ShioriEngine::Unknown => "unknown",
+        }
+    }
+}
+
+/// SHIORI ごとの DLL 名（小文字）
+const ENGINE_DLLS: [(&str, ShioriEngine); 9] = [
+    ("yaya.dll", ShioriEngine::Yaya),
+    ("aya5.dll", ShioriEngine::Aya),
+    ("aya.dll", ShioriEngine::Aya),
+    ("satori.dll", ShioriEngine::Satori),
+    ("kawari.dll", ShioriEngine::Kawari),
+    ("kawari7.dll", ShioriEngine::Kawari),
+    ("kawarirc.dll", ShioriEngine::Kawari),
+    ("misaka.dll", ShioriEngine::Misaka),
+    ("kagari.dll", ShioriEngine::Kagari),
+];
+
+/// SHIORI ごとの設定ファイル・辞書ファイル名（小文字）。上から順に判定する
+/// （篝火は華和梨の派生で kawarirc.kis も持つため、篝火を先に見る。YAYA は aya.txt を読む場合もあるため yaya.txt を先に見る）
+const ENGINE_FILES: [(&str, ShioriEngine); 9] = [
+    ("kagari.ini", ShioriEngine::Kagari),
+    ("kagarirc.kis", ShioriEngine::Kagari),
+    ("kawarirc.kis", ShioriEngine::Kawari),
+    ("kawari.ini", ShioriEngine::Kawari),
+    ("misaka.ini", ShioriEngine::Misaka),
+    ("satori_conf.txt", ShioriEngine::Satori),
+    ("replace_after.txt", ShioriEngine::Satori),
+    ("yaya.txt", ShioriEngine::Yaya),
+    ("aya.txt", ShioriEngine::Aya),
+];
+
+/// ゴーストの SHIORI を判定する。
+///
+/// 1. descript.txt の shiori フィールドの DLL 名（`shiori.dll` のような汎用名は判定に使わない）
+/// 2. `ghost/master` にある DLL
+/// 3. `ghost/master` にある設定ファイル・辞書ファイル（kawarirc.kis・satori_conf.txt・yaya.txt など）
+///
+/// ファイル名は大文字小文字を区別しない。どれにも当てはまらなければ `Unknown`
+pub fn detect_shiori(ghost_master: &Path, shiori_field: Option<&str>) -> ShioriEngine {
+    if let Some(engine) = shiori_field.and_then(engine_from_dll) {
+        return engine;
+    }
+
+    let file_names: Vec<String> = fs::read_dir(ghost_master)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+                .filter_map(|entry| entry.file_name().to_str().map(str::to_ascii_lowercase))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ENGINE_DLLS
+        .iter()
+        .chain(ENGINE_FILES.iter())
+        .find(|(name, _)| file_names.iter().any(|file_name| file_name == name))
+        .map_or(ShioriEngine::Unknown, |(_, engine)| *engine)
+}
+
+/// shiori フィールドの値（パス区切りを含む場合はファイル名部分）から SHIORI を引く
+fn engine_from_dll(value: &str) -> Option<ShioriEngine> {
+    let file_name = value.trim().rsplit(['/', '\\']).next()?.to_ascii_lowercase();
+    ENGINE_DLLS
+        .iter()
+        .find(|(name, _)| *name == file_name)
+        .map(|(_, engine)| *engine)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+
+    #[test]
+    fn shiori_フィールドの_dll_名を優先する() {
+        let tmp = TempDirGuard::new("ghost_meta_shiori_field");
+        fs::write(tmp.path().join("kawarirc.kis"), "").unwrap();
+
+        assert_eq!(detect_shiori(tmp.path(), Some("YAYA.dll")), ShioriEngine::Yaya);
+        assert_eq!(detect_shiori(tmp.path(), Some("bin\\satori.dll")), ShioriEngine::Satori);
+        // 汎用名はファイルから判定する
+        assert_eq!(detect_shiori(tmp.path(), Some("shiori.dll")), ShioriEngine::Kawari);
+    }
+
+    #[test]
+    fn ghost_master_の_dll_と辞書ファイルから判定する() {
+        let cases: [(&[&str], ShioriEngine); 6] = [
+            (&["shiori.dll", "Misaka.dll"], ShioriEngine::Misaka),
+            (&["aya.txt", "aya_bootend.dic"], ShioriEngine::Aya),
+            (&["yaya.txt", "aya.txt"], ShioriEngine::Yaya),
+            (&["dic01.txt", "satori_conf.txt"], ShioriEngine::Satori),
+            (&["kawarirc.kis", "kagari.ini"], ShioriEngine::Kagari),
+            (&["descript.txt", "shiori.dll"], ShioriEngine::Unknown),
+        ];
+        for (index, (files, expected)) in cases.iter().enumerate() {
+            let tmp = TempDirGuard::new(&format!("ghost_meta_shiori_files_{index}"));
+            for file in *files {
+                fs::write(tmp.path().join(file), "").unwrap();
+            }
+            assert_eq!(detect_shiori(tmp.path(), None), *expected, "{files:?}");
+        }
+    }
+
+    #[test]
+    fn ghost_master_がなければ_unknown() {
+        assert_eq!(
+            detect_shiori(Path::new("/nonexistent/ghost/master"), Some("shiori.dll")),
+            ShioriEngine::Unknown
+        );
+    }
+}
@@ -0,0 +1,660 @@
+use crate::descript::decode_descript_bytes;
+use crate::GhostMetaError;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `surface1-100000` のような巨大な範囲指定でメモリを使い果たさないための上限
+const MAX_SURFACE_RANGE: u32 = 100_000;
+
+/// 1 つのヘッダーで展開する ID の合計の上限（範囲指定を大量に並べたヘッダー対策）
+const MAX_SURFACE_IDS: usize = 100_000;
+
+/// element / animation パターンの描画メソッド
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DrawMethod {
+    Base,
+    Overlay,
+    OverlayFast,
+    OverlayMultiply,
+    Replace,
+    Interpolate,
+    Add,
+    Reduce,
+    Asis,
+    Bind,
+    Insert,
+    Move,
+    Start,
+    AlternativeStart,
+    Stop,
+    /// 上記以外のメソッド（小文字化した名前）
+    Other(String),
+}
+
+impl DrawMethod {
+    fn parse(value: &str) -> Self {
+        let lower = value.trim().to_ascii_lowercase();
+        match lower.as_str() {
+            "base" => DrawMethod::Base,
+            "overlay" => DrawMethod::Overlay,
+            "overlayfast" => DrawMethod::OverlayFast,
+            "overlaymultiply" => DrawMethod::OverlayMultiply,
+            "replace" => DrawMethod::Replace,
+            "interpolate" => DrawMethod::Interpolate,
+            "add" => DrawMethod::Add,
+            "reduce" => DrawMethod::Reduce,
+            "asis" => DrawMethod::Asis,
+            "bind" => DrawMethod::Bind,
+            "insert" => DrawMethod::Insert,
+            "move" => DrawMethod::Move,
+            "start" => DrawMethod::Start,
+            "alternativestart" => DrawMethod::AlternativeStart,
+            "stop" => DrawMethod::Stop,
+            _ => DrawMethod::Other(lower),
+        }
+    }
+}
+
+/// `elementN,method,filename,x,y`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceElement {
+    pub id: u32,
+    pub method: DrawMethod,
+    /// シェルディレクトリからの相対パス（`\` は `/` に正規化済み）
+    pub filename: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// `collisionN,left,top,right,bottom,name`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceCollision {
+    pub id: u32,
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub name: String,
+}
+
+/// アニメーションの 1 パターン。
+/// 新書式 `animationN.patternM,method,surface,wait,x,y` と旧書式 `NpatternM,surface,wait,method,x,y` を同じ形に揃える。
+/// start 系メソッドでは surface に開始するアニメーション ID が入る。解釈できない値は 0 になる。
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationPattern {
+    pub id: u32,
+    pub method: DrawMethod,
+    /// 表示するサーフェス ID。-1 は非表示
+    pub surface: i32,
+    /// ウェイト（`50-100` のような範囲指定を含むため文字列のまま保持）
+    pub wait: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// SERIKO アニメーション定義（`animationN.*` / 旧書式 `N*`）
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceAnimation {
+    pub id: u32,
+    /// interval の値（`sometimes` / `runonce` / `bind` など）。未指定なら空文字列
+    pub interval: String,
+    /// option の値（`exclusive` など）。未指定なら空文字列
+    pub option: String,
+    /// パターン ID 順
+    pub patterns: Vec<AnimationPattern>,
+}
+
+/// 1 サーフェス分の定義
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceDefinition {
+    /// element ID 順
+    pub elements: Vec<SurfaceElement>,
+    /// collision ID 順
+    pub collisions: Vec<SurfaceCollision>,
+    /// animation ID 順
+    pub animations: Vec<SurfaceAnimation>,
+    /// 上記以外の `key,value` 行（出現順）
+    pub extra: Vec<(String, String)>,
+}
+
+/// `surface.alias` / `sakura.surface.alias` 等の 1 エントリ
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfaceAlias {
+    /// ブロック名の接頭辞（`sakura.surface.alias` なら `sakura`）。`surface.alias` は None
+    pub scope: Option<String>,
+    /// エイリアス名（数値または文字列）
+    pub name: String,
+    /// 候補となるサーフェス ID
+    pub surfaces: Vec<u32>,
+}
+
+/// surfaces*.txt の解析結果
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SurfacesFile {
+    pub surfaces: BTreeMap<u32, SurfaceDefinition>,
+    pub aliases: Vec<SurfaceAlias>,
+}
+
+/// 現在開いているブロックの種類
+enum Block {
+    Surface { ids: Vec<u32>, append: bool },
+    Alias { scope: Option<String> },
+    Skip,
+}
+
+/// シェルディレクトリの `surfaces*.txt` のパスを読む順（`surfaces.txt` が最初、残りはファイル名の辞書順）で返す。
+pub fn surfaces_files(shell_dir: &Path) -> Result<Vec<PathBuf>, GhostMetaError> {
+    let mut files: Vec<(String, PathBuf)> = fs::read_dir(shell_dir)?
+        .flatten()
+        .filter_map(|entry| {
+            let lower = entry.file_name().to_string_lossy().to_ascii_lowercase();
+            (lower.starts_with("surfaces") && lower.ends_with(".txt"))
+                .then(|| (lower, entry.path()))
+        })
+        .collect();
+    files.sort_by(|a, b| (a.0 != "surfaces.txt", &a.0).cmp(&(b.0 != "surfaces.txt", &b.0)));
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// シェルディレクトリの `surfaces*.txt` をすべて読んで 1 つにまとめる。
+/// `surfaces_files` の順に読む（後のファイルの定義が優先・`surface.append` は追記）。
+/// surfaces*.txt が 1 つもなければ空の SurfacesFile を返す。
+pub fn read_surfaces(shell_dir: &Path) -> Result<SurfacesFile, GhostMetaError> {
+    let mut surfaces = SurfacesFile::default();
+    for path in surfaces_files(shell_dir)? {
+        let bytes = fs::read(&path)?;
+        surfaces.parse_text(&decode_descript_bytes(&bytes).text);
+    }
+    Ok(surfaces)
+}
+
+/// surfaces.txt のテキストを解析する。
+pub fn parse_surfaces(text: &str) -> SurfacesFile {
+    let mut surfaces = SurfacesFile::default();
+    surfaces.parse_text(text);
+    surfaces
+}
+
+impl SurfacesFile {
+    /// サーフェスの element 定義（ID 順）。定義がなければ空
+    pub fn elements(&self, surface_id: u32) -> &[SurfaceElement] {
+        self.surfaces
+            .get(&surface_id)
+            .map(|definition| definition.elements.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// テキストを解析して既存の定義にマージする
+    fn parse_text(&mut self, text: &str) {
+        let mut pending_header: Option<String> = None;
+        let mut block: Option<Block> = None;
+        let mut definition = SurfaceDefinition::default();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with("//") {
+                continue;
+            }
+
+            if block.is_none() {
+                // `surface0{` のようにヘッダーと `{` が同じ行にある書き方も受け付ける
+                let (header, opens) = match line.strip_suffix('{') {
+                    Some(header) => (header.trim(), true),
+                    None => (line, false),
+                };
+                if !header.is_empty() {
+                    pending_header = Some(header.to_string());
+                }
+                if opens {
+                    block = Some(classify_header(
+                        pending_header.take().as_deref().unwrap_or(""),
+                    ));
+                    definition = SurfaceDefinition::default();
+                }
+                continue;
+            }
+
+            if line == "}" {
+                if let Some(Block::Surface { ids, append }) = block.take() {
+                    self.apply_definition(&ids, append, &definition);
+                }
+                continue;
+            }
+
+            match &block {
+                Some(Block::Surface { .. }) => parse_surface_line(&mut definition, line),
+                Some(Block::Alias { scope }) => {
+                    if let Some(alias) = parse_alias_line(scope.clone(), line) {
+                        self.aliases.push(alias);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn apply_definition(&mut self, ids: &[u32], append: bool, definition: &SurfaceDefinition) {
+        for &id in ids {
+            if !append {
+                self.surfaces.insert(id, definition.clone());
+                continue;
+            }
+            // surface.append は既存定義がある ID にだけ追記する
+            let Some(existing) = self.surfaces.get_mut(&id) else {
+                continue;
+            };
+            for element in &definition.elements {
+                upsert_by_id(&mut existing.elements, element.clone(), |e| e.id);
+            }
+            for collision in &definition.collisions {
+                upsert_by_id(&mut existing.collisions, collision.clone(), |c| c.id);
+            }
+            for animation in &definition.animations {
+                upsert_by_id(&mut existing.animations, animation.clone(), |a| a.id);
+            }
+            existing.extra.extend(definition.extra.iter().cloned());
+        }
+    }
+}
+
+/// ブロックヘッダーの種類を判定する
+fn classify_header(header: &str) -> Block {
+    let lower = header.to_ascii_lowercase();
+    if let Some(scope) = lower.strip_suffix("surface.alias") {
+        let scope = scope.trim_end_matches('.');
+        return Block::Alias {
+            scope: (!scope.is_empty()).then(|| scope.to_string()),
+        };
+    }
+    if let Some(spec) = lower.strip_prefix("surface.append") {
+        return Block::Surface {
+            ids: parse_surface_ids(spec),
+            append: true,
+        };
+    }
+    if lower.starts_with("surface") {
+        return Block::Surface {
+            ids: parse_surface_ids(&lower),
+            append: false,
+        };
+    }
+    // descript / sakura.cursor / sakura.tooltips など、サーフェス以外のブロック
+    Block::Skip
+}
+
+/// `surface1,surface3-5,!surface4` 形式の ID 指定を展開する（昇順・重複なし）。
+/// 展開した ID が合計 `MAX_SURFACE_IDS` を超える範囲は捨てる。除外は範囲のまま持ち、二分探索で引く
+fn parse_surface_ids(spec: &str) -> Vec<u32> {
+    let mut included: Vec<u32> = Vec::new();
+    let mut excluded: Vec<(u32, u32)> = Vec::new();
+    for token in spec.split(',') {
+        let token = token.trim();
+        let (token, exclude) = match token.strip_prefix('!') {
+            Some(rest) => (rest, true),
+            None => (token, false),
+        };
+        let token = token.trim().trim_start_matches("surface");
+        let range = match token.split_once('-') {
+            Some((start, end)) => start.trim().parse().ok().zip(end.trim().parse().ok()),
+            None => token.parse::<u32>().ok().map(|id| (id, id)),
+        };
+        let Some((start, end)) =
+            range.filter(|&(start, end)| start <= end && end - start < MAX_SURFACE_RANGE)
+        else {
+            continue;
+        };
+        if exclude {
+            excluded.push((start, end));
+        } else if included.len() + ((end - start) as usize) < MAX_SURFACE_IDS {
+            included.extend(start..=end);
+        }
+    }
+    included.sort_unstable();
+    included.dedup();
+
+    // 重なる除外範囲をまとめ、開始位置の昇順に並べる
+    excluded.sort_unstable();
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(excluded.len());
+    for (start, end) in excluded {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    included.retain(|&id| {
+        let index = merged.partition_point(|&(start, _)| start <= id);
+        index == 0 || merged[index - 1].1 < id
+    });
+    included
+}
+
+/// サーフェスブロック内の 1 行を解析して definition に追加する
+fn parse_surface_line(definition: &mut SurfaceDefinition, line: &str) {
+    let Some((key, value)) = line.split_once(',') else {
+        return;
+    };
+    let key = key.trim().to_ascii_lowercase();
+    let args: Vec<&str> = value.split(',').map(str::trim).collect();
+
+    if let Some(id) = key.strip_prefix("element").and_then(|id| id.parse().ok())
+        && args.len() >= 2
+    {
+        let element = SurfaceElement {
+            id,
+            method: DrawMethod::parse(args[0]),
+            filename: args[1].replace('\\', "/"),
+            x: parse_arg(&args, 2),
+            y: parse_arg(&args, 3),
+        };
+        upsert_by_id(&mut definition.elements, element, |e| e.id);
+        return;
+    }
+
+    if let Some(id) = key.strip_prefix("collision").and_then(|id| id.parse().ok())
+        && args.len() >= 4
+    {
+        let collision = SurfaceCollision {
+            id,
+            left: parse_arg(&args, 0),
+            top: parse_arg(&args, 1),
+            right: parse_arg(&args, 2),
+            bottom: parse_arg(&args, 3),
+            name: args.get(4).copied().unwrap_or("").to_string(),
+        };
+        upsert_by_id(&mut definition.collisions, collision, |c| c.id);
+        return;
+    }
+
+    if parse_animation_line(definition, &key, &args) {
+        return;
+    }
+
+    definition.extra.push((key, value.trim().to_string()));
+}
+
+/// `animationN.interval` / `animationN.patternM` / `animationN.option` と旧書式 `Ninterval` / `NpatternM` / `Noption`。
+/// 解析できた場合は true を返す。
+fn parse_animation_line(definition: &mut SurfaceDefinition, key: &str, args: &[&str]) -> bool {
+    let (id_str, property, legacy) = match key.strip_prefix("animation") {
+        Some(rest) => match rest.split_once('.') {
+            Some((id, property)) => (id, property, false),
+            None => return false,
+        },
+        None => {
+            let digits = key.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return false;
+            }
+            (&key[..digits], &key[digits..], true)
+        }
+    };
+    let Ok(id) = id_str.parse::<u32>() else {
+        return false;
+    };
+    let pattern_id = match property {
+        "interval" | "option" => None,
+        _ => match property
+            .strip_prefix("pattern")
+            .and_then(|id| id.parse().ok())
+        {
+            Some(pattern_id) => Some(pattern_id),
+            None => return false,
+        },
+    };
+
+    let index = match definition.animations.binary_search_by_key(&id, |a| a.id) {
+        Ok(index) => index,
+        Err(index) => {
+            definition.animations.insert(
+                index,
+                SurfaceAnimation {
+                    id,
+                    ..Default::default()
+                },
+            );
+            index
+        }
+    };
+    let animation = &mut definition.animations[index];
+
+    let Some(pattern_id) = pattern_id else {
+        if property == "interval" {
+            animation.interval = args.join(",");
+        } else {
+            animation.option = args.join(",");
+        }
+        return true;
+    };
+    // 旧書式は surface,wait,method,x,y、新書式は method,surface,wait,x,y
+    let (method, surface, wait) = if legacy { (2, 0, 1) } else { (0, 1, 2) };
+    let pattern = AnimationPattern {
+        id: pattern_id,
+        method: DrawMethod::parse(args.get(method).copied().unwrap_or("")),
+        surface: parse_arg(args, surface),
+        wait: args.get(wait).copied().unwrap_or("").to_string(),
+        x: parse_arg(args, 3),
+        y: parse_arg(args, 4),
+    };
+    upsert_by_id(&mut animation.patterns, pattern, |p| p.id);
+    true
+}
+
+/// `0,[0,100,101]` 形式のエイリアス行を解析する
+fn parse_alias_line(scope: Option<String>, line: &str) -> Option<SurfaceAlias> {
+    let (name, list) = line.split_once(',')?;
+    let surfaces = list
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect();
+    Some(SurfaceAlias {
+        scope,
+        name: name.trim().to_string(),
+        surfaces,
+    })
+}
+
+fn parse_arg<T: std::str::FromStr + Default>(args: &[&str], index: usize) -> T {
+    args.get(index)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or_default()
+}
+
+/// ID 順を保ったまま挿入する。同じ ID があれば後の定義で置き換える
+fn upsert_by_id<T>(items: &mut Vec<T>, item: T, id_of: impl Fn(&T) -> u32) {
+    let id = id_of(&item);
+    match items.binary_search_by_key(&id, &id_of) {
+        Ok(index) => items[index] = item,
+        Err(index) => items.insert(index, item),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+
+    const SAMPLE: &str = "\
+charset,UTF-8
+descript
+{
+version,1
+}
+
+// 素体 + 表情パーツ
+surface0
+{
+element0,overlay,body\\base.png,0,0
+element1,overlay,face/normal.png,100,50
+collision0,110,60,200,120,Head
+animation0.interval,sometimes
+animation0.pattern0,overlay,100,50,110,60
+animation0.pattern1,overlay,-1,50,0,0
+0interval,runonce
+point.basepos.x,150
+}
+
+surface1,surface3-5,!surface4 {
+element0,base,surface1.png,0,0
+}
+
+surface.append1
+{
+collision0,0,0,10,10,Bust
+}
+
+sakura.surface.alias
+{
+0,[0,100]
+笑顔,[5]
+}
+";
+
+    #[test]
+    fn element_collision_animation_を解析する() {
+        let surfaces = parse_surfaces(SAMPLE);
+        let surface0 = &surfaces.surfaces[&0];
+
+        assert_eq!(
+            surface0.elements,
+            vec![
+                SurfaceElement {
+                    id: 0,
+                    method: DrawMethod::Overlay,
+                    filename: "body/base.png".to_string(),
+                    x: 0,
+                    y: 0,
+                },
+                SurfaceElement {
+                    id: 1,
+                    method: DrawMethod::Overlay,
+                    filename: "face/normal.png".to_string(),
+                    x: 100,
+                    y: 50,
+                },
+            ]
+        );
+        assert_eq!(
+            surface0.collisions,
+            vec![SurfaceCollision {
+                id: 0,
+                left: 110,
+                top: 60,
+                right: 200,
+                bottom: 120,
+                name: "Head".to_string(),
+            }]
+        );
+        let animation = &surface0.animations[0];
+        assert_eq!(animation.interval, "runonce");
+        assert_eq!(animation.patterns.len(), 2);
+        assert_eq!(animation.patterns[0].surface, 100);
+        assert_eq!(animation.patterns[0].wait, "50");
+        assert_eq!(animation.patterns[1].surface, -1);
+        assert_eq!(
+            surface0.extra,
+            vec![("point.basepos.x".to_string(), "150".to_string())]
+        );
+    }
+
+    #[test]
+    fn 範囲指定と除外と_append_を展開する() {
+        let surfaces = parse_surfaces(SAMPLE);
+        let ids: Vec<u32> = surfaces.surfaces.keys().copied().collect();
+        assert_eq!(ids, vec![0, 1, 3, 5]);
+
+        assert_eq!(surfaces.surfaces[&1].collisions.len(), 1);
+        assert!(surfaces.surfaces[&3].collisions.is_empty());
+        assert_eq!(surfaces.surfaces[&5].elements[0].method, DrawMethod::Base);
+    }
+
+    #[test]
+    fn 巨大な範囲指定を並べたヘッダーも上限までしか展開しない() {
+        let header = vec!["surface0-99999"; 1000].join(",");
+        assert_eq!(parse_surface_ids(&header).len(), MAX_SURFACE_IDS);
+
+        let header = format!("{header},surface200000");
+        let ids = parse_surface_ids(&header);
+        assert_eq!(ids.len(), MAX_SURFACE_IDS);
+        assert_eq!(ids.last(), Some(&99_999));
+
+        assert!(parse_surface_ids("surface0-99999,!surface0-99999").is_empty());
+        assert_eq!(
+            parse_surface_ids("surface0-9,!surface2-4,!surface3-6,!surface9"),
+            vec![0, 1, 7, 8]
+        );
+    }
+
+    #[test]
+    fn 旧書式のアニメーションパターンを新書式と同じ形に揃える() {
+        let surfaces =
+            parse_surfaces("surface10\n{\n2interval,sometimes\n2pattern0,11,5,overlay,3,4\n}\n");
+        let animation = &surfaces.surfaces[&10].animations[0];
+        assert_eq!(animation.id, 2);
+        assert_eq!(
+            animation.patterns[0],
+            AnimationPattern {
+                id: 0,
+                method: DrawMethod::Overlay,
+                surface: 11,
+                wait: "5".to_string(),
+                x: 3,
+                y: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn surface_alias_を解析する() {
+        let surfaces = parse_surfaces(SAMPLE);
+        assert_eq!(
+            surfaces.aliases,
+            vec![
+                SurfaceAlias {
+                    scope: Some("sakura".to_string()),
+                    name: "0".to_string(),
+                    surfaces: vec![0, 100],
+                },
+                SurfaceAlias {
+                    scope: Some("sakura".to_string()),
+                    name: "笑顔".to_string(),
+                    surfaces: vec![5],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn read_surfaces_が_surfaces_txt_を先に読み追加ファイルで上書きする() {
+        let tmp = TempDirGuard::new("ghost_meta_read_surfaces");
+        fs::write(
+            tmp.path().join("surfaces.txt"),
+            "surface0\n{\nelement0,overlay,a.png,0,0\n}\n",
+        )
+        .unwrap();
+        fs::write(
+            tmp.path().join("surfaces_dress.txt"),
+            "surface.append0\n{\nelement1,overlay,b.png,0,0\n}\n",
+        )
+        .unwrap();
+
+        let surfaces = read_surfaces(tmp.path()).unwrap();
+        let filenames: Vec<&str> = surfaces.surfaces[&0]
+            .elements
+            .iter()
+            .map(|e| e.filename.as_str())
+            .collect();
+        assert_eq!(filenames, vec!["a.png", "b.png"]);
+    }
+}
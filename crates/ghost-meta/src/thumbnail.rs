@@ -1,4 +1,5 @@
 use crate::descript::parse_descript;
+use crate::surfaces::{read_surfaces, surfaces_files};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -10,15 +11,19 @@ pub enum AlphaMode {
     SelfAlpha,
     /// 左上ピクセルをキーカラーとして透過する
     KeyColor,
+    /// 同名の .pna（グレースケール画像）の輝度をアルファ値として透過する
+    PnaMask(PathBuf),
 }
 
 /// サムネイル画像の種別
 #[derive(Debug, Clone, PartialEq)]
 pub enum ThumbnailKind {
-    /// shell/master/surface0* 画像
+    /// シェルディレクトリ（既定は shell/master）の surface0* 画像
     Surface,
-    /// ゴーストルート直下の thumbnail.png
+    /// ゴースト・バルーンのルート直下の thumbnail.png
     Thumbnail,
+    /// バルーンディレクトリの balloons0.png
+    Balloon,
 }
 
 /// 解決済みサムネイルの情報
@@ -33,44 +38,88 @@ pub struct ThumbnailInfo {
 ///
 /// フォールバックチェーン（優先度順）:
 /// 1. `shell/master/surface0*.apng` — seriko.use_self_alpha を参照
-/// 2. `shell/master/surface0*.png`  — seriko.use_self_alpha を参照
-/// 3. `thumbnail.png`（ghost_root 直下）— PNG アルファチャンネルの有無を検査
-/// 4. `None`
+/// 2. `shell/master/surface0*.png`  — seriko.use_self_alpha を参照。同名の .pna があればマスクとして使う
+/// 3. `shell/master/surfaces*.txt` の surface0 の element 定義 — パスは surfaces*.txt。描画時に element を合成する
+/// 4. `thumbnail.png`（ghost_root 直下）— PNG アルファチャンネルの有無を検査
+/// 5. `None`
 ///
 /// surface0* の選択: 名前辞書順の最初（OS 依存の列挙順を回避するためソートする）
 pub fn resolve_thumbnail(
     ghost_root: &Path,
     shell_descript: Option<&HashMap<String, String>>,
 ) -> Option<ThumbnailInfo> {
-    if let Some(info) = resolve_surface0(ghost_root, shell_descript) {
-        return Some(info);
-    }
+    let shell_master = ghost_root.join("shell").join("master");
+    resolve_shell_thumbnail(&shell_master, shell_descript)
+        .or_else(|| resolve_root_thumbnail(ghost_root))
+}
 
+/// ゴースト（またはバルーン）のルート直下の thumbnail.png を ThumbnailInfo として返す
+pub(crate) fn resolve_root_thumbnail(ghost_root: &Path) -> Option<ThumbnailInfo> {
     let thumbnail_path = ghost_root.join("thumbnail.png");
-    if thumbnail_path.is_file() {
-        let alpha = detect_thumbnail_alpha(&thumbnail_path);
-        return Some(ThumbnailInfo {
-            path: thumbnail_path,
-            alpha,
-            kind: ThumbnailKind::Thumbnail,
-        });
+    if !thumbnail_path.is_file() {
+        return None;
     }
-
-    None
+    let alpha = detect_thumbnail_alpha(&thumbnail_path);
+    Some(ThumbnailInfo {
+        path: thumbnail_path,
+        alpha,
+        kind: ThumbnailKind::Thumbnail,
+    })
 }
 
-/// shell/master/ 内の surface0* ファイルを探して ThumbnailInfo を返す
-fn resolve_surface0(
-    ghost_root: &Path,
+/// シェルディレクトリ（`shell/<name>/`）内の surface0* ファイルを探して ThumbnailInfo を返す。
+/// shell_descript が None の場合は `shell_dir/descript.txt` から seriko.use_self_alpha を読む。
+/// seriko.use_self_alpha が "1" でない PNG に同名の .pna があれば `AlphaMode::PnaMask` を返す。
+pub fn resolve_shell_thumbnail(
+    shell_dir: &Path,
     shell_descript: Option<&HashMap<String, String>>,
 ) -> Option<ThumbnailInfo> {
-    let shell_master = ghost_root.join("shell").join("master");
+    let filenames = fs::read_dir(shell_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned());
+    let alpha = match shell_descript {
+        Some(fields) => seriko_alpha_from_fields(fields),
+        None => read_seriko_use_self_alpha(shell_dir),
+    };
+    let Some((filename, is_apng)) = pick_surface0(filenames) else {
+        return resolve_composed_surface0(shell_dir, alpha);
+    };
 
+    let path = shell_dir.join(filename);
+    // use_self_alpha の場合 SSP は .pna を使わない。APNG はマスクしない
+    let alpha = match find_pna_mask(&path) {
+        Some(mask) if !is_apng && alpha == AlphaMode::KeyColor => AlphaMode::PnaMask(mask),
+        _ => alpha,
+    };
+    Some(ThumbnailInfo {
+        path,
+        alpha,
+        kind: ThumbnailKind::Surface,
+    })
+}
+
+/// surface0* の画像はないが surfaces*.txt に surface0 の element 定義がある場合、
+/// 最初の surfaces*.txt をパスにした ThumbnailInfo を返す（描画は `compose::load_thumbnail` が element を合成する）
+fn resolve_composed_surface0(shell_dir: &Path, alpha: AlphaMode) -> Option<ThumbnailInfo> {
+    if read_surfaces(shell_dir).ok()?.elements(0).is_empty() {
+        return None;
+    }
+    Some(ThumbnailInfo {
+        path: surfaces_files(shell_dir).ok()?.into_iter().next()?,
+        alpha,
+        kind: ThumbnailKind::Surface,
+    })
+}
+
+/// ファイル名の一覧から surface0* 画像を選ぶ。戻り値は (ファイル名, APNG かどうか)。
+/// APNG を PNG より優先し、同じ種類の中では名前辞書順（大文字小文字不問）の最初を選ぶ
+/// （OS 依存の列挙順を回避するため）。
+pub(crate) fn pick_surface0(filenames: impl IntoIterator<Item = String>) -> Option<(String, bool)> {
     let mut apng_files: Vec<String> = Vec::new();
     let mut png_files: Vec<String> = Vec::new();
 
-    for entry in fs::read_dir(&shell_master).ok()?.flatten() {
-        let filename = entry.file_name().to_string_lossy().into_owned();
+    for filename in filenames {
         let lower = filename.to_ascii_lowercase();
         if let Some(rest) = lower.strip_prefix("surface") {
             let trimmed = rest.trim_start_matches('0');
@@ -85,36 +134,34 @@ fn resolve_surface0(
         }
     }
 
-    // OS 依存の列挙順を回避するためソートして最初を選ぶ
     apng_files.sort_by_key(|f| f.to_ascii_lowercase());
     png_files.sort_by_key(|f| f.to_ascii_lowercase());
-
-    let alpha = match shell_descript {
-        Some(fields) => seriko_alpha_from_fields(fields),
-        None => read_seriko_use_self_alpha(ghost_root),
-    };
-
-    // APNG が PNG より優先
-    if let Some(filename) = apng_files.first() {
-        return Some(ThumbnailInfo {
-            path: shell_master.join(filename),
-            alpha,
-            kind: ThumbnailKind::Surface,
-        });
-    }
-    if let Some(filename) = png_files.first() {
-        return Some(ThumbnailInfo {
-            path: shell_master.join(filename),
-            alpha,
-            kind: ThumbnailKind::Surface,
-        });
+    match apng_files.into_iter().next() {
+        Some(filename) => Some((filename, true)),
+        None => png_files.into_iter().next().map(|filename| (filename, false)),
     }
+}
 
-    None
+/// 画像と同じディレクトリにある同名（拡張子以外が大文字小文字不問で一致）の .pna を探す
+pub(crate) fn find_pna_mask(image_path: &Path) -> Option<PathBuf> {
+    let dir = image_path.parent()?;
+    let stem = image_path.file_stem()?.to_string_lossy().to_ascii_lowercase();
+    let mut candidates: Vec<String> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|filename| {
+            let lower = filename.to_ascii_lowercase();
+            lower.strip_suffix(".pna") == Some(stem.as_str())
+        })
+        .collect();
+    // OS 依存の列挙順を回避するためソートして最初を選ぶ
+    candidates.sort();
+    candidates.first().map(|filename| dir.join(filename))
 }
 
 /// HashMap から seriko.use_self_alpha を判定する。
-fn seriko_alpha_from_fields(fields: &HashMap<String, String>) -> AlphaMode {
+pub(crate) fn seriko_alpha_from_fields(fields: &HashMap<String, String>) -> AlphaMode {
     if fields.get("seriko.use_self_alpha").map(|v| v.as_str()) == Some("1") {
         AlphaMode::SelfAlpha
     } else {
@@ -122,17 +169,13 @@ fn seriko_alpha_from_fields(fields: &HashMap<String, String>) -> AlphaMode {
     }
 }
 
-/// shell/master/descript.txt から seriko.use_self_alpha を読み取る。
+/// シェルの descript.txt から seriko.use_self_alpha を読み取る。
 /// 値が "1" なら SelfAlpha、それ以外または欠落なら KeyColor。
-fn read_seriko_use_self_alpha(ghost_root: &Path) -> AlphaMode {
-    let shell_descript = ghost_root
-        .join("shell")
-        .join("master")
-        .join("descript.txt");
-    if let Ok(fields) = parse_descript(&shell_descript) {
-        if fields.get("seriko.use_self_alpha").map(|v| v.as_str()) == Some("1") {
-            return AlphaMode::SelfAlpha;
-        }
+fn read_seriko_use_self_alpha(shell_dir: &Path) -> AlphaMode {
+    if let Ok(fields) = parse_descript(&shell_dir.join("descript.txt"))
+        && fields.get("seriko.use_self_alpha").map(|v| v.as_str()) == Some("1")
+    {
+        return AlphaMode::SelfAlpha;
     }
     AlphaMode::KeyColor
 }
@@ -140,17 +183,27 @@ fn read_seriko_use_self_alpha(ghost_root: &Path) -> AlphaMode {
 /// thumbnail.png のアルファチャンネルを検査して AlphaMode を返す。
 /// feature: "thumbnail" が有効な場合、PNG の IHDR ヘッダーのみ読み込んで color type を検査する。
 /// feature が無効な場合は常に KeyColor を返す。
-#[cfg(feature = "thumbnail")]
 fn detect_thumbnail_alpha(path: &Path) -> AlphaMode {
-    use image::codecs::png::PngDecoder;
-    use image::ColorType;
-    use image::ImageDecoder;
-
     let Ok(file) = fs::File::open(path) else {
         return AlphaMode::KeyColor;
     };
     // PngDecoder は BufRead + Seek を要求するため BufReader で包む
-    let Ok(decoder) = PngDecoder::new(std::io::BufReader::new(file)) else {
+    detect_png_alpha(std::io::BufReader::new(file))
+}
+
+/// `detect_thumbnail_alpha` のバイト列版（アーカイブ内の thumbnail.png 用）
+#[cfg_attr(not(feature = "nar"), allow(dead_code))]
+pub(crate) fn detect_thumbnail_alpha_bytes(bytes: &[u8]) -> AlphaMode {
+    detect_png_alpha(std::io::Cursor::new(bytes))
+}
+
+#[cfg(feature = "thumbnail")]
+fn detect_png_alpha<R: std::io::BufRead + std::io::Seek>(reader: R) -> AlphaMode {
+    use image::codecs::png::PngDecoder;
+    use image::ColorType;
+    use image::ImageDecoder;
+
+    let Ok(decoder) = PngDecoder::new(reader) else {
         return AlphaMode::KeyColor;
     };
     // color_type() は ImageDecoder トレイトのメソッド。IHDR チャンクのみ参照し全ピクセル展開は行わない
@@ -163,7 +216,7 @@ fn detect_thumbnail_alpha(path: &Path) -> AlphaMode {
 }
 
 #[cfg(not(feature = "thumbnail"))]
-fn detect_thumbnail_alpha(_path: &Path) -> AlphaMode {
+fn detect_png_alpha<R: std::io::BufRead + std::io::Seek>(_reader: R) -> AlphaMode {
     AlphaMode::KeyColor
 }
 
@@ -173,13 +226,13 @@ mod tests {
     use crate::testutil::TempDirGuard;
     use std::path::PathBuf;
 
-    fn create_shell_master(ghost_root: &PathBuf) -> PathBuf {
+    fn create_shell_master(ghost_root: &Path) -> PathBuf {
         let shell_master = ghost_root.join("shell").join("master");
         fs::create_dir_all(&shell_master).unwrap();
         shell_master
     }
 
-    fn write_shell_descript(ghost_root: &PathBuf, content: &str) {
+    fn write_shell_descript(ghost_root: &Path, content: &str) {
         let shell_master = create_shell_master(ghost_root);
         fs::write(shell_master.join("descript.txt"), content).unwrap();
     }
@@ -193,6 +246,32 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn surface0_がなくても_element_定義があれば_surfaces_txt_をパスにする() {
+        let tmp = TempDirGuard::new("ghost_meta_thumb_composed");
+        let shell_master = create_shell_master(tmp.path());
+        fs::write(tmp.path().join("thumbnail.png"), b"").unwrap();
+        fs::write(
+            shell_master.join("surfaces.txt"),
+            "surface0\n{\nelement0,base,body.png,0,0\n}\n",
+        )
+        .unwrap();
+
+        let info = resolve_thumbnail(tmp.path(), None).unwrap();
+        assert_eq!(info.path, shell_master.join("surfaces.txt"));
+        assert_eq!(info.kind, ThumbnailKind::Surface);
+        assert_eq!(info.alpha, AlphaMode::KeyColor);
+
+        // surface0 以外の element 定義しかなければ使わない
+        fs::write(
+            shell_master.join("surfaces.txt"),
+            "surface10\n{\nelement0,base,body.png,0,0\n}\n",
+        )
+        .unwrap();
+        let info = resolve_thumbnail(tmp.path(), None).unwrap();
+        assert_eq!(info.kind, ThumbnailKind::Thumbnail);
+    }
+
     #[test]
     fn surface0もthumbnailpngもない場合はnoneを返す() {
         let tmp = TempDirGuard::new("ghost_meta_thumb_nothing");
@@ -295,6 +374,36 @@ mod tests {
         assert_eq!(info.alpha, AlphaMode::KeyColor);
     }
 
+    // --- .pna マスク ---
+
+    #[test]
+    fn 同名のpnaがあればpna_maskになる() {
+        let tmp = TempDirGuard::new("ghost_meta_thumb_pna");
+        let shell_master = create_shell_master(tmp.path());
+        fs::write(shell_master.join("surface0.png"), "").unwrap();
+        fs::write(shell_master.join("Surface0.PNA"), "").unwrap();
+        fs::write(shell_master.join("surface00.pna"), "").unwrap();
+
+        let info = resolve_thumbnail(tmp.path(), None).unwrap();
+        assert_eq!(info.path, shell_master.join("surface0.png"));
+        assert_eq!(
+            info.alpha,
+            AlphaMode::PnaMask(shell_master.join("Surface0.PNA"))
+        );
+    }
+
+    #[test]
+    fn use_self_alphaのときはpnaを使わない() {
+        let tmp = TempDirGuard::new("ghost_meta_thumb_pna_self_alpha");
+        let shell_master = create_shell_master(tmp.path());
+        write_shell_descript(tmp.path(), "charset,UTF-8\nseriko.use_self_alpha,1\n");
+        fs::write(shell_master.join("surface0.png"), "").unwrap();
+        fs::write(shell_master.join("surface0.pna"), "").unwrap();
+
+        let info = resolve_thumbnail(tmp.path(), None).unwrap();
+        assert_eq!(info.alpha, AlphaMode::SelfAlpha);
+    }
+
     // --- surface0 パターンマッチ ---
 
     #[test]
@@ -0,0 +1,353 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use ts_rs::TS;
+
+use ghost_meta::AlphaMode;
+
+use super::ghost::ensure_request_key;
+use super::ghost::scan::mask_path_string;
+use super::ghost::store::{configure_connection, normalize_for_key, GHOST_KEY_SEPARATOR};
+use super::ghost::thumbnail_cache::thumbnail_kind_name;
+
+/// インストール済みバルーン 1 件分（balloons テーブルの 1 行）
+#[derive(Debug, Clone)]
+pub(crate) struct Balloon {
+    /// descript.txt の name フィールド（表示名）。未設定の場合はディレクトリ名
+    pub name: String,
+    /// descript.txt の id フィールド。未設定の場合は空文字列
+    pub descript_id: String,
+    /// descript.txt の craftman フィールド（作者名）。未設定の場合は空文字列
+    pub craftman: String,
+    /// descript.txt の craftmanw フィールド（作者名2）。未設定の場合は空文字列
+    pub craftmanw: String,
+    /// descript.txt の craftmanurl フィールド（作者 URL）。未設定の場合は空文字列
+    pub craftmanurl: String,
+    /// descript.txt の homeurl フィールド（ネットワーク更新 URL）。未設定の場合は空文字列
+    pub homeurl: String,
+    /// バルーンのディレクトリ名
+    pub directory_name: String,
+    /// バルーンのフルパス
+    pub path: String,
+    /// プレビュー画像（thumbnail.png または balloons0.png）のフルパス。存在しない場合は空文字列
+    pub thumbnail_path: String,
+    /// プレビュー画像の透過方式（Ghost::thumbnail_use_self_alpha と同じ意味）
+    pub thumbnail_use_self_alpha: bool,
+    /// プレビュー画像のアルファマスク（Ghost::thumbnail_mask_path と同じ意味）
+    pub thumbnail_mask_path: String,
+    /// プレビュー画像の種別。"balloon" / "thumbnail" / ""（画像なし）
+    pub thumbnail_kind: String,
+}
+
+/// デフォルトバルーン（descript.txt の balloon フィールド）が未インストールのゴースト
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct MissingBalloon {
+    pub ghost_identity_key: String,
+    pub ghost_name: String,
+    pub balloon: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct BalloonScanResult {
+    pub total: usize,
+    pub missing_balloons: Vec<MissingBalloon>,
+}
+
+/// `{ssp_path}/balloon` を走査して balloons テーブルを入れ替え、
+/// request_key のゴーストのうちデフォルトバルーンが見つからないものを返すコマンド。
+///
+/// - ssp_key は JS の `normalizePathKey(sspPath)`。balloons テーブルは SSP ごとに ssp_key で分ける
+/// - バルーンは数十件程度なので差分更新はせず、ssp_key の行をすべて入れ替える
+/// - ゴーストのスキャン（`scan_and_store`）後に呼ぶこと。未スキャンなら missing_balloons は空
+/// - 走査と DB の書き込みは blocking プールで実行する
+#[tauri::command]
+pub async fn scan_balloons(
+    app: tauri::AppHandle,
+    ssp_path: String,
+    ssp_key: String,
+    request_key: String,
+) -> Result<BalloonScanResult, String> {
+    if ssp_key.is_empty() {
+        return Err("ssp_key が空です".to_string());
+    }
+    ensure_request_key(&request_key)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        scan_balloons_blocking(&app, &ssp_path, &ssp_key, &request_key)
+    })
+    .await
+    .map_err(|e| format!("バルーンのスキャンの実行エラー: {e}"))?
+}
+
+/// scan_balloons の本体
+fn scan_balloons_blocking(
+    app: &tauri::AppHandle,
+    ssp_path: &str,
+    ssp_key: &str,
+    request_key: &str,
+) -> Result<BalloonScanResult, String> {
+    use tauri::Manager;
+
+    let balloons = scan_balloons_internal(ssp_path)?;
+
+    let db_path = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("app_config_dir 取得エラー: {e}"))?
+        .join("ghosts.db");
+    let conn =
+        Connection::open(&db_path).map_err(|e| format!("DB オープンエラー: {e}"))?;
+    configure_connection(&conn)?;
+
+    let total = store_balloons(&conn, ssp_key, &balloons)?;
+    let missing_balloons = find_missing_balloons(&conn, ssp_key, request_key)?;
+    Ok(BalloonScanResult {
+        total,
+        missing_balloons,
+    })
+}
+
+/// `{ssp_path}/balloon` 配下のバルーンを読み込む（ディレクトリ名順）
+pub(crate) fn scan_balloons_internal(ssp_path: &str) -> Result<Vec<Balloon>, String> {
+    let balloon_dir = Path::new(ssp_path).join("balloon");
+    if !balloon_dir.is_dir() {
+        return Err(format!(
+            "balloon フォルダが見つかりません: {}",
+            balloon_dir.display()
+        ));
+    }
+    let metas = ghost_meta::scan_balloons(&balloon_dir)
+        .map_err(|e| format!("balloon フォルダの読み込みエラー: {e}"))?;
+    Ok(metas.into_iter().map(balloon_from_meta).collect())
+}
+
+/// BalloonMeta から Balloon 構造体へ変換するヘルパー
+fn balloon_from_meta(meta: ghost_meta::BalloonMeta) -> Balloon {
+    let (thumbnail_path, thumbnail_use_self_alpha, thumbnail_mask_path, thumbnail_kind) =
+        meta.thumbnail.map_or(
+            (String::new(), false, String::new(), String::new()),
+            |info| {
+                (
+                    info.path.to_string_lossy().into_owned(),
+                    info.alpha == AlphaMode::SelfAlpha,
+                    mask_path_string(&info.alpha),
+                    thumbnail_kind_name(&info.kind).to_string(),
+                )
+            },
+        );
+    Balloon {
+        name: meta.name,
+        descript_id: meta.id.unwrap_or_default(),
+        craftman: meta.craftman.unwrap_or_default(),
+        craftmanw: meta.craftmanw.unwrap_or_default(),
+        craftmanurl: meta.craftmanurl.unwrap_or_default(),
+        homeurl: meta.homeurl.unwrap_or_default(),
+        directory_name: meta.directory_name,
+        path: meta.path.to_string_lossy().into_owned(),
+        thumbnail_path,
+        thumbnail_use_self_alpha,
+        thumbnail_mask_path,
+        thumbnail_kind,
+    }
+}
+
+/// balloon_identity_key を構築する（ssp_key + ディレクトリ名。ghost_identity_key と同じ区切り文字）
+fn build_balloon_identity_key(ssp_key: &str, balloon: &Balloon) -> String {
+    format!(
+        "{}{}{}",
+        ssp_key,
+        GHOST_KEY_SEPARATOR,
+        normalize_for_key(&balloon.directory_name)
+    )
+}
+
+/// ssp_key のバルーンを balloons テーブルに書き込む（既存行は削除して入れ替える。1 トランザクション）
+pub(crate) fn store_balloons(
+    conn: &Connection,
+    ssp_key: &str,
+    balloons: &[Balloon],
+) -> Result<usize, String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクション開始エラー: {e}"))?;
+
+    tx.execute("DELETE FROM balloons WHERE ssp_key = ?1", [ssp_key])
+        .map_err(|e| format!("バルーン DELETE エラー: {e}"))?;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT INTO balloons (\
+                    ssp_key, balloon_identity_key,\
+                    name, descript_id, craftman, craftmanw, craftmanurl, homeurl,\
+                    directory_name, path,\
+                    thumbnail_path, thumbnail_use_self_alpha, thumbnail_mask_path, thumbnail_kind,\
+                    name_lower, craftman_lower, directory_name_lower,\
+                    updated_at\
+                ) VALUES (\
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,\
+                    ?11, ?12, ?13, ?14, ?15, ?16, ?17,\
+                    datetime('now')\
+                )",
+            )
+            .map_err(|e| format!("バルーン INSERT 準備エラー: {e}"))?;
+        for balloon in balloons {
+            stmt.execute(rusqlite::params![
+                ssp_key,
+                build_balloon_identity_key(ssp_key, balloon),
+                balloon.name,
+                balloon.descript_id,
+                balloon.craftman,
+                balloon.craftmanw,
+                balloon.craftmanurl,
+                balloon.homeurl,
+                balloon.directory_name,
+                balloon.path,
+                balloon.thumbnail_path,
+                balloon.thumbnail_use_self_alpha as i32,
+                balloon.thumbnail_mask_path,
+                balloon.thumbnail_kind,
+                normalize_for_key(&balloon.name),
+                normalize_for_key(&balloon.craftman),
+                normalize_for_key(&balloon.directory_name),
+            ])
+            .map_err(|e| format!("バルーン INSERT エラー: {e}"))?;
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("コミットエラー: {e}"))?;
+    Ok(balloons.len())
+}
+
+/// request_key のゴーストのうち、balloon フィールドが ssp_key のどのバルーンの
+/// name・ディレクトリ名とも一致しない（大文字小文字・全角半角不問）ものをゴースト名順に返す。
+/// balloon フィールドが空のゴーストは SSP の既定バルーンを使うので対象外。
+pub(crate) fn find_missing_balloons(
+    conn: &Connection,
+    ssp_key: &str,
+    request_key: &str,
+) -> Result<Vec<MissingBalloon>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT g.ghost_identity_key, g.name, g.balloon FROM ghosts g \
+             WHERE g.request_key = ?1 AND g.balloon_lower != '' \
+               AND NOT EXISTS (\
+                 SELECT 1 FROM balloons b WHERE b.ssp_key = ?2 \
+                   AND (b.name_lower = g.balloon_lower OR b.directory_name_lower = g.balloon_lower)\
+               ) \
+             ORDER BY g.name_lower, g.ghost_identity_key",
+        )
+        .map_err(|e| format!("SELECT 準備エラー: {e}"))?;
+    stmt.query_map(rusqlite::params![request_key, ssp_key], |row| {
+        Ok(MissingBalloon {
+            ghost_identity_key: row.get(0)?,
+            ghost_name: row.get(1)?,
+            balloon: row.get(2)?,
+        })
+    })
+    .map_err(|e| format!("SELECT エラー: {e}"))?
+    .collect::<rusqlite::Result<Vec<_>>>()
+    .map_err(|e| format!("SELECT エラー: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ghost::store::{store_ghosts, EntryTokens};
+    use crate::commands::ghost::store::tests::{make_ghost, setup_db};
+    use crate::testutil::TempDirGuard;
+    use std::fs;
+
+    fn create_balloon(ssp_root: &Path, dir_name: &str, descript: &str) {
+        let root = ssp_root.join("balloon").join(dir_name);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("descript.txt"), descript).unwrap();
+    }
+
+    #[test]
+    fn scan_balloons_internal_がバルーンを読み込みプレビュー画像を解決する() {
+        let tmp = TempDirGuard::new("ghost_launcher_scan_balloons");
+        create_balloon(tmp.path(), "sample", "charset,UTF-8\nname,標準\ncraftman,作者\n");
+        fs::write(
+            tmp.path().join("balloon").join("sample").join("balloons0.png"),
+            "",
+        )
+        .unwrap();
+        create_balloon(tmp.path(), "plain", "charset,UTF-8\n");
+
+        let balloons = scan_balloons_internal(&tmp.path().to_string_lossy()).unwrap();
+        assert_eq!(balloons.len(), 2);
+        assert_eq!(balloons[0].name, "plain");
+        assert_eq!(balloons[0].thumbnail_kind, "");
+        assert_eq!(balloons[1].name, "標準");
+        assert_eq!(balloons[1].craftman, "作者");
+        assert_eq!(balloons[1].thumbnail_kind, "balloon");
+        assert!(!balloons[1].thumbnail_use_self_alpha);
+    }
+
+    #[test]
+    fn scan_balloons_internal_は_balloon_フォルダがなければエラーを返す() {
+        let tmp = TempDirGuard::new("ghost_launcher_scan_balloons_missing");
+        let error = scan_balloons_internal(&tmp.path().to_string_lossy()).unwrap_err();
+        assert!(error.contains("balloon フォルダが見つかりません"));
+    }
+
+    #[test]
+    fn store_balloons_は_ssp_key_の行を入れ替え未インストールのバルーンを検出する() {
+        let tmp = TempDirGuard::new("ghost_launcher_store_balloons");
+        create_balloon(tmp.path(), "Sample", "charset,UTF-8\nname,標準バルーン\n");
+        create_balloon(tmp.path(), "other", "charset,UTF-8\nname,その他\n");
+        let balloons = scan_balloons_internal(&tmp.path().to_string_lossy()).unwrap();
+
+        let conn = setup_db();
+        let mut by_name = make_ghost("Alice", "alice", "ssp");
+        by_name.balloon = "標準バルーン".to_string();
+        let mut by_directory = make_ghost("Bob", "bob", "ssp");
+        by_directory.balloon = "SAMPLE".to_string();
+        let mut missing = make_ghost("Carol", "carol", "ssp");
+        missing.balloon = "未導入".to_string();
+        let default_balloon = make_ghost("Dave", "dave", "ssp");
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[by_name, by_directory, missing, default_balloon],
+            &EntryTokens::default(),
+            "fp",
+            "",
+        )
+        .unwrap();
+
+        assert_eq!(store_balloons(&conn, "c:/ssp", &balloons).unwrap(), 2);
+        // 別の SSP のバルーンは検出に使わない
+        assert_eq!(store_balloons(&conn, "d:/ssp", &balloons[..1]).unwrap(), 1);
+        // 再スキャンで行が重複しない
+        assert_eq!(store_balloons(&conn, "c:/ssp", &balloons).unwrap(), 2);
+        let count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM balloons WHERE ssp_key = 'c:/ssp'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let missing = find_missing_balloons(&conn, "c:/ssp", "rk1").unwrap();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].ghost_name, "Carol");
+        assert_eq!(missing[0].balloon, "未導入");
+        assert_eq!(missing[0].ghost_identity_key, "ssp\x1fcarol");
+
+        // d:/ssp には "other" しかないので Alice・Bob・Carol が未インストール扱い
+        let names: Vec<String> = find_missing_balloons(&conn, "d:/ssp", "rk1")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.ghost_name)
+            .collect();
+        assert_eq!(names, vec!["Alice", "Bob", "Carol"]);
+    }
+}
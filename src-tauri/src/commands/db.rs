@@ -1,6 +1,7 @@
 use tauri::Manager;
 
-/// ghosts.db と関連ファイル（WAL/SHM）を削除してマイグレーション競合を解消する
+/// ghosts.db と関連ファイル（WAL/SHM）を削除してマイグレーション競合を解消する。
+/// ghosts テーブルから参照されるサムネイルキャッシュも合わせて削除する。
 #[tauri::command]
 pub fn reset_ghost_db(app_handle: tauri::AppHandle) -> Result<(), String> {
     let app_data_dir = app_handle
@@ -15,5 +16,11 @@ pub fn reset_ghost_db(app_handle: tauri::AppHandle) -> Result<(), String> {
                 .map_err(|e| format!("{filename} の削除に失敗: {e}"))?;
         }
     }
+
+    let thumbnail_dir = crate::commands::ghost::thumbnail_cache::thumbnail_cache_dir(&app_handle)?;
+    if thumbnail_dir.exists() {
+        std::fs::remove_dir_all(&thumbnail_dir)
+            .map_err(|e| format!("サムネイルキャッシュの削除に失敗: {e}"))?;
+    }
     Ok(())
 }
@@ -0,0 +1,439 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, OnceLock};
+
+use super::progress::ScanProgress;
+use super::types::{ScanProgressEvent, ScanStoreResult};
+
+/// scan_and_store 1 回分の依頼
+#[derive(Debug, Clone)]
+pub(crate) struct ScanRequest {
+    pub ssp_path: String,
+    pub additional_folders: Vec<String>,
+    pub request_key: String,
+    pub cached_fingerprint: Option<String>,
+}
+
+/// スキャン本体。ドライバのスレッドで 1 回だけ呼ぶ
+type ScanJob =
+    Box<dyn FnOnce(&ScanRequest, &Arc<ScanProgress>) -> Result<ScanStoreResult, String> + Send>;
+
+/// 走査と書き込み 1 回分。同じ依頼の scan_and_store はこれに相乗りし、結果を共有する
+struct Flight {
+    id: u64,
+    request: ScanRequest,
+    progress: Arc<ScanProgress>,
+    job: Mutex<Option<ScanJob>>,
+    result: OnceLock<Result<ScanStoreResult, String>>,
+}
+
+#[derive(Default)]
+struct CoordinatorState {
+    next_id: u64,
+    /// 順番待ち・実行中のスキャン（先頭が実行中）
+    queue: VecDeque<Arc<Flight>>,
+    /// 結果を待っている scan ID と、その相乗り先
+    waiters: HashMap<String, Arc<Flight>>,
+    /// queue を先頭から実行するスレッドが動いているか
+    driving: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<CoordinatorState>,
+    /// 順番・結果・キャンセルが変わったときに、待っているスレッドを起こす
+    changed: Condvar,
+}
+
+/// scan_and_store を 1 本ずつ実行する調停役（lib.rs で manage する）。
+/// 同じ依頼（request_key と cached_fingerprint が同じ）は実行中・順番待ちのスキャンに相乗りして結果を共有し、
+/// 違う依頼は到着順に並べて、ghosts.db への書き込みが重ならないようにする。
+/// スキャンは呼び出し元とは別のスレッド（ドライバ）で実行するので、どの呼び出しもキャンセルするとすぐに戻る
+#[derive(Default)]
+pub struct ScanCoordinator(Arc<Shared>);
+
+/// キャンセルされた scan_and_store の戻り値。DB は書き換えていない
+pub(crate) fn cancelled_result(request_key: String) -> ScanStoreResult {
+    ScanStoreResult {
+        cache_hit: false,
+        total: 0,
+        fingerprint: String::new(),
+        request_key,
+        cancelled: true,
+    }
+}
+
+impl ScanCoordinator {
+    /// scan_id として依頼を登録し、結果を待つ（ブロッキングプールから呼ぶ）。
+    /// 相乗りできるスキャンがなければ新しく並べ、scan はドライバのスレッドで順番が来たときに実行する。
+    /// sink は相乗りしている scan ID ごとの進捗イベントの送り先で、スキャンを新しく作ったときだけ使う。
+    /// 待っている間に cancel されたら、ほかの相乗りやスキャンの終わりを待たずに `cancelled: true` を返す
+    pub(crate) fn run(
+        &self,
+        scan_id: &str,
+        request: ScanRequest,
+        sink: impl Fn(ScanProgressEvent) + Send + Sync + 'static,
+        scan: impl FnOnce(&ScanRequest, &Arc<ScanProgress>) -> Result<ScanStoreResult, String>
+        + Send
+        + 'static,
+    ) -> Result<ScanStoreResult, String> {
+        let flight = {
+            let mut state = self.lock()?;
+            if state.waiters.contains_key(scan_id) {
+                return Err(format!("同じスキャン ID のスキャンが実行中です: {scan_id}"));
+            }
+            // 全員がキャンセルしたスキャンには相乗りしない（止まるので結果が使えない）
+            let joined = state
+                .queue
+                .iter()
+                .find(|flight| {
+                    flight.request.request_key == request.request_key
+                        && flight.request.cached_fingerprint == request.cached_fingerprint
+                        && !flight.progress.is_cancelled()
+                })
+                .cloned();
+            let flight = match joined {
+                Some(flight) => flight,
+                None => {
+                    state.next_id += 1;
+                    let id = state.next_id;
+                    let flight = Arc::new(Flight {
+                        id,
+                        request,
+                        progress: Arc::new(self.progress_for(id, sink)),
+                        job: Mutex::new(Some(Box::new(scan))),
+                        result: OnceLock::new(),
+                    });
+                    state.queue.push_back(Arc::clone(&flight));
+                    if !state.driving {
+                        let shared = Arc::clone(&self.0);
+                        if let Err(e) = std::thread::Builder::new()
+                            .name("ghost-scan".to_string())
+                            .spawn(move || drive(&shared))
+                        {
+                            state.queue.pop_back();
+                            return Err(format!("スキャンスレッドの起動エラー: {e}"));
+                        }
+                        state.driving = true;
+                    }
+                    flight
+                }
+            };
+            state
+                .waiters
+                .insert(scan_id.to_string(), Arc::clone(&flight));
+            flight
+        };
+
+        self.wait_result(scan_id, &flight)
+    }
+
+    /// scan_id の待ちを打ち切る。同じスキャンに相乗りしている scan ID がほかになければ、スキャン自体も止める
+    /// （順番待ちならキューから外し、走査しない）。待っている scan ID が見つかれば true
+    pub(crate) fn cancel(&self, scan_id: &str) -> Result<bool, String> {
+        let mut state = self.lock()?;
+        let Some(flight) = state.waiters.remove(scan_id) else {
+            return Ok(false);
+        };
+        if !state
+            .waiters
+            .values()
+            .any(|other| Arc::ptr_eq(other, &flight))
+        {
+            flight.progress.cancel();
+            // 先頭は実行中（またはドライバが取り出したところ）なので、ドライバが外す
+            if let Some(position) = state
+                .queue
+                .iter()
+                .skip(1)
+                .position(|queued| Arc::ptr_eq(queued, &flight))
+            {
+                state.queue.remove(position + 1);
+            }
+        }
+        drop(state);
+        self.0.changed.notify_all();
+        Ok(true)
+    }
+
+    /// 相乗りしている scan ID ごとに進捗イベントを sink へ送る ScanProgress を作る。
+    /// Shared は弱参照で持つ（Shared → Flight → ScanProgress の循環を作らない）
+    fn progress_for(
+        &self,
+        flight_id: u64,
+        sink: impl Fn(ScanProgressEvent) + Send + Sync + 'static,
+    ) -> ScanProgress {
+        let shared = Arc::downgrade(&self.0);
+        ScanProgress::new(move |progress| {
+            let Some(shared) = shared.upgrade() else {
+                return;
+            };
+            let scan_ids: Vec<String> = match shared.state.lock() {
+                Ok(state) => state
+                    .waiters
+                    .iter()
+                    .filter(|(_, flight)| flight.id == flight_id)
+                    .map(|(scan_id, _)| scan_id.clone())
+                    .collect(),
+                Err(_) => return,
+            };
+            for scan_id in scan_ids {
+                sink(progress.event(&scan_id));
+            }
+        })
+    }
+
+    /// scan_id の結果を待つ。cancel で待ちから外されたら `cancelled: true` を返す
+    fn wait_result(&self, scan_id: &str, flight: &Arc<Flight>) -> Result<ScanStoreResult, String> {
+        let mut state = self.lock()?;
+        loop {
+            // 同じ scan ID で次のスキャンが登録されていることもあるので、相乗り先まで比べる
+            if !state
+                .waiters
+                .get(scan_id)
+                .is_some_and(|waiting| Arc::ptr_eq(waiting, flight))
+            {
+                return Ok(cancelled_result(flight.request.request_key.clone()));
+            }
+            if let Some(result) = flight.result.get() {
+                state.waiters.remove(scan_id);
+                return result.clone();
+            }
+            state = self
+                .0
+                .changed
+                .wait(state)
+                .map_err(|_| "スキャンの状態を取得できません".to_string())?;
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, CoordinatorState>, String> {
+        self.0
+            .state
+            .lock()
+            .map_err(|_| "スキャンの状態を取得できません".to_string())
+    }
+}
+
+/// ドライバのスレッド本体。queue の先頭から 1 本ずつ実行し、queue が空になったら終わる。
+/// scan が panic しても結果をエラーにして次のスキャンに順番を回す
+fn drive(shared: &Shared) {
+    loop {
+        let flight = {
+            let mut state = shared
+                .state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            match state.queue.front() {
+                Some(front) => Arc::clone(front),
+                None => {
+                    state.driving = false;
+                    return;
+                }
+            }
+        };
+
+        let job = flight
+            .job
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        // 順番待ちの間に全員がキャンセルしていたら走査しない
+        let result = match job {
+            Some(job) if !flight.progress.is_cancelled() => {
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    job(&flight.request, &flight.progress)
+                }))
+                .unwrap_or_else(|_| Err("スキャンが途中で異常終了しました".to_string()))
+            }
+            _ => Ok(cancelled_result(flight.request.request_key.clone())),
+        };
+
+        let mut state = shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = flight.result.set(result);
+        if state
+            .queue
+            .front()
+            .is_some_and(|front| Arc::ptr_eq(front, &flight))
+        {
+            state.queue.pop_front();
+        }
+        drop(state);
+        shared.changed.notify_all();
+    }
+}
+
+/// 実行中のスキャンの待ちを打ち切るコマンド。打ち切れたら true（終わっていた・見つからなければ false）。
+/// 同じスキャンに相乗りしているほかの scan ID がなければ、スキャンは走査の次の区切りで止まり、DB を書き換えない
+#[tauri::command]
+pub fn cancel_ghost_scan(
+    state: tauri::State<'_, ScanCoordinator>,
+    scan_id: String,
+) -> Result<bool, String> {
+    state.cancel(&scan_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn request(request_key: &str) -> ScanRequest {
+        ScanRequest {
+            ssp_path: "C:/SSP".to_string(),
+            additional_folders: Vec::new(),
+            request_key: request_key.to_string(),
+            cached_fingerprint: Some("fp".to_string()),
+        }
+    }
+
+    fn stored(request: &ScanRequest) -> Result<ScanStoreResult, String> {
+        Ok(ScanStoreResult {
+            cache_hit: false,
+            total: 1,
+            fingerprint: "fp2".to_string(),
+            request_key: request.request_key.clone(),
+            cancelled: false,
+        })
+    }
+
+    fn waiter_count(coordinator: &ScanCoordinator) -> usize {
+        coordinator.0.state.lock().unwrap().waiters.len()
+    }
+
+    fn wait_until(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// release に送るまで scan を止めておくスキャン。started には走査を始めたら送る
+    fn spawn_blocked_scan(
+        coordinator: &Arc<ScanCoordinator>,
+        scan_id: &str,
+        request_key: &str,
+        scans: &Arc<AtomicUsize>,
+    ) -> (
+        thread::JoinHandle<Result<ScanStoreResult, String>>,
+        mpsc::Sender<()>,
+    ) {
+        let (release, released) = mpsc::channel::<()>();
+        let coordinator = Arc::clone(coordinator);
+        let scans = Arc::clone(scans);
+        let scan_id = scan_id.to_string();
+        let request = request(request_key);
+        let handle = thread::spawn(move || {
+            coordinator.run(
+                &scan_id,
+                request,
+                |_| {},
+                move |request, _| {
+                    scans.fetch_add(1, Ordering::SeqCst);
+                    let _ = released.recv();
+                    stored(request)
+                },
+            )
+        });
+        (handle, release)
+    }
+
+    #[test]
+    fn 同じ依頼は実行中のスキャンに相乗りし_走査は1回だけ行う() {
+        let coordinator = Arc::new(ScanCoordinator::default());
+        let scans = Arc::new(AtomicUsize::new(0));
+        let (first, release) = spawn_blocked_scan(&coordinator, "scan-1", "rk1", &scans);
+        wait_until(|| scans.load(Ordering::SeqCst) == 1);
+
+        let (second, _unused) = spawn_blocked_scan(&coordinator, "scan-2", "rk1", &scans);
+        wait_until(|| waiter_count(&coordinator) == 2);
+        release.send(()).unwrap();
+
+        let first = first.join().unwrap().unwrap();
+        let second = second.join().unwrap().unwrap();
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+        assert_eq!((first.total, second.total), (1, 1));
+        assert!(!first.cancelled && !second.cancelled);
+        assert_eq!(waiter_count(&coordinator), 0);
+    }
+
+    #[test]
+    fn 違う依頼は到着順に1本ずつ実行する() {
+        let coordinator = Arc::new(ScanCoordinator::default());
+        let scans = Arc::new(AtomicUsize::new(0));
+        let (first, release_first) = spawn_blocked_scan(&coordinator, "scan-1", "rk1", &scans);
+        wait_until(|| scans.load(Ordering::SeqCst) == 1);
+
+        let (second, release_second) = spawn_blocked_scan(&coordinator, "scan-2", "rk2", &scans);
+        wait_until(|| waiter_count(&coordinator) == 2);
+        // 1 本目が終わるまで 2 本目は走査を始めない
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+
+        release_first.send(()).unwrap();
+        assert_eq!(first.join().unwrap().unwrap().request_key, "rk1");
+        wait_until(|| scans.load(Ordering::SeqCst) == 2);
+        release_second.send(()).unwrap();
+        assert_eq!(second.join().unwrap().unwrap().request_key, "rk2");
+    }
+
+    #[test]
+    fn キャンセルした待ちだけが抜け_全員が抜けたらスキャンも止める() {
+        let coordinator = Arc::new(ScanCoordinator::default());
+        let scans = Arc::new(AtomicUsize::new(0));
+        let (first, release) = spawn_blocked_scan(&coordinator, "scan-1", "rk1", &scans);
+        wait_until(|| scans.load(Ordering::SeqCst) == 1);
+        let (second, _unused) = spawn_blocked_scan(&coordinator, "scan-2", "rk1", &scans);
+        wait_until(|| waiter_count(&coordinator) == 2);
+        // 順番待ちのうちに全員がキャンセルした依頼はキューから外し、走査しない
+        let (third, _unused) = spawn_blocked_scan(&coordinator, "scan-3", "rk2", &scans);
+        wait_until(|| waiter_count(&coordinator) == 3);
+
+        assert!(coordinator.cancel("scan-2").unwrap());
+        assert!(second.join().unwrap().unwrap().cancelled);
+        let progress = Arc::clone(&coordinator.0.state.lock().unwrap().queue[0].progress);
+        assert!(!progress.is_cancelled());
+
+        assert!(coordinator.cancel("scan-3").unwrap());
+        assert!(!coordinator.cancel("scan-3").unwrap());
+        assert!(third.join().unwrap().unwrap().cancelled);
+        assert_eq!(coordinator.0.state.lock().unwrap().queue.len(), 1);
+
+        assert!(coordinator.cancel("scan-1").unwrap());
+        assert!(progress.is_cancelled());
+        release.send(()).unwrap();
+        assert!(first.join().unwrap().unwrap().cancelled);
+        wait_until(|| coordinator.0.state.lock().unwrap().queue.is_empty());
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn スキャンを始めた呼び出しもキャンセルしたらスキャンの終わりを待たずに戻る() {
+        let coordinator = Arc::new(ScanCoordinator::default());
+        let scans = Arc::new(AtomicUsize::new(0));
+        let (first, release_first) = spawn_blocked_scan(&coordinator, "scan-1", "rk1", &scans);
+        wait_until(|| scans.load(Ordering::SeqCst) == 1);
+        let (second, _unused) = spawn_blocked_scan(&coordinator, "scan-2", "rk1", &scans);
+        // 順番待ちのスキャンを作った呼び出し
+        let (queued, _unused) = spawn_blocked_scan(&coordinator, "scan-3", "rk2", &scans);
+        wait_until(|| waiter_count(&coordinator) == 3);
+
+        // 相乗りが残っていてもスキャンを作った呼び出しだけが抜け、スキャンは続ける
+        assert!(coordinator.cancel("scan-1").unwrap());
+        assert!(first.join().unwrap().unwrap().cancelled);
+        assert!(coordinator.cancel("scan-3").unwrap());
+        assert!(queued.join().unwrap().unwrap().cancelled);
+
+        release_first.send(()).unwrap();
+        let second = second.join().unwrap().unwrap();
+        assert!(!second.cancelled);
+        assert_eq!(second.total, 1);
+        assert_eq!(scans.load(Ordering::SeqCst), 1);
+    }
+}
@@ -98,8 +98,31 @@ pub(crate) fn collect_parent_mtimes(
     entries.join("\n")
 }
 
+/// 入れ子の親ディレクトリ（追加フォルダ配下の作者フォルダなど）の parent_mtimes 行の接頭辞
+const NESTED_PARENT_PREFIX: &str = "nested|";
+
+/// 入れ子の親ディレクトリ 1 件分の parent_mtimes 行を生成する。
+/// Layer 1 で再度 stat するため、パスは正規化せずにそのまま末尾に置く
+pub(crate) fn nested_parent_mtime_line(dir: &Path, modified: &str) -> String {
+    format!(
+        "{}{}|{}",
+        NESTED_PARENT_PREFIX,
+        modified,
+        dir.to_string_lossy()
+    )
+}
+
+/// collect_parent_mtimes の結果にスキャンでたどった入れ子の親ディレクトリの行を加え、保存用の parent_mtimes を作る
+pub(crate) fn with_nested_parent_mtimes(parent_mtimes: &str, nested_parents: &[String]) -> String {
+    std::iter::once(parent_mtimes)
+        .chain(nested_parents.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 /// Layer 1 高速チェック: 親ディレクトリの mtime が前回と一致するか判定する。
 /// 一致すればゴーストフォルダの追加・削除がないことが保証される（NTFS の特性）。
+/// 入れ子の親ディレクトリは保存済みの行のパスを stat し直して比較する（前回のスキャンでたどったものだけが対象）。
 /// ただし既存ゴースト内の descript.txt 編集は検出できない（Layer 2 が必要）。
 pub(crate) fn check_parent_mtimes_match(
     conn: &rusqlite::Connection,
@@ -114,10 +137,24 @@ pub(crate) fn check_parent_mtimes_match(
         )
         .ok();
 
-    match stored {
-        Some(ref s) if !s.is_empty() => s == current_mtimes,
-        _ => false,
-    }
+    let Some(stored) = stored.filter(|s| !s.is_empty()) else {
+        return false;
+    };
+    let (nested, top_level): (Vec<&str>, Vec<&str>) = stored
+        .lines()
+        .partition(|line| line.starts_with(NESTED_PARENT_PREFIX));
+    top_level.join("\n") == current_mtimes
+        && nested.iter().all(|line| {
+            line[NESTED_PARENT_PREFIX.len()..]
+                .split_once('|')
+                .is_some_and(|(modified, dir)| {
+                    let current = fs::metadata(dir)
+                        .as_ref()
+                        .map(metadata_modified_string)
+                        .unwrap_or_else(|_| "unreadable".to_string());
+                    current == modified
+                })
+        })
 }
 
 /// トークン列からフィンガープリントハッシュを計算する
@@ -141,16 +178,29 @@ pub(crate) fn build_fingerprint(
     ssp_path: &str,
     additional_folders: &[String],
 ) -> Result<String, String> {
-    use super::scan::{unique_sorted_additional_folders, walk_parent};
-
-    let mut tokens = vec!["fingerprint-version|1".to_string()];
+    use super::scan::{
+        unique_sorted_additional_folders, walk_parent, WalkOutput, ADDITIONAL_FOLDER_SCAN_DEPTH,
+        SSP_GHOST_SCAN_DEPTH,
+    };
+
+    let mut out = WalkOutput {
+        tokens: vec!["fingerprint-version|1".to_string()],
+        ..WalkOutput::default()
+    };
     let ghost_dir = Path::new(ssp_path).join("ghost");
 
-    walk_parent(&ghost_dir, "ssp", true, &mut tokens, None)?;
+    walk_parent(&ghost_dir, "ssp", true, SSP_GHOST_SCAN_DEPTH, &mut out, None)?;
     for (_, folder_path, normalized_folder) in unique_sorted_additional_folders(additional_folders)
     {
-        walk_parent(&folder_path, &normalized_folder, false, &mut tokens, None)?;
+        walk_parent(
+            &folder_path,
+            &normalized_folder,
+            false,
+            ADDITIONAL_FOLDER_SCAN_DEPTH,
+            &mut out,
+            None,
+        )?;
     }
 
-    Ok(compute_fingerprint_hash(&tokens))
+    Ok(compute_fingerprint_hash(&out.tokens))
 }
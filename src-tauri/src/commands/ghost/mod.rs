@@ -1,15 +1,26 @@
 // mod.rs
+pub(crate) mod coordinator;
 mod fingerprint;
 mod path_utils;
-mod scan;
+pub(crate) mod progress;
+pub(crate) mod scan;
+pub(crate) mod search;
 pub(crate) mod store;
+pub(crate) mod thumbnail;
+pub(crate) mod thumbnail_cache;
 mod types;
+pub(crate) mod watcher;
 
-pub use types::ScanStoreResult;
+use std::sync::Arc;
+
+use coordinator::{cancelled_result, ScanCoordinator, ScanRequest};
+use progress::{ScanProgress, SCAN_PROGRESS_EVENT};
+
+pub use types::{GhostScanSkip, ScanStoreResult};
 
 /// request_key が空なら Err を返す。JS 単一権威の信頼境界での最小防御。
 /// 空キーで書き込むと全ゴーストが request_key='' パーティションに同居する事故を防ぐ。
-fn ensure_request_key(request_key: &str) -> Result<(), String> {
+pub(crate) fn ensure_request_key(request_key: &str) -> Result<(), String> {
     if request_key.is_empty() {
         return Err("request_key が空です".to_string());
     }
@@ -21,21 +32,69 @@ fn ensure_request_key(request_key: &str) -> Result<(), String> {
 ///
 /// 2 層フィンガープリント:
 /// - Layer 1: 親ディレクトリ mtime チェック（< 1ms）。ゴーストフォルダの追加・削除を検出
-/// - Layer 2: 従来のフル fingerprint。全エントリの mtime + descript.txt 有無を走査
+/// - Layer 2: 従来のフル fingerprint。全エントリの mtime + descript.txt 有無を走査。
+///   miss 時は、エントリトークン（フォルダ mtime + descript.txt の有無・mtime）が前回と変わったゴーストだけを読み直す
+///
+/// 走査はブロッキングプールで行い、進捗を SCAN_PROGRESS_EVENT で送る。
+/// 同時に呼ばれたときは ScanCoordinator が 1 本ずつ実行する（同じ依頼は相乗りして結果を共有する）。
+/// `cancel_ghost_scan(scan_id)` で打ち切ると `cancelled: true` を返し、ほかに待っている呼び出しがなければ DB を書き換えない
 #[tauri::command]
-pub fn scan_and_store(
+pub async fn scan_and_store(
     app: tauri::AppHandle,
     ssp_path: String,
     additional_folders: Vec<String>,
     request_key: String,
     cached_fingerprint: Option<String>,
+    scan_id: String,
 ) -> Result<ScanStoreResult, String> {
-    use tauri::Manager;
+    use tauri::{Emitter, Manager};
 
     ensure_request_key(&request_key)?;
 
+    let request = ScanRequest {
+        ssp_path,
+        additional_folders,
+        request_key,
+        cached_fingerprint,
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let emitter = app.clone();
+        let sink = move |event| {
+            let _ = emitter.emit(SCAN_PROGRESS_EVENT, event);
+        };
+        app.state::<ScanCoordinator>()
+            .run(&scan_id, request, sink, {
+                let app = app.clone();
+                move |request, progress| {
+                    scan_and_store_blocking(
+                        &app,
+                        &request.ssp_path,
+                        &request.additional_folders,
+                        request.request_key.clone(),
+                        request.cached_fingerprint.clone(),
+                        progress,
+                    )
+                }
+            })
+    })
+    .await
+    .map_err(|e| format!("スキャンの実行エラー: {e}"))?
+}
+
+/// scan_and_store の本体（ScanCoordinator のスキャンスレッドで実行する）。
+/// progress がキャンセルされていたら、書き込みの前に `cancelled: true` で返す
+fn scan_and_store_blocking(
+    app: &tauri::AppHandle,
+    ssp_path: &str,
+    additional_folders: &[String],
+    request_key: String,
+    cached_fingerprint: Option<String>,
+    progress: &Arc<ScanProgress>,
+) -> Result<ScanStoreResult, String> {
+    use tauri::Manager;
+
     // 親ディレクトリ mtime を 1 回だけ収集（Layer 1 / Layer 2 hit / cache miss で共用）
-    let current_mtimes = fingerprint::collect_parent_mtimes(&ssp_path, &additional_folders);
+    let current_mtimes = fingerprint::collect_parent_mtimes(ssp_path, additional_folders);
 
     // DB パスを 1 回だけ解決
     let db_path = app
@@ -47,23 +106,58 @@ pub fn scan_and_store(
     // Layer 1: 親ディレクトリ mtime 高速チェック（< 1ms）
     // NTFS では親の mtime は直下のエントリ追加・削除でのみ変化する。
     // 既存ゴースト内の descript.txt 編集は検出できない（「再読込」で対応）。
-    if cached_fingerprint.is_some() && db_path.exists() {
-        if let Ok(conn) = rusqlite::Connection::open(&db_path) {
-            let _ = store::configure_connection(&conn);
-            if fingerprint::check_parent_mtimes_match(&conn, &request_key, &current_mtimes) {
-                return Ok(ScanStoreResult {
-                    cache_hit: true,
-                    total: 0,
-                    fingerprint: cached_fingerprint.unwrap_or_default(),
-                    request_key,
-                });
-            }
+    if cached_fingerprint.is_some()
+        && db_path.exists()
+        && let Ok(conn) = rusqlite::Connection::open(&db_path)
+    {
+        let _ = store::configure_connection(&conn);
+        if fingerprint::check_parent_mtimes_match(&conn, &request_key, &current_mtimes) {
+            return Ok(ScanStoreResult {
+                cache_hit: true,
+                total: 0,
+                fingerprint: cached_fingerprint.unwrap_or_default(),
+                request_key,
+                cancelled: false,
+            });
         }
     }
 
-    // Layer 2: フル fingerprint 計算 + ゴーストスキャン
-    let (ghosts, fingerprint) =
-        scan::scan_ghosts_with_fingerprint_internal(&ssp_path, &additional_folders)?;
+    // 走査から書き込みまでフォルダ監視・install_nar の書き込みと排他にする
+    let write_lock = app.state::<store::GhostWriteLock>();
+    let _writes = write_lock.lock();
+
+    // 前回のエントリトークン。トークンが変わっていないゴーストは読み直さず、DB の行をそのまま使う。
+    // 「再読込」（cached_fingerprint なし）では使わず、全件読み直す
+    let previous = match cached_fingerprint {
+        Some(_) if db_path.exists() => rusqlite::Connection::open(&db_path)
+            .ok()
+            .and_then(|conn| {
+                let _ = store::configure_connection(&conn);
+                store::load_previous_entries(&conn, &request_key).ok()
+            })
+            .unwrap_or_default(),
+        _ => store::PreviousEntries::new(),
+    };
+
+    // Layer 2: フル fingerprint 計算 + 変化したゴーストだけのスキャン
+    let scan::ScanOutput {
+        mut ghosts,
+        fingerprint,
+        nested_parents,
+        skipped,
+        entry_tokens,
+    } = scan::scan_ghosts_with_fingerprint_internal(
+        ssp_path,
+        additional_folders,
+        previous,
+        Arc::clone(progress),
+    )?;
+    // 打ち切った走査の結果は不完全なので、fingerprint も含めて何も書き込まない
+    if progress.is_cancelled() {
+        return Ok(cancelled_result(request_key));
+    }
+    // 入れ子の親ディレクトリ（追加フォルダ配下の作者フォルダなど）も Layer 1 の対象に加える
+    let current_mtimes = fingerprint::with_nested_parent_mtimes(&current_mtimes, &nested_parents);
     let cache_hit = cached_fingerprint.as_deref() == Some(fingerprint.as_str());
 
     if cache_hit {
@@ -75,12 +169,14 @@ pub fn scan_and_store(
                 "UPDATE ghost_fingerprints SET parent_mtimes = ?1 WHERE request_key = ?2",
                 rusqlite::params![current_mtimes, request_key],
             );
+            let _ = store::store_scan_skips(&conn, &request_key, &skipped);
         }
         return Ok(ScanStoreResult {
             cache_hit: true,
             total: 0,
             fingerprint,
             request_key,
+            cancelled: false,
         });
     }
 
@@ -89,30 +185,78 @@ pub fn scan_and_store(
         .map_err(|e| format!("DB オープンエラー: {e}"))?;
     store::configure_connection(&conn)?;
 
-    let total = store::store_ghosts(&conn, &request_key, &ghosts, &fingerprint, &current_mtimes)?;
+    // サムネイルキャッシュを生成してから書き込む（キャッシュパスも ghosts テーブルに保存する）
+    let thumbnail_dir = thumbnail_cache::thumbnail_cache_dir(app)?;
+    thumbnail_cache::populate_thumbnail_cache(&thumbnail_dir, &mut ghosts)?;
+    if progress.is_cancelled() {
+        return Ok(cancelled_result(request_key));
+    }
+
+    let total = store::store_ghosts(
+        &conn,
+        &request_key,
+        &ghosts,
+        &entry_tokens,
+        &fingerprint,
+        &current_mtimes,
+    )?;
+    store::store_scan_skips(&conn, &request_key, &skipped)?;
+
+    // 削除されたゴーストのキャッシュを掃除する（失敗してもスキャン結果は有効）
+    let _ = thumbnail_cache::evict_unreferenced_thumbnails(&conn, &thumbnail_dir);
 
     Ok(ScanStoreResult {
         cache_hit: false,
         total,
         fingerprint,
         request_key,
+        cancelled: false,
     })
 }
 
+/// 直近のスキャンで一覧に載らなかった・壊れていたフォルダを返すコマンド（パス順）。
+/// `scan_and_store` が書き込んだ ghost_scan_skips を読むだけで、走査はしない。未スキャンなら空
+#[tauri::command]
+pub fn get_scan_report(
+    app: tauri::AppHandle,
+    request_key: String,
+) -> Result<Vec<GhostScanSkip>, String> {
+    use tauri::Manager;
+
+    ensure_request_key(&request_key)?;
+    let db_path = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("app_config_dir 取得エラー: {e}"))?
+        .join("ghosts.db");
+    if !db_path.exists() {
+        return Ok(Vec::new());
+    }
+    let conn = rusqlite::Connection::open(&db_path)
+        .map_err(|e| format!("DB オープンエラー: {e}"))?;
+    store::configure_connection(&conn)?;
+    store::load_scan_skips(&conn, &request_key)
+}
+
 #[cfg(test)]
 mod tests {
     use super::fingerprint::build_fingerprint;
+    use super::progress::ScanProgress;
     use super::scan::{scan_ghosts_with_fingerprint_internal, unique_sorted_additional_folders};
+    use super::store::tests::setup_db;
+    use super::store::{load_previous_entries, store_ghosts, PreviousEntries};
     use crate::testutil::TempDirGuard;
     use std::fs;
-    use std::path::PathBuf;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, SystemTime};
 
-    fn create_ghost_dir(root: &PathBuf, name: &str) -> Result<(), String> {
+    fn create_ghost_dir(root: &Path, name: &str) -> Result<(), String> {
         create_ghost_dir_with_descript(root, name, "name,Test Ghost\ncharset,UTF-8\n")
     }
 
     fn create_ghost_dir_with_descript(
-        root: &PathBuf,
+        root: &Path,
         name: &str,
         descript: &str,
     ) -> Result<(), String> {
@@ -200,8 +344,13 @@ mod tests {
             additional_b.to_string_lossy().to_string(),
             additional_a.to_string_lossy().to_string(),
         ];
-        let (ghosts, _) =
-            scan_ghosts_with_fingerprint_internal(&ssp_root.to_string_lossy(), &additional_paths)?;
+        let ghosts = scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &additional_paths,
+            PreviousEntries::new(),
+            Arc::default(),
+        )?
+        .ghosts;
 
         assert_eq!(ghosts.len(), 3);
         assert_eq!(ghosts[0].name, "Alpha");
@@ -247,7 +396,13 @@ mod tests {
             "name,作者なし\ncharset,UTF-8\n",
         )?;
 
-        let (ghosts, _) = scan_ghosts_with_fingerprint_internal(&ssp_root.to_string_lossy(), &[])?;
+        let ghosts = scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &[],
+            PreviousEntries::new(),
+            Arc::default(),
+        )?
+        .ghosts;
 
         let with_craftman = ghosts
             .iter()
@@ -264,6 +419,335 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn scan_ghosts_internal_detects_shiori_engine() -> Result<(), String> {
+        let workspace = TempDirGuard::new("ghost_launcher_shiori_engine_test");
+        let ssp_root = workspace.path().join("ssp");
+        let ssp_ghost = ssp_root.join("ghost");
+        fs::create_dir_all(&ssp_ghost)
+            .map_err(|error| format!("failed to create ssp ghost dir: {}", error))?;
+        create_ghost_dir_with_descript(
+            &ssp_ghost,
+            "by_field",
+            "name,栞指定
+shiori,yaya.dll
+charset,UTF-8
+",
+        )?;
+        create_ghost_dir_with_descript(&ssp_ghost, "by_files", "name,辞書
+charset,UTF-8
+")?;
+        fs::write(
+            ssp_ghost.join("by_files").join("ghost").join("master").join("satori_conf.txt"),
+            "",
+        )
+        .map_err(|error| format!("failed to write satori_conf: {}", error))?;
+        create_ghost_dir(&ssp_ghost, "plain")?;
+
+        let ghosts = scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &[],
+            PreviousEntries::new(),
+            Arc::default(),
+        )?
+        .ghosts;
+        let engine_of = |dir: &str| {
+            ghosts
+                .iter()
+                .find(|ghost| ghost.directory_name == dir)
+                .map(|ghost| ghost.shiori_engine.clone())
+        };
+        assert_eq!(engine_of("by_field").as_deref(), Some("yaya"));
+        assert_eq!(engine_of("by_files").as_deref(), Some("satori"));
+        assert_eq!(engine_of("plain").as_deref(), Some("unknown"));
+        Ok(())
+    }
+
+    #[test]
+    fn scan_ghosts_internal_finds_nested_ghosts_in_additional_folders() -> Result<(), String> {
+        let workspace = TempDirGuard::new("ghost_launcher_nested_scan_test");
+        let ssp_root = workspace.path().join("ssp");
+        let ssp_ghost = ssp_root.join("ghost");
+        fs::create_dir_all(&ssp_ghost)
+            .map_err(|error| format!("failed to create ssp ghost dir: {}", error))?;
+        // SSP の ghost/ はサブフォルダをたどらない
+        create_ghost_dir(&ssp_ghost.join("group"), "ssp_nested")?;
+
+        let additional = workspace.path().join("Ghosts");
+        create_ghost_dir_with_descript(&additional, "flat", "name,flat\ncharset,UTF-8\n")?;
+        create_ghost_dir_with_descript(
+            &additional.join("author"),
+            "nested",
+            "name,nested\ncharset,UTF-8\n",
+        )?;
+        // ゴーストの中にはゴーストを探しに入らない
+        create_ghost_dir(&additional.join("flat").join("ghost"), "inner")?;
+        // 深さの上限を超えた位置のゴーストは拾わない
+        create_ghost_dir(&additional.join("a").join("b").join("c"), "too_deep")?;
+
+        let additional_folders = vec![additional.to_string_lossy().to_string()];
+        let ssp_path = ssp_root.to_string_lossy().to_string();
+        let output = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &additional_folders,
+            PreviousEntries::new(),
+            Arc::default(),
+        )?;
+
+        let directory_names: Vec<&str> = output
+            .ghosts
+            .iter()
+            .map(|ghost| ghost.directory_name.as_str())
+            .collect();
+        assert_eq!(directory_names, vec!["flat", "author/nested"]);
+        assert!(output.ghosts.iter().all(|ghost| ghost.source == additional_folders[0]));
+
+        // たどった作者フォルダは Layer 1 の対象、ゴーストのフォルダは対象外
+        assert!(output.nested_parents.iter().any(|line| line.ends_with("author")));
+        assert!(!output.nested_parents.iter().any(|line| line.ends_with("flat")));
+
+        // 入れ子のゴーストの追加で Layer 2 のフィンガープリントも変わる
+        let before = build_fingerprint(&ssp_path, &additional_folders)?;
+        assert_eq!(before, output.fingerprint);
+        create_ghost_dir(&additional.join("author"), "added")?;
+        let after = build_fingerprint(&ssp_path, &additional_folders)?;
+        assert_ne!(before, after);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_ghosts_internal_rereads_only_entries_whose_token_changed() -> Result<(), String> {
+        let workspace = TempDirGuard::new("ghost_launcher_incremental_scan_test");
+        let ssp_root = workspace.path().join("ssp");
+        let ssp_ghost = ssp_root.join("ghost");
+        create_ghost_dir_with_descript(&ssp_ghost, "alice", "name,Alice\ncharset,UTF-8\n")?;
+        create_ghost_dir_with_descript(&ssp_ghost, "bob", "name,Bob\ncharset,UTF-8\n")?;
+        let ssp_path = ssp_root.to_string_lossy().to_string();
+        let conn = setup_db();
+
+        let first = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &[],
+            PreviousEntries::new(),
+            Arc::default(),
+        )?;
+        assert_eq!(first.ghosts.len(), 2);
+        assert!(first.entry_tokens.reused.is_empty());
+        store_ghosts(
+            &conn,
+            "rk1",
+            &first.ghosts,
+            &first.entry_tokens,
+            &first.fingerprint,
+            "",
+        )?;
+
+        // bob の descript.txt だけを書き換える（更新日時の分解能に頼らないよう明示的にずらす）
+        let bob_descript = ssp_ghost
+            .join("bob")
+            .join("ghost")
+            .join("master")
+            .join("descript.txt");
+        fs::write(&bob_descript, "name,Bob v2\ncharset,UTF-8\n")
+            .map_err(|error| format!("failed to write descript: {}", error))?;
+        fs::File::options()
+            .write(true)
+            .open(&bob_descript)
+            .and_then(|file| file.set_modified(SystemTime::now() + Duration::from_secs(60)))
+            .map_err(|error| format!("failed to set mtime: {}", error))?;
+
+        let second = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &[],
+            load_previous_entries(&conn, "rk1")?,
+            Arc::default(),
+        )?;
+        let names: Vec<&str> = second
+            .ghosts
+            .iter()
+            .map(|ghost| ghost.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Bob v2"]);
+        assert_eq!(second.entry_tokens.reused.len(), 1);
+        assert_ne!(first.fingerprint, second.fingerprint);
+        let total = store_ghosts(
+            &conn,
+            "rk1",
+            &second.ghosts,
+            &second.entry_tokens,
+            &second.fingerprint,
+            "",
+        )?;
+        assert_eq!(total, 2);
+
+        let mut stmt = conn
+            .prepare("SELECT name FROM ghosts WHERE request_key = 'rk1' ORDER BY name")
+            .map_err(|error| error.to_string())?;
+        let stored: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|error| error.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|error| error.to_string())?;
+        assert_eq!(stored, vec!["Alice", "Bob v2"]);
+
+        // 何も変わっていなければ 1 件も読み直さない
+        let third = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &[],
+            load_previous_entries(&conn, "rk1")?,
+            Arc::default(),
+        )?;
+        assert!(third.ghosts.is_empty());
+        assert_eq!(third.entry_tokens.reused.len(), 2);
+        assert_eq!(third.fingerprint, second.fingerprint);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_ghosts_internal_does_not_report_author_folder_whose_ghosts_were_reused()
+    -> Result<(), String> {
+        let workspace = TempDirGuard::new("ghost_launcher_nested_reuse_test");
+        let ssp_root = workspace.path().join("ssp");
+        fs::create_dir_all(ssp_root.join("ghost"))
+            .map_err(|error| format!("failed to create ssp ghost dir: {}", error))?;
+        let additional = workspace.path().join("Ghosts");
+        create_ghost_dir(&additional, "flat")?;
+        create_ghost_dir(&additional.join("author"), "nested")?;
+        let additional_folders = vec![additional.to_string_lossy().to_string()];
+        let ssp_path = ssp_root.to_string_lossy().to_string();
+        let conn = setup_db();
+
+        let first = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &additional_folders,
+            PreviousEntries::new(),
+            Arc::default(),
+        )?;
+        assert_eq!(first.ghosts.len(), 2);
+        assert!(first.skipped.is_empty());
+        store_ghosts(
+            &conn,
+            "rk1",
+            &first.ghosts,
+            &first.entry_tokens,
+            &first.fingerprint,
+            "",
+        )?;
+
+        // 作者フォルダの中のゴーストを読み直さなくても、作者フォルダを descript.txt なしとして報告しない
+        let second = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &additional_folders,
+            load_previous_entries(&conn, "rk1")?,
+            Arc::default(),
+        )?;
+        assert!(second.ghosts.is_empty());
+        assert_eq!(second.entry_tokens.reused.len(), 2);
+        assert!(second.skipped.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn scan_ghosts_internal_reports_progress_counts() -> Result<(), String> {
+        let workspace = TempDirGuard::new("ghost_launcher_scan_progress_test");
+        let ssp_root = workspace.path().join("ssp");
+        let ssp_ghost = ssp_root.join("ghost");
+        create_ghost_dir(&ssp_ghost, "alice")?;
+        create_ghost_dir(&ssp_ghost, "bob")?;
+        fs::create_dir_all(ssp_ghost.join("not_a_ghost"))
+            .map_err(|error| format!("failed to create not_a_ghost: {}", error))?;
+        let additional = workspace.path().join("additional");
+        create_ghost_dir(&additional, "carol")?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let progress = Arc::new(ScanProgress::new(move |progress| {
+            sink.lock().unwrap().push(progress.event("scan-1"));
+        }));
+        scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &[additional.to_string_lossy().to_string()],
+            PreviousEntries::new(),
+            Arc::clone(&progress),
+        )?;
+
+        let last = events
+            .lock()
+            .unwrap()
+            .last()
+            .cloned()
+            .ok_or_else(|| "no progress event".to_string())?;
+        assert_eq!(last.scan_id, "scan-1");
+        assert_eq!((last.parents_done, last.parents_total), (2, 2));
+        assert_eq!((last.entries_processed, last.ghosts_found), (4, 3));
+        Ok(())
+    }
+
+    #[test]
+    fn scan_ghosts_internal_stops_walking_once_cancelled() -> Result<(), String> {
+        let workspace = TempDirGuard::new("ghost_launcher_scan_cancel_test");
+        let ssp_root = workspace.path().join("ssp");
+        create_ghost_dir(&ssp_root.join("ghost"), "alice")?;
+
+        let progress = Arc::new(ScanProgress::default());
+        progress.cancel();
+        let output = scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &[],
+            PreviousEntries::new(),
+            Arc::clone(&progress),
+        )?;
+
+        assert!(output.ghosts.is_empty());
+        assert_eq!(progress.event("").entries_processed, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn scan_ghosts_internal_reports_skipped_directories() -> Result<(), String> {
+        let workspace = TempDirGuard::new("ghost_launcher_scan_report_test");
+        let ssp_root = workspace.path().join("ssp");
+        let ssp_ghost = ssp_root.join("ghost");
+        fs::create_dir_all(ssp_ghost.join("extracted_wrong"))
+            .map_err(|error| format!("failed to create dir: {}", error))?;
+        create_ghost_dir(&ssp_ghost, "mojibake")?;
+        // UTF-8 宣言なのに Shift_JIS のバイト列
+        fs::write(
+            ssp_ghost.join("mojibake").join("ghost").join("master").join("descript.txt"),
+            b"charset,UTF-8\nname,\x82\xa0\n",
+        )
+        .map_err(|error| format!("failed to write descript: {}", error))?;
+
+        // 追加フォルダ: ゴーストのある作者フォルダは報告せず、何もない作者フォルダは報告する
+        let additional = workspace.path().join("Ghosts");
+        create_ghost_dir(&additional.join("author"), "nested")?;
+        fs::create_dir_all(additional.join("empty_author"))
+            .map_err(|error| format!("failed to create dir: {}", error))?;
+
+        let output = scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &[additional.to_string_lossy().to_string()],
+            PreviousEntries::new(),
+            Arc::default(),
+        )?;
+
+        assert_eq!(output.ghosts.len(), 2);
+        let reasons: Vec<(String, &str)> = output
+            .skipped
+            .iter()
+            .map(|skip| (skip.path.clone(), skip.reason.as_str()))
+            .collect();
+        let path_of = |path: &Path| path.to_string_lossy().to_string();
+        let mut expected = vec![
+            (path_of(&additional.join("empty_author")), "no_descript"),
+            (path_of(&ssp_ghost.join("extracted_wrong")), "no_descript"),
+            (path_of(&ssp_ghost.join("mojibake")), "decode_failure"),
+        ];
+        expected.sort();
+        assert_eq!(reasons, expected);
+        Ok(())
+    }
+
     #[test]
     fn scan_ghosts_internal_falls_back_to_directory_name_without_name_field() -> Result<(), String>
     {
@@ -278,7 +762,13 @@ mod tests {
             "charset,UTF-8\n// no name field\n",
         )?;
 
-        let (ghosts, _) = scan_ghosts_with_fingerprint_internal(&ssp_root.to_string_lossy(), &[])?;
+        let ghosts = scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &[],
+            PreviousEntries::new(),
+            Arc::default(),
+        )?
+        .ghosts;
         let fallback = ghosts
             .iter()
             .find(|ghost| ghost.directory_name == "fallback_dir")
@@ -295,7 +785,12 @@ mod tests {
         fs::create_dir_all(&ssp_root)
             .map_err(|error| format!("failed to create ssp root dir: {}", error))?;
 
-        let result = scan_ghosts_with_fingerprint_internal(&ssp_root.to_string_lossy(), &[]);
+        let result = scan_ghosts_with_fingerprint_internal(
+            &ssp_root.to_string_lossy(),
+            &[],
+            PreviousEntries::new(),
+            Arc::default(),
+        );
         assert!(result.is_err());
         let error = result.err().ok_or_else(|| "expected error".to_string())?;
         assert!(error.contains("ghost フォルダが見つかりません"));
@@ -324,8 +819,13 @@ mod tests {
         let ssp_path = ssp_root.to_string_lossy().to_string();
 
         let standalone = build_fingerprint(&ssp_path, &additional_folders)?;
-        let (_, integrated) =
-            scan_ghosts_with_fingerprint_internal(&ssp_path, &additional_folders)?;
+        let integrated = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &additional_folders,
+            PreviousEntries::new(),
+            Arc::default(),
+        )?
+        .fingerprint;
 
         assert_eq!(standalone, integrated);
         Ok(())
@@ -350,8 +850,13 @@ mod tests {
         let ssp_path = ssp_root.to_string_lossy().to_string();
 
         let standalone = build_fingerprint(&ssp_path, &additional_folders)?;
-        let (_, integrated) =
-            scan_ghosts_with_fingerprint_internal(&ssp_path, &additional_folders)?;
+        let integrated = scan_ghosts_with_fingerprint_internal(
+            &ssp_path,
+            &additional_folders,
+            PreviousEntries::new(),
+            Arc::default(),
+        )?
+        .fingerprint;
 
         assert_eq!(standalone, integrated);
         Ok(())
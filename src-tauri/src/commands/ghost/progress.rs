@@ -0,0 +1,129 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use super::types::ScanProgressEvent;
+
+/// スキャンの進捗をフロントエンドへ送るイベント名
+pub const SCAN_PROGRESS_EVENT: &str = "ghost-scan-progress";
+
+/// 進捗イベントを送る最短間隔。エントリごとに送ると IPC が詰まる
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+type Reporter = Box<dyn Fn(&ScanProgress) + Send + Sync>;
+
+/// 実行中のスキャン 1 件の進捗カウンタとキャンセル要求。
+/// walk_entries が rayon のスレッドから数え、REPORT_INTERVAL ごとに間引いて reporter に渡す。
+/// 相乗りしている scan ID ごとのイベントは reporter が event で作る。
+/// 既定値は何も通知せず、キャンセルもされない
+#[derive(Default)]
+pub(crate) struct ScanProgress {
+    cancelled: AtomicBool,
+    parents_done: AtomicUsize,
+    parents_total: AtomicUsize,
+    entries_processed: AtomicUsize,
+    ghosts_found: AtomicUsize,
+    reporter: Option<Reporter>,
+    last_report: Mutex<Option<Instant>>,
+}
+
+impl ScanProgress {
+    /// 進捗を reporter に送るスキャン。reporter は走査中のスレッドから呼ばれる
+    pub(crate) fn new(reporter: impl Fn(&ScanProgress) + Send + Sync + 'static) -> Self {
+        Self {
+            reporter: Some(Box::new(reporter)),
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_parents_total(&self, total: usize) {
+        self.parents_total.store(total, Ordering::Relaxed);
+    }
+
+    /// 起点（SSP の ghost/ または追加フォルダ）1 件の走査を終えた。間引かずに通知する
+    pub(crate) fn parent_done(&self) {
+        self.parents_done.fetch_add(1, Ordering::Relaxed);
+        self.report(true);
+    }
+
+    /// フォルダ 1 件を調べた。ghost_found はそれがゴーストだったか
+    pub(crate) fn entry_processed(&self, ghost_found: bool) {
+        self.entries_processed.fetch_add(1, Ordering::Relaxed);
+        if ghost_found {
+            self.ghosts_found.fetch_add(1, Ordering::Relaxed);
+        }
+        self.report(false);
+    }
+
+    /// scan_id 宛ての進捗イベントを作る
+    pub(crate) fn event(&self, scan_id: &str) -> ScanProgressEvent {
+        ScanProgressEvent {
+            scan_id: scan_id.to_string(),
+            parents_done: self.parents_done.load(Ordering::Relaxed),
+            parents_total: self.parents_total.load(Ordering::Relaxed),
+            entries_processed: self.entries_processed.load(Ordering::Relaxed),
+            ghosts_found: self.ghosts_found.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 進捗を reporter に送る。force でなければ、前回から REPORT_INTERVAL 経っていない呼び出しは捨てる
+    fn report(&self, force: bool) {
+        let Some(reporter) = &self.reporter else {
+            return;
+        };
+        let now = Instant::now();
+        {
+            let mut last = self
+                .last_report
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if !force && last.is_some_and(|at| now.duration_since(at) < REPORT_INTERVAL) {
+                return;
+            }
+            *last = Some(now);
+        }
+        reporter(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn 進捗は間引いて送り_起点の完了は必ず送る() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let progress = ScanProgress::new(move |progress| {
+            sink.lock().unwrap().push(progress.event("scan-1"));
+        });
+        progress.set_parents_total(2);
+
+        for index in 0..10 {
+            progress.entry_processed(index % 2 == 0);
+        }
+        progress.parent_done();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1],
+            ScanProgressEvent {
+                scan_id: "scan-1".to_string(),
+                parents_done: 1,
+                parents_total: 2,
+                entries_processed: 10,
+                ghosts_found: 5,
+            }
+        );
+    }
+}
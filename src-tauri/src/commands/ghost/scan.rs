@@ -1,42 +1,91 @@
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use rayon::prelude::*;
 use sha2::{Digest, Sha256};
 
-use ghost_meta::{AlphaMode, ThumbnailKind};
+use ghost_meta::{AlphaMode, ScanSkipReason};
 
 use super::fingerprint::{
     build_entry_token, compute_fingerprint_hash, metadata_modified_string,
-    push_absent_parent_token,
+    nested_parent_mtime_line, push_absent_parent_token,
 };
 use super::path_utils::normalize_path;
-use super::types::Ghost;
+use super::progress::ScanProgress;
+use super::store::{EntryTokens, PreviousEntries};
+use super::thumbnail_cache::thumbnail_kind_name;
+use super::types::{Ghost, GhostScanSkip, GhostShell};
 
-/// GhostMeta から Ghost 構造体へ変換するヘルパー
-fn ghost_from_meta(meta: ghost_meta::GhostMeta, source: String) -> Ghost {
-    let (thumbnail_path, thumbnail_use_self_alpha, thumbnail_kind) = meta.thumbnail.map_or(
+/// ShellMeta から GhostShell 構造体へ変換するヘルパー
+fn shell_from_meta(meta: ghost_meta::ShellMeta) -> GhostShell {
+    let (thumbnail_path, thumbnail_use_self_alpha, thumbnail_mask_path) = meta.thumbnail.map_or(
         (String::new(), false, String::new()),
         |info| {
-            let kind = match info.kind {
-                ThumbnailKind::Surface => "surface".to_string(),
-                ThumbnailKind::Thumbnail => "thumbnail".to_string(),
-            };
             (
                 info.path.to_string_lossy().into_owned(),
                 info.alpha == AlphaMode::SelfAlpha,
-                kind,
+                mask_path_string(&info.alpha),
             )
         },
     );
+    GhostShell {
+        name: meta.name,
+        craftman: meta.craftman.unwrap_or_default(),
+        directory_name: meta.directory_name,
+        path: meta.path.to_string_lossy().into_owned(),
+        thumbnail_path,
+        thumbnail_use_self_alpha,
+        thumbnail_mask_path,
+    }
+}
+
+/// AlphaMode::PnaMask のマスクパスを文字列で返す（それ以外は空文字列）
+pub(crate) fn mask_path_string(alpha: &AlphaMode) -> String {
+    match alpha {
+        AlphaMode::PnaMask(mask) => mask.to_string_lossy().into_owned(),
+        _ => String::new(),
+    }
+}
+
+/// GhostMeta から Ghost 構造体へ変換するヘルパー
+fn ghost_from_meta(meta: ghost_meta::GhostMeta, source: String) -> Ghost {
+    let (thumbnail_path, thumbnail_use_self_alpha, thumbnail_mask_path, thumbnail_kind) =
+        meta.thumbnail.map_or(
+            (String::new(), false, String::new(), String::new()),
+            |info| {
+                (
+                    info.path.to_string_lossy().into_owned(),
+                    info.alpha == AlphaMode::SelfAlpha,
+                    mask_path_string(&info.alpha),
+                    thumbnail_kind_name(&info.kind).to_string(),
+                )
+            },
+        );
     let name = meta.name;
     let sakura_name = meta.sakura_name.unwrap_or_default();
     let kero_name = meta.kero_name.unwrap_or_default();
     let craftman = meta.craftman.unwrap_or_default();
     let craftmanw = meta.craftmanw.unwrap_or_default();
+    let descript_id = meta.id.unwrap_or_default();
+    let ghost_type = meta.ghost_type.unwrap_or_default();
+    let shiori = meta.shiori.unwrap_or_default();
+    let shiori_engine = meta.shiori_engine.as_str().to_string();
+    let homeurl = meta.homeurl.unwrap_or_default();
+    let craftmanurl = meta.craftmanurl.unwrap_or_default();
+    let readme = meta.readme.unwrap_or_default();
+    let icon = meta.icon.unwrap_or_default();
+    let balloon = meta.balloon.unwrap_or_default();
+    let sakura_default_surface = meta.sakura_default_surface;
+    let kero_default_surface = meta.kero_default_surface;
+    let install_accept = meta.install_accept.unwrap_or_default();
     let directory_name = meta.directory_name;
     let path = meta.path.to_string_lossy().into_owned();
     let alpha_str = if thumbnail_use_self_alpha { "1" } else { "0" };
+    let surface_str = |surface: Option<u32>| surface.map(|s| s.to_string()).unwrap_or_default();
+    let sakura_surface_str = surface_str(sakura_default_surface);
+    let kero_surface_str = surface_str(kero_default_surface);
+    let shells: Vec<GhostShell> = meta.shells.into_iter().map(shell_from_meta).collect();
     let diff_fingerprint = {
         let mut hasher = Sha256::new();
         for fragment in [
@@ -45,14 +94,40 @@ fn ghost_from_meta(meta: ghost_meta::GhostMeta, source: String) -> Ghost {
             kero_name.as_str(),
             craftman.as_str(),
             craftmanw.as_str(),
+            descript_id.as_str(),
+            ghost_type.as_str(),
+            shiori.as_str(),
+            shiori_engine.as_str(),
+            homeurl.as_str(),
+            craftmanurl.as_str(),
+            readme.as_str(),
+            icon.as_str(),
+            balloon.as_str(),
+            sakura_surface_str.as_str(),
+            kero_surface_str.as_str(),
+            install_accept.as_str(),
             path.as_str(),
             thumbnail_path.as_str(),
             alpha_str,
+            thumbnail_mask_path.as_str(),
             thumbnail_kind.as_str(),
         ] {
             hasher.update(fragment.as_bytes());
             hasher.update([0x1f]);
         }
+        for shell in &shells {
+            for fragment in [
+                shell.name.as_str(),
+                shell.craftman.as_str(),
+                shell.directory_name.as_str(),
+                shell.thumbnail_path.as_str(),
+                if shell.thumbnail_use_self_alpha { "1" } else { "0" },
+                shell.thumbnail_mask_path.as_str(),
+            ] {
+                hasher.update(fragment.as_bytes());
+                hasher.update([0x1f]);
+            }
+        }
         format!("{:x}", hasher.finalize())
     };
 
@@ -63,15 +138,40 @@ fn ghost_from_meta(meta: ghost_meta::GhostMeta, source: String) -> Ghost {
         kero_name,
         craftman,
         craftmanw,
+        descript_id,
+        ghost_type,
+        shiori,
+        shiori_engine,
+        homeurl,
+        craftmanurl,
+        readme,
+        icon,
+        balloon,
+        sakura_default_surface,
+        kero_default_surface,
+        install_accept,
         directory_name,
         path,
         source,
         thumbnail_path,
         thumbnail_use_self_alpha,
+        thumbnail_mask_path,
+        // サムネイルキャッシュは scan_and_store が populate_thumbnail_cache で埋める
+        thumbnail_card_path: String::new(),
+        thumbnail_preview_path: String::new(),
         thumbnail_kind,
+        shells,
     }
 }
 
+/// ゴーストディレクトリ 1 件を読み込む（.nar インストール後の単体再スキャン用）。
+/// descript.txt がない・読めない場合は None
+pub(crate) fn read_ghost_entry(ghost_dir: &Path, source: &str) -> Option<Ghost> {
+    ghost_meta::read_ghost(ghost_dir)
+        .ok()
+        .map(|meta| ghost_from_meta(meta, source.to_string()))
+}
+
 pub(crate) fn unique_sorted_additional_folders(
     additional_folders: &[String],
 ) -> Vec<(String, PathBuf, String)> {
@@ -89,15 +189,83 @@ pub(crate) fn unique_sorted_additional_folders(
     folders
 }
 
+/// 追加フォルダで、ゴーストではないサブフォルダをたどってゴーストを探す深さ。
+/// 追加フォルダ直下を 1 とし、`<追加フォルダ>/<作者>/<ゴースト>` の構成なら 2 が必要
+pub(crate) const ADDITIONAL_FOLDER_SCAN_DEPTH: usize = 3;
+
+/// SSP の ghost/ は直下のゴーストしか起動できないため、サブフォルダはたどらない
+pub(crate) const SSP_GHOST_SCAN_DEPTH: usize = 1;
+
+/// スキャン結果（ゴースト一覧・Layer 2 フィンガープリント・Layer 1 用の入れ子の親ディレクトリ・スキャンレポート）
+pub(crate) struct ScanOutput {
+    pub ghosts: Vec<Ghost>,
+    pub fingerprint: String,
+    /// たどった入れ子の親ディレクトリの mtime 行（`nested_parent_mtime_line` 形式）
+    pub nested_parents: Vec<String>,
+    /// 一覧に載らなかった・壊れていたフォルダ（パス順）
+    pub skipped: Vec<GhostScanSkip>,
+    /// 読み直さなかったゴーストと、読み直したゴーストのエントリトークン
+    pub entry_tokens: EntryTokens,
+}
+
+/// walk_parent が積んでいく走査結果
+#[derive(Default)]
+pub(crate) struct WalkOutput {
+    /// Layer 2 のフィンガープリントトークン
+    pub tokens: Vec<String>,
+    /// たどった入れ子の親ディレクトリの mtime 行（Layer 1 の判定用）
+    pub nested_parents: Vec<String>,
+    /// 一覧に載らなかった・壊れていたフォルダ（Ghost を収集するときだけ積む）
+    pub skipped: Vec<GhostScanSkip>,
+    /// 前回のスキャンのエントリトークン（入力）。トークンが一致するゴーストは読み直さない。空なら全件読む
+    pub previous: PreviousEntries,
+    /// 読み直さなかったゴーストと、読み直したゴーストのエントリトークン（Ghost を収集するときだけ積む）
+    pub entry_tokens: EntryTokens,
+    /// 進捗の通知先とキャンセル要求（入力）。キャンセルされたら走査を途中で打ち切る
+    pub progress: Arc<ScanProgress>,
+}
+
+/// スキャンレポートの 1 件を作る
+fn scan_skip(source: &str, path: &Path, reason: ScanSkipReason, detail: String) -> GhostScanSkip {
+    GhostScanSkip {
+        source: source.to_string(),
+        path: path.to_string_lossy().into_owned(),
+        reason: reason.as_str().to_string(),
+        detail,
+    }
+}
+
+/// descript.txt が見つからないフォルダのスキャンレポート。アクセス権がなくて見えない場合は PermissionDenied にする
+fn missing_descript_skip(source: &str, path: &Path) -> GhostScanSkip {
+    match fs::metadata(path.join("ghost").join("master").join("descript.txt")) {
+        Err(error) if error.kind() == std::io::ErrorKind::PermissionDenied => scan_skip(
+            source,
+            path,
+            ScanSkipReason::PermissionDenied,
+            format!("descript.txt を読み込めません: {error}"),
+        ),
+        _ => scan_skip(
+            source,
+            path,
+            ScanSkipReason::NoDescript,
+            "ghost/master/descript.txt がありません".to_string(),
+        ),
+    }
+}
+
 /// 親ディレクトリを走査し、フィンガープリントトークン生成（+ オプションで Ghost 収集）を行う。
 /// required=true のとき、ディレクトリが存在しない・読めない場合はエラーを返す。
-/// ghosts が Some のとき、descript.txt が存在するエントリを Ghost として収集する。
+/// ghosts が Some のとき、descript.txt が存在するエントリを Ghost として収集し、
+/// 読み込めなかった・壊れていたフォルダを out.skipped に積む。
+/// max_depth が 2 以上なら、descript.txt のないサブフォルダをその深さまでたどる（ゴーストの中には入らない）。
+/// たどったサブフォルダは out.nested_parents に mtime 行として積む（Layer 1 の判定用）。
 pub(crate) fn walk_parent(
     parent_dir: &Path,
     parent_label: &str,
     required: bool,
-    tokens: &mut Vec<String>,
-    mut ghosts: Option<(&str, &mut Vec<Ghost>)>,
+    max_depth: usize,
+    out: &mut WalkOutput,
+    ghosts: Option<(&str, &mut Vec<Ghost>)>,
 ) -> Result<(), String> {
     let normalized_parent = normalize_path(parent_dir);
 
@@ -108,7 +276,7 @@ pub(crate) fn walk_parent(
                 parent_dir.display()
             ));
         }
-        push_absent_parent_token(tokens, parent_label, &normalized_parent, "missing");
+        push_absent_parent_token(&mut out.tokens, parent_label, &normalized_parent, "missing");
         return Ok(());
     }
     if !parent_dir.is_dir() {
@@ -118,87 +286,265 @@ pub(crate) fn walk_parent(
                 parent_dir.display()
             ));
         }
-        push_absent_parent_token(tokens, parent_label, &normalized_parent, "not-directory");
+        push_absent_parent_token(
+            &mut out.tokens,
+            parent_label,
+            &normalized_parent,
+            "not-directory",
+        );
         return Ok(());
     }
 
-    let parent_modified = fs::metadata(parent_dir)
+    walk_entries(
+        parent_dir,
+        parent_label,
+        if required { Some(parent_dir) } else { None },
+        "",
+        max_depth,
+        out,
+        ghosts,
+    )
+}
+
+/// walk_parent の本体。dir 直下のエントリのトークン生成と Ghost 読み取りを行い、
+/// ゴーストでないサブフォルダは depth_left が残っていれば再帰的にたどる。
+/// relative_prefix は走査の起点から dir までの相対パス（"" または "作者/" の形）で、入れ子のゴーストの directory_name に前置する。
+/// required_dir が Some のとき（SSP の ghost/ 直下）、読み取れなければエラーを返す
+fn walk_entries(
+    dir: &Path,
+    parent_label: &str,
+    required_dir: Option<&Path>,
+    relative_prefix: &str,
+    depth_left: usize,
+    out: &mut WalkOutput,
+    mut ghosts: Option<(&str, &mut Vec<Ghost>)>,
+) -> Result<(), String> {
+    if out.progress.is_cancelled() {
+        return Ok(());
+    }
+    let normalized_parent = normalize_path(dir);
+    let parent_modified = fs::metadata(dir)
         .as_ref()
         .map(metadata_modified_string)
         .unwrap_or_else(|_| "unreadable".to_string());
-    tokens.push(format!(
+    out.tokens.push(format!(
         "parent|{}|{}|{}",
         parent_label, normalized_parent, parent_modified
     ));
+    if !relative_prefix.is_empty() {
+        out.nested_parents
+            .push(nested_parent_mtime_line(dir, &parent_modified));
+    }
 
-    let entries = match fs::read_dir(parent_dir) {
+    let source_str = ghosts.as_ref().map(|(s, _)| s.to_string());
+    let entries = match fs::read_dir(dir) {
         Ok(e) => e,
         Err(error) => {
-            if required {
+            if let Some(required_dir) = required_dir {
                 return Err(format!(
                     "ディレクトリを読み取れませんでした ({}): {}",
-                    parent_dir.display(),
+                    required_dir.display(),
                     error
                 ));
             }
-            tokens.push(format!(
+            out.tokens.push(format!(
                 "entries|{}|{}|unreadable",
                 parent_label, normalized_parent
             ));
+            if let Some(source) = &source_str {
+                out.skipped.push(scan_skip(
+                    source,
+                    dir,
+                    ScanSkipReason::from_io_error(&error),
+                    format!("フォルダの一覧を読み取れません: {error}"),
+                ));
+            }
             return Ok(());
         }
     };
 
     // エントリを Vec に収集（par_iter の前提）
     // is_dir() のフィルタは逐次で行い、OS ディレクトリハンドルを早期に解放する
-    let paths: Vec<PathBuf> = entries
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| p.is_dir())
-        .collect();
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for entry in entries {
+        match entry {
+            Ok(entry) => {
+                let path = entry.path();
+                if path.is_dir() {
+                    paths.push(path);
+                }
+            }
+            Err(error) => {
+                if let Some(source) = &source_str {
+                    out.skipped.push(scan_skip(
+                        source,
+                        dir,
+                        ScanSkipReason::from_io_error(&error),
+                        format!("フォルダの一覧の一部を読み取れません: {error}"),
+                    ));
+                }
+            }
+        }
+    }
 
     // 並列処理: 各エントリのトークン生成 + Ghost 読み取り
+    #[derive(Default)]
     struct EntryResult {
-        token: String,
+        /// metadata を読めなかった・名前が UTF-8 でないエントリは None（トークンを積まない）
+        token: Option<String>,
         ghost: Option<Ghost>,
+        skip: Option<GhostScanSkip>,
+        /// descript.txt がなく、さらにたどる候補のサブフォルダ（directory_name 付き）
+        nested: Option<(PathBuf, String)>,
+        /// エントリトークンが前回と同じで読み直さなかったゴーストの ghost_identity_key
+        reused: Option<String>,
+        /// 読み直したゴーストの (パス, エントリトークン)
+        refreshed: Option<(String, String)>,
     }
 
-    let source_str = ghosts.as_ref().map(|(s, _)| s.to_string());
+    let previous = &out.previous;
+    let progress = &out.progress;
+
     let results: Vec<EntryResult> = paths
         .par_iter()
-        .filter_map(|path| {
+        .map(|path| {
+            if progress.is_cancelled() {
+                return EntryResult::default();
+            }
             // fs::metadata は Windows NTFS の遅延タイムスタンプ問題を回避するため entry.metadata() を使わない
-            let entry_meta = fs::metadata(path).ok()?;
-            let directory_name = path.file_name()?.to_str()?.to_string();
+            let entry_meta = match fs::metadata(path) {
+                Ok(meta) => meta,
+                Err(error) => {
+                    return EntryResult {
+                        skip: source_str.as_deref().map(|source| {
+                            scan_skip(
+                                source,
+                                path,
+                                ScanSkipReason::from_io_error(&error),
+                                format!("フォルダの情報を読み取れません: {error}"),
+                            )
+                        }),
+                        ..EntryResult::default()
+                    };
+                }
+            };
+            let Some(directory_name) = path.file_name().and_then(|n| n.to_str()) else {
+                return EntryResult {
+                    skip: source_str.as_deref().map(|source| {
+                        scan_skip(
+                            source,
+                            path,
+                            ScanSkipReason::NonUtf8Name,
+                            "フォルダ名を UTF-8 として解釈できません".to_string(),
+                        )
+                    }),
+                    ..EntryResult::default()
+                };
+            };
             let descript_path = path.join("ghost").join("master").join("descript.txt");
 
             let (token, descript_state) = build_entry_token(
                 parent_label,
                 &normalized_parent,
-                &directory_name,
+                directory_name,
                 &entry_meta,
                 &descript_path,
             );
+            let nested = (depth_left > 1 && descript_state == "missing")
+                .then(|| (path.clone(), format!("{relative_prefix}{directory_name}/")));
+            let path_key = path.to_string_lossy();
+            let reused = match (source_str.as_deref(), descript_state.as_str()) {
+                (Some(_), "present") => previous
+                    .get(path_key.as_ref())
+                    .filter(|(stored_token, _)| *stored_token == token)
+                    .map(|(_, identity_key)| identity_key.clone()),
+                _ => None,
+            };
 
-            let ghost = if source_str.is_some() && descript_state == "present" {
-                ghost_meta::read_ghost(path)
-                    .ok()
-                    .map(|meta| ghost_from_meta(meta, source_str.as_ref().unwrap().clone()))
-            } else {
-                None
+            let (ghost, skip) = match (source_str.as_deref(), descript_state.as_str()) {
+                // 前回から変わっていないゴーストは読み直さず、DB の行をそのまま使う
+                (Some(_), "present") if reused.is_some() => (None, None),
+                (Some(source), "present") => {
+                    let (meta, skip) = ghost_meta::inspect_ghost(path);
+                    let ghost = meta.map(|meta| {
+                        let mut ghost = ghost_from_meta(meta, source.to_string());
+                        if !relative_prefix.is_empty() {
+                            // 入れ子のゴーストは起点からの相対パスを directory_name にする（起動・一意キーで source と結合する）
+                            ghost.directory_name = format!("{relative_prefix}{directory_name}");
+                        }
+                        ghost
+                    });
+                    let skip =
+                        skip.map(|skip| scan_skip(source, &skip.path, skip.reason, skip.detail));
+                    (ghost, skip)
+                }
+                (Some(source), "unreadable") => (
+                    None,
+                    Some(scan_skip(
+                        source,
+                        path,
+                        ScanSkipReason::Unreadable,
+                        "descript.txt の更新日時を読み取れません".to_string(),
+                    )),
+                ),
+                // たどるサブフォルダは、中で何も見つからなかったときだけ後で報告する
+                (Some(source), _) if nested.is_none() => {
+                    (None, Some(missing_descript_skip(source, path)))
+                }
+                _ => (None, None),
             };
 
-            Some(EntryResult { token, ghost })
+            let refreshed = ghost
+                .as_ref()
+                .map(|_| (path_key.into_owned(), token.clone()));
+            progress.entry_processed(ghost.is_some() || reused.is_some());
+            EntryResult {
+                token: Some(token),
+                ghost,
+                skip,
+                nested,
+                reused,
+                refreshed,
+            }
         })
         .collect();
 
-    // 逐次: 結果をマージ
+    // 逐次: 結果をマージし、ゴーストでないサブフォルダをたどる
+    let mut nested_dirs = Vec::new();
     for result in results {
-        tokens.push(result.token);
-        if let Some(ghost) = result.ghost {
-            if let Some((_, ref mut ghost_list)) = ghosts {
-                ghost_list.push(ghost);
-            }
+        out.tokens.extend(result.token);
+        out.skipped.extend(result.skip);
+        if let Some(ghost) = result.ghost
+            && let Some((_, ref mut ghost_list)) = ghosts
+        {
+            ghost_list.push(ghost);
+        }
+        nested_dirs.extend(result.nested);
+        out.entry_tokens.reused.extend(result.reused);
+        out.entry_tokens.refreshed.extend(result.refreshed);
+    }
+    // 前回から変わらず読み直さなかったゴーストも中身のあるフォルダとして数える
+    let found_count = |ghosts: &Option<(&str, &mut Vec<Ghost>)>, out: &WalkOutput| {
+        ghosts.as_ref().map_or(0, |(_, list)| list.len()) + out.entry_tokens.reused.len()
+    };
+    for (nested_dir, nested_prefix) in nested_dirs {
+        let found_before = found_count(&ghosts, out);
+        let skipped_count = out.skipped.len();
+        walk_entries(
+            &nested_dir,
+            parent_label,
+            None,
+            &nested_prefix,
+            depth_left - 1,
+            out,
+            ghosts.as_mut().map(|(source, list)| (*source, &mut **list)),
+        )?;
+        // 中にゴーストも報告すべきフォルダもなければ、このフォルダ自体を descript.txt なしとして報告する
+        if let Some(source) = &source_str
+            && found_count(&ghosts, out) == found_before
+            && out.skipped.len() == skipped_count
+        {
+            out.skipped.push(missing_descript_skip(source, &nested_dir));
         }
     }
 
@@ -207,27 +553,64 @@ pub(crate) fn walk_parent(
 
 /// scan と fingerprint を 1 パスで実行する統合関数。
 /// ゴーストメタデータ収集とフィンガープリントトークン生成を同じ read_dir ループで行う。
+/// previous のエントリトークンと一致するゴーストは読み直さず、
+/// ScanOutput::ghosts には含めずに entry_tokens.reused に ghost_identity_key を積む。
+/// progress がキャンセルされたら走査を打ち切って不完全な結果を返すので、呼び出し側は書き込まずに捨てる
 pub(crate) fn scan_ghosts_with_fingerprint_internal(
     ssp_path: &str,
     additional_folders: &[String],
-) -> Result<(Vec<Ghost>, String), String> {
+    previous: PreviousEntries,
+    progress: Arc<ScanProgress>,
+) -> Result<ScanOutput, String> {
     let ghost_dir = Path::new(ssp_path).join("ghost");
-    let mut tokens = vec!["fingerprint-version|1".to_string()];
+    let additional_folders = unique_sorted_additional_folders(additional_folders);
+    progress.set_parents_total(1 + additional_folders.len());
+    let mut out = WalkOutput {
+        tokens: vec!["fingerprint-version|1".to_string()],
+        previous,
+        progress,
+        ..WalkOutput::default()
+    };
     let mut ghosts: Vec<Ghost> = Vec::new();
 
-    walk_parent(&ghost_dir, "ssp", true, &mut tokens, Some(("ssp", &mut ghosts)))?;
+    walk_parent(
+        &ghost_dir,
+        "ssp",
+        true,
+        SSP_GHOST_SCAN_DEPTH,
+        &mut out,
+        Some(("ssp", &mut ghosts)),
+    )?;
+    out.progress.parent_done();
 
-    for (source, folder_path, normalized_folder) in unique_sorted_additional_folders(additional_folders) {
+    for (source, folder_path, normalized_folder) in additional_folders {
         walk_parent(
             &folder_path,
             &normalized_folder,
             false,
-            &mut tokens,
+            ADDITIONAL_FOLDER_SCAN_DEPTH,
+            &mut out,
             Some((&source, &mut ghosts)),
         )?;
+        out.progress.parent_done();
     }
 
     ghosts.sort_by_cached_key(|ghost| ghost.name.to_lowercase());
+    let WalkOutput {
+        tokens,
+        mut nested_parents,
+        mut skipped,
+        entry_tokens,
+        ..
+    } = out;
+    nested_parents.sort();
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
 
-    Ok((ghosts, compute_fingerprint_hash(&tokens)))
+    Ok(ScanOutput {
+        ghosts,
+        fingerprint: compute_fingerprint_hash(&tokens),
+        nested_parents,
+        skipped,
+        entry_tokens,
+    })
 }
@@ -0,0 +1,314 @@
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+
+use super::ensure_request_key;
+use super::store::normalize_for_key;
+use super::types::GhostSearchPage;
+
+/// trigram 索引で引ける最短の検索語（NFKC 正規化後の文字数）。
+/// これより短い検索語はフロントエンドが従来の LIKE で検索する
+pub(crate) const MIN_TRIGRAM_QUERY_CHARS: usize = 3;
+
+/// 検索語を FTS5 のフレーズにする（"..." で囲み、中の " は重ねる）。
+/// 演算子や列指定として解釈させず、部分文字列として照合する
+fn fts_phrase(query: &str) -> String {
+    format!("\"{}\"", query.replace('"', "\"\""))
+}
+
+/// 並び順ごとの (JOIN, ORDER BY)。ghostDatabase.ts の buildOrderBy と同じ（random は名前順で返し、フロントエンドが混ぜる）
+fn order_clause(sort_order: &str) -> (&'static str, &'static str) {
+    match sort_order {
+        "recent" => (
+            "LEFT JOIN (SELECT ghost_identity_key, MAX(launched_at) AS last_launched \
+             FROM ghost_launches GROUP BY ghost_identity_key) gl \
+             ON g.ghost_identity_key = gl.ghost_identity_key",
+            "gl.last_launched DESC NULLS LAST, g.name_lower ASC",
+        ),
+        "frequency" => (
+            "LEFT JOIN (SELECT ghost_identity_key, COUNT(*) AS launch_count \
+             FROM ghost_launches GROUP BY ghost_identity_key) gl \
+             ON g.ghost_identity_key = gl.ghost_identity_key",
+            "gl.launch_count DESC NULLS LAST, g.name_lower ASC",
+        ),
+        _ => ("", "g.name_lower ASC"),
+    }
+}
+
+/// ghosts_fts（trigram 索引）で request_key のゴーストを部分一致検索する。
+/// 照合対象は LIKE 検索と同じ（lower カラム・shiori_engine・シェル名）。shiori_engine が "" なら絞り込まない
+pub(crate) fn search_ghost_keys(
+    conn: &Connection,
+    request_key: &str,
+    query: &str,
+    limit: u32,
+    offset: u32,
+    sort_order: &str,
+    shiori_engine: &str,
+) -> Result<GhostSearchPage, String> {
+    let normalized = normalize_for_key(query);
+    if normalized.chars().count() < MIN_TRIGRAM_QUERY_CHARS {
+        return Err(format!(
+            "検索語は {MIN_TRIGRAM_QUERY_CHARS} 文字以上必要です"
+        ));
+    }
+    let phrase = fts_phrase(&normalized);
+    let (join, order_by) = order_clause(sort_order);
+    // CROSS JOIN で索引から引いた行を起点にする（request_key の索引から全件を舐めさせない）
+    let from = "ghosts_fts CROSS JOIN ghosts g ON g.id = ghosts_fts.rowid";
+    let filter = "ghosts_fts MATCH ?2 AND g.request_key = ?1 \
+         AND (?3 = '' OR g.shiori_engine = ?3)";
+
+    let total: usize = conn
+        .prepare_cached(&format!("SELECT COUNT(*) FROM {from} WHERE {filter}"))
+        .and_then(|mut stmt| {
+            stmt.query_row(
+                rusqlite::params![request_key, phrase, shiori_engine],
+                |row| row.get(0),
+            )
+        })
+        .map_err(|e| format!("検索件数エラー: {e}"))?;
+
+    let mut stmt = conn
+        .prepare_cached(&format!(
+            "SELECT g.ghost_identity_key FROM {from} {join} WHERE {filter} \
+             ORDER BY {order_by} LIMIT ?4 OFFSET ?5"
+        ))
+        .map_err(|e| format!("検索準備エラー: {e}"))?;
+    let rows = stmt
+        .query_map(
+            rusqlite::params![request_key, phrase, shiori_engine, limit, offset],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("検索エラー: {e}"))?;
+    let ghost_identity_keys = rows
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| format!("検索結果の読み込みエラー: {e}"))?;
+
+    Ok(GhostSearchPage {
+        ghost_identity_keys,
+        total,
+    })
+}
+
+/// 3 文字以上の検索語でゴーストを部分一致検索するコマンド（ghosts_fts の trigram 索引を使う）。
+/// 1 ページ分の ghost_identity_key と総数だけを返し、行の中身はフロントエンドが SQL で引く。
+/// 3 文字未満はエラー（フロントエンドは LIKE 検索に切り替える）。DB が未作成なら 0 件。
+/// DB のオープンと検索は blocking プールで実行する
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn search_ghosts(
+    app: tauri::AppHandle,
+    request_key: String,
+    query: String,
+    limit: u32,
+    offset: u32,
+    sort_order: String,
+    shiori_engine: String,
+) -> Result<GhostSearchPage, String> {
+    use tauri::Manager;
+
+    ensure_request_key(&request_key)?;
+    let db_path = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("app_config_dir 取得エラー: {e}"))?
+        .join("ghosts.db");
+    tauri::async_runtime::spawn_blocking(move || {
+        search_ghosts_blocking(
+            &db_path,
+            &request_key,
+            &query,
+            limit,
+            offset,
+            &sort_order,
+            &shiori_engine,
+        )
+    })
+    .await
+    .map_err(|e| format!("ゴースト検索の実行エラー: {e}"))?
+}
+
+/// search_ghosts の本体
+fn search_ghosts_blocking(
+    db_path: &Path,
+    request_key: &str,
+    query: &str,
+    limit: u32,
+    offset: u32,
+    sort_order: &str,
+    shiori_engine: &str,
+) -> Result<GhostSearchPage, String> {
+    if !db_path.exists() {
+        return Ok(GhostSearchPage {
+            ghost_identity_keys: Vec::new(),
+            total: 0,
+        });
+    }
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| format!("DB オープンエラー: {e}"))?;
+    search_ghost_keys(
+        &conn,
+        request_key,
+        query,
+        limit,
+        offset,
+        sort_order,
+        shiori_engine,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ghost::store::tests::{make_ghost, setup_db};
+    use crate::commands::ghost::store::{
+        EntryTokens, RemovedGhostRoot, store_ghost_changes, store_ghosts, upsert_ghost,
+    };
+    use crate::commands::ghost::types::GhostShell;
+
+    fn keys(conn: &Connection, request_key: &str, query: &str) -> Vec<String> {
+        search_ghost_keys(conn, request_key, query, 100, 0, "name", "")
+            .unwrap()
+            .ghost_identity_keys
+    }
+
+    fn identity(dir: &str) -> String {
+        format!("ssp\u{1f}{dir}")
+    }
+
+    #[test]
+    fn lower_カラムとシェル名を部分一致で引き_フィールドはまたがない() {
+        let conn = setup_db();
+        let mut alice = make_ghost("Alice", "alice", "ssp");
+        alice.craftman = "Ｔａｒｏ".to_string();
+        alice.shells.push(GhostShell {
+            name: "冬服セット".to_string(),
+            craftman: String::new(),
+            directory_name: "winter".to_string(),
+            path: "/ghosts/alice/shell/winter".to_string(),
+            thumbnail_path: String::new(),
+            thumbnail_use_self_alpha: false,
+            thumbnail_mask_path: String::new(),
+        });
+        let mut bob = make_ghost("Bob", "bob", "ssp");
+        bob.shiori_engine = "satori".to_string();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[alice, bob],
+            &EntryTokens::default(),
+            "fp-1",
+            "",
+        )
+        .unwrap();
+
+        assert_eq!(keys(&conn, "rk1", "ＬＩＣ"), vec![identity("alice")]);
+        assert_eq!(keys(&conn, "rk1", "taro"), vec![identity("alice")]);
+        assert_eq!(keys(&conn, "rk1", "冬服セ"), vec![identity("alice")]);
+        assert_eq!(keys(&conn, "rk1", "sat"), vec![identity("bob")]);
+        // name の末尾と craftman の先頭をつなげた語にはヒットしない
+        assert!(keys(&conn, "rk1", "icetaro").is_empty());
+        // FTS5 の構文として解釈しない
+        assert!(keys(&conn, "rk1", "a\" OR \"b").is_empty());
+        assert!(keys(&conn, "rk2", "alice").is_empty());
+        assert!(search_ghost_keys(&conn, "rk1", "ａl", 100, 0, "name", "").is_err());
+    }
+
+    #[test]
+    fn 差分書き込み_監視_単体の書き込みに索引が追従する() {
+        let conn = setup_db();
+        let ghosts = vec![
+            make_ghost("Alice", "alice", "ssp"),
+            make_ghost("Bob", "bob", "ssp"),
+        ];
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp-1", "").unwrap();
+
+        let mut alice = make_ghost("Alicia", "alice", "ssp");
+        alice.diff_fingerprint = "fp-Alicia".to_string();
+        store_ghosts(&conn, "rk1", &[alice], &EntryTokens::default(), "fp-2", "").unwrap();
+        assert_eq!(keys(&conn, "rk1", "licia"), vec![identity("alice")]);
+        assert!(keys(&conn, "rk1", "bob").is_empty());
+
+        let removed = vec![RemovedGhostRoot {
+            path: "/ghosts/alice".to_string(),
+            descendants: false,
+        }];
+        store_ghost_changes(
+            &conn,
+            "rk1",
+            &[make_ghost("Carol", "carol", "ssp")],
+            &removed,
+        )
+        .unwrap();
+        assert!(keys(&conn, "rk1", "alicia").is_empty());
+        assert_eq!(keys(&conn, "rk1", "carol"), vec![identity("carol")]);
+
+        upsert_ghost(&conn, "rk1", &make_ghost("Caroline", "carol", "ssp")).unwrap();
+        assert_eq!(keys(&conn, "rk1", "oline"), vec![identity("carol")]);
+
+        // フロントエンドの世代掃除（ghosts の DELETE）でも索引から消える
+        conn.execute("DELETE FROM ghosts WHERE request_key = 'rk1'", [])
+            .unwrap();
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM ghosts_fts", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn 並び順_ページ_shiori_engine_の絞り込みは_like_検索と同じ() {
+        let conn = setup_db();
+        let mut ghosts = vec![
+            make_ghost("Carol Test", "carol", "ssp"),
+            make_ghost("Alice Test", "alice", "ssp"),
+            make_ghost("Bob Test", "bob", "ssp"),
+        ];
+        ghosts[0].shiori_engine = "yaya".to_string();
+        ghosts[2].shiori_engine = "yaya".to_string();
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp-1", "").unwrap();
+        conn.execute(
+            "INSERT INTO ghost_launches (ghost_identity_key, launched_at) VALUES (?1, datetime('now'))",
+            [identity("carol")],
+        )
+        .unwrap();
+
+        let page = search_ghost_keys(&conn, "rk1", "test", 2, 1, "name", "").unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(
+            page.ghost_identity_keys,
+            vec![identity("bob"), identity("carol")]
+        );
+
+        let page = search_ghost_keys(&conn, "rk1", "test", 10, 0, "recent", "").unwrap();
+        assert_eq!(page.ghost_identity_keys[0], identity("carol"));
+
+        let page = search_ghost_keys(&conn, "rk1", "test", 10, 0, "name", "yaya").unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(
+            page.ghost_identity_keys,
+            vec![identity("bob"), identity("carol")]
+        );
+    }
+
+    #[test]
+    fn db_が未作成なら_0_件を返す() {
+        let tmp = crate::testutil::TempDirGuard::new("search_ghosts_missing_db");
+        let page = search_ghosts_blocking(
+            &tmp.path().join("ghosts.db"),
+            "rk1",
+            "さくら",
+            100,
+            0,
+            "name",
+            "",
+        )
+        .unwrap();
+        assert!(page.ghost_identity_keys.is_empty());
+        assert_eq!(page.total, 0);
+        assert!(!tmp.path().join("ghosts.db").exists());
+    }
+}
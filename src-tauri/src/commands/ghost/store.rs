@@ -1,14 +1,15 @@
 use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
 use unicode_normalization::UnicodeNormalization;
 
-use super::types::Ghost;
+use super::types::{Ghost, GhostScanSkip, GhostShell};
 
-const GHOST_KEY_SEPARATOR: char = '\x1f';
+pub(crate) const GHOST_KEY_SEPARATOR: char = '\x1f';
 
 /// NFKC 正規化 + 小文字化（JS 側の normalizeForKey と同一ロジック）
-fn normalize_for_key(s: &str) -> String {
+pub(crate) fn normalize_for_key(s: &str) -> String {
     s.nfkc().collect::<String>().to_lowercase()
 }
 
@@ -22,6 +23,211 @@ fn build_ghost_identity_key(ghost: &Ghost) -> String {
     )
 }
 
+/// 前回のスキャンで書き込んだゴーストのエントリトークン（パス → (エントリトークン, ghost_identity_key)）
+pub(crate) type PreviousEntries = HashMap<String, (String, String)>;
+
+/// エントリトークンによる差分スキャンの結果（store_ghosts に渡す）
+#[derive(Debug, Default)]
+pub(crate) struct EntryTokens {
+    /// エントリトークンが前回と同じで読み直さなかったゴーストの ghost_identity_key（行をそのまま残す）
+    pub reused: Vec<String>,
+    /// 読み直したゴーストのエントリトークン（パス → トークン）
+    pub refreshed: HashMap<String, String>,
+}
+
+/// ghosts テーブルの INSERT 文（プレースホルダ順は `execute_ghost_row` と同じ）
+const INSERT_GHOST_SQL: &str = "INSERT INTO ghosts (\
+        request_key, ghost_identity_key, row_fingerprint,\
+        name, sakura_name, kero_name, craftman, craftmanw,\
+        directory_name, path, source,\
+        name_lower, sakura_name_lower, kero_name_lower,\
+        craftman_lower, craftmanw_lower, directory_name_lower,\
+        thumbnail_path, thumbnail_use_self_alpha, thumbnail_kind,\
+        descript_id, ghost_type, shiori, homeurl, craftmanurl,\
+        readme, icon, balloon,\
+        sakura_default_surface, kero_default_surface, install_accept,\
+        descript_id_lower, shiori_lower, balloon_lower,\
+        homeurl_lower, craftmanurl_lower, shell_count,\
+        thumbnail_mask_path, thumbnail_card_path, thumbnail_preview_path,\
+        shiori_engine, updated_at\
+    ) VALUES (\
+        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11,\
+        ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,\
+        ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28,\
+        ?29, ?30, ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38,\
+        ?39, ?40, ?41,\
+        datetime('now')\
+    )";
+
+/// INSERT / UPDATE 共通のプレースホルダ順（?1〜?41）で 1 行分をバインドして実行する
+fn execute_ghost_row(
+    stmt: &mut rusqlite::CachedStatement<'_>,
+    request_key: &str,
+    identity_key: &str,
+    ghost: &Ghost,
+) -> rusqlite::Result<usize> {
+    stmt.execute(rusqlite::params![
+        request_key,
+        identity_key,
+        ghost.diff_fingerprint,
+        ghost.name,
+        ghost.sakura_name,
+        ghost.kero_name,
+        ghost.craftman,
+        ghost.craftmanw,
+        ghost.directory_name,
+        ghost.path,
+        ghost.source,
+        normalize_for_key(&ghost.name),
+        normalize_for_key(&ghost.sakura_name),
+        normalize_for_key(&ghost.kero_name),
+        normalize_for_key(&ghost.craftman),
+        normalize_for_key(&ghost.craftmanw),
+        normalize_for_key(&ghost.directory_name),
+        ghost.thumbnail_path,
+        ghost.thumbnail_use_self_alpha as i32,
+        ghost.thumbnail_kind,
+        ghost.descript_id,
+        ghost.ghost_type,
+        ghost.shiori,
+        ghost.homeurl,
+        ghost.craftmanurl,
+        ghost.readme,
+        ghost.icon,
+        ghost.balloon,
+        ghost.sakura_default_surface,
+        ghost.kero_default_surface,
+        ghost.install_accept,
+        normalize_for_key(&ghost.descript_id),
+        normalize_for_key(&ghost.shiori),
+        normalize_for_key(&ghost.balloon),
+        normalize_for_key(&ghost.homeurl),
+        normalize_for_key(&ghost.craftmanurl),
+        ghost.shells.len(),
+        ghost.thumbnail_mask_path,
+        ghost.thumbnail_card_path,
+        ghost.thumbnail_preview_path,
+        ghost.shiori_engine,
+    ])
+}
+
+/// ゴースト 1 件分の ghost_shells 行を入れ替える（DELETE → INSERT）
+fn replace_ghost_shells(
+    tx: &rusqlite::Transaction<'_>,
+    request_key: &str,
+    identity_key: &str,
+    shells: &[GhostShell],
+) -> Result<(), String> {
+    delete_ghost_shells(tx, request_key, identity_key)?;
+    if shells.is_empty() {
+        return Ok(());
+    }
+
+    let mut stmt = tx
+        .prepare_cached(
+            "INSERT INTO ghost_shells (\
+                request_key, ghost_identity_key,\
+                name, craftman, directory_name, path,\
+                thumbnail_path, thumbnail_use_self_alpha, thumbnail_mask_path,\
+                name_lower, craftman_lower, directory_name_lower\
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        )
+        .map_err(|e| format!("シェル INSERT 準備エラー: {e}"))?;
+    for shell in shells {
+        stmt.execute(rusqlite::params![
+            request_key,
+            identity_key,
+            shell.name,
+            shell.craftman,
+            shell.directory_name,
+            shell.path,
+            shell.thumbnail_path,
+            shell.thumbnail_use_self_alpha as i32,
+            shell.thumbnail_mask_path,
+            normalize_for_key(&shell.name),
+            normalize_for_key(&shell.craftman),
+            normalize_for_key(&shell.directory_name),
+        ])
+        .map_err(|e| format!("シェル INSERT エラー: {e}"))?;
+    }
+    Ok(())
+}
+
+/// 検索用の本文（ghosts_fts.body）を組み立てる。
+/// LIKE 検索と同じ lower カラム・shiori_engine・シェル名を改行で区切って並べる。
+/// trigram は改行をまたがないので、別のフィールドにまたがる語にはヒットしない
+fn build_search_body(ghost: &Ghost) -> String {
+    let fields = [
+        &ghost.name,
+        &ghost.sakura_name,
+        &ghost.kero_name,
+        &ghost.craftman,
+        &ghost.craftmanw,
+        &ghost.directory_name,
+        &ghost.descript_id,
+        &ghost.shiori,
+        &ghost.balloon,
+        &ghost.homeurl,
+        &ghost.craftmanurl,
+        &ghost.shiori_engine,
+    ];
+    fields
+        .into_iter()
+        .chain(ghost.shells.iter().map(|shell| &shell.name))
+        .map(|field| normalize_for_key(field))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// ゴースト 1 件分の ghosts_fts 行を書き直す（rowid は ghosts.id）。
+/// ghosts の行を消したときは ghosts_fts_delete トリガーが消すので、ここでは扱わない
+fn write_search_row(
+    tx: &rusqlite::Transaction<'_>,
+    request_key: &str,
+    identity_key: &str,
+    ghost: &Ghost,
+) -> Result<(), String> {
+    tx.prepare_cached(
+        "INSERT OR REPLACE INTO ghosts_fts (rowid, body) \
+         SELECT id, ?3 FROM ghosts WHERE request_key = ?1 AND ghost_identity_key = ?2",
+    )
+    .and_then(|mut stmt| {
+        stmt.execute(rusqlite::params![
+            request_key,
+            identity_key,
+            build_search_body(ghost)
+        ])
+    })
+    .map_err(|e| format!("検索索引の書き込みエラー: {e}"))?;
+    Ok(())
+}
+
+/// ゴースト 1 件分の ghost_shells 行を削除する
+fn delete_ghost_shells(
+    tx: &rusqlite::Transaction<'_>,
+    request_key: &str,
+    identity_key: &str,
+) -> Result<(), String> {
+    tx.prepare_cached(
+        "DELETE FROM ghost_shells WHERE request_key = ?1 AND ghost_identity_key = ?2",
+    )
+    .and_then(|mut stmt| stmt.execute(rusqlite::params![request_key, identity_key]))
+    .map_err(|e| format!("シェル DELETE エラー: {e}"))?;
+    Ok(())
+}
+
+/// ghosts テーブルとサムネイルキャッシュへの書き込みの排他（lib.rs で manage する）。
+/// scan_and_store は走査から書き込み・キャッシュの掃除まで持つので、フォルダ監視などの反映が
+/// スキャンの読んだ古い内容で書き戻されることはない
+#[derive(Default)]
+pub struct GhostWriteLock(Mutex<()>);
+
+impl GhostWriteLock {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, ()> {
+        self.0.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
 /// rusqlite 接続に書き込み用 PRAGMA を設定する
 pub(crate) fn configure_connection(conn: &Connection) -> Result<(), String> {
     conn.execute_batch(
@@ -35,14 +241,93 @@ pub(crate) fn configure_connection(conn: &Connection) -> Result<(), String> {
     .map_err(|e| format!("PRAGMA 設定エラー: {e}"))
 }
 
+/// 分類済みの差分（INSERT / UPDATE / DELETE）を ghosts・ghost_shells・ghosts_fts に書き込む（store_ghosts と store_ghost_changes で共通）
+fn apply_ghost_diff(
+    tx: &rusqlite::Transaction<'_>,
+    request_key: &str,
+    to_insert: &[(&Ghost, String)],
+    to_update: &[(&Ghost, String)],
+    to_delete: &[String],
+) -> Result<(), String> {
+    if !to_insert.is_empty() {
+        let mut stmt = tx
+            .prepare_cached(INSERT_GHOST_SQL)
+            .map_err(|e| format!("INSERT 準備エラー: {e}"))?;
+
+        for (ghost, identity_key) in to_insert {
+            execute_ghost_row(&mut stmt, request_key, identity_key, ghost)
+                .map_err(|e| format!("INSERT エラー: {e}"))?;
+            replace_ghost_shells(tx, request_key, identity_key, &ghost.shells)?;
+            write_search_row(tx, request_key, identity_key, ghost)?;
+        }
+    }
+
+    if !to_update.is_empty() {
+        let mut stmt = tx
+            .prepare_cached(
+                "UPDATE ghosts SET \
+                    row_fingerprint = ?3,\
+                    name = ?4, sakura_name = ?5, kero_name = ?6,\
+                    craftman = ?7, craftmanw = ?8,\
+                    directory_name = ?9, path = ?10, source = ?11,\
+                    name_lower = ?12, sakura_name_lower = ?13, kero_name_lower = ?14,\
+                    craftman_lower = ?15, craftmanw_lower = ?16,\
+                    directory_name_lower = ?17,\
+                    thumbnail_path = ?18, thumbnail_use_self_alpha = ?19,\
+                    thumbnail_kind = ?20,\
+                    descript_id = ?21, ghost_type = ?22, shiori = ?23,\
+                    homeurl = ?24, craftmanurl = ?25, readme = ?26,\
+                    icon = ?27, balloon = ?28,\
+                    sakura_default_surface = ?29, kero_default_surface = ?30,\
+                    install_accept = ?31,\
+                    descript_id_lower = ?32, shiori_lower = ?33, balloon_lower = ?34,\
+                    homeurl_lower = ?35, craftmanurl_lower = ?36,\
+                    shell_count = ?37, thumbnail_mask_path = ?38,\
+                    thumbnail_card_path = ?39, thumbnail_preview_path = ?40,\
+                    shiori_engine = ?41,\
+                    updated_at = datetime('now')\
+                WHERE request_key = ?1 AND ghost_identity_key = ?2",
+            )
+            .map_err(|e| format!("UPDATE 準備エラー: {e}"))?;
+
+        for (ghost, identity_key) in to_update {
+            execute_ghost_row(&mut stmt, request_key, identity_key, ghost)
+                .map_err(|e| format!("UPDATE エラー: {e}"))?;
+            replace_ghost_shells(tx, request_key, identity_key, &ghost.shells)?;
+            write_search_row(tx, request_key, identity_key, ghost)?;
+        }
+    }
+
+    if !to_delete.is_empty() {
+        let mut stmt = tx
+            .prepare_cached(
+                "DELETE FROM ghosts \
+                 WHERE request_key = ?1 AND ghost_identity_key = ?2",
+            )
+            .map_err(|e| format!("DELETE 準備エラー: {e}"))?;
+
+        for identity_key in to_delete {
+            stmt.execute(rusqlite::params![request_key, identity_key])
+                .map_err(|e| format!("DELETE エラー: {e}"))?;
+            delete_ghost_shells(tx, request_key, identity_key)?;
+        }
+    }
+
+    Ok(())
+}
+
 /// ゴースト一覧を SQLite に差分書き込みする（1 トランザクション）。
-/// 既存行の (ghost_identity_key, row_fingerprint) をカバリングインデックスから読み、
+/// 既存行の (ghost_identity_key, row_fingerprint) を読み、
 /// スキャン結果と比較して INSERT / UPDATE / DELETE を最小限に実行する。
-/// fingerprint と parent_mtimes も同一トランザクション内で保存する。
+/// entry_tokens.reused の行は読み直していないので DELETE せずに残し、
+/// 読み直したゴーストのエントリトークンを次回のスキャン用に保存する。
+/// 検索用の ghosts_fts も同じトランザクションで書き直す（再利用・スキップした行はそのまま）。
+/// fingerprint と parent_mtimes も同一トランザクション内で保存する。戻り値はゴーストの総数（再利用した行を含む）
 pub(crate) fn store_ghosts(
     conn: &Connection,
     request_key: &str,
     ghosts: &[Ghost],
+    entry_tokens: &EntryTokens,
     fingerprint: &str,
     parent_mtimes: &str,
 ) -> Result<usize, String> {
@@ -50,141 +335,67 @@ pub(crate) fn store_ghosts(
         .unchecked_transaction()
         .map_err(|e| format!("トランザクション開始エラー: {e}"))?;
 
-    // フェーズ 1: 既存の (identity_key -> row_fingerprint) をカバリングインデックスから読む
-    let mut existing: HashMap<String, String> = {
+    // フェーズ 1: 既存の (identity_key -> (row_fingerprint, entry_token)) を読む
+    let mut existing: HashMap<String, (String, String)> = {
         let mut stmt = tx
             .prepare_cached(
-                "SELECT ghost_identity_key, row_fingerprint \
+                "SELECT ghost_identity_key, row_fingerprint, entry_token \
                  FROM ghosts WHERE request_key = ?1",
             )
             .map_err(|e| format!("SELECT 準備エラー: {e}"))?;
-        stmt.query_map([request_key], |row| Ok((row.get(0)?, row.get(1)?)))
-            .map_err(|e| format!("SELECT エラー: {e}"))?
-            .filter_map(|r| r.ok())
-            .collect()
+        stmt.query_map([request_key], |row| {
+            Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+        })
+        .map_err(|e| format!("SELECT エラー: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect()
     };
 
     // フェーズ 2: スキャン結果を INSERT / UPDATE / skip に分類
     let mut to_insert: Vec<(&Ghost, String)> = Vec::new();
     let mut to_update: Vec<(&Ghost, String)> = Vec::new();
+    // エントリトークンが保存済みの値と違う行（INSERT した行を含む）
+    let mut token_updates: Vec<(String, &str)> = Vec::new();
 
     for ghost in ghosts {
         let identity_key = build_ghost_identity_key(ghost);
-        match existing.remove(&identity_key) {
+        let entry_token = entry_tokens
+            .refreshed
+            .get(&ghost.path)
+            .map_or("", String::as_str);
+        let stored = existing.remove(&identity_key);
+        if stored.as_ref().map_or("", |(_, token)| token.as_str()) != entry_token {
+            token_updates.push((identity_key.clone(), entry_token));
+        }
+        match stored {
             None => to_insert.push((ghost, identity_key)),
-            Some(ref stored_fp) if stored_fp != &ghost.diff_fingerprint => {
+            Some((ref stored_fp, _)) if stored_fp != &ghost.diff_fingerprint => {
                 to_update.push((ghost, identity_key));
             }
             Some(_) => {} // row_fingerprint 一致 → スキップ
         }
     }
+    // 読み直さなかった行はそのまま残す
+    for identity_key in &entry_tokens.reused {
+        existing.remove(identity_key);
+    }
     // existing に残ったキーはスキャン結果に存在しない → DELETE 対象
     let to_delete: Vec<String> = existing.into_keys().collect();
 
     // フェーズ 3: 差分のみ書き込む
     {
-        if !to_insert.is_empty() {
-            let mut stmt = tx
-                .prepare_cached(
-                    "INSERT INTO ghosts (\
-                        request_key, ghost_identity_key, row_fingerprint,\
-                        name, sakura_name, kero_name, craftman, craftmanw,\
-                        directory_name, path, source,\
-                        name_lower, sakura_name_lower, kero_name_lower,\
-                        craftman_lower, craftmanw_lower, directory_name_lower,\
-                        thumbnail_path, thumbnail_use_self_alpha, thumbnail_kind,\
-                        updated_at\
-                    ) VALUES (\
-                        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11,\
-                        ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,\
-                        datetime('now')\
-                    )",
-                )
-                .map_err(|e| format!("INSERT 準備エラー: {e}"))?;
-
-            for (ghost, identity_key) in &to_insert {
-                stmt.execute(rusqlite::params![
-                    request_key,
-                    identity_key,
-                    ghost.diff_fingerprint,
-                    ghost.name,
-                    ghost.sakura_name,
-                    ghost.kero_name,
-                    ghost.craftman,
-                    ghost.craftmanw,
-                    ghost.directory_name,
-                    ghost.path,
-                    ghost.source,
-                    normalize_for_key(&ghost.name),
-                    normalize_for_key(&ghost.sakura_name),
-                    normalize_for_key(&ghost.kero_name),
-                    normalize_for_key(&ghost.craftman),
-                    normalize_for_key(&ghost.craftmanw),
-                    normalize_for_key(&ghost.directory_name),
-                    ghost.thumbnail_path,
-                    ghost.thumbnail_use_self_alpha as i32,
-                    ghost.thumbnail_kind,
-                ])
-                .map_err(|e| format!("INSERT エラー: {e}"))?;
-            }
-        }
-
-        if !to_update.is_empty() {
-            let mut stmt = tx
-                .prepare_cached(
-                    "UPDATE ghosts SET \
-                        row_fingerprint = ?3,\
-                        name = ?4, sakura_name = ?5, kero_name = ?6,\
-                        craftman = ?7, craftmanw = ?8,\
-                        directory_name = ?9, path = ?10, source = ?11,\
-                        name_lower = ?12, sakura_name_lower = ?13, kero_name_lower = ?14,\
-                        craftman_lower = ?15, craftmanw_lower = ?16,\
-                        directory_name_lower = ?17,\
-                        thumbnail_path = ?18, thumbnail_use_self_alpha = ?19,\
-                        thumbnail_kind = ?20,\
-                        updated_at = datetime('now')\
-                    WHERE request_key = ?1 AND ghost_identity_key = ?2",
-                )
-                .map_err(|e| format!("UPDATE 準備エラー: {e}"))?;
-
-            for (ghost, identity_key) in &to_update {
-                stmt.execute(rusqlite::params![
-                    request_key,
-                    identity_key,
-                    ghost.diff_fingerprint,
-                    ghost.name,
-                    ghost.sakura_name,
-                    ghost.kero_name,
-                    ghost.craftman,
-                    ghost.craftmanw,
-                    ghost.directory_name,
-                    ghost.path,
-                    ghost.source,
-                    normalize_for_key(&ghost.name),
-                    normalize_for_key(&ghost.sakura_name),
-                    normalize_for_key(&ghost.kero_name),
-                    normalize_for_key(&ghost.craftman),
-                    normalize_for_key(&ghost.craftmanw),
-                    normalize_for_key(&ghost.directory_name),
-                    ghost.thumbnail_path,
-                    ghost.thumbnail_use_self_alpha as i32,
-                    ghost.thumbnail_kind,
-                ])
-                .map_err(|e| format!("UPDATE エラー: {e}"))?;
-            }
-        }
+        apply_ghost_diff(&tx, request_key, &to_insert, &to_update, &to_delete)?;
 
-        if !to_delete.is_empty() {
+        if !token_updates.is_empty() {
             let mut stmt = tx
                 .prepare_cached(
-                    "DELETE FROM ghosts \
+                    "UPDATE ghosts SET entry_token = ?3 \
                      WHERE request_key = ?1 AND ghost_identity_key = ?2",
                 )
-                .map_err(|e| format!("DELETE 準備エラー: {e}"))?;
-
-            for identity_key in &to_delete {
-                stmt.execute(rusqlite::params![request_key, identity_key])
-                    .map_err(|e| format!("DELETE エラー: {e}"))?;
+                .map_err(|e| format!("エントリトークン UPDATE 準備エラー: {e}"))?;
+            for (identity_key, entry_token) in &token_updates {
+                stmt.execute(rusqlite::params![request_key, identity_key, entry_token])
+                    .map_err(|e| format!("エントリトークン UPDATE エラー: {e}"))?;
             }
         }
 
@@ -200,16 +411,218 @@ pub(crate) fn store_ghosts(
     tx.commit()
         .map_err(|e| format!("コミットエラー: {e}"))?;
 
-    Ok(ghosts.len())
+    Ok(ghosts.len() + entry_tokens.reused.len())
+}
+
+/// 前回のスキャンで書き込んだゴーストのエントリトークンを読む。
+/// スキャンレポートに載っているゴースト（decode_failure など）は、報告を作り直すため含めない
+pub(crate) fn load_previous_entries(
+    conn: &Connection,
+    request_key: &str,
+) -> Result<PreviousEntries, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT path, entry_token, ghost_identity_key FROM ghosts \
+             WHERE request_key = ?1 AND entry_token != '' \
+               AND path NOT IN (SELECT path FROM ghost_scan_skips WHERE request_key = ?1)",
+        )
+        .map_err(|e| format!("エントリトークン SELECT 準備エラー: {e}"))?;
+    let rows = stmt
+        .query_map([request_key], |row| {
+            Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+        })
+        .map_err(|e| format!("エントリトークン SELECT エラー: {e}"))?;
+    rows.collect::<Result<PreviousEntries, _>>()
+        .map_err(|e| format!("エントリトークン読み込みエラー: {e}"))
+}
+
+/// スキャンレポートを request_key ごとに入れ替える（1 トランザクション）。対象のフォルダは少ないため差分更新はしない
+pub(crate) fn store_scan_skips(
+    conn: &Connection,
+    request_key: &str,
+    skipped: &[GhostScanSkip],
+) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクション開始エラー: {e}"))?;
+    tx.execute(
+        "DELETE FROM ghost_scan_skips WHERE request_key = ?1",
+        [request_key],
+    )
+    .map_err(|e| format!("スキャンレポート DELETE エラー: {e}"))?;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT INTO ghost_scan_skips (request_key, source, path, reason, detail) \
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )
+            .map_err(|e| format!("スキャンレポート INSERT 準備エラー: {e}"))?;
+        for skip in skipped {
+            stmt.execute(rusqlite::params![
+                request_key,
+                skip.source,
+                skip.path,
+                skip.reason,
+                skip.detail
+            ])
+            .map_err(|e| format!("スキャンレポート INSERT エラー: {e}"))?;
+        }
+    }
+    tx.commit()
+        .map_err(|e| format!("コミットエラー: {e}"))
+}
+
+/// request_key のスキャンレポートをパス順に読む
+pub(crate) fn load_scan_skips(
+    conn: &Connection,
+    request_key: &str,
+) -> Result<Vec<GhostScanSkip>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT source, path, reason, detail FROM ghost_scan_skips \
+             WHERE request_key = ?1 ORDER BY path",
+        )
+        .map_err(|e| format!("スキャンレポート SELECT 準備エラー: {e}"))?;
+    let rows = stmt
+        .query_map([request_key], |row| {
+            Ok(GhostScanSkip {
+                source: row.get(0)?,
+                path: row.get(1)?,
+                reason: row.get(2)?,
+                detail: row.get(3)?,
+            })
+        })
+        .map_err(|e| format!("スキャンレポート SELECT エラー: {e}"))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("スキャンレポート読み込みエラー: {e}"))
+}
+
+/// ゴースト 1 件だけを書き込む（既存行があれば置き換える）。ghost_identity_key を返す。
+/// .nar のインストール後など、影響を受けたゴーストだけを再スキャンするときに使う。
+/// 他のゴーストと ghost_fingerprints には触れない（次回の scan_and_store は Layer 1 で miss し、差分書き込みで整合する）
+pub(crate) fn upsert_ghost(
+    conn: &Connection,
+    request_key: &str,
+    ghost: &Ghost,
+) -> Result<String, String> {
+    let identity_key = build_ghost_identity_key(ghost);
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクション開始エラー: {e}"))?;
+    tx.execute(
+        "DELETE FROM ghosts WHERE request_key = ?1 AND ghost_identity_key = ?2",
+        rusqlite::params![request_key, identity_key],
+    )
+    .map_err(|e| format!("DELETE エラー: {e}"))?;
+    {
+        let mut stmt = tx
+            .prepare_cached(INSERT_GHOST_SQL)
+            .map_err(|e| format!("INSERT 準備エラー: {e}"))?;
+        execute_ghost_row(&mut stmt, request_key, &identity_key, ghost)
+            .map_err(|e| format!("INSERT エラー: {e}"))?;
+    }
+    replace_ghost_shells(&tx, request_key, &identity_key, &ghost.shells)?;
+    write_search_row(&tx, request_key, &identity_key, ghost)?;
+    tx.commit()
+        .map_err(|e| format!("コミットエラー: {e}"))?;
+    Ok(identity_key)
+}
+
+/// 監視で消えた（またはゴーストではなくなった）フォルダ。
+/// `descendants` が true のときはフォルダごと消えているので、配下の入れ子のゴーストも削除対象にする
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RemovedGhostRoot {
+    pub path: String,
+    pub descendants: bool,
+}
+
+/// 監視で変化を検知したゴーストだけを差分書き込みする（1 トランザクション）。
+/// 分類は store_ghosts と同じ（未登録なら INSERT、row_fingerprint が違えば UPDATE、同じならスキップ）で、
+/// `removed` に当たる行は DELETE する。戻り値は（INSERT + UPDATE した件数, DELETE した件数）。
+/// ghost_fingerprints には触れない（次回の scan_and_store が Layer 2 の差分書き込みで整合させる）
+pub(crate) fn store_ghost_changes(
+    conn: &Connection,
+    request_key: &str,
+    ghosts: &[Ghost],
+    removed: &[RemovedGhostRoot],
+) -> Result<(usize, usize), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("トランザクション開始エラー: {e}"))?;
+
+    let mut to_insert: Vec<(&Ghost, String)> = Vec::new();
+    let mut to_update: Vec<(&Ghost, String)> = Vec::new();
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "SELECT row_fingerprint FROM ghosts \
+                 WHERE request_key = ?1 AND ghost_identity_key = ?2",
+            )
+            .map_err(|e| format!("SELECT 準備エラー: {e}"))?;
+        for ghost in ghosts {
+            let identity_key = build_ghost_identity_key(ghost);
+            let stored: Option<String> = stmt
+                .query_row(rusqlite::params![request_key, identity_key], |row| {
+                    row.get(0)
+                })
+                .optional()
+                .map_err(|e| format!("SELECT エラー: {e}"))?;
+            match stored {
+                None => to_insert.push((ghost, identity_key)),
+                Some(ref stored_fp) if stored_fp != &ghost.diff_fingerprint => {
+                    to_update.push((ghost, identity_key));
+                }
+                Some(_) => {} // row_fingerprint 一致 → スキップ
+            }
+        }
+    }
+
+    let mut to_delete: Vec<String> = Vec::new();
+    if !removed.is_empty() {
+        // 区切り文字は OS で異なるため '/' と '\' の両方で配下を判定する
+        let mut stmt = tx
+            .prepare_cached(
+                "SELECT ghost_identity_key FROM ghosts \
+                 WHERE request_key = ?1 AND (path = ?2 OR (?3 AND ( \
+                     substr(path, 1, length(?2) + 1) = ?2 || '/' \
+                     OR substr(path, 1, length(?2) + 1) = ?2 || '\\')))",
+            )
+            .map_err(|e| format!("SELECT 準備エラー: {e}"))?;
+        let kept: std::collections::HashSet<&str> = to_insert
+            .iter()
+            .chain(to_update.iter())
+            .map(|(_, key)| key.as_str())
+            .collect();
+        for root in removed {
+            let keys = stmt
+                .query_map(
+                    rusqlite::params![request_key, root.path, root.descendants],
+                    |row| row.get::<_, String>(0),
+                )
+                .map_err(|e| format!("SELECT エラー: {e}"))?;
+            for key in keys {
+                let key = key.map_err(|e| format!("SELECT エラー: {e}"))?;
+                if !kept.contains(key.as_str()) && !to_delete.contains(&key) {
+                    to_delete.push(key);
+                }
+            }
+        }
+    }
+
+    apply_ghost_diff(&tx, request_key, &to_insert, &to_update, &to_delete)?;
+    tx.commit()
+        .map_err(|e| format!("コミットエラー: {e}"))?;
+
+    Ok((to_insert.len() + to_update.len(), to_delete.len()))
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::migrations;
 
     /// テスト用にマイグレーション適用済みの in-memory DB を作成する
-    fn setup_db() -> Connection {
+    pub(crate) fn setup_db() -> Connection {
         let conn = Connection::open_in_memory().unwrap();
         let mut sorted = migrations();
         sorted.sort_by_key(|m| m.version);
@@ -224,7 +637,7 @@ mod tests {
         conn
     }
 
-    fn make_ghost(name: &str, dir: &str, source: &str) -> Ghost {
+    pub(crate) fn make_ghost(name: &str, dir: &str, source: &str) -> Ghost {
         Ghost {
             diff_fingerprint: format!("fp-{name}"),
             name: name.to_string(),
@@ -232,19 +645,35 @@ mod tests {
             kero_name: String::new(),
             craftman: String::new(),
             craftmanw: String::new(),
+            descript_id: String::new(),
+            ghost_type: String::new(),
+            shiori: String::new(),
+            shiori_engine: String::new(),
+            homeurl: String::new(),
+            craftmanurl: String::new(),
+            readme: String::new(),
+            icon: String::new(),
+            balloon: String::new(),
+            sakura_default_surface: None,
+            kero_default_surface: None,
+            install_accept: String::new(),
             directory_name: dir.to_string(),
             path: format!("/ghosts/{dir}"),
             source: source.to_string(),
             thumbnail_path: String::new(),
             thumbnail_use_self_alpha: false,
+            thumbnail_mask_path: String::new(),
+            thumbnail_card_path: String::new(),
+            thumbnail_preview_path: String::new(),
             thumbnail_kind: String::new(),
+            shells: Vec::new(),
         }
     }
 
     #[test]
     fn store_ghosts_が空の配列で成功する() {
         let conn = setup_db();
-        let result = store_ghosts(&conn, "rk1", &[], "fp-empty", "");
+        let result = store_ghosts(&conn, "rk1", &[], &EntryTokens::default(), "fp-empty", "");
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), 0);
     }
@@ -256,7 +685,15 @@ mod tests {
             make_ghost("Alice", "alice", "ssp"),
             make_ghost("Bob", "bob", "ssp"),
         ];
-        let total = store_ghosts(&conn, "rk1", &ghosts, "fp-test", "mtimes").unwrap();
+        let total = store_ghosts(
+            &conn,
+            "rk1",
+            &ghosts,
+            &EntryTokens::default(),
+            "fp-test",
+            "mtimes",
+        )
+        .unwrap();
         assert_eq!(total, 2);
 
         // DB から件数確認
@@ -275,7 +712,15 @@ mod tests {
         let conn = setup_db();
         // 全角英字 "Ａｌｉｃｅ" → NFKC → "Alice" → lower → "alice"
         let ghosts = vec![make_ghost("Ａｌｉｃｅ", "alice_dir", "ssp")];
-        store_ghosts(&conn, "rk1", &ghosts, "fp-nfkc", "").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &ghosts,
+            &EntryTokens::default(),
+            "fp-nfkc",
+            "",
+        )
+        .unwrap();
 
         let name_lower: String = conn
             .query_row(
@@ -291,13 +736,29 @@ mod tests {
     fn store_ghosts_が既存データを置換する() {
         let conn = setup_db();
         let ghosts_v1 = vec![make_ghost("Old", "old", "ssp")];
-        store_ghosts(&conn, "rk1", &ghosts_v1, "fp-v1", "mt-v1").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &ghosts_v1,
+            &EntryTokens::default(),
+            "fp-v1",
+            "mt-v1",
+        )
+        .unwrap();
 
         let ghosts_v2 = vec![
             make_ghost("New1", "new1", "ssp"),
             make_ghost("New2", "new2", "ssp"),
         ];
-        store_ghosts(&conn, "rk1", &ghosts_v2, "fp-v2", "mt-v2").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &ghosts_v2,
+            &EntryTokens::default(),
+            "fp-v2",
+            "mt-v2",
+        )
+        .unwrap();
 
         let count: i64 = conn
             .query_row(
@@ -324,8 +785,24 @@ mod tests {
         let conn = setup_db();
         let ghosts_a = vec![make_ghost("A", "a", "ssp")];
         let ghosts_b = vec![make_ghost("B", "b", "ssp")];
-        store_ghosts(&conn, "rk-a", &ghosts_a, "fp-a", "").unwrap();
-        store_ghosts(&conn, "rk-b", &ghosts_b, "fp-b", "").unwrap();
+        store_ghosts(
+            &conn,
+            "rk-a",
+            &ghosts_a,
+            &EntryTokens::default(),
+            "fp-a",
+            "",
+        )
+        .unwrap();
+        store_ghosts(
+            &conn,
+            "rk-b",
+            &ghosts_b,
+            &EntryTokens::default(),
+            "fp-b",
+            "",
+        )
+        .unwrap();
 
         let count_a: i64 = conn
             .query_row(
@@ -337,10 +814,83 @@ mod tests {
         assert_eq!(count_a, 1);
     }
 
+    #[test]
+    fn store_ghosts_が_descript_拡張フィールドと検索用_lower_カラムを格納する() {
+        let conn = setup_db();
+        let mut ghost = make_ghost("Test", "test_dir", "ssp");
+        ghost.descript_id = "ＴｅｓｔＩＤ".to_string();
+        ghost.shiori = "YAYA.dll".to_string();
+        ghost.shiori_engine = "yaya".to_string();
+        ghost.balloon = "Sample".to_string();
+        ghost.homeurl = "https://Example.com/".to_string();
+        ghost.sakura_default_surface = Some(3);
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[ghost],
+            &EntryTokens::default(),
+            "fp-ext",
+            "",
+        )
+        .unwrap();
+
+        let (descript_id, shiori, surface, kero_surface, id_lower, shiori_lower, homeurl_lower): (
+            String,
+            String,
+            Option<u32>,
+            Option<u32>,
+            String,
+            String,
+            String,
+        ) = conn
+            .query_row(
+                "SELECT descript_id, shiori, sakura_default_surface, kero_default_surface,\
+                        descript_id_lower, shiori_lower, homeurl_lower \
+                 FROM ghosts WHERE request_key = ?1",
+                ["rk1"],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                        row.get(5)?,
+                        row.get(6)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(descript_id, "ＴｅｓｔＩＤ");
+        assert_eq!(shiori, "YAYA.dll");
+        assert_eq!(surface, Some(3));
+        assert_eq!(kero_surface, None);
+        assert_eq!(id_lower, "testid");
+        assert_eq!(shiori_lower, "yaya.dll");
+        assert_eq!(homeurl_lower, "https://example.com/");
+
+        let shiori_engine: String = conn
+            .query_row(
+                "SELECT shiori_engine FROM ghosts WHERE request_key = ?1",
+                ["rk1"],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(shiori_engine, "yaya");
+    }
+
     #[test]
     fn store_ghosts_が_fingerprint_を保存する() {
         let conn = setup_db();
-        store_ghosts(&conn, "rk1", &[], "fp-123abc", "mt-test").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[],
+            &EntryTokens::default(),
+            "fp-123abc",
+            "mt-test",
+        )
+        .unwrap();
 
         let fp: String = conn
             .query_row(
@@ -356,7 +906,7 @@ mod tests {
     fn store_ghosts_が_ghost_identity_key_を正しく構築する() {
         let conn = setup_db();
         let ghosts = vec![make_ghost("Test", "test_dir", "ssp")];
-        store_ghosts(&conn, "rk1", &ghosts, "fp-id", "").unwrap();
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp-id", "").unwrap();
 
         let identity_key: String = conn
             .query_row(
@@ -373,7 +923,15 @@ mod tests {
     #[test]
     fn store_ghosts_が_parent_mtimes_を保存する() {
         let conn = setup_db();
-        store_ghosts(&conn, "rk1", &[], "fp-1", "c:/ssp/ghost:12345\nc:/extra:67890").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[],
+            &EntryTokens::default(),
+            "fp-1",
+            "c:/ssp/ghost:12345\nc:/extra:67890",
+        )
+        .unwrap();
 
         let mtimes: String = conn
             .query_row(
@@ -388,7 +946,15 @@ mod tests {
     #[test]
     fn check_parent_mtimes_match_が一致時にtrueを返す() {
         let conn = setup_db();
-        store_ghosts(&conn, "rk1", &[], "fp-1", "c:/ssp/ghost:12345").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[],
+            &EntryTokens::default(),
+            "fp-1",
+            "c:/ssp/ghost:12345",
+        )
+        .unwrap();
 
         assert!(super::super::fingerprint::check_parent_mtimes_match(
             &conn,
@@ -400,7 +966,15 @@ mod tests {
     #[test]
     fn check_parent_mtimes_match_が不一致時にfalseを返す() {
         let conn = setup_db();
-        store_ghosts(&conn, "rk1", &[], "fp-1", "c:/ssp/ghost:12345").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[],
+            &EntryTokens::default(),
+            "fp-1",
+            "c:/ssp/ghost:12345",
+        )
+        .unwrap();
 
         assert!(!super::super::fingerprint::check_parent_mtimes_match(
             &conn,
@@ -420,6 +994,32 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn check_parent_mtimes_match_が入れ子の親ディレクトリの変化を検出する() {
+        use super::super::fingerprint::{
+            check_parent_mtimes_match, metadata_modified_string, nested_parent_mtime_line,
+            with_nested_parent_mtimes,
+        };
+
+        let tmp = crate::testutil::TempDirGuard::new("ghost_launcher_nested_parent_mtimes");
+        let author_dir = tmp.path().join("author");
+        std::fs::create_dir_all(&author_dir).unwrap();
+        let modified = metadata_modified_string(&std::fs::metadata(&author_dir).unwrap());
+        let stored = with_nested_parent_mtimes(
+            "c:/ssp/ghost:12345",
+            &[nested_parent_mtime_line(&author_dir, &modified)],
+        );
+        let conn = setup_db();
+        store_ghosts(&conn, "rk1", &[], &EntryTokens::default(), "fp-1", &stored).unwrap();
+
+        assert!(check_parent_mtimes_match(&conn, "rk1", "c:/ssp/ghost:12345"));
+
+        // 作者フォルダにゴーストが追加されると入れ子の親ディレクトリの mtime が変わる
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::create_dir_all(author_dir.join("new_ghost")).unwrap();
+        assert!(!check_parent_mtimes_match(&conn, "rk1", "c:/ssp/ghost:12345"));
+    }
+
     #[test]
     fn normalize_for_key_が共有_fixture_の期待値と一致する() {
         // JS の normalizeForKey と同一の fixture を参照し、言語間パリティを縛る。
@@ -440,7 +1040,7 @@ mod tests {
     fn store_ghosts_が変更なしのゴーストをスキップする() {
         let conn = setup_db();
         let ghosts = vec![make_ghost("Alice", "alice", "ssp")];
-        store_ghosts(&conn, "rk1", &ghosts, "fp-1", "").unwrap();
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp-1", "").unwrap();
 
         let updated_at_1: String = conn
             .query_row(
@@ -451,7 +1051,7 @@ mod tests {
             .unwrap();
 
         // 同一データで再度書き込み → updated_at は変わらない（UPDATE が発行されない）
-        store_ghosts(&conn, "rk1", &ghosts, "fp-2", "").unwrap();
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp-2", "").unwrap();
 
         let updated_at_2: String = conn
             .query_row(
@@ -477,12 +1077,28 @@ mod tests {
     fn store_ghosts_がフィンガープリント変化時のみupdateする() {
         let conn = setup_db();
         let ghosts_v1 = vec![make_ghost("Alice", "alice", "ssp")];
-        store_ghosts(&conn, "rk1", &ghosts_v1, "fp-1", "").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &ghosts_v1,
+            &EntryTokens::default(),
+            "fp-1",
+            "",
+        )
+        .unwrap();
 
         // diff_fingerprint が異なる同一ゴースト → UPDATE される
         let mut ghost_v2 = make_ghost("Alice-Updated", "alice", "ssp");
         ghost_v2.diff_fingerprint = "fp-changed".to_string();
-        store_ghosts(&conn, "rk1", &[ghost_v2], "fp-2", "").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[ghost_v2],
+            &EntryTokens::default(),
+            "fp-2",
+            "",
+        )
+        .unwrap();
 
         let count: i64 = conn
             .query_row(
@@ -511,11 +1127,19 @@ mod tests {
             make_ghost("Alice", "alice", "ssp"),
             make_ghost("Bob", "bob", "ssp"),
         ];
-        store_ghosts(&conn, "rk1", &ghosts, "fp-1", "").unwrap();
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp-1", "").unwrap();
 
         // Alice のみ残す → Bob は DELETE される
         let ghosts_v2 = vec![make_ghost("Alice", "alice", "ssp")];
-        store_ghosts(&conn, "rk1", &ghosts_v2, "fp-2", "").unwrap();
+        store_ghosts(
+            &conn,
+            "rk1",
+            &ghosts_v2,
+            &EntryTokens::default(),
+            "fp-2",
+            "",
+        )
+        .unwrap();
 
         let count: i64 = conn
             .query_row(
@@ -535,4 +1159,257 @@ mod tests {
             .unwrap();
         assert_eq!(name, "Alice");
     }
+
+    #[test]
+    fn store_ghosts_がシェルを_ghost_shells_に格納し差分に追従する() {
+        let conn = setup_db();
+        let shell = |name: &str, dir: &str| GhostShell {
+            name: name.to_string(),
+            craftman: String::new(),
+            directory_name: dir.to_string(),
+            path: format!("/ghosts/alice/shell/{dir}"),
+            thumbnail_path: String::new(),
+            thumbnail_use_self_alpha: false,
+            thumbnail_mask_path: String::new(),
+        };
+        let shell_rows = |conn: &Connection| -> Vec<(String, String)> {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT directory_name, name_lower FROM ghost_shells \
+                     WHERE request_key = 'rk1' ORDER BY directory_name",
+                )
+                .unwrap();
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .map(|r| r.unwrap())
+                .collect()
+        };
+        let shell_count = |conn: &Connection| -> i64 {
+            conn.query_row(
+                "SELECT shell_count FROM ghosts WHERE request_key = 'rk1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+
+        let mut alice = make_ghost("Alice", "alice", "ssp");
+        alice.shells = vec![shell("通常", "master"), shell("Ｗｉｎｔｅｒ", "winter")];
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[alice.clone()],
+            &EntryTokens::default(),
+            "fp-1",
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            shell_rows(&conn),
+            vec![
+                ("master".to_string(), "通常".to_string()),
+                ("winter".to_string(), "winter".to_string()),
+            ]
+        );
+        assert_eq!(shell_count(&conn), 2);
+
+        // UPDATE 時は行を入れ替える
+        alice.shells.pop();
+        alice.diff_fingerprint = "fp-Alice-v2".to_string();
+        store_ghosts(&conn, "rk1", &[alice], &EntryTokens::default(), "fp-2", "").unwrap();
+        assert_eq!(
+            shell_rows(&conn),
+            vec![("master".to_string(), "通常".to_string())]
+        );
+        assert_eq!(shell_count(&conn), 1);
+
+        // ゴーストが消えたらシェルも消える
+        store_ghosts(&conn, "rk1", &[], &EntryTokens::default(), "fp-3", "").unwrap();
+        assert!(shell_rows(&conn).is_empty());
+    }
+
+    #[test]
+    fn store_scan_skips_が_request_key_ごとに入れ替える() {
+        let conn = setup_db();
+        let skip = |path: &str, reason: &str| GhostScanSkip {
+            source: "ssp".to_string(),
+            path: path.to_string(),
+            reason: reason.to_string(),
+            detail: String::new(),
+        };
+        store_scan_skips(&conn, "rk1", &[skip("/b", "no_descript"), skip("/a", "unreadable")])
+            .unwrap();
+        store_scan_skips(&conn, "rk2", &[skip("/c", "no_descript")]).unwrap();
+
+        let loaded = load_scan_skips(&conn, "rk1").unwrap();
+        assert_eq!(loaded, vec![skip("/a", "unreadable"), skip("/b", "no_descript")]);
+
+        store_scan_skips(&conn, "rk1", &[]).unwrap();
+        assert!(load_scan_skips(&conn, "rk1").unwrap().is_empty());
+        assert_eq!(load_scan_skips(&conn, "rk2").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn upsert_ghost_は対象のゴーストだけを置き換える() {
+        let conn = setup_db();
+        let alice = make_ghost("Alice", "alice", "ssp");
+        let bob = make_ghost("Bob", "bob", "ssp");
+        store_ghosts(
+            &conn,
+            "rk1",
+            &[alice, bob],
+            &EntryTokens::default(),
+            "fp-1",
+            "",
+        )
+        .unwrap();
+
+        let mut alice = make_ghost("Alice v2", "alice", "ssp");
+        alice.diff_fingerprint = "fp-Alice-v2".to_string();
+        let identity_key = upsert_ghost(&conn, "rk1", &alice).unwrap();
+        assert_eq!(identity_key, format!("ssp{GHOST_KEY_SEPARATOR}alice"));
+        let carol = make_ghost("Carol", "carol", "ssp");
+        upsert_ghost(&conn, "rk1", &carol).unwrap();
+
+        let mut stmt = conn
+            .prepare("SELECT name FROM ghosts WHERE request_key = 'rk1' ORDER BY name")
+            .unwrap();
+        let names: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(names, vec!["Alice v2", "Bob", "Carol"]);
+        let fingerprint: String = conn
+            .query_row(
+                "SELECT fingerprint FROM ghost_fingerprints WHERE request_key = 'rk1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(fingerprint, "fp-1");
+    }
+
+    #[test]
+    fn store_ghost_changes_は変化したゴーストと消えたフォルダだけを反映する() {
+        let conn = setup_db();
+        let mut nested = make_ghost("Nested", "author/nested", "/extra");
+        nested.path = "/extra/author/nested".to_string();
+        let ghosts = vec![
+            make_ghost("Alice", "alice", "ssp"),
+            make_ghost("Bob", "bob", "ssp"),
+            nested,
+        ];
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp-1", "").unwrap();
+
+        // 変更なし → スキップ、変更あり → UPDATE、新規 → INSERT
+        let mut alice = make_ghost("Alice v2", "alice", "ssp");
+        alice.diff_fingerprint = "fp-Alice-v2".to_string();
+        let unchanged_bob = make_ghost("Bob", "bob", "ssp");
+        let carol = make_ghost("Carol", "carol", "ssp");
+        let removed = vec![
+            // 存在するがゴーストではないフォルダ: 配下は消さない
+            RemovedGhostRoot {
+                path: "/ghosts".to_string(),
+                descendants: false,
+            },
+            // フォルダごと消えた: 配下の入れ子のゴーストも消す
+            RemovedGhostRoot {
+                path: "/extra/author".to_string(),
+                descendants: true,
+            },
+        ];
+        let (changed, deleted) =
+            store_ghost_changes(&conn, "rk1", &[alice, unchanged_bob, carol], &removed).unwrap();
+        assert_eq!((changed, deleted), (2, 1));
+
+        let mut stmt = conn
+            .prepare("SELECT name FROM ghosts WHERE request_key = 'rk1' ORDER BY name")
+            .unwrap();
+        let names: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(names, vec!["Alice v2", "Bob", "Carol"]);
+
+        // ゴーストのフォルダそのものが消えた場合は完全一致で消す
+        let removed = vec![RemovedGhostRoot {
+            path: "/ghosts/bob".to_string(),
+            descendants: false,
+        }];
+        assert_eq!(
+            store_ghost_changes(&conn, "rk1", &[], &removed).unwrap(),
+            (0, 1)
+        );
+        let fingerprint: String = conn
+            .query_row(
+                "SELECT fingerprint FROM ghost_fingerprints WHERE request_key = 'rk1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(fingerprint, "fp-1");
+    }
+
+    #[test]
+    fn store_ghosts_が再利用した行を残しエントリトークンを保存する() {
+        let conn = setup_db();
+        let ghosts = vec![
+            make_ghost("Alice", "alice", "ssp"),
+            make_ghost("Bob", "bob", "ssp"),
+            make_ghost("Carol", "carol", "ssp"),
+        ];
+        let tokens = EntryTokens {
+            reused: Vec::new(),
+            refreshed: ghosts
+                .iter()
+                .map(|ghost| (ghost.path.clone(), format!("token-{}", ghost.name)))
+                .collect(),
+        };
+        store_ghosts(&conn, "rk1", &ghosts, &tokens, "fp-1", "").unwrap();
+        // Carol は壊れていたのでスキャンレポートに載っている → 次回は読み直す
+        store_scan_skips(
+            &conn,
+            "rk1",
+            &[GhostScanSkip {
+                source: "ssp".to_string(),
+                path: "/ghosts/carol".to_string(),
+                reason: "decode_failure".to_string(),
+                detail: String::new(),
+            }],
+        )
+        .unwrap();
+
+        let previous = load_previous_entries(&conn, "rk1").unwrap();
+        assert_eq!(previous.len(), 2);
+        let (alice_token, alice_key) = &previous["/ghosts/alice"];
+        assert_eq!(alice_token, "token-Alice");
+        assert_eq!(alice_key, &format!("ssp{GHOST_KEY_SEPARATOR}alice"));
+
+        // Alice は再利用、Bob は読み直して内容は同じ・トークンだけ変化、Carol は消えた
+        let bob = make_ghost("Bob", "bob", "ssp");
+        let tokens = EntryTokens {
+            reused: vec![alice_key.clone()],
+            refreshed: HashMap::from([(bob.path.clone(), "token-Bob-2".to_string())]),
+        };
+        let total = store_ghosts(&conn, "rk1", &[bob], &tokens, "fp-2", "").unwrap();
+        assert_eq!(total, 2);
+
+        let mut stmt = conn
+            .prepare("SELECT name, entry_token FROM ghosts WHERE request_key = 'rk1' ORDER BY name")
+            .unwrap();
+        let rows: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("Alice".to_string(), "token-Alice".to_string()),
+                ("Bob".to_string(), "token-Bob-2".to_string()),
+            ]
+        );
+    }
 }
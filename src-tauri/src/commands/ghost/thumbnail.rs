@@ -0,0 +1,439 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use tauri::http::{header, Request, Response, StatusCode};
+
+use ghost_meta::AnimatedPreviewLimits;
+
+use super::thumbnail_cache::{
+    thumbnail_cache_dir, thumbnail_info, write_back_rendered, ThumbnailSize,
+};
+
+/// サムネイル配信用のカスタム URI スキーム名
+pub(crate) const THUMBNAIL_SCHEME: &str = "ghostthumb";
+
+/// `ghostthumb://localhost/<ghost_identity_key>?size=<card|preview|animated>` を処理する。
+/// Windows の WebView2 では `http://ghostthumb.localhost/...` として届く（パス以降は同じ）。
+///
+/// - identity key は URL エンコードされたパスの 1 セグメント目。`ghosts` テーブルから引く
+/// - identity key が `balloon/<balloon_identity_key>` ならバルーンのプレビュー画像。
+///   `balloons` テーブルから引き、キャッシュせずにその場で生成する
+/// - size 省略時は card
+/// - animated は元画像が APNG ならアニメーションプレビュー（APNG）をその場で生成して返す。
+///   APNG でなければ preview と同じ静止画を返す
+/// - キャッシュ（`thumbnail_card_path` / `thumbnail_preview_path`）があればそれを返し、
+///   なければ元画像からその場で生成し、行が参照するキャッシュファイルが消えていれば書き戻す
+pub(crate) fn handle_thumbnail_request(
+    app: &tauri::AppHandle,
+    request: &Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+    use tauri::Manager;
+
+    let Ok(config_dir) = app.path().app_config_dir() else {
+        return status_response(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    let Ok(cache_dir) = thumbnail_cache_dir(app) else {
+        return status_response(StatusCode::INTERNAL_SERVER_ERROR);
+    };
+    respond_thumbnail(&config_dir.join("ghosts.db"), &cache_dir, request)
+}
+
+/// ghosts テーブルから引いたサムネイル情報
+struct ThumbnailRow {
+    thumbnail_path: String,
+    thumbnail_use_self_alpha: bool,
+    thumbnail_mask_path: String,
+    thumbnail_kind: String,
+    card_path: String,
+    preview_path: String,
+}
+
+fn respond_thumbnail(db_path: &Path, cache_dir: &Path, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    let uri = request.uri();
+    let Some(decoded) = uri
+        .path()
+        .trim_start_matches('/')
+        .split('/')
+        .next()
+        .and_then(percent_decode)
+    else {
+        return status_response(StatusCode::BAD_REQUEST);
+    };
+    // ghost_identity_key の source は "ssp" か絶対パスなので "balloon/" で始まることはない
+    let (table, identity_key) = match decoded.strip_prefix("balloon/") {
+        Some(key) => (ThumbnailTable::Balloons, key.to_string()),
+        None => (ThumbnailTable::Ghosts, decoded),
+    };
+    if identity_key.is_empty() {
+        return status_response(StatusCode::BAD_REQUEST);
+    }
+    let size_name = uri
+        .query()
+        .and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("size="))
+        })
+        .unwrap_or("card");
+    // animated は APNG のときだけアニメーションを返し、それ以外は preview と同じ静止画を返す
+    let animated = size_name == "animated";
+    let size = if animated {
+        ThumbnailSize::Preview
+    } else {
+        match ThumbnailSize::from_name(size_name) {
+            Some(size) => size,
+            None => return status_response(StatusCode::BAD_REQUEST),
+        }
+    };
+
+    let row = match lookup_thumbnail(db_path, table, &identity_key) {
+        Ok(Some(row)) => row,
+        Ok(None) => return status_response(StatusCode::NOT_FOUND),
+        Err(_) => return status_response(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    if animated
+        && let Some(body) = thumbnail_info(
+            &row.thumbnail_path,
+            row.thumbnail_use_self_alpha,
+            &row.thumbnail_mask_path,
+            &row.thumbnail_kind,
+        )
+        .and_then(|info| {
+            ghost_meta::render_animated_preview(&info, &AnimatedPreviewLimits::default())
+        })
+    {
+        // アニメーションはキャッシュしない（詳細表示でだけ使うため毎回生成する）
+        return Response::builder()
+            .header(header::CACHE_CONTROL, "no-store")
+            .header(header::CONTENT_TYPE, "image/png")
+            .body(body)
+            .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    let cached = PathBuf::from(match size {
+        ThumbnailSize::Card => &row.card_path,
+        ThumbnailSize::Preview => &row.preview_path,
+    });
+    // キャッシュは thumbnail_cache が管理するディレクトリ内のファイルだけを配信する
+    if cached.parent() == Some(cache_dir)
+        && let Ok(body) = fs::read(&cached)
+    {
+        // キャッシュのファイル名は内容ハッシュなので、そのまま ETag に使える
+        let etag = format!(
+            "\"{}\"",
+            cached.file_stem().unwrap_or_default().to_string_lossy()
+        );
+        let not_modified = request
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|value| value.as_bytes() == etag.as_bytes());
+        let builder = Response::builder()
+            .header(header::CACHE_CONTROL, "no-cache")
+            .header(header::ETAG, &etag);
+        return if not_modified {
+            builder.status(StatusCode::NOT_MODIFIED).body(Vec::new())
+        } else {
+            builder
+                .header(header::CONTENT_TYPE, "image/png")
+                .body(body)
+        }
+        .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    // キャッシュがない（生成失敗・キャッシュ削除後）場合はその場で生成する
+    let Some(info) = thumbnail_info(
+        &row.thumbnail_path,
+        row.thumbnail_use_self_alpha,
+        &row.thumbnail_mask_path,
+        &row.thumbnail_kind,
+    ) else {
+        return status_response(StatusCode::NOT_FOUND);
+    };
+    let Some(body) = ghost_meta::render_thumbnail(&info, size.max_edge())
+        .and_then(|image| ghost_meta::encode_png(&image))
+    else {
+        return status_response(StatusCode::NOT_FOUND);
+    };
+    // 行がキャッシュファイルを参照していれば書き戻し、次の要求からはキャッシュを返す
+    if !cached.as_os_str().is_empty() {
+        write_back_rendered(cache_dir, &cached, &info, size, &body);
+    }
+    Response::builder()
+        .header(header::CACHE_CONTROL, "no-store")
+        .header(header::CONTENT_TYPE, "image/png")
+        .body(body)
+        .unwrap_or_else(|_| status_response(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// サムネイルを引くテーブル
+#[derive(Debug, Clone, Copy)]
+enum ThumbnailTable {
+    Ghosts,
+    Balloons,
+}
+
+/// identity key に対応するサムネイル情報を引く。
+/// 同じゴーストが複数の request_key に存在する場合は最後に更新された行を使う。
+/// バルーンはキャッシュを持たないので card / preview のパスは常に空文字列。
+fn lookup_thumbnail(
+    db_path: &Path,
+    table: ThumbnailTable,
+    identity_key: &str,
+) -> rusqlite::Result<Option<ThumbnailRow>> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    let sql = match table {
+        ThumbnailTable::Ghosts => {
+            "SELECT thumbnail_path, thumbnail_use_self_alpha, thumbnail_mask_path, thumbnail_kind,\
+                    thumbnail_card_path, thumbnail_preview_path \
+             FROM ghosts WHERE ghost_identity_key = ?1 \
+             ORDER BY updated_at DESC LIMIT 1"
+        }
+        ThumbnailTable::Balloons => {
+            "SELECT thumbnail_path, thumbnail_use_self_alpha, thumbnail_mask_path, thumbnail_kind,\
+                    '', '' \
+             FROM balloons WHERE balloon_identity_key = ?1 LIMIT 1"
+        }
+    };
+    conn.query_row(
+        sql,
+        [identity_key],
+        |row| {
+            Ok(ThumbnailRow {
+                thumbnail_path: row.get(0)?,
+                thumbnail_use_self_alpha: row.get::<_, i64>(1)? != 0,
+                thumbnail_mask_path: row.get(2)?,
+                thumbnail_kind: row.get(3)?,
+                card_path: row.get(4)?,
+                preview_path: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+}
+
+fn status_response(status: StatusCode) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = status;
+    response
+}
+
+/// URL のパーセントエンコーディングを UTF-8 文字列に戻す。不正なエンコーディングは None
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::balloon::{scan_balloons_internal, store_balloons};
+    use crate::commands::ghost::store::{store_ghosts, EntryTokens};
+    use crate::commands::ghost::store::tests::make_ghost;
+    use crate::commands::ghost::thumbnail_cache::populate_thumbnail_cache;
+    use crate::migrations;
+    use crate::testutil::TempDirGuard;
+    use image::{Rgba, RgbaImage};
+
+    /// "ssp\x1falice" をエンコードしたもの
+    const ALICE_KEY: &str = "ssp%1Falice";
+
+    fn setup(tmp: &TempDirGuard, populate: bool) -> (PathBuf, PathBuf) {
+        let surface = tmp.path().join("alice").join("surface0.png");
+        fs::create_dir_all(surface.parent().unwrap()).unwrap();
+        RgbaImage::from_pixel(600, 300, Rgba([255, 0, 0, 255]))
+            .save(&surface)
+            .unwrap();
+
+        let mut ghost = make_ghost("Alice", "alice", "ssp");
+        ghost.thumbnail_path = surface.to_string_lossy().into_owned();
+        ghost.thumbnail_use_self_alpha = true;
+        ghost.thumbnail_kind = "surface".to_string();
+        let mut ghosts = vec![ghost];
+        let cache_dir = tmp.path().join("cache");
+        if populate {
+            populate_thumbnail_cache(&cache_dir, &mut ghosts).unwrap();
+        }
+
+        let db_path = tmp.path().join("ghosts.db");
+        let conn = Connection::open(&db_path).unwrap();
+        let mut sorted = migrations();
+        sorted.sort_by_key(|m| m.version);
+        for m in &sorted {
+            conn.execute_batch(m.sql).unwrap();
+        }
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp", "").unwrap();
+        (db_path, cache_dir)
+    }
+
+    fn get(uri: &str, etag: Option<&str>) -> Request<Vec<u8>> {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(etag) = etag {
+            builder = builder.header(header::IF_NONE_MATCH, etag);
+        }
+        builder.body(Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn キャッシュ済みサムネイルを_etag_付きで返し一致すれば_304_を返す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_protocol_cached");
+        let (db_path, cache_dir) = setup(&tmp, true);
+
+        let response = respond_thumbnail(
+            &db_path,
+            &cache_dir,
+            &get(&format!("ghostthumb://localhost/{ALICE_KEY}?size=preview"), None),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        let image = image::load_from_memory(response.body()).unwrap();
+        assert_eq!((image.width(), image.height()), (512, 256));
+
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        let response = respond_thumbnail(
+            &db_path,
+            &cache_dir,
+            &get(&format!("http://ghostthumb.localhost/{ALICE_KEY}"), Some(&etag)),
+        );
+        // card と preview は別ファイルなので ETag も異なる
+        assert_eq!(response.status(), StatusCode::OK);
+        let card_etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        let response = respond_thumbnail(
+            &db_path,
+            &cache_dir,
+            &get(&format!("http://ghostthumb.localhost/{ALICE_KEY}"), Some(&card_etag)),
+        );
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert!(response.body().is_empty());
+    }
+
+    #[test]
+    fn キャッシュがなければ元画像から生成して返す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_protocol_render");
+        let (db_path, cache_dir) = setup(&tmp, false);
+
+        let response = respond_thumbnail(
+            &db_path,
+            &cache_dir,
+            &get(&format!("ghostthumb://localhost/{ALICE_KEY}?size=card"), None),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        let image = image::load_from_memory(response.body()).unwrap();
+        assert_eq!((image.width(), image.height()), (256, 128));
+    }
+
+    #[test]
+    fn キャッシュファイルが消えていれば生成した画像を書き戻す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_protocol_write_back");
+        let (db_path, cache_dir) = setup(&tmp, true);
+        for entry in fs::read_dir(&cache_dir).unwrap() {
+            fs::remove_file(entry.unwrap().path()).unwrap();
+        }
+
+        let uri = format!("ghostthumb://localhost/{ALICE_KEY}?size=card");
+        let response = respond_thumbnail(&db_path, &cache_dir, &get(&uri, None));
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 1);
+
+        let response = respond_thumbnail(&db_path, &cache_dir, &get(&uri, None));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+    }
+
+    #[test]
+    fn animated_は元画像が_apng_でなければ_preview_の静止画を返す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_protocol_animated");
+        let (db_path, cache_dir) = setup(&tmp, true);
+
+        let response = respond_thumbnail(
+            &db_path,
+            &cache_dir,
+            &get(&format!("ghostthumb://localhost/{ALICE_KEY}?size=animated"), None),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+        let image = image::load_from_memory(response.body()).unwrap();
+        assert_eq!((image.width(), image.height()), (512, 256));
+    }
+
+    #[test]
+    fn balloon_パスはバルーンのプレビュー画像をその場で生成して返す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_protocol_balloon");
+        let (db_path, cache_dir) = setup(&tmp, false);
+        let balloon_dir = tmp.path().join("ssp").join("balloon").join("sample");
+        fs::create_dir_all(&balloon_dir).unwrap();
+        fs::write(balloon_dir.join("descript.txt"), "charset,UTF-8\nname,標準\n").unwrap();
+        RgbaImage::from_pixel(300, 600, Rgba([0, 0, 255, 255]))
+            .save(balloon_dir.join("balloons0.png"))
+            .unwrap();
+        let balloons =
+            scan_balloons_internal(&tmp.path().join("ssp").to_string_lossy()).unwrap();
+        let conn = Connection::open(&db_path).unwrap();
+        store_balloons(&conn, "c:/ssp", &balloons).unwrap();
+
+        let response = respond_thumbnail(
+            &db_path,
+            &cache_dir,
+            &get("ghostthumb://localhost/balloon%2Fc%3A%2Fssp%1Fsample", None),
+        );
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+        let image = image::load_from_memory(response.body()).unwrap();
+        // キーカラー（左上ピクセル）と同色なので全面透明になり、余白除去されずに縮小される
+        assert_eq!((image.width(), image.height()), (128, 256));
+
+        let response = respond_thumbnail(
+            &db_path,
+            &cache_dir,
+            &get("ghostthumb://localhost/balloon%2Fc%3A%2Fssp%1Fnone", None),
+        );
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn 不正なリクエストと未知のゴーストはエラーを返す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_protocol_errors");
+        let (db_path, cache_dir) = setup(&tmp, true);
+
+        for (uri, status) in [
+            ("ghostthumb://localhost/", StatusCode::BAD_REQUEST),
+            ("ghostthumb://localhost/%ZZ", StatusCode::BAD_REQUEST),
+            (
+                "ghostthumb://localhost/ssp%1Falice?size=huge",
+                StatusCode::BAD_REQUEST,
+            ),
+            ("ghostthumb://localhost/ssp%1Fbob", StatusCode::NOT_FOUND),
+            ("ghostthumb://localhost/balloon%2F", StatusCode::BAD_REQUEST),
+        ] {
+            let response = respond_thumbnail(&db_path, &cache_dir, &get(uri, None));
+            assert_eq!(response.status(), status, "{uri}");
+        }
+    }
+
+    #[test]
+    fn percent_decode_は_utf8_のマルチバイトを復元する() {
+        assert_eq!(
+            percent_decode("c%3A%2Fghosts%1F%E3%81%95%E3%81%8F%E3%82%89").as_deref(),
+            Some("c:/ghosts\x1fさくら")
+        );
+        assert_eq!(percent_decode("%E3%81").as_deref(), None);
+        assert_eq!(percent_decode("%4").as_deref(), None);
+    }
+}
@@ -0,0 +1,481 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rayon::prelude::*;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+
+use ghost_meta::{AlphaMode, ThumbnailInfo, ThumbnailKind};
+
+use super::types::Ghost;
+
+/// 生成ロジックを変えたら上げる（キーが変わり、古いキャッシュは次回の掃除で消える）
+const THUMBNAIL_CACHE_VERSION: &str = "thumbnail-cache-version|1";
+
+/// これより新しいファイルは参照されていなくても掃除しない（書き込み直後で行がまだない場合に備える）
+const EVICTION_GRACE: Duration = Duration::from_secs(10 * 60);
+
+/// キャッシュする画像サイズ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ThumbnailSize {
+    /// 一覧のカード用
+    Card,
+    /// ホバー時のプレビュー用
+    Preview,
+}
+
+impl ThumbnailSize {
+    pub(crate) const ALL: [ThumbnailSize; 2] = [ThumbnailSize::Card, ThumbnailSize::Preview];
+
+    /// 長辺の最大ピクセル数
+    pub(crate) fn max_edge(self) -> u32 {
+        match self {
+            ThumbnailSize::Card => 256,
+            ThumbnailSize::Preview => 512,
+        }
+    }
+
+    /// キャッシュファイル名・URL で使う名前
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            ThumbnailSize::Card => "card",
+            ThumbnailSize::Preview => "preview",
+        }
+    }
+
+    /// `as_str` の逆変換
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|size| size.as_str() == name)
+    }
+}
+
+/// サムネイルキャッシュのディレクトリ（`{app_cache_dir}/thumbnails`）
+pub(crate) fn thumbnail_cache_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    use tauri::Manager;
+
+    app.path()
+        .app_cache_dir()
+        .map(|dir| dir.join("thumbnails"))
+        .map_err(|e| format!("app_cache_dir 取得エラー: {e}"))
+}
+
+/// キャッシュファイルのパス（`{key}_{size}.png`）
+fn cache_file_path(cache_dir: &Path, key: &str, size: ThumbnailSize) -> PathBuf {
+    cache_dir.join(format!("{key}_{}.png", size.as_str()))
+}
+
+/// `{key}_{size}.png`（key は SHA-256 の 16 進 64 文字）の形のファイル名か。
+/// 掃除はこの形のファイルだけを対象にし、一時ファイルなどには触れない
+fn is_cache_file_name(name: &str) -> bool {
+    ThumbnailSize::ALL.into_iter().any(|size| {
+        name.strip_suffix(&format!("_{}.png", size.as_str()))
+            .is_some_and(|key| {
+                key.len() == 64 && key.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+            })
+    })
+}
+
+/// Ghost のサムネイル列から ghost-meta の ThumbnailInfo を組み立てる。サムネイルなしは None
+pub(crate) fn thumbnail_info_from_ghost(ghost: &Ghost) -> Option<ThumbnailInfo> {
+    thumbnail_info(
+        &ghost.thumbnail_path,
+        ghost.thumbnail_use_self_alpha,
+        &ghost.thumbnail_mask_path,
+        &ghost.thumbnail_kind,
+    )
+}
+
+/// ThumbnailKind を thumbnail_kind 列の文字列にする（`thumbnail_info` の逆変換）
+pub(crate) fn thumbnail_kind_name(kind: &ThumbnailKind) -> &'static str {
+    match kind {
+        ThumbnailKind::Surface => "surface",
+        ThumbnailKind::Thumbnail => "thumbnail",
+        ThumbnailKind::Balloon => "balloon",
+    }
+}
+
+/// ghosts テーブルのサムネイル列（thumbnail_path / thumbnail_use_self_alpha /
+/// thumbnail_mask_path / thumbnail_kind）から ThumbnailInfo を組み立てる。サムネイルなしは None
+pub(crate) fn thumbnail_info(
+    thumbnail_path: &str,
+    use_self_alpha: bool,
+    mask_path: &str,
+    kind: &str,
+) -> Option<ThumbnailInfo> {
+    if thumbnail_path.is_empty() {
+        return None;
+    }
+    let alpha = if use_self_alpha {
+        AlphaMode::SelfAlpha
+    } else if !mask_path.is_empty() {
+        AlphaMode::PnaMask(PathBuf::from(mask_path))
+    } else {
+        AlphaMode::KeyColor
+    };
+    let kind = match kind {
+        "thumbnail" => ThumbnailKind::Thumbnail,
+        "balloon" => ThumbnailKind::Balloon,
+        _ => ThumbnailKind::Surface,
+    };
+    Some(ThumbnailInfo {
+        path: PathBuf::from(thumbnail_path),
+        alpha,
+        kind,
+    })
+}
+
+/// 元画像・マスクの内容と透過方式から SHA-256 のキャッシュキーを計算する。
+/// パスではなく内容をキーにするため、同じ画像を持つゴースト同士でキャッシュを共有する。
+/// サーフェスは surface0 を element で合成することがあるので、surfaces*.txt と element の画像の内容も含める。
+fn content_key(info: &ThumbnailInfo) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(THUMBNAIL_CACHE_VERSION.as_bytes());
+    hasher.update([0x1f]);
+    hash_file(&mut hasher, &info.path)?;
+    hasher.update([0x1f]);
+    match &info.alpha {
+        AlphaMode::SelfAlpha => hasher.update(b"self-alpha"),
+        AlphaMode::KeyColor => hasher.update(b"key-color"),
+        AlphaMode::PnaMask(mask) => {
+            hasher.update(b"pna-mask");
+            hasher.update([0x1f]);
+            hash_file(&mut hasher, mask)?;
+        }
+    }
+    if info.kind == ThumbnailKind::Surface
+        && let Some(shell_dir) = info.path.parent()
+    {
+        for path in ghost_meta::surface_source_files(shell_dir, 0) {
+            hasher.update([0x1f]);
+            hash_file(&mut hasher, &path)?;
+        }
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// ファイルの内容をハッシュに流し込む（キーの計算のために画像全体をメモリに読み込まない）
+fn hash_file(hasher: &mut Sha256, path: &Path) -> io::Result<()> {
+    io::copy(&mut fs::File::open(path)?, hasher)?;
+    Ok(())
+}
+
+/// 全サイズのキャッシュファイルがなければ生成する。生成できなければ false
+fn ensure_cached(cache_dir: &Path, key: &str, info: &ThumbnailInfo) -> bool {
+    ThumbnailSize::ALL.into_iter().all(|size| {
+        let path = cache_file_path(cache_dir, key, size);
+        if path.is_file() {
+            return true;
+        }
+        ghost_meta::render_thumbnail(info, size.max_edge())
+            .and_then(|image| ghost_meta::encode_png(&image))
+            .is_some_and(|png| write_cache_file(&path, &png))
+    })
+}
+
+/// 書きかけのファイルを読まれないよう一時ファイル経由でキャッシュファイルを置き換える
+fn write_cache_file(path: &Path, png: &[u8]) -> bool {
+    let tmp = path.with_extension(format!("png.{}.tmp", std::process::id()));
+    fs::write(&tmp, png)
+        .and_then(|()| fs::rename(&tmp, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+        .is_ok()
+}
+
+/// `ghostthumb` スキームがその場で生成した PNG を、行が参照しているキャッシュファイルとして書き戻す。
+/// 元画像の内容から計算したキーが参照先のファイル名と一致する場合だけ書く（元画像が変わっていれば次のスキャンに任せる）
+pub(crate) fn write_back_rendered(
+    cache_dir: &Path,
+    cached: &Path,
+    info: &ThumbnailInfo,
+    size: ThumbnailSize,
+    png: &[u8],
+) -> bool {
+    let Ok(key) = content_key(info) else {
+        return false;
+    };
+    cache_file_path(cache_dir, &key, size) == cached
+        && fs::create_dir_all(cache_dir).is_ok()
+        && write_cache_file(cached, png)
+}
+
+/// スキャン結果のサムネイルをキャッシュに生成し、Ghost のキャッシュパス列を埋める。
+///
+/// - 既にキャッシュがあるキーは生成しない（キー計算のためにファイルをハッシュへ流し込むだけで、画像全体をメモリに読み込まない）
+/// - 生成に失敗したゴーストのキャッシュパスは空文字列のまま（`ghostthumb` スキームがその場で生成する）
+/// - キャッシュパスは diff_fingerprint に含めず、ここで diff_fingerprint に畳み込む。
+///   元画像のパスが同じでも内容が変われば行が UPDATE される
+pub(crate) fn populate_thumbnail_cache(cache_dir: &Path, ghosts: &mut [Ghost]) -> Result<(), String> {
+    fs::create_dir_all(cache_dir)
+        .map_err(|e| format!("サムネイルキャッシュの作成エラー ({}): {e}", cache_dir.display()))?;
+
+    let keyed: Vec<Option<(String, ThumbnailInfo)>> = ghosts
+        .par_iter()
+        .map(|ghost| {
+            let info = thumbnail_info_from_ghost(ghost)?;
+            let key = content_key(&info).ok()?;
+            Some((key, info))
+        })
+        .collect();
+
+    // 同じ内容の画像は 1 回だけ生成する
+    let unique: HashMap<&str, &ThumbnailInfo> = keyed
+        .iter()
+        .flatten()
+        .map(|(key, info)| (key.as_str(), info))
+        .collect();
+    let cached: HashSet<&str> = unique
+        .into_par_iter()
+        .filter(|(key, info)| ensure_cached(cache_dir, key, info))
+        .map(|(key, _)| key)
+        .collect();
+
+    for (ghost, entry) in ghosts.iter_mut().zip(&keyed) {
+        let Some((key, _)) = entry.as_ref().filter(|(key, _)| cached.contains(key.as_str()))
+        else {
+            ghost.thumbnail_card_path = String::new();
+            ghost.thumbnail_preview_path = String::new();
+            continue;
+        };
+        ghost.thumbnail_card_path = cache_file_path(cache_dir, key, ThumbnailSize::Card)
+            .to_string_lossy()
+            .into_owned();
+        ghost.thumbnail_preview_path = cache_file_path(cache_dir, key, ThumbnailSize::Preview)
+            .to_string_lossy()
+            .into_owned();
+        let mut hasher = Sha256::new();
+        hasher.update(ghost.diff_fingerprint.as_bytes());
+        hasher.update([0x1f]);
+        hasher.update(key.as_bytes());
+        ghost.diff_fingerprint = format!("{:x}", hasher.finalize());
+    }
+    Ok(())
+}
+
+/// ghosts テーブルのどの行からも参照されていないキャッシュファイルを削除し、削除件数を返す。
+/// ゴーストの削除・古い request_key の掃除で参照が外れたファイルはここで消える。
+///
+/// - 対象は `{key}_{size}.png` の形のファイルだけ（一時ファイルなどは消さない）
+/// - 更新から `EVICTION_GRACE` 経っていないファイルは、行の書き込み前のものかもしれないので残す
+/// - 呼び出し側は `GhostWriteLock` を取って、キャッシュを書く処理（スキャン・監視・インストール）と直列にする
+pub(crate) fn evict_unreferenced_thumbnails(
+    conn: &Connection,
+    cache_dir: &Path,
+) -> Result<usize, String> {
+    let referenced: HashSet<PathBuf> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT thumbnail_card_path FROM ghosts WHERE thumbnail_card_path != '' \
+                 UNION SELECT thumbnail_preview_path FROM ghosts WHERE thumbnail_preview_path != ''",
+            )
+            .map_err(|e| format!("サムネイル参照 SELECT 準備エラー: {e}"))?;
+        stmt.query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| format!("サムネイル参照 SELECT エラー: {e}"))?
+            .filter_map(|r| r.ok())
+            .map(PathBuf::from)
+            .collect()
+    };
+
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return Ok(0);
+    };
+    let mut evicted = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age >= EVICTION_GRACE));
+        if expired
+            && is_cache_file_name(&entry.file_name().to_string_lossy())
+            && !referenced.contains(&path)
+            && fs::remove_file(&path).is_ok()
+        {
+            evicted += 1;
+        }
+    }
+    Ok(evicted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ghost::store::{
+        store_ghosts, tests::make_ghost, tests::setup_db, EntryTokens,
+    };
+    use crate::testutil::TempDirGuard;
+    use image::{Rgba, RgbaImage};
+
+    fn write_surface(path: &Path, width: u32, height: u32) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut image = RgbaImage::from_pixel(width, height, Rgba([0, 255, 0, 255]));
+        image.put_pixel(width / 2, height / 2, Rgba([255, 0, 0, 255]));
+        image.save(path).unwrap();
+    }
+
+    fn ghost_with_surface(name: &str, surface: &Path) -> Ghost {
+        let mut ghost = make_ghost(name, &name.to_lowercase(), "ssp");
+        ghost.thumbnail_path = surface.to_string_lossy().into_owned();
+        ghost.thumbnail_kind = "surface".to_string();
+        ghost
+    }
+
+    #[test]
+    fn populate_thumbnail_cache_が内容ごとに縮小済み画像を生成する() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_cache_populate");
+        let cache_dir = tmp.path().join("cache");
+        let alice = tmp.path().join("alice").join("surface0.png");
+        let bob = tmp.path().join("bob").join("surface0.png");
+        write_surface(&alice, 1000, 800);
+        fs::create_dir_all(bob.parent().unwrap()).unwrap();
+        fs::copy(&alice, &bob).unwrap();
+
+        let mut ghosts = vec![
+            ghost_with_surface("Alice", &alice),
+            ghost_with_surface("Bob", &bob),
+            make_ghost("Carol", "carol", "ssp"),
+        ];
+        let original_fingerprint = ghosts[0].diff_fingerprint.clone();
+        populate_thumbnail_cache(&cache_dir, &mut ghosts).unwrap();
+
+        // 同じ内容の画像はキャッシュを共有する
+        assert_eq!(ghosts[0].thumbnail_card_path, ghosts[1].thumbnail_card_path);
+        assert_ne!(ghosts[0].diff_fingerprint, original_fingerprint);
+        assert!(ghosts[2].thumbnail_card_path.is_empty());
+        assert_eq!(fs::read_dir(&cache_dir).unwrap().count(), 2);
+
+        // キーカラー透過で緑の背景が落ち、赤の 1 ピクセルだけが残る
+        let card = image::open(&ghosts[0].thumbnail_card_path).unwrap();
+        assert_eq!((card.width(), card.height()), (1, 1));
+
+        // 元画像の内容が変わるとキーと diff_fingerprint が変わる
+        write_surface(&alice, 400, 400);
+        let before = ghosts[0].clone();
+        let mut rescanned = vec![ghost_with_surface("Alice", &alice)];
+        rescanned[0].diff_fingerprint = original_fingerprint;
+        populate_thumbnail_cache(&cache_dir, &mut rescanned).unwrap();
+        assert_ne!(rescanned[0].thumbnail_card_path, before.thumbnail_card_path);
+        assert_ne!(rescanned[0].diff_fingerprint, before.diff_fingerprint);
+    }
+
+    #[test]
+    fn surface0_を合成するシェルは_surfaces_txt_と_element_の内容もキーに含める() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_cache_composed");
+        let cache_dir = tmp.path().join("cache");
+        let shell_dir = tmp.path().join("alice");
+        write_surface(&shell_dir.join("surface0.png"), 4, 4);
+        write_surface(&shell_dir.join("body.png"), 6, 2);
+        fs::write(
+            shell_dir.join("surfaces.txt"),
+            "surface0\n{\nelement0,base,body.png,0,0\n}\n",
+        )
+        .unwrap();
+
+        let mut ghosts = vec![ghost_with_surface("Alice", &shell_dir.join("surface0.png"))];
+        populate_thumbnail_cache(&cache_dir, &mut ghosts).unwrap();
+        let first = ghosts[0].thumbnail_card_path.clone();
+        // element の画像（中央の赤 1 ピクセルだけが残る）を合成した結果をキャッシュする
+        let card = image::open(&first).unwrap();
+        assert_eq!((card.width(), card.height()), (1, 1));
+
+        // element の画像が変わればキーが変わる
+        write_surface(&shell_dir.join("body.png"), 6, 4);
+        populate_thumbnail_cache(&cache_dir, &mut ghosts).unwrap();
+        let second = ghosts[0].thumbnail_card_path.clone();
+        assert_ne!(second, first);
+
+        // surfaces.txt が変わればキーが変わる
+        fs::write(
+            shell_dir.join("surfaces.txt"),
+            "surface0\n{\nelement0,base,body.png,2,0\n}\n",
+        )
+        .unwrap();
+        populate_thumbnail_cache(&cache_dir, &mut ghosts).unwrap();
+        assert_ne!(ghosts[0].thumbnail_card_path, second);
+    }
+
+    #[test]
+    fn evict_unreferenced_thumbnails_が削除されたゴーストのキャッシュを消す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_cache_evict");
+        let cache_dir = tmp.path().join("cache");
+        let alice = tmp.path().join("alice").join("surface0.png");
+        let bob = tmp.path().join("bob").join("surface0.png");
+        write_surface(&alice, 4, 4);
+        write_surface(&bob, 8, 8);
+
+        let conn = setup_db();
+        let mut ghosts = vec![
+            ghost_with_surface("Alice", &alice),
+            ghost_with_surface("Bob", &bob),
+        ];
+        populate_thumbnail_cache(&cache_dir, &mut ghosts).unwrap();
+        store_ghosts(&conn, "rk1", &ghosts, &EntryTokens::default(), "fp1", "").unwrap();
+        assert_eq!(evict_unreferenced_thumbnails(&conn, &cache_dir).unwrap(), 0);
+
+        store_ghosts(
+            &conn,
+            "rk1",
+            &ghosts[..1],
+            &EntryTokens::default(),
+            "fp2",
+            "",
+        )
+        .unwrap();
+        // 書き込み直後のファイルは参照が外れていても猶予期間のあいだ残す
+        assert_eq!(evict_unreferenced_thumbnails(&conn, &cache_dir).unwrap(), 0);
+
+        // 一時ファイル・キャッシュ以外の名前のファイルは古くても消さない
+        let tmp_file = Path::new(&ghosts[1].thumbnail_card_path).with_extension("png.123.tmp");
+        let other = cache_dir.join("notes.png");
+        fs::write(&tmp_file, b"partial").unwrap();
+        fs::write(&other, b"other").unwrap();
+        let old = std::time::SystemTime::now() - EVICTION_GRACE * 2;
+        for entry in fs::read_dir(&cache_dir).unwrap() {
+            fs::File::options()
+                .write(true)
+                .open(entry.unwrap().path())
+                .and_then(|file| file.set_modified(old))
+                .unwrap();
+        }
+
+        assert_eq!(evict_unreferenced_thumbnails(&conn, &cache_dir).unwrap(), 2);
+        assert!(Path::new(&ghosts[0].thumbnail_card_path).is_file());
+        assert!(!Path::new(&ghosts[1].thumbnail_card_path).exists());
+        assert!(tmp_file.is_file());
+        assert!(other.is_file());
+    }
+
+    #[test]
+    fn write_back_rendered_はキーが一致するときだけ書き戻す() {
+        let tmp = TempDirGuard::new("ghost_launcher_thumbnail_cache_write_back");
+        let cache_dir = tmp.path().join("cache");
+        let alice = tmp.path().join("alice").join("surface0.png");
+        write_surface(&alice, 4, 4);
+
+        let mut ghosts = vec![ghost_with_surface("Alice", &alice)];
+        populate_thumbnail_cache(&cache_dir, &mut ghosts).unwrap();
+        let card = PathBuf::from(&ghosts[0].thumbnail_card_path);
+        fs::remove_file(&card).unwrap();
+
+        let info = thumbnail_info_from_ghost(&ghosts[0]).unwrap();
+        let write_back =
+            |path: &Path, size| write_back_rendered(&cache_dir, path, &info, size, b"png");
+        assert!(write_back(&card, ThumbnailSize::Card));
+        assert_eq!(fs::read(&card).unwrap(), b"png");
+        // サイズ違い・キャッシュ外のパスには書かない
+        assert!(!write_back(&card, ThumbnailSize::Preview));
+        let outside = tmp.path().join("outside.png");
+        assert!(!write_back(&outside, ThumbnailSize::Card));
+        assert!(!outside.exists());
+
+        // 元画像が変わった後はキーが変わるので、古いパスには書き戻さない
+        fs::remove_file(&card).unwrap();
+        write_surface(&alice, 8, 8);
+        assert!(!write_back(&card, ThumbnailSize::Card));
+        assert!(!card.exists());
+    }
+}
@@ -16,6 +16,30 @@ pub struct Ghost {
     pub craftman: String,
     /// descript.txt の craftmanw フィールド（作者名2）。未設定の場合は空文字列
     pub craftmanw: String,
+    /// descript.txt の id フィールド。未設定の場合は空文字列
+    pub descript_id: String,
+    /// descript.txt の type フィールド。未設定の場合は空文字列
+    pub ghost_type: String,
+    /// descript.txt の shiori フィールド（SHIORI DLL 名）。未設定の場合は空文字列
+    pub shiori: String,
+    /// shiori フィールドと ghost/master のファイルから判定した SHIORI（"yaya" / "aya" / "satori" / "kawari" / "misaka" / "kagari" / "unknown"）
+    pub shiori_engine: String,
+    /// descript.txt の homeurl フィールド（ネットワーク更新 URL）。未設定の場合は空文字列
+    pub homeurl: String,
+    /// descript.txt の craftmanurl フィールド（作者 URL）。未設定の場合は空文字列
+    pub craftmanurl: String,
+    /// descript.txt の readme フィールド。未設定の場合は空文字列
+    pub readme: String,
+    /// descript.txt の icon フィールド。未設定の場合は空文字列
+    pub icon: String,
+    /// descript.txt の balloon フィールド（デフォルトバルーン）。未設定の場合は空文字列
+    pub balloon: String,
+    /// descript.txt の sakura.seriko.defaultsurface フィールド。未設定の場合は None
+    pub sakura_default_surface: Option<u32>,
+    /// descript.txt の kero.seriko.defaultsurface フィールド。未設定の場合は None
+    pub kero_default_surface: Option<u32>,
+    /// descript.txt の install.accept フィールド。未設定の場合は空文字列
+    pub install_accept: String,
     /// ゴーストのディレクトリ名（SSP起動時に使用）
     pub directory_name: String,
     /// ゴーストのフルパス
@@ -26,8 +50,50 @@ pub struct Ghost {
     pub thumbnail_path: String,
     /// サムネイルの透過方式。true = PNG アルファチャンネル、false = 左上ピクセルをキーカラーとして透過
     pub thumbnail_use_self_alpha: bool,
+    /// サムネイルのアルファマスク（同名の .pna）のフルパス。マスクを使わない場合は空文字列
+    pub thumbnail_mask_path: String,
+    /// 透過・余白除去・縮小済みのカード用サムネイル（キャッシュ）のフルパス。未生成の場合は空文字列
+    pub thumbnail_card_path: String,
+    /// 透過・余白除去・縮小済みのプレビュー用サムネイル（キャッシュ）のフルパス。未生成の場合は空文字列
+    pub thumbnail_preview_path: String,
     /// サムネイルの種別。"surface" / "thumbnail" / ""（サムネイルなし）
     pub thumbnail_kind: String,
+    /// `shell/` 配下の全シェル（ディレクトリ名順）
+    pub shells: Vec<GhostShell>,
+}
+
+/// ゴーストが持つシェル 1 件分（ghost_shells テーブルの 1 行）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GhostShell {
+    /// シェルの descript.txt の name フィールド。未設定の場合はディレクトリ名
+    pub name: String,
+    /// シェルの descript.txt の craftman フィールド。未設定の場合は空文字列
+    pub craftman: String,
+    /// シェルのディレクトリ名（"master" など）
+    pub directory_name: String,
+    /// シェルディレクトリのフルパス
+    pub path: String,
+    /// シェルの surface0* 画像のフルパス。存在しない場合は空文字列
+    pub thumbnail_path: String,
+    /// サムネイルの透過方式（Ghost::thumbnail_use_self_alpha と同じ意味）
+    pub thumbnail_use_self_alpha: bool,
+    /// サムネイルのアルファマスク（Ghost::thumbnail_mask_path と同じ意味）
+    pub thumbnail_mask_path: String,
+}
+
+/// スキャンで一覧に載らなかった（または壊れていた）フォルダ 1 件（ghost_scan_skips テーブルの 1 行）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct GhostScanSkip {
+    /// 走査した場所（"ssp" or 追加フォルダのパス。Ghost::source と同じ）
+    pub source: String,
+    /// 対象フォルダのフルパス
+    pub path: String,
+    /// "no_descript" / "unreadable" / "non_utf8_name" / "decode_failure" / "permission_denied"
+    pub reason: String,
+    /// 元のエラーメッセージなどの補足（表示用）
+    pub detail: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +104,49 @@ pub struct ScanStoreResult {
     pub total: usize,
     pub fingerprint: String,
     pub request_key: String,
+    /// cancel_ghost_scan で打ち切られた。このとき DB は書き換えていない
+    pub cancelled: bool,
+}
+
+/// スキャン中に送る進捗イベント（"ghost-scan-progress"）のペイロード
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct ScanProgressEvent {
+    /// scan_and_store に渡したスキャン ID
+    pub scan_id: String,
+    /// 走査を終えた起点（SSP の ghost/ と追加フォルダ）の数
+    pub parents_done: usize,
+    /// 走査する起点の数
+    pub parents_total: usize,
+    /// 調べたフォルダの数（入れ子のサブフォルダを含む）
+    pub entries_processed: usize,
+    /// 見つかったゴーストの数（読み直さなかったものを含む）
+    pub ghosts_found: usize,
+}
+
+/// search_ghosts の結果（1 ページ分の ghost_identity_key と一致した総数）。行の中身はフロントエンドが SQL で引く
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct GhostSearchPage {
+    /// 並び順どおりの ghost_identity_key（limit / offset の範囲）
+    pub ghost_identity_keys: Vec<String>,
+    /// 一致したゴーストの総数
+    pub total: usize,
+}
+
+/// フォルダ監視が ghosts テーブルを書き換えたときに送るイベント（"ghosts-changed"）のペイロード
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct GhostsChangedEvent {
+    /// 書き換えた request_key（start_ghost_watcher に渡したもの）
+    pub request_key: String,
+    /// 追加・更新したゴーストの数
+    pub updated: usize,
+    /// 削除したゴーストの数
+    pub removed: usize,
 }
 
 #[cfg(test)]
@@ -55,12 +164,28 @@ mod tests {
             kero_name: String::new(),
             craftman: String::new(),
             craftmanw: String::new(),
+            descript_id: String::new(),
+            ghost_type: String::new(),
+            shiori: String::new(),
+            shiori_engine: String::new(),
+            homeurl: String::new(),
+            craftmanurl: String::new(),
+            readme: String::new(),
+            icon: String::new(),
+            balloon: String::new(),
+            sakura_default_surface: None,
+            kero_default_surface: None,
+            install_accept: String::new(),
             directory_name: String::new(),
             path: String::new(),
             source: String::new(),
             thumbnail_path: String::new(),
             thumbnail_use_self_alpha: false,
+            thumbnail_mask_path: String::new(),
+            thumbnail_card_path: String::new(),
+            thumbnail_preview_path: String::new(),
             thumbnail_kind: String::new(),
+            shells: Vec::new(),
         };
         let json: serde_json::Value = serde_json::to_value(&ghost).unwrap();
         let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(|k| k.as_str()).collect();
@@ -68,17 +193,33 @@ mod tests {
         assert_eq!(
             keys,
             vec![
+                "balloon",
                 "craftman",
+                "craftmanurl",
                 "craftmanw",
+                "descript_id",
                 "diff_fingerprint",
                 "directory_name",
+                "ghost_type",
+                "homeurl",
+                "icon",
+                "install_accept",
+                "kero_default_surface",
                 "kero_name",
                 "name",
                 "path",
+                "readme",
+                "sakura_default_surface",
                 "sakura_name",
+                "shells",
+                "shiori",
+                "shiori_engine",
                 "source",
+                "thumbnail_card_path",
                 "thumbnail_kind",
+                "thumbnail_mask_path",
                 "thumbnail_path",
+                "thumbnail_preview_path",
                 "thumbnail_use_self_alpha",
             ]
         );
@@ -0,0 +1,523 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use rusqlite::Connection;
+
+use super::ensure_request_key;
+use super::scan::{
+    ADDITIONAL_FOLDER_SCAN_DEPTH, SSP_GHOST_SCAN_DEPTH, read_ghost_entry,
+    unique_sorted_additional_folders,
+};
+use super::store::{self, RemovedGhostRoot};
+use super::thumbnail_cache;
+use super::types::{Ghost, GhostsChangedEvent};
+
+/// ghosts テーブルを書き換えたときにフロントエンドへ送るイベント名
+pub const GHOSTS_CHANGED_EVENT: &str = "ghosts-changed";
+
+/// 変更がこの時間途切れたら、溜まった分をまとめて反映する
+const DEBOUNCE_QUIET: Duration = Duration::from_millis(500);
+
+/// 変更が途切れなくても（大きなフォルダのコピー中など）、最初の変更からこの時間で一度反映する
+const DEBOUNCE_MAX: Duration = Duration::from_secs(3);
+
+/// 監視の起点（SSP の ghost/ または追加フォルダ）。source と max_depth は scan_and_store の走査と同じ
+#[derive(Debug, Clone)]
+struct WatchRoot {
+    path: PathBuf,
+    source: String,
+    max_depth: usize,
+}
+
+/// 変更のあったパスから割り出した、読み直すゴーストと消えたフォルダ
+#[derive(Debug, Default, PartialEq)]
+struct AffectedRoots {
+    /// (WatchRoot の添字, ゴーストのフォルダ)
+    reread: BTreeSet<(usize, PathBuf)>,
+    removed: Vec<RemovedGhostRoot>,
+}
+
+impl AffectedRoots {
+    fn is_empty(&self) -> bool {
+        self.reread.is_empty() && self.removed.is_empty()
+    }
+
+    fn push_removed(&mut self, path: &Path, descendants: bool) {
+        let removed = RemovedGhostRoot {
+            path: path.to_string_lossy().into_owned(),
+            descendants,
+        };
+        if !self.removed.contains(&removed) {
+            self.removed.push(removed);
+        }
+    }
+}
+
+/// start_ghost_watcher / stop_ghost_watcher が共有する監視の状態（lib.rs で manage する）。
+/// 監視を drop すると notify の送信側が閉じ、ワーカースレッドも終わる
+#[derive(Default)]
+pub struct GhostWatcherState(Mutex<Option<RecommendedWatcher>>);
+
+/// ワーカースレッドが持つ反映先
+struct WatcherContext {
+    app: tauri::AppHandle,
+    db_path: PathBuf,
+    thumbnail_dir: PathBuf,
+    request_key: String,
+    roots: Vec<WatchRoot>,
+}
+
+/// SSP の ghost/ と追加フォルダの監視を始めるコマンド（起動中の監視は置き換える）。
+/// 変更のあったゴーストだけを読み直して ghosts テーブルに差分反映し、GHOSTS_CHANGED_EVENT を送る。
+/// scan_and_store と同じく、見つからない追加フォルダは飛ばす
+#[tauri::command]
+pub fn start_ghost_watcher(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, GhostWatcherState>,
+    ssp_path: String,
+    additional_folders: Vec<String>,
+    request_key: String,
+) -> Result<(), String> {
+    use tauri::Manager;
+
+    ensure_request_key(&request_key)?;
+    let db_path = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("app_config_dir 取得エラー: {e}"))?
+        .join("ghosts.db");
+    let thumbnail_dir = thumbnail_cache::thumbnail_cache_dir(&app)?;
+    let roots = watch_roots(&ssp_path, &additional_folders);
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| format!("フォルダ監視の初期化エラー: {e}"))?;
+    for (index, root) in roots.iter().enumerate() {
+        match watcher.watch(&root.path, RecursiveMode::Recursive) {
+            Ok(()) => {}
+            Err(e) if index == 0 => {
+                return Err(format!(
+                    "ghost フォルダを監視できません ({}): {e}",
+                    root.path.display()
+                ));
+            }
+            Err(_) => {}
+        }
+    }
+
+    let context = WatcherContext {
+        app,
+        db_path,
+        thumbnail_dir,
+        request_key,
+        roots,
+    };
+    std::thread::spawn(move || run_watcher(&receiver, &context));
+
+    *state
+        .0
+        .lock()
+        .map_err(|_| "フォルダ監視の状態を取得できません".to_string())? = Some(watcher);
+    Ok(())
+}
+
+/// フォルダの監視を止めるコマンド。監視していなければ何もしない
+#[tauri::command]
+pub fn stop_ghost_watcher(state: tauri::State<'_, GhostWatcherState>) -> Result<(), String> {
+    state
+        .0
+        .lock()
+        .map_err(|_| "フォルダ監視の状態を取得できません".to_string())?
+        .take();
+    Ok(())
+}
+
+/// 監視の起点を scan_ghosts_with_fingerprint_internal と同じ順で並べる（先頭は SSP の ghost/）
+fn watch_roots(ssp_path: &str, additional_folders: &[String]) -> Vec<WatchRoot> {
+    let mut roots = vec![WatchRoot {
+        path: Path::new(ssp_path).join("ghost"),
+        source: "ssp".to_string(),
+        max_depth: SSP_GHOST_SCAN_DEPTH,
+    }];
+    roots.extend(
+        unique_sorted_additional_folders(additional_folders)
+            .into_iter()
+            .map(|(source, path, _)| WatchRoot {
+                path,
+                source,
+                max_depth: ADDITIONAL_FOLDER_SCAN_DEPTH,
+            }),
+    );
+    roots
+}
+
+/// ワーカースレッドの本体。送信側が閉じる（監視が drop される）まで、変更をまとめて反映し続ける
+fn run_watcher(receiver: &Receiver<notify::Result<notify::Event>>, context: &WatcherContext) {
+    use tauri::{Emitter, Manager};
+
+    while let Some(changed) = next_batch(receiver, DEBOUNCE_QUIET, DEBOUNCE_MAX) {
+        let affected = resolve_affected_roots(&context.roots, &changed);
+        if affected.is_empty() {
+            continue;
+        }
+        // 読み直しから書き込みまでスキャンと排他にする。実行中のスキャンが走査で読んだ古い内容を、
+        // この反映の後に書き戻さないようにする（スキャンの後に読み直すので、新しい内容が残る）
+        let applied = {
+            let write_lock = context.app.state::<store::GhostWriteLock>();
+            let _writes = write_lock.lock();
+            apply_affected_roots(context, &affected)
+        };
+        // 反映に失敗しても監視は続ける（次回の scan_and_store が差分書き込みで整合する）
+        let Ok((updated, removed)) = applied else {
+            continue;
+        };
+        if updated + removed > 0 {
+            let _ = context.app.emit(
+                GHOSTS_CHANGED_EVENT,
+                GhostsChangedEvent {
+                    request_key: context.request_key.clone(),
+                    updated,
+                    removed,
+                },
+            );
+        }
+    }
+}
+
+/// 変更を 1 件待ち、続く変更が quiet のあいだ途切れるか、最初の変更から max が過ぎるまで溜める。
+/// 送信側が閉じていれば None（溜まっていた分は先に返す）
+fn next_batch(
+    receiver: &Receiver<notify::Result<notify::Event>>,
+    quiet: Duration,
+    max: Duration,
+) -> Option<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    push_event_paths(&mut paths, receiver.recv().ok()?);
+    let deadline = Instant::now() + max;
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        match receiver.recv_timeout(quiet.min(deadline - now)) {
+            Ok(event) => push_event_paths(&mut paths, event),
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Some(paths)
+}
+
+/// 内容が変わりうるイベントのパスだけを積む（読み取りアクセスと監視のエラーは無視する）
+fn push_event_paths(paths: &mut Vec<PathBuf>, event: notify::Result<notify::Event>) {
+    if let Ok(event) = event
+        && !matches!(event.kind, EventKind::Access(_))
+    {
+        paths.extend(event.paths);
+    }
+}
+
+/// 変更のあったパスごとに、属するゴーストのフォルダを起点から max_depth までたどって探す。
+/// - descript.txt のあるフォルダに当たれば、そのゴーストを読み直す（ゴーストの中には入らない）
+/// - 存在しないフォルダに当たれば、そのフォルダと配下のゴーストを削除する
+/// - ゴーストではないフォルダは、以前ゴーストだった場合に備えて完全一致で削除対象にし、さらにたどる。
+///   変更されたのがそのフォルダ自体（作成・移動）なら、中のゴーストのイベントを取りこぼしうるので配下を探す
+fn resolve_affected_roots(roots: &[WatchRoot], changed: &[PathBuf]) -> AffectedRoots {
+    let mut affected = AffectedRoots::default();
+    for path in changed {
+        // 追加フォルダが入れ子になっていても、いちばん深い起点に属させる
+        let Some((root_index, root)) = roots
+            .iter()
+            .enumerate()
+            .filter(|(_, root)| path.starts_with(&root.path))
+            .max_by_key(|(_, root)| root.path.components().count())
+        else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(&root.path) else {
+            continue;
+        };
+        let names: Vec<_> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+
+        let mut candidate = root.path.clone();
+        for (depth, name) in names.iter().enumerate().take(root.max_depth) {
+            candidate.push(name);
+            if is_ghost_root(&candidate) {
+                affected.reread.insert((root_index, candidate.clone()));
+                break;
+            }
+            if !candidate.exists() {
+                affected.push_removed(&candidate, true);
+                break;
+            }
+            affected.push_removed(&candidate, false);
+            if depth + 1 == names.len() {
+                let mut found = Vec::new();
+                find_ghost_roots(&candidate, root.max_depth - depth - 1, &mut found);
+                affected
+                    .reread
+                    .extend(found.into_iter().map(|ghost_root| (root_index, ghost_root)));
+            }
+        }
+    }
+    affected
+}
+
+/// ghost/master/descript.txt があるフォルダをゴーストのフォルダとみなす（scan の判定と同じ）
+fn is_ghost_root(dir: &Path) -> bool {
+    dir.join("ghost")
+        .join("master")
+        .join("descript.txt")
+        .is_file()
+}
+
+/// dir 配下のゴーストのフォルダを depth_left の深さまで探す
+fn find_ghost_roots(dir: &Path, depth_left: usize, found: &mut Vec<PathBuf>) {
+    if depth_left == 0 {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if is_ghost_root(&path) {
+            found.push(path);
+        } else {
+            find_ghost_roots(&path, depth_left - 1, found);
+        }
+    }
+}
+
+/// 割り出したゴーストを読み直し、サムネイルキャッシュを作ってから ghosts テーブルに差分反映する。
+/// 戻り値は store::store_ghost_changes と同じ（追加・更新した件数, 削除した件数）
+fn apply_affected_roots(
+    context: &WatcherContext,
+    affected: &AffectedRoots,
+) -> Result<(usize, usize), String> {
+    let conn = Connection::open(&context.db_path).map_err(|e| format!("DB オープンエラー: {e}"))?;
+    store::configure_connection(&conn)?;
+    apply_to_connection(
+        &conn,
+        &context.thumbnail_dir,
+        &context.request_key,
+        &context.roots,
+        affected,
+    )
+}
+
+/// apply_affected_roots の本体（DB 接続を受け取る）
+fn apply_to_connection(
+    conn: &Connection,
+    thumbnail_dir: &Path,
+    request_key: &str,
+    roots: &[WatchRoot],
+    affected: &AffectedRoots,
+) -> Result<(usize, usize), String> {
+    // 読めなかったゴースト（書き込み途中など）は削除扱いにせず、次の変更か scan_and_store に任せる
+    let mut ghosts: Vec<Ghost> = affected
+        .reread
+        .iter()
+        .filter_map(|(root_index, ghost_root)| read_watched_ghost(&roots[*root_index], ghost_root))
+        .collect();
+    thumbnail_cache::populate_thumbnail_cache(thumbnail_dir, &mut ghosts)?;
+    let (updated, removed) =
+        store::store_ghost_changes(conn, request_key, &ghosts, &affected.removed)?;
+    if removed > 0 {
+        // 削除されたゴーストのキャッシュを掃除する（失敗しても反映は有効）
+        let _ = thumbnail_cache::evict_unreferenced_thumbnails(conn, thumbnail_dir);
+    }
+    Ok((updated, removed))
+}
+
+/// ゴースト 1 件を読み直す。入れ子のゴーストは scan と同じく起点からの相対パスを directory_name にする
+fn read_watched_ghost(root: &WatchRoot, ghost_root: &Path) -> Option<Ghost> {
+    let mut ghost = read_ghost_entry(ghost_root, &root.source)?;
+    let relative = ghost_root.strip_prefix(&root.path).ok()?;
+    if relative.components().count() > 1 {
+        ghost.directory_name = relative
+            .components()
+            .map(|component| component.as_os_str().to_str())
+            .collect::<Option<Vec<_>>>()?
+            .join("/");
+    }
+    Some(ghost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ghost::store::tests::setup_db;
+    use crate::testutil::TempDirGuard;
+    use notify::event::{AccessKind, ModifyKind};
+
+    fn create_ghost(dir: &Path, name: &str) {
+        let master = dir.join("ghost").join("master");
+        fs::create_dir_all(&master).unwrap();
+        fs::write(
+            master.join("descript.txt"),
+            format!("charset,UTF-8\r\nname,{name}\r\n"),
+        )
+        .unwrap();
+    }
+
+    fn roots_for(ssp: &Path, extra: &Path) -> Vec<WatchRoot> {
+        watch_roots(
+            &ssp.to_string_lossy(),
+            &[extra.to_string_lossy().into_owned()],
+        )
+    }
+
+    fn names_in_db(conn: &Connection) -> Vec<(String, String)> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT name, directory_name FROM ghosts WHERE request_key = 'rk1' ORDER BY name",
+            )
+            .unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .map(|r| r.unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn next_batch_は途切れるまで変更をまとめ送信側が閉じたら終わる() {
+        let (sender, receiver) = mpsc::channel();
+        for name in ["b", "a", "b"] {
+            sender
+                .send(Ok(notify::Event::new(EventKind::Modify(ModifyKind::Any))
+                    .add_path(PathBuf::from(name))))
+                .unwrap();
+        }
+        sender
+            .send(Ok(
+                notify::Event::new(EventKind::Access(AccessKind::Any)).add_path(PathBuf::from("c"))
+            ))
+            .unwrap();
+        drop(sender);
+
+        let batch = next_batch(&receiver, Duration::from_millis(10), Duration::from_secs(1));
+        assert_eq!(batch, Some(vec![PathBuf::from("a"), PathBuf::from("b")]));
+        assert_eq!(
+            next_batch(&receiver, Duration::from_millis(10), Duration::from_secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn resolve_affected_roots_が変更のあったゴーストと消えたフォルダを割り出す() {
+        let temp = TempDirGuard::new("ghost_launcher_watcher_resolve");
+        let ssp = temp.path().join("ssp");
+        let extra = temp.path().join("extra");
+        create_ghost(&ssp.join("ghost").join("alice"), "Alice");
+        create_ghost(&extra.join("author").join("nested"), "Nested");
+        let roots = roots_for(&ssp, &extra);
+
+        let changed = vec![
+            // ゴースト内のファイル → そのゴーストを読み直す
+            ssp.join("ghost")
+                .join("alice")
+                .join("ghost")
+                .join("master")
+                .join("descript.txt"),
+            // 消えたフォルダ → 配下ごと削除
+            ssp.join("ghost").join("bob"),
+            // 作られた作者フォルダ → 中のゴーストを探す
+            extra.join("author"),
+        ];
+        let affected = resolve_affected_roots(&roots, &changed);
+
+        assert_eq!(
+            affected.reread,
+            BTreeSet::from([
+                (0, ssp.join("ghost").join("alice")),
+                (1, extra.join("author").join("nested")),
+            ])
+        );
+        assert_eq!(
+            affected.removed,
+            vec![
+                RemovedGhostRoot {
+                    path: ssp.join("ghost").join("bob").to_string_lossy().into_owned(),
+                    descendants: true,
+                },
+                RemovedGhostRoot {
+                    path: extra.join("author").to_string_lossy().into_owned(),
+                    descendants: false,
+                },
+            ]
+        );
+
+        // SSP の ghost/ はサブフォルダをたどらない
+        let deep = ssp.join("ghost").join("alice2").join("inner");
+        fs::create_dir_all(&deep).unwrap();
+        let affected = resolve_affected_roots(&roots, &[deep]);
+        assert!(affected.reread.is_empty());
+    }
+
+    #[test]
+    fn apply_to_connection_が読み直したゴーストと削除を反映する() {
+        let temp = TempDirGuard::new("ghost_launcher_watcher_apply");
+        let ssp = temp.path().join("ssp");
+        let extra = temp.path().join("extra");
+        let thumbnail_dir = temp.path().join("thumbnails");
+        create_ghost(&ssp.join("ghost").join("alice"), "Alice");
+        create_ghost(&extra.join("author").join("nested"), "Nested");
+        let roots = roots_for(&ssp, &extra);
+        let conn = setup_db();
+
+        let created = vec![
+            ssp.join("ghost").join("alice"),
+            extra.join("author").join("nested").join("ghost"),
+        ];
+        let affected = resolve_affected_roots(&roots, &created);
+        let result = apply_to_connection(&conn, &thumbnail_dir, "rk1", &roots, &affected).unwrap();
+        assert_eq!(result, (2, 0));
+        assert_eq!(
+            names_in_db(&conn),
+            vec![
+                ("Alice".to_string(), "alice".to_string()),
+                ("Nested".to_string(), "author/nested".to_string()),
+            ]
+        );
+
+        // 変化がなければ何も書き換えない
+        let affected = resolve_affected_roots(&roots, &created);
+        let result = apply_to_connection(&conn, &thumbnail_dir, "rk1", &roots, &affected).unwrap();
+        assert_eq!(result, (0, 0));
+
+        // descript.txt の編集と作者フォルダごとの削除
+        create_ghost(&ssp.join("ghost").join("alice"), "Alice v2");
+        fs::remove_dir_all(extra.join("author")).unwrap();
+        let changed = vec![
+            ssp.join("ghost")
+                .join("alice")
+                .join("ghost")
+                .join("master")
+                .join("descript.txt"),
+            extra.join("author"),
+        ];
+        let affected = resolve_affected_roots(&roots, &changed);
+        let result = apply_to_connection(&conn, &thumbnail_dir, "rk1", &roots, &affected).unwrap();
+        assert_eq!(result, (1, 1));
+        assert_eq!(
+            names_in_db(&conn),
+            vec![("Alice v2".to_string(), "alice".to_string())]
+        );
+    }
+}
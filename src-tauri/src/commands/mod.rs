@@ -1,4 +1,8 @@
+pub mod balloon;
 pub mod db;
 pub mod ghost;
 pub mod locale;
+pub mod nar;
+pub mod readme;
 pub mod ssp;
+pub mod update;
@@ -0,0 +1,369 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use ts_rs::TS;
+
+use ghost_meta::{InstallOutcome, InstallRoots, PackageIssue, Severity};
+
+use super::ghost::ensure_request_key;
+use super::ghost::scan::read_ghost_entry;
+use super::ghost::store::{configure_connection, upsert_ghost, GhostWriteLock};
+use super::ghost::thumbnail_cache;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct InstallNarResult {
+    /// false なら何もインストールしていない（existing_paths の上書き確認が必要）
+    pub installed: bool,
+    /// install.txt の type（"ghost" / "shell" / "balloon" / "supplement"）
+    pub kind: String,
+    /// install.txt の name。未設定の場合は空文字列
+    pub name: String,
+    /// 展開先のフルパス
+    pub target_path: String,
+    /// 上書きになる既存ディレクトリのフルパス
+    pub existing_paths: Vec<String>,
+    /// 展開したファイル数（installed が false なら 0）
+    pub file_count: usize,
+    /// 再スキャンしたゴーストの ghost_identity_key。ゴーストに影響しない場合（balloon）は空文字列
+    pub ghost_identity_key: String,
+}
+
+/// .nar 作成前の検査で見つかった問題
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct NarPackageIssue {
+    /// "error"（.nar を書き出さない）または "warning"
+    pub severity: String,
+    /// 問題のあるファイル（ゴーストのルートからの相対パス）。ゴースト全体に対する問題では空文字列
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct PackageGhostResult {
+    /// .nar を書き出したか（dry_run または error があれば false）
+    pub written: bool,
+    /// 出力先のフルパス
+    pub output_path: String,
+    /// アーカイブに入れるファイル数（install.txt・マニフェストを含む）
+    pub file_count: usize,
+    /// developer_options.txt の nonar で除外したファイル（相対パス）
+    pub excluded_files: Vec<String>,
+    /// install.txt がなく生成したか
+    pub install_txt_generated: bool,
+    /// updates2.dau / updates.txt に載せたファイル数
+    pub manifest_file_count: usize,
+    pub issues: Vec<NarPackageIssue>,
+}
+
+/// .nar をインストールし、影響を受けたゴーストだけを再スキャンして ghosts テーブルに書き込むコマンド。
+///
+/// - target_folder が None なら `{ssp_path}/ghost`、Some なら追加フォルダにゴーストを置く（バルーンは常に `{ssp_path}/balloon`）
+/// - overwrite が false で既存のゴースト・シェル・バルーンを上書きする場合は、何もせず installed=false を返す。
+///   ユーザーが確認したら overwrite=true で呼び直す
+/// - アーカイブ外を指すエントリ（zip-slip）を含む .nar はエラー
+/// - 展開と再スキャンは blocking プールで実行する
+#[tauri::command]
+pub async fn install_nar(
+    app: tauri::AppHandle,
+    nar_path: String,
+    ssp_path: String,
+    target_folder: Option<String>,
+    request_key: String,
+    overwrite: bool,
+) -> Result<InstallNarResult, String> {
+    ensure_request_key(&request_key)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        install_nar_blocking(
+            &app,
+            &nar_path,
+            &ssp_path,
+            target_folder.as_deref(),
+            &request_key,
+            overwrite,
+        )
+    })
+    .await
+    .map_err(|e| format!(".nar のインストールの実行エラー: {e}"))?
+}
+
+/// install_nar の本体
+fn install_nar_blocking(
+    app: &tauri::AppHandle,
+    nar_path: &str,
+    ssp_path: &str,
+    target_folder: Option<&str>,
+    request_key: &str,
+    overwrite: bool,
+) -> Result<InstallNarResult, String> {
+    use tauri::Manager;
+
+    let (roots, source) = install_roots(ssp_path, target_folder);
+    let outcome = ghost_meta::install_nar(Path::new(nar_path), &roots, overwrite)
+        .map_err(|e| format!(".nar のインストールエラー: {e}"))?;
+
+    let (plan, file_count) = match outcome {
+        InstallOutcome::NeedsConfirmation(plan) => (plan, None),
+        InstallOutcome::Installed { plan, files } => (plan, Some(files)),
+    };
+    let mut result = InstallNarResult {
+        installed: file_count.is_some(),
+        kind: plan.kind.as_str().to_string(),
+        name: plan.install.name.clone().unwrap_or_default(),
+        target_path: plan.target.path.to_string_lossy().into_owned(),
+        existing_paths: plan
+            .existing
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect(),
+        file_count: file_count.unwrap_or(0),
+        ghost_identity_key: String::new(),
+    };
+    let Some(ghost_dir) = plan.ghost_dir.filter(|_| result.installed) else {
+        return Ok(result);
+    };
+
+    let db_path = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("app_config_dir 取得エラー: {e}"))?
+        .join("ghosts.db");
+    let conn =
+        Connection::open(&db_path).map_err(|e| format!("DB オープンエラー: {e}"))?;
+    configure_connection(&conn)?;
+    let thumbnail_dir = thumbnail_cache::thumbnail_cache_dir(app)?;
+
+    // 再スキャンの読み直しと書き込みはスキャン・フォルダ監視と排他にする
+    let write_lock = app.state::<GhostWriteLock>();
+    let _writes = write_lock.lock();
+    // インストール自体は完了しているため、再スキャンに失敗しても結果は返す（次回の scan_and_store で整合する）
+    if let Ok(identity_key) =
+        rescan_installed_ghost(&conn, &thumbnail_dir, request_key, &ghost_dir, &source)
+    {
+        result.ghost_identity_key = identity_key;
+    }
+    Ok(result)
+}
+
+/// ゴーストフォルダから .nar を作るコマンド（開発者向け）。
+///
+/// - 検査（descript.txt の name・install.txt の type / directory・アーカイブに入れられないパス）を先に行い、
+///   error があれば何も書き込まずに issues を返す
+/// - dry_run が true なら検査結果だけを返す
+/// - 書き出すときは updates2.dau / updates.txt をゴーストフォルダに再生成してからアーカイブに入れる
+/// - 検査と書き出しは blocking プールで実行する
+#[tauri::command]
+pub async fn package_ghost(
+    ghost_path: String,
+    output_path: String,
+    dry_run: bool,
+) -> Result<PackageGhostResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        package_ghost_blocking(&ghost_path, output_path, dry_run)
+    })
+    .await
+    .map_err(|e| format!(".nar の作成の実行エラー: {e}"))?
+}
+
+/// package_ghost の本体
+fn package_ghost_blocking(
+    ghost_path: &str,
+    output_path: String,
+    dry_run: bool,
+) -> Result<PackageGhostResult, String> {
+    let plan = ghost_meta::plan_package(Path::new(ghost_path))
+        .map_err(|e| format!("ゴーストフォルダの読み込みエラー: {e}"))?;
+
+    let written = !dry_run && !plan.has_errors();
+    let file_count = if written {
+        ghost_meta::write_package(&plan, Path::new(&output_path))
+            .map_err(|e| format!(".nar の書き出しエラー: {e}"))?
+    } else {
+        // install.txt（生成時）+ ファイル + マニフェスト 2 件
+        usize::from(plan.generated_install_txt.is_some()) + plan.files.len() + 2
+    };
+    Ok(PackageGhostResult {
+        written,
+        output_path,
+        file_count,
+        excluded_files: plan.excluded.clone(),
+        install_txt_generated: plan.generated_install_txt.is_some(),
+        manifest_file_count: plan.manifest.len(),
+        issues: plan.issues.iter().map(issue_view).collect(),
+    })
+}
+
+/// PackageIssue を TS に渡す形に変換する
+fn issue_view(issue: &PackageIssue) -> NarPackageIssue {
+    NarPackageIssue {
+        severity: match issue.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+        .to_string(),
+        path: issue.path.clone().unwrap_or_default(),
+        message: issue.message.clone(),
+    }
+}
+
+/// インストール先のルートと、ゴーストの出自（Ghost::source）を決める
+fn install_roots(ssp_path: &str, target_folder: Option<&str>) -> (InstallRoots, String) {
+    let ssp = Path::new(ssp_path);
+    let (ghost_root, source) = match target_folder {
+        Some(folder) => (PathBuf::from(folder), folder.to_string()),
+        None => (ssp.join("ghost"), "ssp".to_string()),
+    };
+    (
+        InstallRoots {
+            ghost_root,
+            balloon_root: ssp.join("balloon"),
+        },
+        source,
+    )
+}
+
+/// インストールしたゴースト 1 件を読み直し、サムネイルキャッシュを作って ghosts テーブルに書き込む
+fn rescan_installed_ghost(
+    conn: &Connection,
+    thumbnail_dir: &Path,
+    request_key: &str,
+    ghost_dir: &Path,
+    source: &str,
+) -> Result<String, String> {
+    let ghost = read_ghost_entry(ghost_dir, source).ok_or_else(|| {
+        format!(
+            "インストールしたゴーストを読み込めません: {}",
+            ghost_dir.display()
+        )
+    })?;
+    let mut ghosts = [ghost];
+    thumbnail_cache::populate_thumbnail_cache(thumbnail_dir, &mut ghosts)?;
+    upsert_ghost(conn, request_key, &ghosts[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::ghost::store::tests::setup_db;
+    use crate::testutil::TempDirGuard;
+    use std::fs;
+
+    #[test]
+    fn install_roots_は追加フォルダ指定時にそのフォルダを_source_にする() {
+        let (roots, source) = install_roots("/ssp", None);
+        assert_eq!(roots.ghost_root, Path::new("/ssp").join("ghost"));
+        assert_eq!(roots.balloon_root, Path::new("/ssp").join("balloon"));
+        assert_eq!(source, "ssp");
+
+        let (roots, source) = install_roots("/ssp", Some("/extra"));
+        assert_eq!(roots.ghost_root, PathBuf::from("/extra"));
+        assert_eq!(roots.balloon_root, Path::new("/ssp").join("balloon"));
+        assert_eq!(source, "/extra");
+    }
+
+    #[test]
+    fn rescan_installed_ghost_はインストールしたゴーストだけを書き込む() {
+        let tmp = TempDirGuard::new("install_nar_rescan");
+        let ghost_dir = tmp.path().join("ghost").join("sakura");
+        fs::create_dir_all(ghost_dir.join("ghost/master")).unwrap();
+        fs::write(
+            ghost_dir.join("ghost/master/descript.txt"),
+            "charset,UTF-8\nname,さくら\n",
+        )
+        .unwrap();
+        let conn = setup_db();
+
+        let identity_key = rescan_installed_ghost(
+            &conn,
+            &tmp.path().join("thumbnails"),
+            "rk1",
+            &ghost_dir,
+            "ssp",
+        )
+        .unwrap();
+        assert_eq!(identity_key, "ssp\u{1f}sakura");
+        let name: String = conn
+            .query_row(
+                "SELECT name FROM ghosts WHERE request_key = 'rk1' AND ghost_identity_key = ?1",
+                [&identity_key],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(name, "さくら");
+
+        assert!(rescan_installed_ghost(
+            &conn,
+            &tmp.path().join("thumbnails"),
+            "rk1",
+            &tmp.path().join("missing"),
+            "ssp",
+        )
+        .is_err());
+    }
+
+    fn create_package_ghost(root: &Path, descript: &str) -> PathBuf {
+        let ghost_root = root.join("sakura");
+        fs::create_dir_all(ghost_root.join("ghost/master")).unwrap();
+        fs::write(ghost_root.join("ghost/master/descript.txt"), descript).unwrap();
+        fs::write(ghost_root.join("ghost/master/debug.log"), "log").unwrap();
+        fs::write(ghost_root.join("developer_options.txt"), "*.log,nonar\r\n").unwrap();
+        ghost_root
+    }
+
+    #[test]
+    fn package_ghost_は_dry_run_では書き出さずに検査結果を返す() {
+        let tmp = TempDirGuard::new("package_ghost_dry_run");
+        let ghost_root = create_package_ghost(tmp.path(), "charset,UTF-8\nname,さくら\n");
+        let output = tmp.path().join("sakura.nar");
+
+        let result = package_ghost_blocking(
+            &ghost_root.to_string_lossy(),
+            output.to_string_lossy().into_owned(),
+            true,
+        )
+        .unwrap();
+        assert!(!result.written);
+        assert!(result.install_txt_generated);
+        assert_eq!(result.excluded_files, vec!["ghost/master/debug.log"]);
+        assert_eq!(result.file_count, 4);
+        assert!(!output.exists());
+
+        let result = package_ghost_blocking(
+            &ghost_root.to_string_lossy(),
+            output.to_string_lossy().into_owned(),
+            false,
+        )
+        .unwrap();
+        assert!(result.written);
+        assert_eq!(result.file_count, 4);
+        assert_eq!(result.manifest_file_count, 1);
+        assert!(output.is_file());
+    }
+
+    #[test]
+    fn package_ghost_は_name_がなければ_error_を返して書き出さない() {
+        let tmp = TempDirGuard::new("package_ghost_missing_name");
+        let ghost_root = create_package_ghost(tmp.path(), "charset,UTF-8\n");
+        let output = tmp.path().join("sakura.nar");
+
+        let result = package_ghost_blocking(
+            &ghost_root.to_string_lossy(),
+            output.to_string_lossy().into_owned(),
+            false,
+        )
+        .unwrap();
+        assert!(!result.written);
+        assert!(result
+            .issues
+            .iter()
+            .any(|issue| issue.severity == "error" && issue.path == "ghost/master/descript.txt"));
+        assert!(!output.exists());
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use ts_rs::TS;
+
+const MAX_README_BYTES: u64 = 1_024 * 1_024; // 1 MB
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct GhostReadme {
+    /// readme ファイルのフルパス
+    pub path: String,
+    /// デコードしたテキスト
+    pub text: String,
+    /// デコードに使った文字コード名（"Shift_JIS" / "UTF-8" など）
+    pub encoding: String,
+    /// text に含まれる http / https の URL（出現順、重複なし）
+    pub urls: Vec<String>,
+}
+
+/// ゴーストの readme を探してデコードする（テスト用に分離）
+fn read_readme_from_dir(ghost_root: &Path) -> Result<Option<GhostReadme>, String> {
+    let Some(readme_path) = ghost_meta::find_readme(ghost_root) else {
+        return Ok(None);
+    };
+
+    // 上限 + 1 バイトまで読み、読めてしまえば上限超えとみなす
+    let mut bytes = Vec::new();
+    File::open(&readme_path)
+        .and_then(|file| file.take(MAX_README_BYTES + 1).read_to_end(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    if bytes.len() as u64 > MAX_README_BYTES {
+        return Err(format!(
+            "readme が大きすぎます（最大 1 MB）: {}",
+            readme_path.display()
+        ));
+    }
+
+    let readme = ghost_meta::parse_readme(&readme_path, &bytes);
+    Ok(Some(GhostReadme {
+        path: readme.path.to_string_lossy().into_owned(),
+        text: readme.text,
+        encoding: readme.encoding,
+        urls: readme.urls,
+    }))
+}
+
+/// ゴーストの readme を読み込む Tauri コマンド（起動前に利用規約を確認するため）。
+/// descript.txt の readme フィールド、なければゴーストのルート直下の readme.txt などを読む。
+/// 文字コードは descript.txt と同じ規則で判定する。readme がない場合は null を返す。
+/// ファイルの探索と読み込みは blocking プールで実行する
+#[tauri::command]
+pub async fn read_ghost_readme(ghost_path: String) -> Result<Option<GhostReadme>, String> {
+    tauri::async_runtime::spawn_blocking(move || read_readme_from_dir(Path::new(&ghost_path)))
+        .await
+        .map_err(|e| format!("readme の読み込みの実行エラー: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+    use std::fs;
+
+    #[test]
+    fn readme_がない場合は_none_を返す() {
+        let dir = TempDirGuard::new("readme_test");
+        let result = read_readme_from_dir(dir.path());
+        assert!(result.unwrap().is_none());
+    }
+
+    #[test]
+    fn shift_jis_の_readme_をデコードして返す() {
+        let dir = TempDirGuard::new("readme_test");
+        let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode("利用規約\r\nhttps://example.com/\r\n");
+        fs::write(dir.path().join("readme.txt"), &bytes).unwrap();
+
+        let readme = read_readme_from_dir(dir.path()).unwrap().unwrap();
+        assert_eq!(readme.text, "利用規約\r\nhttps://example.com/\r\n");
+        assert_eq!(readme.encoding, "Shift_JIS");
+        assert_eq!(readme.urls, vec!["https://example.com/"]);
+    }
+
+    #[test]
+    fn ファイルサイズ超過の場合はエラーを返す() {
+        let dir = TempDirGuard::new("readme_test");
+        let oversized = vec![b'x'; (MAX_README_BYTES + 1) as usize];
+        fs::write(dir.path().join("readme.txt"), &oversized).unwrap();
+
+        let result = read_readme_from_dir(dir.path());
+        assert!(result.unwrap_err().contains("大きすぎます"));
+    }
+}
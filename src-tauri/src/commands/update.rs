@@ -0,0 +1,813 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use ts_rs::TS;
+
+use ghost_meta::manifest::{UPDATES2_DAU, UPDATES_TXT};
+use ghost_meta::UpdateEntry;
+
+/// サーバーのマニフェストの読み込み上限
+const MANIFEST_SIZE_LIMIT: u64 = 4 * 1024 * 1024;
+/// ダウンロードする 1 ファイルの上限（マニフェストに size がない場合）
+const FILE_SIZE_LIMIT: u64 = 512 * 1024 * 1024;
+/// ダウンロード中のファイルを置くディレクトリ（ゴーストのルート直下。更新が終われば消す）
+const STAGING_DIR: &str = ".ghost_launcher_update";
+/// 置き換え前のファイルを退避するディレクトリ（失敗すれば書き戻し、更新が終われば消す）
+const BACKUP_DIR: &str = ".ghost_launcher_update_backup";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct GhostVerifyResult {
+    /// 照合に使ったマニフェスト（"updates2.dau" / "updates.txt"）。マニフェストがなければ空文字列
+    pub manifest: String,
+    /// MD5 が一致したファイル数
+    pub verified_count: usize,
+    /// マニフェストと内容が異なるファイル（ゴーストのルートからの相対パス）
+    pub modified: Vec<String>,
+    /// マニフェストにあるが存在しないファイル
+    pub missing: Vec<String>,
+    /// マニフェストにないファイル（profile 配下など実行時に作られるファイルは除く）
+    pub extra: Vec<String>,
+}
+
+/// インストール済みゴーストのファイルをハッシュし、updates2.dau（なければ updates.txt）と照合するコマンド。
+/// 手で編集されたゴースト（modified）や不完全なインストール（missing）を見つけるために使う。
+/// マニフェストがないゴーストは manifest が空文字列の結果を返す（エラーにしない）。
+/// 全ファイルをハッシュするため blocking プールで実行する
+#[tauri::command]
+pub async fn verify_ghost(ghost_path: String) -> Result<GhostVerifyResult, String> {
+    tauri::async_runtime::spawn_blocking(move || verify_ghost_blocking(&ghost_path))
+        .await
+        .map_err(|e| format!("ゴーストの照合の実行エラー: {e}"))?
+}
+
+/// verify_ghost の本体
+fn verify_ghost_blocking(ghost_path: &str) -> Result<GhostVerifyResult, String> {
+    let verification = ghost_meta::verify_ghost(Path::new(ghost_path))
+        .map_err(|e| format!("ゴーストの照合エラー ({ghost_path}): {e}"))?;
+    Ok(GhostVerifyResult {
+        manifest: verification.manifest.unwrap_or_default(),
+        verified_count: verification.verified,
+        modified: verification.modified,
+        missing: verification.missing,
+        extra: verification.extra,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct GhostUpdateStatus {
+    pub ghost_path: String,
+    /// descript.txt の homeurl。未設定の場合は空文字列
+    pub homeurl: String,
+    /// サーバーのマニフェストと照合できたか（homeurl がない・通信に失敗した場合は false）
+    pub checked: bool,
+    pub update_available: bool,
+    /// 取得が必要なファイル（ゴーストのルートからの相対パス）
+    pub changed_files: Vec<String>,
+    /// 取得が必要なファイルの合計サイズ（バイト）。マニフェストに size がないファイルは数えない
+    #[cfg_attr(test, ts(type = "number"))]
+    pub download_bytes: u64,
+    /// 照合できなかった理由。checked が true なら空文字列
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(test, derive(TS))]
+#[cfg_attr(test, ts(export))]
+pub struct GhostUpdateApplyResult {
+    pub ghost_path: String,
+    /// 書き換えたファイル（ゴーストのルートからの相対パス）
+    pub updated_files: Vec<String>,
+    #[cfg_attr(test, ts(type = "number"))]
+    pub downloaded_bytes: u64,
+}
+
+/// ネットワーク更新の通信設定
+#[derive(Debug, Clone)]
+pub(crate) struct UpdatePolicy {
+    /// 接続のタイムアウト
+    pub(crate) connect_timeout: Duration,
+    /// 応答・受信が止まった場合のタイムアウト（1 回の読み込みごと）
+    pub(crate) read_timeout: Duration,
+    /// 1 回のリクエスト全体（応答の待ち・本文の受信）の期限。超えれば再試行せずにエラーにする
+    pub(crate) request_timeout: Duration,
+    /// 同時に通信するゴースト数・ファイル数
+    pub(crate) max_concurrency: usize,
+    /// 1 リクエストあたりの試行回数（初回を含む）
+    pub(crate) max_attempts: u32,
+    /// 再試行までの待ち時間（試行ごとに倍にする）
+    pub(crate) retry_delay: Duration,
+}
+
+impl Default for UpdatePolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            request_timeout: Duration::from_secs(600),
+            max_concurrency: 4,
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+/// 1 回のリクエストの失敗
+#[derive(Debug)]
+enum FetchError {
+    /// 404 / 410（updates2.dau がなければ updates.txt を試す）
+    NotFound(String),
+    /// 通信エラー・タイムアウト・408 / 429 / 5xx（再試行する）
+    Retryable(String),
+    /// それ以外（再試行しない）
+    Fatal(String),
+}
+
+impl FetchError {
+    fn into_message(self) -> String {
+        match self {
+            FetchError::NotFound(message)
+            | FetchError::Retryable(message)
+            | FetchError::Fatal(message) => message,
+        }
+    }
+}
+
+/// サーバーのマニフェストとの照合結果
+struct PendingUpdate {
+    /// サーバーから取得したマニフェストのファイル名と内容（更新後にゴーストフォルダへ書き込む）
+    manifest_name: &'static str,
+    manifest_bytes: Vec<u8>,
+    changed: Vec<UpdateEntry>,
+}
+
+/// homeurl を使ったネットワーク更新のクライアント
+pub(crate) struct UpdateClient {
+    agent: ureq::Agent,
+    policy: UpdatePolicy,
+}
+
+impl UpdateClient {
+    pub(crate) fn new(policy: UpdatePolicy) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(policy.connect_timeout)
+            .timeout_read(policy.read_timeout)
+            .user_agent(concat!("ghost-launcher/", env!("CARGO_PKG_VERSION")))
+            .build();
+        Self { agent, policy }
+    }
+
+    /// 複数のゴーストを max_concurrency 並列で照合する（ghost_paths の順序を保つ）
+    pub(crate) fn check_all(
+        &self,
+        ghost_paths: &[String],
+    ) -> Result<Vec<GhostUpdateStatus>, String> {
+        self.thread_pool()?.install(|| {
+            Ok(ghost_paths
+                .par_iter()
+                .map(|ghost_path| self.check(Path::new(ghost_path)))
+                .collect())
+        })
+    }
+
+    /// 1 つのゴーストを照合する。失敗は error に入れて返す
+    pub(crate) fn check(&self, ghost_root: &Path) -> GhostUpdateStatus {
+        let mut status = GhostUpdateStatus {
+            ghost_path: ghost_root.to_string_lossy().into_owned(),
+            homeurl: String::new(),
+            checked: false,
+            update_available: false,
+            changed_files: Vec::new(),
+            download_bytes: 0,
+            error: String::new(),
+        };
+        let pending = read_homeurl(ghost_root).and_then(|homeurl| {
+            status.homeurl = homeurl;
+            self.pending_update(ghost_root, &status.homeurl)
+        });
+        match pending {
+            Ok(pending) => {
+                status.checked = true;
+                status.update_available = !pending.changed.is_empty();
+                status.download_bytes = pending.changed.iter().filter_map(|entry| entry.size).sum();
+                status.changed_files = pending
+                    .changed
+                    .into_iter()
+                    .map(|entry| entry.path)
+                    .collect();
+            }
+            Err(error) => status.error = error,
+        }
+        status
+    }
+
+    /// 変更のあったファイルをダウンロードしてゴーストフォルダに反映する。
+    ///
+    /// すべてのファイルを作業ディレクトリにダウンロードし、MD5 とサイズを確かめてから置き換える。
+    /// 置き換える前のファイルは退避しておき、置き換えの途中で失敗すれば元に戻す。
+    /// 1 つでも失敗すればゴーストフォルダは更新前の内容のままになる。最後にサーバーのマニフェストを書き込む
+    pub(crate) fn apply(&self, ghost_root: &Path) -> Result<GhostUpdateApplyResult, String> {
+        let homeurl = read_homeurl(ghost_root)?;
+        let pending = self.pending_update(ghost_root, &homeurl)?;
+
+        let staging = ghost_root.join(STAGING_DIR);
+        let backup = ghost_root.join(BACKUP_DIR);
+        let _ = fs::remove_dir_all(&staging);
+        let _ = fs::remove_dir_all(&backup);
+        let downloaded = self
+            .thread_pool()?
+            .install(|| {
+                pending
+                    .changed
+                    .par_iter()
+                    .map(|entry| self.download_entry(&homeurl, entry, &staging))
+                    .collect::<Result<Vec<u64>, String>>()
+            })
+            .and_then(|sizes| {
+                let manifest = PathBuf::from(pending.manifest_name);
+                fs::write(staging.join(&manifest), &pending.manifest_bytes)
+                    .map_err(|e| format!("マニフェストの書き込みエラー: {e}"))?;
+                let mut relatives = pending
+                    .changed
+                    .iter()
+                    .map(|entry| {
+                        entry
+                            .relative_path()
+                            .ok_or_else(|| format!("不正なパスです: {}", entry.path))
+                    })
+                    .collect::<Result<Vec<PathBuf>, String>>()?;
+                relatives.push(manifest);
+                replace_files(&staging, &backup, ghost_root, &relatives)?;
+                Ok(sizes.iter().sum())
+            });
+        let _ = fs::remove_dir_all(&staging);
+        let _ = fs::remove_dir_all(&backup);
+
+        Ok(GhostUpdateApplyResult {
+            ghost_path: ghost_root.to_string_lossy().into_owned(),
+            updated_files: pending
+                .changed
+                .into_iter()
+                .map(|entry| entry.path)
+                .collect(),
+            downloaded_bytes: downloaded?,
+        })
+    }
+
+    /// サーバーの updates2.dau（なければ updates.txt）を取得してローカルのマニフェストと比べる
+    fn pending_update(&self, ghost_root: &Path, homeurl: &str) -> Result<PendingUpdate, String> {
+        let (manifest_name, manifest_bytes) =
+            match self.fetch(&remote_url(homeurl, UPDATES2_DAU)?, MANIFEST_SIZE_LIMIT) {
+                Ok(bytes) => (UPDATES2_DAU, bytes),
+                Err(FetchError::NotFound(_)) => (
+                    UPDATES_TXT,
+                    self.fetch(&remote_url(homeurl, UPDATES_TXT)?, MANIFEST_SIZE_LIMIT)
+                        .map_err(FetchError::into_message)?,
+                ),
+                Err(error) => return Err(error.into_message()),
+            };
+        let remote = ghost_meta::parse_manifest(&manifest_bytes);
+        if remote.is_empty() {
+            return Err(format!(
+                "サーバーのマニフェストにファイルがありません: {homeurl}"
+            ));
+        }
+        let local = ghost_meta::read_manifest(ghost_root)
+            .map_err(|e| format!("マニフェストの読み込みエラー: {e}"))?
+            .map(|(_, entries)| entries)
+            .unwrap_or_default();
+        Ok(PendingUpdate {
+            manifest_name,
+            manifest_bytes,
+            changed: ghost_meta::diff_manifests(&local, &remote),
+        })
+    }
+
+    /// 1 ファイルを staging にダウンロードし、マニフェストの MD5 とサイズを確かめる
+    fn download_entry(
+        &self,
+        homeurl: &str,
+        entry: &UpdateEntry,
+        staging: &Path,
+    ) -> Result<u64, String> {
+        let relative = entry
+            .relative_path()
+            .ok_or_else(|| format!("不正なパスです: {}", entry.path))?;
+        let limit = entry.size.unwrap_or(FILE_SIZE_LIMIT);
+        let bytes = self
+            .fetch(&remote_url(homeurl, &entry.path)?, limit)
+            .map_err(FetchError::into_message)?;
+
+        let path = staging.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("作業ディレクトリの作成エラー: {e}"))?;
+        }
+        fs::write(&path, &bytes)
+            .map_err(|e| format!("ダウンロードしたファイルの書き込みエラー: {e}"))?;
+        let (md5, size) =
+            ghost_meta::md5_file(&path).map_err(|e| format!("MD5 の計算エラー: {e}"))?;
+        if md5 != entry.md5 || entry.size.is_some_and(|expected| expected != size) {
+            return Err(format!(
+                "ダウンロードしたファイルがマニフェストと一致しません: {}",
+                entry.path
+            ));
+        }
+        Ok(size)
+    }
+
+    /// policy に従って再試行しながら url を取得する（limit バイトを超える応答はエラー）
+    fn fetch(&self, url: &str, limit: u64) -> Result<Vec<u8>, FetchError> {
+        let mut attempt = 1;
+        loop {
+            match self.fetch_once(url, limit) {
+                Err(FetchError::Retryable(_)) if attempt < self.policy.max_attempts => {
+                    thread::sleep(self.policy.retry_delay * 2u32.pow(attempt - 1));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn fetch_once(&self, url: &str, limit: u64) -> Result<Vec<u8>, FetchError> {
+        // ureq の timeout は timeout_read を上書きしてしまうため、全体の期限はここで確かめる
+        let deadline = Instant::now() + self.policy.request_timeout;
+        let response = match self.agent.get(url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code @ (404 | 410), _)) => {
+                return Err(FetchError::NotFound(format!("HTTP {code}: {url}")));
+            }
+            Err(ureq::Error::Status(code, _)) if code == 408 || code == 429 || code >= 500 => {
+                return Err(FetchError::Retryable(format!("HTTP {code}: {url}")));
+            }
+            Err(ureq::Error::Status(code, _)) => {
+                return Err(FetchError::Fatal(format!("HTTP {code}: {url}")));
+            }
+            Err(ureq::Error::Transport(error)) => {
+                return Err(FetchError::Retryable(format!(
+                    "通信エラー ({url}): {error}"
+                )));
+            }
+        };
+        let mut reader = response.into_reader().take(limit + 1);
+        let mut bytes = Vec::new();
+        let mut buffer = [0u8; 64 * 1024];
+        loop {
+            if Instant::now() >= deadline {
+                return Err(FetchError::Fatal(format!(
+                    "受信が期限内に終わりませんでした: {url}"
+                )));
+            }
+            match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => bytes.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    return Err(FetchError::Retryable(format!("受信エラー ({url}): {e}")));
+                }
+            }
+        }
+        if bytes.len() as u64 > limit {
+            return Err(FetchError::Fatal(format!("応答が大きすぎます: {url}")));
+        }
+        Ok(bytes)
+    }
+
+    fn thread_pool(&self) -> Result<rayon::ThreadPool, String> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.policy.max_concurrency.max(1))
+            .build()
+            .map_err(|e| format!("スレッドプールの作成エラー: {e}"))
+    }
+}
+
+/// descript.txt の homeurl を読む。未設定ならエラー
+fn read_homeurl(ghost_root: &Path) -> Result<String, String> {
+    let descript_path = ghost_root.join("ghost").join("master").join("descript.txt");
+    let descript = ghost_meta::read_descript(&descript_path).map_err(|e| {
+        format!(
+            "descript.txt の読み込みエラー ({}): {e}",
+            descript_path.display()
+        )
+    })?;
+    descript
+        .homeurl
+        .map(|homeurl| homeurl.trim().to_string())
+        .filter(|homeurl| !homeurl.is_empty())
+        .ok_or_else(|| "homeurl が設定されていません".to_string())
+}
+
+/// homeurl と相対パスからダウンロード URL を作る（homeurl は http / https のみ）
+fn remote_url(homeurl: &str, path: &str) -> Result<String, String> {
+    let scheme_ok = ["http://", "https://"].iter().any(|scheme| {
+        homeurl
+            .get(..scheme.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(scheme))
+    });
+    if !scheme_ok {
+        return Err(format!("homeurl が http / https ではありません: {homeurl}"));
+    }
+    let separator = if homeurl.ends_with('/') { "" } else { "/" };
+    let path = path
+        .replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('#', "%23")
+        .replace('?', "%3F");
+    Ok(format!("{homeurl}{separator}{path}"))
+}
+
+/// staging のファイルをゴーストフォルダの同じ相対パスへ移す。
+/// 既存のファイルは backup へ退避し、途中で失敗すれば移したファイルを消して退避したファイルを書き戻す
+fn replace_files(
+    staging: &Path,
+    backup: &Path,
+    ghost_root: &Path,
+    relatives: &[PathBuf],
+) -> Result<(), String> {
+    // 置き換えたファイルの相対パスと、退避したかどうか
+    let mut replaced: Vec<(&Path, bool)> = Vec::new();
+    for relative in relatives {
+        match replace_file(staging, backup, ghost_root, relative) {
+            Ok(backed_up) => replaced.push((relative, backed_up)),
+            Err((error, backed_up)) => {
+                if let Some(backed_up) = backed_up {
+                    replaced.push((relative, backed_up));
+                }
+                rollback(backup, ghost_root, &replaced);
+                return Err(error);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 1 ファイルを置き換える。戻り値は既存のファイルを退避したかどうか。
+/// エラー時は退避より前に失敗した（このファイルは何も変えていない）なら None
+fn replace_file(
+    staging: &Path,
+    backup: &Path,
+    ghost_root: &Path,
+    relative: &Path,
+) -> Result<bool, (String, Option<bool>)> {
+    let display = relative.display();
+    let target = ghost_root.join(relative);
+    let backed_up = target.is_file();
+    if backed_up {
+        let saved = backup.join(relative);
+        if let Some(parent) = saved.parent() {
+            fs::create_dir_all(parent).map_err(|e| (format!("退避先の作成エラー: {e}"), None))?;
+        }
+        fs::rename(&target, &saved)
+            .map_err(|e| (format!("ファイルの退避エラー ({display}): {e}"), None))?;
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| (format!("ディレクトリの作成エラー: {e}"), Some(backed_up)))?;
+    }
+    fs::rename(staging.join(relative), &target).map_err(|e| {
+        (
+            format!("ファイルの置き換えエラー ({display}): {e}"),
+            Some(backed_up),
+        )
+    })?;
+    Ok(backed_up)
+}
+
+/// replace_files の途中までの置き換えを後ろから取り消す（取り消しの失敗は無視する）
+fn rollback(backup: &Path, ghost_root: &Path, replaced: &[(&Path, bool)]) {
+    for &(relative, backed_up) in replaced.iter().rev() {
+        let target = ghost_root.join(relative);
+        if target.is_file() {
+            let _ = fs::remove_file(&target);
+        }
+        if backed_up {
+            let _ = fs::rename(backup.join(relative), &target);
+        } else {
+            // 置き換えのために作ったディレクトリを消す（空でなければ残る）
+            for dir in target.ancestors().skip(1) {
+                if dir == ghost_root || fs::remove_dir(dir).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// descript.txt の homeurl からサーバーの updates2.dau を取得し、ゴーストごとに更新の有無を返すコマンド。
+/// 照合はローカルの updates2.dau / updates.txt と MD5 で行う（ファイルはダウンロードしない）。
+/// ゴーストごとの失敗は error に入れて返し、コマンド全体は失敗させない。
+/// 通信を待つあいだ UI を止めないよう blocking プールで実行する
+#[tauri::command]
+pub async fn check_ghost_updates(
+    ghost_paths: Vec<String>,
+) -> Result<Vec<GhostUpdateStatus>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        UpdateClient::new(UpdatePolicy::default()).check_all(&ghost_paths)
+    })
+    .await
+    .map_err(|e| format!("更新確認の実行エラー: {e}"))?
+}
+
+/// 変更のあったファイルをダウンロードしてゴーストフォルダに反映するコマンド。
+/// 反映後に ghosts テーブルを最新にするには scan_and_store を呼び直す。
+/// ダウンロードとファイルの置き換えは blocking プールで実行する
+#[tauri::command]
+pub async fn apply_ghost_update(ghost_path: String) -> Result<GhostUpdateApplyResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        UpdateClient::new(UpdatePolicy::default())
+            .apply(Path::new(&ghost_path))
+            .map_err(|e| format!("ネットワーク更新エラー ({ghost_path}): {e}"))
+    })
+    .await
+    .map_err(|e| format!("ネットワーク更新の実行エラー: {e}"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDirGuard;
+    use std::fs;
+
+    #[test]
+    fn verify_ghost_はマニフェストと照合した結果を返す() {
+        let tmp = TempDirGuard::new("verify_ghost_command");
+        fs::create_dir_all(tmp.path().join("ghost/master")).unwrap();
+        fs::write(tmp.path().join("ghost/master/descript.txt"), "abc").unwrap();
+        fs::write(
+            tmp.path().join("updates.txt"),
+            "charset,UTF-8\r\nfile,ghost/master/descript.txt\x01900150983cd24fb0d6963f7d28e17f72\x01size=3\x01\r\nfile,readme.txt\x01d41d8cd98f00b204e9800998ecf8427e\x01size=0\x01\r\n",
+        )
+        .unwrap();
+
+        let result = verify_ghost_blocking(&tmp.path().to_string_lossy()).unwrap();
+        assert_eq!(result.manifest, "updates.txt");
+        assert_eq!(result.verified_count, 1);
+        assert_eq!(result.missing, vec!["readme.txt"]);
+        assert!(result.modified.is_empty());
+        assert!(result.extra.is_empty());
+    }
+
+    #[test]
+    fn verify_ghost_は存在しないフォルダでも空の結果を返す() {
+        let result = verify_ghost_blocking("/nonexistent/ghost").unwrap();
+        assert_eq!(result.manifest, "");
+    }
+
+    /// テスト用の HTTP サーバー。routes のパスに本文を返し、fail_first 回目までは 503 を返す
+    fn serve(routes: Vec<(&str, Vec<u8>)>, fail_first: usize) -> String {
+        use std::collections::HashMap;
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let routes: HashMap<String, Vec<u8>> = routes
+            .into_iter()
+            .map(|(path, body)| (path.to_string(), body))
+            .collect();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/ghost/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let mut hits: HashMap<String, usize> = HashMap::new();
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split(' ').nth(1).unwrap_or("").to_string();
+                let hit = hits.entry(path.clone()).or_default();
+                *hit += 1;
+                let (status, body) = match routes.get(&path) {
+                    Some(_) if *hit <= fail_first => ("503 Service Unavailable", Vec::new()),
+                    Some(body) => ("200 OK", body.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                };
+                let header = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(&body);
+            }
+        });
+        base
+    }
+
+    fn test_client() -> UpdateClient {
+        UpdateClient::new(UpdatePolicy {
+            connect_timeout: Duration::from_secs(2),
+            read_timeout: Duration::from_secs(2),
+            request_timeout: Duration::from_secs(10),
+            max_concurrency: 2,
+            max_attempts: 2,
+            retry_delay: Duration::ZERO,
+        })
+    }
+
+    /// homeurl を設定したゴーストを作る。ghost/master/descript.txt は "abc"（マニフェストにも載せる）
+    fn create_update_ghost(root: &Path, homeurl: &str) -> std::path::PathBuf {
+        let ghost_root = root.join("sakura");
+        fs::create_dir_all(ghost_root.join("ghost/master")).unwrap();
+        fs::write(
+            ghost_root.join("ghost/master/descript.txt"),
+            format!("charset,UTF-8\r\nname,さくら\r\nhomeurl,{homeurl}\r\n"),
+        )
+        .unwrap();
+        fs::write(
+            ghost_root.join(UPDATES2_DAU),
+            "charset,UTF-8\r\nreadme.txt\x01900150983cd24fb0d6963f7d28e17f72\x01size=3\x01\r\n",
+        )
+        .unwrap();
+        fs::write(ghost_root.join("readme.txt"), "abc").unwrap();
+        ghost_root
+    }
+
+    /// "abc" → "hello" に変わった readme.txt と追加された shell/master/surface0.png を載せたサーバーのマニフェスト
+    fn remote_manifest() -> Vec<u8> {
+        "charset,UTF-8\r\nreadme.txt\x015d41402abc4b2a76b9719d911017c592\x01size=5\x01\r\nshell/master/surface0.png\x01acbd18db4cc2f85cedef654fccc4a4d8\x01size=3\x01\r\n"
+            .as_bytes()
+            .to_vec()
+    }
+
+    #[test]
+    fn サーバーのマニフェストと比べて更新の有無とダウンロード量を返す() {
+        let tmp = TempDirGuard::new("update_check");
+        let base = serve(vec![("/ghost/updates2.dau", remote_manifest())], 0);
+        let ghost_root = create_update_ghost(tmp.path(), &base);
+        let no_homeurl = tmp.path().join("no_homeurl");
+        fs::create_dir_all(no_homeurl.join("ghost/master")).unwrap();
+        fs::write(
+            no_homeurl.join("ghost/master/descript.txt"),
+            "name,test\r\n",
+        )
+        .unwrap();
+
+        let statuses = test_client()
+            .check_all(&[
+                ghost_root.to_string_lossy().into_owned(),
+                no_homeurl.to_string_lossy().into_owned(),
+            ])
+            .unwrap();
+        assert!(statuses[0].checked, "{}", statuses[0].error);
+        assert!(statuses[0].update_available);
+        assert_eq!(
+            statuses[0].changed_files,
+            vec!["readme.txt", "shell/master/surface0.png"]
+        );
+        assert_eq!(statuses[0].download_bytes, 8);
+        assert!(!statuses[1].checked);
+        assert!(!statuses[1].error.is_empty());
+    }
+
+    #[test]
+    fn 更新を適用するとファイルとマニフェストを書き換える() {
+        let tmp = TempDirGuard::new("update_apply");
+        let base = serve(
+            vec![
+                ("/ghost/updates2.dau", remote_manifest()),
+                ("/ghost/readme.txt", b"hello".to_vec()),
+                ("/ghost/shell/master/surface0.png", b"foo".to_vec()),
+            ],
+            1,
+        );
+        let ghost_root = create_update_ghost(tmp.path(), &base);
+
+        // 各パスの初回は 503 を返すため、再試行で取得できることも確かめる
+        let client = test_client();
+        let result = client.apply(&ghost_root).unwrap();
+        assert_eq!(result.updated_files.len(), 2);
+        assert_eq!(result.downloaded_bytes, 8);
+        assert_eq!(
+            fs::read_to_string(ghost_root.join("readme.txt")).unwrap(),
+            "hello"
+        );
+        assert_eq!(
+            fs::read_to_string(ghost_root.join("shell/master/surface0.png")).unwrap(),
+            "foo"
+        );
+        assert_eq!(
+            fs::read(ghost_root.join(UPDATES2_DAU)).unwrap(),
+            remote_manifest()
+        );
+        assert!(!ghost_root.join(STAGING_DIR).exists());
+
+        let status = client.check(&ghost_root);
+        assert!(status.checked);
+        assert!(!status.update_available);
+    }
+
+    #[test]
+    fn md5_が一致しないファイルがあればゴーストフォルダを変更しない() {
+        let tmp = TempDirGuard::new("update_apply_mismatch");
+        let base = serve(
+            vec![
+                ("/ghost/updates2.dau", remote_manifest()),
+                ("/ghost/readme.txt", b"hello".to_vec()),
+                ("/ghost/shell/master/surface0.png", b"bar".to_vec()),
+            ],
+            0,
+        );
+        let ghost_root = create_update_ghost(tmp.path(), &base);
+        let manifest_before = fs::read(ghost_root.join(UPDATES2_DAU)).unwrap();
+
+        assert!(test_client().apply(&ghost_root).is_err());
+        assert_eq!(
+            fs::read_to_string(ghost_root.join("readme.txt")).unwrap(),
+            "abc"
+        );
+        assert!(!ghost_root.join("shell").exists());
+        assert_eq!(
+            fs::read(ghost_root.join(UPDATES2_DAU)).unwrap(),
+            manifest_before
+        );
+        assert!(!ghost_root.join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn 置き換えの途中で失敗すれば置き換えたファイルを元に戻す() {
+        let tmp = TempDirGuard::new("update_apply_rollback");
+        let base = serve(
+            vec![
+                ("/ghost/updates2.dau", remote_manifest()),
+                ("/ghost/readme.txt", b"hello".to_vec()),
+                ("/ghost/shell/master/surface0.png", b"foo".to_vec()),
+            ],
+            0,
+        );
+        let ghost_root = create_update_ghost(tmp.path(), &base);
+        let manifest_before = fs::read(ghost_root.join(UPDATES2_DAU)).unwrap();
+        // shell がファイルのため shell/master/surface0.png を置けず、readme.txt の置き換え後に失敗する
+        fs::write(ghost_root.join("shell"), "not a directory").unwrap();
+
+        assert!(test_client().apply(&ghost_root).is_err());
+        assert_eq!(
+            fs::read_to_string(ghost_root.join("readme.txt")).unwrap(),
+            "abc"
+        );
+        assert_eq!(
+            fs::read(ghost_root.join(UPDATES2_DAU)).unwrap(),
+            manifest_before
+        );
+        assert!(!ghost_root.join(STAGING_DIR).exists());
+        assert!(!ghost_root.join(BACKUP_DIR).exists());
+    }
+
+    #[test]
+    fn 少しずつ届く応答も全体の期限で打ち切る() {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/ghost/updates2.dau",
+            listener.local_addr().unwrap()
+        );
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0u8; 1024]);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n");
+                // 1 回の読み込みのタイムアウトには掛からない間隔で 1 バイトずつ送る
+                for _ in 0..1000 {
+                    if stream.write_all(b"a").is_err() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(50));
+                }
+            }
+        });
+
+        let client = UpdateClient::new(UpdatePolicy {
+            request_timeout: Duration::from_millis(500),
+            ..test_client().policy
+        });
+        let started = Instant::now();
+        let error = client.fetch(&url, MANIFEST_SIZE_LIMIT).unwrap_err();
+        assert!(matches!(error, FetchError::Fatal(_)), "{error:?}");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn remote_url_は_homeurl_とパスをつなぐ() {
+        assert_eq!(
+            remote_url("http://example.com/ghost", "shell/master/surface 0.png").unwrap(),
+            "http://example.com/ghost/shell/master/surface%200.png"
+        );
+        assert_eq!(
+            remote_url("HTTPS://example.com/ghost/", UPDATES2_DAU).unwrap(),
+            "HTTPS://example.com/ghost/updates2.dau"
+        );
+        assert!(remote_url("ftp://example.com/ghost/", UPDATES2_DAU).is_err());
+    }
+}
@@ -85,9 +85,184 @@ pub(crate) fn migrations() -> Vec<tauri_plugin_sql::Migration> {
             sql: "CREATE TABLE IF NOT EXISTS ghost_launches (\n  id INTEGER PRIMARY KEY AUTOINCREMENT,\n  ghost_identity_key TEXT NOT NULL,\n  launched_at TEXT NOT NULL\n);\nCREATE INDEX IF NOT EXISTS idx_ghost_launches_identity ON ghost_launches(ghost_identity_key);\nCREATE INDEX IF NOT EXISTS idx_ghost_launches_at ON ghost_launches(launched_at DESC);",
             kind: tauri_plugin_sql::MigrationKind::Up,
         },
+        tauri_plugin_sql::Migration {
+            version: 12,
+            description: "add_descript_fields_and_reset_ghosts_cache",
+            sql: "ALTER TABLE ghosts ADD COLUMN descript_id TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN ghost_type TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN shiori TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN homeurl TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN craftmanurl TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN readme TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN icon TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN balloon TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN sakura_default_surface INTEGER;\nALTER TABLE ghosts ADD COLUMN kero_default_surface INTEGER;\nALTER TABLE ghosts ADD COLUMN install_accept TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN descript_id_lower TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN shiori_lower TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN balloon_lower TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN homeurl_lower TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN craftmanurl_lower TEXT NOT NULL DEFAULT '';\nDELETE FROM ghosts;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 13,
+            description: "create_ghost_shells_table_and_reset_ghosts_cache",
+            sql: "CREATE TABLE IF NOT EXISTS ghost_shells (\n  id INTEGER PRIMARY KEY AUTOINCREMENT,\n  request_key TEXT NOT NULL,\n  ghost_identity_key TEXT NOT NULL,\n  name TEXT NOT NULL,\n  craftman TEXT NOT NULL,\n  directory_name TEXT NOT NULL,\n  path TEXT NOT NULL,\n  thumbnail_path TEXT NOT NULL,\n  thumbnail_use_self_alpha INTEGER NOT NULL,\n  name_lower TEXT NOT NULL,\n  craftman_lower TEXT NOT NULL,\n  directory_name_lower TEXT NOT NULL\n);\nCREATE INDEX IF NOT EXISTS idx_ghost_shells_ghost ON ghost_shells(request_key, ghost_identity_key);\nALTER TABLE ghosts ADD COLUMN shell_count INTEGER NOT NULL DEFAULT 0;\nDELETE FROM ghosts;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 14,
+            description: "add_thumbnail_mask_path_and_reset_ghosts_cache",
+            sql: "ALTER TABLE ghosts ADD COLUMN thumbnail_mask_path TEXT NOT NULL DEFAULT '';\nALTER TABLE ghost_shells ADD COLUMN thumbnail_mask_path TEXT NOT NULL DEFAULT '';\nDELETE FROM ghost_shells;\nDELETE FROM ghosts;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 15,
+            description: "add_thumbnail_cache_paths_and_reset_ghosts_cache",
+            sql: "ALTER TABLE ghosts ADD COLUMN thumbnail_card_path TEXT NOT NULL DEFAULT '';\nALTER TABLE ghosts ADD COLUMN thumbnail_preview_path TEXT NOT NULL DEFAULT '';\nDELETE FROM ghost_shells;\nDELETE FROM ghosts;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 16,
+            description: "create_balloons_table",
+            sql: "CREATE TABLE IF NOT EXISTS balloons (\n  id INTEGER PRIMARY KEY AUTOINCREMENT,\n  ssp_key TEXT NOT NULL,\n  balloon_identity_key TEXT NOT NULL,\n  name TEXT NOT NULL,\n  descript_id TEXT NOT NULL,\n  craftman TEXT NOT NULL,\n  craftmanw TEXT NOT NULL,\n  craftmanurl TEXT NOT NULL,\n  homeurl TEXT NOT NULL,\n  directory_name TEXT NOT NULL,\n  path TEXT NOT NULL,\n  thumbnail_path TEXT NOT NULL,\n  thumbnail_use_self_alpha INTEGER NOT NULL,\n  thumbnail_mask_path TEXT NOT NULL,\n  thumbnail_kind TEXT NOT NULL,\n  name_lower TEXT NOT NULL,\n  craftman_lower TEXT NOT NULL,\n  directory_name_lower TEXT NOT NULL,\n  updated_at TEXT NOT NULL\n);\nCREATE INDEX IF NOT EXISTS idx_balloons_ssp_key_name_lower ON balloons(ssp_key, name_lower);\nCREATE INDEX IF NOT EXISTS idx_balloons_identity_key ON balloons(balloon_identity_key);",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 17,
+            description: "add_shiori_engine_and_reset_ghosts_cache",
+            sql: "ALTER TABLE ghosts ADD COLUMN shiori_engine TEXT NOT NULL DEFAULT '';\nCREATE INDEX IF NOT EXISTS idx_ghosts_request_key_shiori_engine ON ghosts(request_key, shiori_engine);\nDELETE FROM ghost_shells;\nDELETE FROM ghosts;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 18,
+            description: "create_ghost_scan_skips_and_reset_fingerprints",
+            sql: "CREATE TABLE IF NOT EXISTS ghost_scan_skips (\n  id INTEGER PRIMARY KEY AUTOINCREMENT,\n  request_key TEXT NOT NULL,\n  source TEXT NOT NULL,\n  path TEXT NOT NULL,\n  reason TEXT NOT NULL,\n  detail TEXT NOT NULL\n);\nCREATE INDEX IF NOT EXISTS idx_ghost_scan_skips_request_key ON ghost_scan_skips(request_key);\nDELETE FROM ghost_fingerprints;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 19,
+            description: "add_ghost_entry_token",
+            sql: "ALTER TABLE ghosts ADD COLUMN entry_token TEXT NOT NULL DEFAULT '';",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
+        tauri_plugin_sql::Migration {
+            version: 20,
+            description: "create_ghosts_fts_and_reset_ghosts_cache",
+            sql: "CREATE VIRTUAL TABLE IF NOT EXISTS ghosts_fts USING fts5(body, tokenize='trigram');\nCREATE TRIGGER IF NOT EXISTS ghosts_fts_delete AFTER DELETE ON ghosts BEGIN\n  DELETE FROM ghosts_fts WHERE rowid = old.id;\nEND;\nDELETE FROM ghost_shells;\nDELETE FROM ghosts;\nDELETE FROM ghost_fingerprints;",
+            kind: tauri_plugin_sql::MigrationKind::Up,
+        },
     ]
 }
 
+/// マイグレーション適用前に ghosts.db の整合性を検証する。
+/// 未適用マイグレーションが ADD COLUMN しようとするカラムが既に存在する場合、
+/// DB ファイルを削除して再作成を促す。ghosts.db はキャッシュなので安全。
+fn sanitize_ghost_db(app: &tauri::App) {
+    let Ok(app_data_dir) = app.path().app_data_dir() else {
+        return;
+    };
+    let db_path = app_data_dir.join("ghosts.db");
+    if !db_path.exists() {
+        return;
+    }
+
+    let should_delete = match rusqlite::Connection::open(&db_path) {
+        Ok(conn) => has_migration_conflict(&conn),
+        Err(_) => true, // DB を開けない場合は削除して再作成
+    };
+
+    if should_delete {
+        for filename in ["ghosts.db", "ghosts.db-wal", "ghosts.db-shm"] {
+            let _ = std::fs::remove_file(app_data_dir.join(filename));
+        }
+    }
+}
+
+/// 未適用マイグレーションの ADD COLUMN が既存カラムと競合するか判定する。
+fn has_migration_conflict(conn: &rusqlite::Connection) -> bool {
+    let max_version: i64 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(0);
+
+    let pending: Vec<_> = migrations()
+        .into_iter()
+        .filter(|m| m.version > max_version)
+        .collect();
+    if pending.is_empty() {
+        return false;
+    }
+
+    let columns: Vec<String> = conn
+        .prepare("PRAGMA table_info(ghosts)")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(1))
+                .map(|rows| rows.filter_map(|r| r.ok()).collect())
+        })
+        .unwrap_or_default();
+    if columns.is_empty() {
+        return false;
+    }
+
+    // 未適用マイグレーションが追加しようとするカラムが既に存在するか
+    for m in &pending {
+        for fragment in m.sql.split("ADD COLUMN ").skip(1) {
+            if let Some(col_name) = fragment.split_whitespace().next()
+                && columns.iter().any(|c| c == col_name)
+            {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(
+            tauri_plugin_sql::Builder::default()
+                .add_migrations("sqlite:ghosts.db", migrations())
+                .build(),
+        )
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_store::Builder::default().build())
+        .plugin(tauri_plugin_window_state::Builder::default().build())
+        .manage(commands::ghost::watcher::GhostWatcherState::default())
+        .manage(commands::ghost::store::GhostWriteLock::default())
+        .manage(commands::ghost::coordinator::ScanCoordinator::default())
+        .setup(|app| {
+            sanitize_ghost_db(app);
+            Ok(())
+        })
+        // サムネイルはファイルパスを WebView に渡さず、identity key 経由でこのスキームから配信する
+        .register_asynchronous_uri_scheme_protocol(
+            commands::ghost::thumbnail::THUMBNAIL_SCHEME,
+            |ctx, request, responder| {
+                let app = ctx.app_handle().clone();
+                // DB 参照と画像生成でメインスレッドを塞がない
+                tauri::async_runtime::spawn_blocking(move || {
+                    responder.respond(commands::ghost::thumbnail::handle_thumbnail_request(
+                        &app, &request,
+                    ));
+                });
+            },
+        )
+        .invoke_handler(tauri::generate_handler![
+            commands::db::reset_ghost_db,
+            commands::ghost::scan_and_store,
+            commands::ghost::coordinator::cancel_ghost_scan,
+            commands::ghost::get_scan_report,
+            commands::ghost::search::search_ghosts,
+            commands::ghost::watcher::start_ghost_watcher,
+            commands::ghost::watcher::stop_ghost_watcher,
+            commands::balloon::scan_balloons,
+            commands::nar::install_nar,
+            commands::nar::package_ghost,
+            commands::update::verify_ghost,
+            commands::update::check_ghost_updates,
+            commands::update::apply_ghost_update,
+            commands::readme::read_ghost_readme,
+
+            commands::ssp::launch_ghost,
+            commands::ssp::validate_ssp_path,
+            commands::locale::read_user_locale,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
 #[cfg(test)]
 mod tests {
     use super::{has_migration_conflict, migrations};
@@ -147,7 +322,7 @@ mod tests {
             conn.execute_batch(m.sql).unwrap();
             conn.execute(
                 "INSERT INTO _sqlx_migrations (version) VALUES (?1)",
-                [m.version as i64],
+                [m.version],
             )
             .unwrap();
         }
@@ -168,7 +343,7 @@ mod tests {
             conn.execute_batch(m.sql).unwrap();
             conn.execute(
                 "INSERT INTO _sqlx_migrations (version) VALUES (?1)",
-                [m.version as i64],
+                [m.version],
             )
             .unwrap();
         }
@@ -191,7 +366,7 @@ mod tests {
             conn.execute_batch(m.sql).unwrap();
             conn.execute(
                 "INSERT INTO _sqlx_migrations (version) VALUES (?1)",
-                [m.version as i64],
+                [m.version],
             )
             .unwrap();
         }
@@ -199,96 +374,3 @@ mod tests {
         assert!(!has_migration_conflict(&conn));
     }
 }
-
-/// マイグレーション適用前に ghosts.db の整合性を検証する。
-/// 未適用マイグレーションが ADD COLUMN しようとするカラムが既に存在する場合、
-/// DB ファイルを削除して再作成を促す。ghosts.db はキャッシュなので安全。
-fn sanitize_ghost_db(app: &tauri::App) {
-    let Ok(app_data_dir) = app.path().app_data_dir() else {
-        return;
-    };
-    let db_path = app_data_dir.join("ghosts.db");
-    if !db_path.exists() {
-        return;
-    }
-
-    let should_delete = match rusqlite::Connection::open(&db_path) {
-        Ok(conn) => has_migration_conflict(&conn),
-        Err(_) => true, // DB を開けない場合は削除して再作成
-    };
-
-    if should_delete {
-        for filename in ["ghosts.db", "ghosts.db-wal", "ghosts.db-shm"] {
-            let _ = std::fs::remove_file(app_data_dir.join(filename));
-        }
-    }
-}
-
-/// 未適用マイグレーションの ADD COLUMN が既存カラムと競合するか判定する。
-fn has_migration_conflict(conn: &rusqlite::Connection) -> bool {
-    let max_version: i64 = conn
-        .query_row(
-            "SELECT COALESCE(MAX(version), 0) FROM _sqlx_migrations",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
-
-    let pending: Vec<_> = migrations()
-        .into_iter()
-        .filter(|m| m.version as i64 > max_version)
-        .collect();
-    if pending.is_empty() {
-        return false;
-    }
-
-    let columns: Vec<String> = conn
-        .prepare("PRAGMA table_info(ghosts)")
-        .and_then(|mut stmt| {
-            stmt.query_map([], |row| row.get::<_, String>(1))
-                .map(|rows| rows.filter_map(|r| r.ok()).collect())
-        })
-        .unwrap_or_default();
-    if columns.is_empty() {
-        return false;
-    }
-
-    // 未適用マイグレーションが追加しようとするカラムが既に存在するか
-    for m in &pending {
-        for fragment in m.sql.split("ADD COLUMN ").skip(1) {
-            if let Some(col_name) = fragment.split_whitespace().next() {
-                if columns.iter().any(|c| c == col_name) {
-                    return true;
-                }
-            }
-        }
-    }
-    false
-}
-
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
-        .plugin(
-            tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:ghosts.db", migrations())
-                .build(),
-        )
-        .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_store::Builder::default().build())
-        .plugin(tauri_plugin_window_state::Builder::default().build())
-        .setup(|app| {
-            sanitize_ghost_db(app);
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            commands::db::reset_ghost_db,
-            commands::ghost::scan_and_store,
-
-            commands::ssp::launch_ghost,
-            commands::ssp::validate_ssp_path,
-            commands::locale::read_user_locale,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
@@ -25,7 +25,7 @@ vi.mock("./hooks/useSettings", () => ({
 // useGhosts / useSearch の戻り値はテストごとに差し替える。GhostContent が受け取る
 // props を捕捉し、App の合成ロジック（requestKey ゲート・エラー抑制）を検証する
 const mocks = vi.hoisted(() => ({
-  ghostsState: { loading: true, error: null as string | null, refresh: () => {} },
+  ghostsState: { loading: true, error: null as string | null, refresh: () => {}, liveUpdateSeq: 0, scanProgress: null, cancelScan: () => {} },
   searchState: {
     ghosts: [] as unknown[],
     total: 0,
@@ -76,7 +76,10 @@ function App() {
     loading: settingsLoading,
     languageApplying,
   } = useSettings();
-  const { loading: ghostsLoading, error, refresh } = useGhosts(sspPath, ghostFolders);
+  const { loading: ghostsLoading, error, refresh, liveUpdateSeq, scanProgress, cancelScan } = useGhosts(
+    sspPath,
+    ghostFolders,
+  );
   const [searchQuery, setSearchQuery] = useState("");
   const [sortOrder, setSortOrder] = useState<SortOrder>("name");
   const deferredSearchQuery = useDeferredValue(searchQuery);
@@ -95,6 +98,7 @@ function App() {
     sspPath,
     deferredSearchQuery,
     ghostsLoading,
+    liveUpdateSeq,
   });
 
   // キャッシュ即時表示（stale-while-revalidate）: sspPath 確定時点で DB を引き、
@@ -169,7 +173,9 @@ function App() {
         <AppHeader
           sspPath={sspPath}
           ghostsLoading={ghostsLoading}
+          scanProgress={scanProgress}
           onRefresh={handleRefresh}
+          onCancelScan={cancelScan}
           onOpenSettings={handleOpenSettings}
         />
         <GhostContent
@@ -1,5 +1,5 @@
 import { describe, it, expect, vi } from "vitest";
-import { render, screen } from "@testing-library/react";
+import { fireEvent, render, screen } from "@testing-library/react";
 import { AppHeader } from "./AppHeader";
 
 vi.mock("react-i18next", () => ({
@@ -12,7 +12,9 @@ describe("AppHeader", () => {
       <AppHeader
         sspPath="C:/SSP"
         ghostsLoading={false}
+        scanProgress={null}
         onRefresh={vi.fn()}
+        onCancelScan={vi.fn()}
         onOpenSettings={vi.fn()}
       />,
     );
@@ -25,11 +27,45 @@ describe("AppHeader", () => {
       <AppHeader
         sspPath={null}
         ghostsLoading={false}
+        scanProgress={null}
         onRefresh={vi.fn()}
+        onCancelScan={vi.fn()}
         onOpenSettings={vi.fn()}
       />,
     );
     expect(screen.queryByText("header.refresh")).not.toBeInTheDocument();
     expect(screen.getByTestId("settings-button")).toBeInTheDocument();
   });
+
+  it("スキャン中は進捗と中止ボタンを表示し、中止ボタンで onCancelScan を呼ぶ", () => {
+    const onCancelScan = vi.fn();
+    render(
+      <AppHeader
+        sspPath="C:/SSP"
+        ghostsLoading={true}
+        scanProgress={{ scan_id: "scan-1", parents_done: 0, parents_total: 1, entries_processed: 10, ghosts_found: 3 }}
+        onRefresh={vi.fn()}
+        onCancelScan={onCancelScan}
+        onOpenSettings={vi.fn()}
+      />,
+    );
+    expect(screen.getByTestId("scan-progress")).toHaveTextContent("header.scanProgress");
+
+    fireEvent.click(screen.getByTestId("cancel-scan-button"));
+    expect(onCancelScan).toHaveBeenCalledTimes(1);
+  });
+
+  it("スキャンしていなければ中止ボタンは表示されない", () => {
+    render(
+      <AppHeader
+        sspPath="C:/SSP"
+        ghostsLoading={false}
+        scanProgress={null}
+        onRefresh={vi.fn()}
+        onCancelScan={vi.fn()}
+        onOpenSettings={vi.fn()}
+      />,
+    );
+    expect(screen.queryByTestId("cancel-scan-button")).not.toBeInTheDocument();
+  });
 });
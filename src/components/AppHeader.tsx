@@ -1,12 +1,15 @@
 import { memo } from "react";
 import { useTranslation } from "react-i18next";
 import { Button, Text, makeStyles, tokens } from "@fluentui/react-components";
-import { ArrowClockwiseRegular, SettingsRegular } from "@fluentui/react-icons";
+import { ArrowClockwiseRegular, DismissRegular, SettingsRegular } from "@fluentui/react-icons";
+import type { ScanProgressEvent } from "../lib/ghostScanProgress";
 
 interface Props {
   sspPath: string | null;
   ghostsLoading: boolean;
+  scanProgress: ScanProgressEvent | null;
   onRefresh: () => void;
+  onCancelScan: () => void;
   onOpenSettings: () => void;
 }
 
@@ -39,9 +42,19 @@ const useStyles = makeStyles({
     lineHeight: tokens.lineHeightBase600,
     fontWeight: tokens.fontWeightSemibold,
   },
+  scanProgress: {
+    color: tokens.colorNeutralForeground3,
+  },
 });
 
-export const AppHeader = memo(function AppHeader({ sspPath, ghostsLoading, onRefresh, onOpenSettings }: Props) {
+export const AppHeader = memo(function AppHeader({
+  sspPath,
+  ghostsLoading,
+  scanProgress,
+  onRefresh,
+  onCancelScan,
+  onOpenSettings,
+}: Props) {
   const styles = useStyles();
   const { t } = useTranslation();
 
@@ -63,6 +76,21 @@ export const AppHeader = memo(function AppHeader({ sspPath, ghostsLoading, onRef
             {t("header.refresh")}
           </Button>
         )}
+        {sspPath && ghostsLoading && (
+          <>
+            {scanProgress && (
+              <Text size={200} className={styles.scanProgress} data-testid="scan-progress">
+                {t("header.scanProgress", {
+                  entries: scanProgress.entries_processed,
+                  ghosts: scanProgress.ghosts_found,
+                })}
+              </Text>
+            )}
+            <Button icon={<DismissRegular />} appearance="subtle" onClick={onCancelScan} data-testid="cancel-scan-button">
+              {t("header.cancelScan")}
+            </Button>
+          </>
+        )}
         <Button icon={<SettingsRegular />} appearance="secondary" onClick={onOpenSettings} data-testid="settings-button">
           {t("header.settings")}
         </Button>
@@ -13,11 +13,20 @@ const makeGhost = (overrides?: Partial<GhostView>): GhostView => ({
   kero_name: "",
   craftman: "",
   craftmanw: "",
+  descript_id: "",
+  ghost_type: "",
+  shiori: "",
+  homeurl: "",
+  craftmanurl: "",
+  readme: "",
+  icon: "",
+  balloon: "",
+  sakura_default_surface: null,
+  kero_default_surface: null,
+  install_accept: "",
   directory_name: "test_ghost",
   path: "/test/path",
   source: "ssp",
-  thumbnail_path: "",
-  thumbnail_use_self_alpha: false,
   thumbnail_kind: "",
   name_lower: "",
   sakura_name_lower: "",
@@ -25,6 +34,13 @@ const makeGhost = (overrides?: Partial<GhostView>): GhostView => ({
   craftman_lower: "",
   craftmanw_lower: "",
   directory_name_lower: "",
+  descript_id_lower: "",
+  shiori_lower: "",
+  balloon_lower: "",
+  homeurl_lower: "",
+  craftmanurl_lower: "",
+  shell_count: 0,
+  shiori_engine: "unknown",
   ...overrides,
 });
 
@@ -1,7 +1,7 @@
 import { memo, useCallback, useEffect, useLayoutEffect, useRef, useState } from "react";
 import { createPortal } from "react-dom";
 import { useTranslation } from "react-i18next";
-import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+import { invoke } from "@tauri-apps/api/core";
 import {
   Badge,
   Button,
@@ -15,6 +15,7 @@ import { PlayRegular } from "@fluentui/react-icons";
 import { getSourceFolderLabel } from "../lib/ghostLaunchUtils";
 import { formatErrorDetail } from "../lib/ghostScanUtils";
 import { recordLaunch } from "../lib/ghostDatabase";
+import { ghostThumbnailUrl } from "../lib/ghostThumbnail";
 import type { GhostView } from "../types";
 
 interface Props {
@@ -155,42 +156,6 @@ const TruncatedText = memo(function TruncatedText({
   );
 });
 
-// KeyColor 透過: 左上ピクセルをキーカラーとして透明化する canvas コンポーネント
-const ThumbnailCanvas = memo(function ThumbnailCanvas({ src, className }: { src: string; className?: string }) {
-  const canvasRef = useRef<HTMLCanvasElement>(null);
-  useEffect(() => {
-    const canvas = canvasRef.current;
-    if (!canvas) return;
-    const ctx = canvas.getContext("2d");
-    if (!ctx) return;
-    let cancelled = false;
-    const img = new Image();
-    img.crossOrigin = "anonymous";
-    img.onload = () => {
-      if (cancelled) return;
-      canvas.width = img.naturalWidth;
-      canvas.height = img.naturalHeight;
-      ctx.drawImage(img, 0, 0);
-      try {
-        const imageData = ctx.getImageData(0, 0, canvas.width, canvas.height);
-        const data = imageData.data;
-        const [keyR, keyG, keyB] = [data[0], data[1], data[2]];
-        for (let i = 0; i < data.length; i += 4) {
-          if (data[i] === keyR && data[i + 1] === keyG && data[i + 2] === keyB) {
-            data[i + 3] = 0;
-          }
-        }
-        ctx.putImageData(imageData, 0, 0);
-      } catch {
-        // CORS 等で getImageData が失敗した場合はそのまま表示
-      }
-    };
-    img.src = src;
-    return () => { cancelled = true; };
-  }, [src]);
-  return <canvas ref={canvasRef} className={className} style={overlayMaxSize} />;
-});
-
 export const GhostCard = memo(function GhostCard({ ghost, sspPath }: Props) {
   const styles = useStyles();
   const { t } = useTranslation();
@@ -241,7 +206,11 @@ export const GhostCard = memo(function GhostCard({ ghost, sspPath }: Props) {
     ? `${ghost.directory_name} | ${ghost.craftman}`
     : ghost.directory_name;
 
-  const thumbnailSrc = ghost.thumbnail_path ? convertFileSrc(ghost.thumbnail_path) : null;
+  // 透過・縮小済みの画像を Rust から受け取る（キーカラー・.pna の処理は Rust 側で済んでいる）
+  const thumbnailSrc =
+    ghost.thumbnail_kind && ghost.ghost_identity_key
+      ? ghostThumbnailUrl(ghost.ghost_identity_key, "preview")
+      : null;
 
   const overlayPositionStyle: React.CSSProperties = {
     bottom: ghost.thumbnail_kind === "surface" ? 0 : "20px",
@@ -295,11 +264,7 @@ export const GhostCard = memo(function GhostCard({ ghost, sspPath }: Props) {
       )}
       {showOverlay && thumbnailSrc && createPortal(
         <div className={styles.overlay} style={overlayPositionStyle}>
-          {Boolean(ghost.thumbnail_use_self_alpha) ? (
-            <img src={thumbnailSrc} alt="" className={roundedClass} style={overlayMaxSize} />
-          ) : (
-            <ThumbnailCanvas src={thumbnailSrc} className={roundedClass} />
-          )}
+          <img src={thumbnailSrc} alt="" className={roundedClass} style={overlayMaxSize} />
         </div>,
         document.body
       )}
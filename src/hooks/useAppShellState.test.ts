@@ -81,6 +81,22 @@ describe("useAppShellState", () => {
     expect(result.current.refreshTrigger).toBe(2);
   });
 
+  it("liveUpdateSeq が増えたら offset を保ったまま refreshTrigger を増やす", () => {
+    const { result, rerender } = renderHook((props) => useAppShellState(props), {
+      initialProps: createProps({ ghostsLoading: false, liveUpdateSeq: 0 }),
+    });
+
+    act(() => {
+      result.current.setOffset(500);
+    });
+    expect(result.current.refreshTrigger).toBe(0);
+
+    rerender(createProps({ ghostsLoading: false, liveUpdateSeq: 1 }));
+
+    expect(result.current.refreshTrigger).toBe(1);
+    expect(result.current.offset).toBe(500);
+  });
+
   it("openSettings/closeSettings で設定ダイアログ状態を変更できる", () => {
     const { result } = renderHook((props) => useAppShellState(props), {
       initialProps: createProps(),
@@ -5,6 +5,8 @@ interface UseAppShellStateParams {
   sspPath: string | null;
   deferredSearchQuery: string;
   ghostsLoading: boolean;
+  /// フォルダ監視による更新の通し番号（useGhosts の liveUpdateSeq）
+  liveUpdateSeq?: number;
 }
 
 export function useAppShellState({
@@ -12,6 +14,7 @@ export function useAppShellState({
   sspPath,
   deferredSearchQuery,
   ghostsLoading,
+  liveUpdateSeq = 0,
 }: UseAppShellStateParams) {
   const [settingsOpen, setSettingsOpen] = useState(false);
   const [offset, setOffset] = useState(0);
@@ -36,6 +39,13 @@ export function useAppShellState({
     prevLoadingRef.current = ghostsLoading;
   }, [ghostsLoading]);
 
+  // 監視による更新は再クエリだけ行い、スクロール位置（offset）は保つ
+  useEffect(() => {
+    if (liveUpdateSeq > 0) {
+      setRefreshTrigger((prev) => prev + 1);
+    }
+  }, [liveUpdateSeq]);
+
   const openSettings = useCallback(() => setSettingsOpen(true), []);
   const closeSettings = useCallback(() => setSettingsOpen(false), []);
 
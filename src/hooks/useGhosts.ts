@@ -1,6 +1,8 @@
 import { useState, useEffect, useCallback, useRef } from "react";
 import { refreshGhostCatalog } from "../lib/ghostCatalogService";
 import { requestKeyFromSettings, buildScanErrorMessage } from "../lib/ghostScanUtils";
+import { onGhostsChanged, startGhostWatcher, stopGhostWatcher } from "../lib/ghostWatcher";
+import { cancelGhostScan, createScanId, onScanProgress, type ScanProgressEvent } from "../lib/ghostScanProgress";
 
 interface RefreshOptions {
   forceFullScan?: boolean;
@@ -9,7 +11,12 @@ interface RefreshOptions {
 export function useGhosts(sspPath: string | null, ghostFolders: string[]) {
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState<string | null>(null);
-  const inFlightKeyRef = useRef<string | null>(null);
+  // フォルダ監視が ghosts テーブルを書き換えるたびに増える（一覧の再クエリのきっかけ）
+  const [liveUpdateSeq, setLiveUpdateSeq] = useState(0);
+  // 実行中のスキャンの進捗（スキャンしていなければ null）
+  const [scanProgress, setScanProgress] = useState<ScanProgressEvent | null>(null);
+  // 結果を待っているスキャン（scanId → requestKey）。同じ依頼の重複は Rust 側のスキャン調停が 1 回にまとめる
+  const activeScansRef = useRef(new Map<string, string>());
   const requestSeqRef = useRef(0);
 
   const ghostFoldersKey = JSON.stringify(ghostFolders);
@@ -25,47 +32,82 @@ export function useGhosts(sspPath: string | null, ghostFolders: string[]) {
 
     const requestKey = requestKeyFromSettings(sspPath, ghostFoldersRef.current);
     const forceFullScan = options.forceFullScan === true;
-    const inFlightKey = `${requestKey}::${forceFullScan ? "force" : "auto"}`;
-
-    if (inFlightKeyRef.current === inFlightKey) {
-      return;
-    }
 
     const requestSeq = requestSeqRef.current + 1;
     requestSeqRef.current = requestSeq;
-    inFlightKeyRef.current = inFlightKey;
+
+    // 設定変更で別の requestKey になったスキャンの結果は使わないので打ち切る
+    for (const [activeScanId, activeRequestKey] of activeScansRef.current) {
+      if (activeRequestKey !== requestKey) {
+        void cancelGhostScan(activeScanId).catch(() => {});
+      }
+    }
+    const scanId = createScanId();
+    activeScansRef.current.set(scanId, requestKey);
 
     try {
       setError(null);
       setLoading(true);
+      setScanProgress(null);
 
-      await refreshGhostCatalog({
-        sspPath,
-        ghostFolders: ghostFoldersRef.current,
-        forceFullScan,
+      const unlistenProgress = await onScanProgress(scanId, (progress) => {
+        if (requestSeq === requestSeqRef.current) {
+          setScanProgress(progress);
+        }
       });
+      let cancelled: boolean;
+      try {
+        ({ cancelled } = await refreshGhostCatalog({
+          sspPath,
+          ghostFolders: ghostFoldersRef.current,
+          forceFullScan,
+          scanId,
+        }));
+      } finally {
+        unlistenProgress();
+      }
 
-      if (requestSeq === requestSeqRef.current) {
+      if (requestSeq === requestSeqRef.current && !cancelled) {
         setError(null);
+        // スキャン後の変更は監視で反映する（監視できなくても一覧は使えるので失敗は無視する）
+        void startGhostWatcher(sspPath, ghostFoldersRef.current).catch(() => {});
       }
     } catch (e) {
       if (requestSeq === requestSeqRef.current) {
         setError(buildScanErrorMessage(e));
       }
     } finally {
+      activeScansRef.current.delete(scanId);
       if (requestSeq === requestSeqRef.current) {
         setLoading(false);
-      }
-      if (inFlightKeyRef.current === inFlightKey) {
-        inFlightKeyRef.current = null;
+        setScanProgress(null);
       }
     }
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [sspPath, ghostFoldersKey]);
 
+  // 結果を待っているスキャンをすべて打ち切る（DB は前の内容のまま残る）
+  const cancelScan = useCallback(() => {
+    for (const scanId of activeScansRef.current.keys()) {
+      void cancelGhostScan(scanId).catch(() => {});
+    }
+  }, []);
+
   useEffect(() => {
     refresh();
   }, [refresh]);
 
-  return { loading, error, refresh };
+  useEffect(() => {
+    if (!sspPath) {
+      return;
+    }
+    const requestKey = requestKeyFromSettings(sspPath, ghostFoldersRef.current);
+    const unlisten = onGhostsChanged(requestKey, () => setLiveUpdateSeq((prev) => prev + 1));
+    return () => {
+      void unlisten.then((fn) => fn());
+      void stopGhostWatcher().catch(() => {});
+    };
+  }, [sspPath, ghostFoldersKey]);
+
+  return { loading, error, refresh, liveUpdateSeq, scanProgress, cancelScan };
 }
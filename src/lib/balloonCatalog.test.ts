@@ -0,0 +1,39 @@
+import { describe, it, expect, vi, beforeEach } from "vitest";
+import { listBalloons, refreshBalloonCatalog } from "./balloonCatalog";
+import { getDb } from "./ghostDatabase";
+import { invoke } from "@tauri-apps/api/core";
+import { requestKeyFromSettings } from "./ghostScanUtils";
+
+vi.mock("./ghostDatabase", () => ({
+  getDb: vi.fn(),
+}));
+
+describe("refreshBalloonCatalog", () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  it("ssp_key と request_key を付けて scan_balloons を呼ぶ", async () => {
+    vi.mocked(invoke).mockResolvedValue({ total: 2, missing_balloons: [] });
+
+    const result = await refreshBalloonCatalog("C:\\SSP\\", ["C:/Ghosts"]);
+
+    expect(result.total).toBe(2);
+    expect(invoke).toHaveBeenCalledWith("scan_balloons", {
+      sspPath: "C:\\SSP\\",
+      sspKey: "c:/ssp",
+      requestKey: requestKeyFromSettings("C:\\SSP\\", ["C:/Ghosts"]),
+    });
+  });
+});
+
+describe("listBalloons", () => {
+  it("正規化した ssp_key で balloons テーブルを引く", async () => {
+    const select = vi.fn().mockResolvedValue([]);
+    vi.mocked(getDb).mockResolvedValue({ select } as never);
+
+    await listBalloons("C:/SSP");
+
+    expect(select).toHaveBeenCalledWith(expect.stringContaining("FROM balloons WHERE ssp_key = ?"), ["c:/ssp"]);
+  });
+});
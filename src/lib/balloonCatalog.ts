@@ -0,0 +1,26 @@
+import { invoke } from "@tauri-apps/api/core";
+import { BalloonView } from "../types";
+import type { BalloonScanResult } from "../types/generated/BalloonScanResult";
+import { getDb } from "./ghostDatabase";
+import { normalizePathKey, requestKeyFromSettings } from "./ghostScanUtils";
+
+/// SSP/balloon を走査して balloons テーブルを更新し、デフォルトバルーンが未インストールのゴーストを返す。
+/// ゴーストの scan_and_store 後に呼ぶ（ghosts テーブルと突き合わせるため）。
+export async function refreshBalloonCatalog(sspPath: string, ghostFolders: string[]): Promise<BalloonScanResult> {
+  return invoke<BalloonScanResult>("scan_balloons", {
+    sspPath,
+    sspKey: normalizePathKey(sspPath),
+    requestKey: requestKeyFromSettings(sspPath, ghostFolders),
+  });
+}
+
+const BALLOON_SELECT_COLUMNS =
+  "name, descript_id, craftman, craftmanw, craftmanurl, homeurl, directory_name, path, thumbnail_kind, balloon_identity_key";
+
+export async function listBalloons(sspPath: string): Promise<BalloonView[]> {
+  const db = await getDb();
+  return db.select<BalloonView[]>(
+    `SELECT ${BALLOON_SELECT_COLUMNS} FROM balloons WHERE ssp_key = ? ORDER BY name_lower, directory_name_lower`,
+    [normalizePathKey(sspPath)]
+  );
+}
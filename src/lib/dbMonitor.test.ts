@@ -108,7 +108,7 @@ describe("dbMonitor - reportScanComplete", () => {
     const spy = vi.spyOn(console, "log").mockImplementation(() => {});
     const { reportScanComplete } = await loadModule();
     reportScanComplete(
-      { cache_hit: false, total: 500, fingerprint: "fp", request_key: "rk" },
+      { cache_hit: false, total: 500, fingerprint: "fp", request_key: "rk", cancelled: false },
       1234,
     );
     const logCall = spy.mock.calls.find((c) =>
@@ -126,7 +126,7 @@ describe("dbMonitor - reportScanComplete", () => {
     vi.spyOn(console, "log").mockImplementation(() => {});
     const { reportScanComplete } = await loadModule();
     reportScanComplete(
-      { cache_hit: false, total: 100_001, fingerprint: "fp", request_key: "rk" },
+      { cache_hit: false, total: 100_001, fingerprint: "fp", request_key: "rk", cancelled: false },
       100,
     );
     const alertCall = warnSpy.mock.calls.find((c) =>
@@ -140,7 +140,7 @@ describe("dbMonitor - reportScanComplete", () => {
     vi.spyOn(console, "log").mockImplementation(() => {});
     const { reportScanComplete } = await loadModule();
     reportScanComplete(
-      { cache_hit: false, total: 100_000, fingerprint: "fp", request_key: "rk" },
+      { cache_hit: false, total: 100_000, fingerprint: "fp", request_key: "rk", cancelled: false },
       100,
     );
     const alertCall = warnSpy.mock.calls.find((c) =>
@@ -3,6 +3,7 @@ import { refreshGhostCatalog } from "./ghostCatalogService";
 import { cleanupOldGhostCaches, getCachedFingerprint, hasGhosts } from "./ghostDatabase";
 import { invoke } from "@tauri-apps/api/core";
 import { requestKeyFromSettings } from "./ghostScanUtils";
+import { reportScanComplete } from "./dbMonitor";
 
 vi.mock("./ghostDatabase", () => ({
   hasGhosts: vi.fn(),
@@ -25,12 +26,13 @@ describe("refreshGhostCatalog", () => {
   it("キャッシュが有効ならスキャンをスキップする", async () => {
     vi.mocked(getCachedFingerprint).mockResolvedValue("fp1");
     vi.mocked(hasGhosts).mockResolvedValue(true);
-    vi.mocked(invoke).mockResolvedValue({ cache_hit: true, total: 0, fingerprint: "fp1", request_key: "c:/ssp::" });
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: true, total: 0, fingerprint: "fp1", request_key: "c:/ssp::", cancelled: false });
 
     const result = await refreshGhostCatalog({
       sspPath: "C:/SSP",
       ghostFolders: ["C:/Ghosts"],
       forceFullScan: false,
+      scanId: "scan-1",
     });
 
     expect(result.skipped).toBe(true);
@@ -42,12 +44,13 @@ describe("refreshGhostCatalog", () => {
   it("キャッシュが無効なら scan_and_store がDB書き込みまで行う", async () => {
     vi.mocked(getCachedFingerprint).mockResolvedValue("fp1");
     vi.mocked(hasGhosts).mockResolvedValue(true);
-    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 1, fingerprint: "fp2", request_key: "c:/ssp::c:/ghosts" });
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 1, fingerprint: "fp2", request_key: "c:/ssp::c:/ghosts", cancelled: false });
 
     const result = await refreshGhostCatalog({
       sspPath: "C:/SSP",
       ghostFolders: ["C:/Ghosts"],
       forceFullScan: false,
+      scanId: "scan-1",
     });
 
     expect(result.skipped).toBe(false);
@@ -57,12 +60,13 @@ describe("refreshGhostCatalog", () => {
   it("DB に ghost が無ければ cachedFingerprint=null でフルスキャンする", async () => {
     vi.mocked(getCachedFingerprint).mockResolvedValue("fp1");
     vi.mocked(hasGhosts).mockResolvedValue(false);
-    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 1, fingerprint: "fp1", request_key: "c:/ssp::" });
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 1, fingerprint: "fp1", request_key: "c:/ssp::", cancelled: false });
 
     await refreshGhostCatalog({
       sspPath: "C:/SSP",
       ghostFolders: [],
       forceFullScan: false,
+      scanId: "scan-1",
     });
 
     expect(invoke).toHaveBeenCalledWith("scan_and_store", expect.objectContaining({
@@ -71,12 +75,13 @@ describe("refreshGhostCatalog", () => {
   });
 
   it("forceFullScan のときはキャッシュ判定を行わず scan_and_store を呼ぶ", async () => {
-    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 0, fingerprint: "fp3", request_key: "c:/ssp::" });
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 0, fingerprint: "fp3", request_key: "c:/ssp::", cancelled: false });
 
     await refreshGhostCatalog({
       sspPath: "C:/SSP",
       ghostFolders: [],
       forceFullScan: true,
+      scanId: "scan-1",
     });
 
     expect(getCachedFingerprint).not.toHaveBeenCalled();
@@ -86,16 +91,33 @@ describe("refreshGhostCatalog", () => {
       additionalFolders: [],
       requestKey: "c:/ssp::",
       cachedFingerprint: null,
+      scanId: "scan-1",
     });
   });
 
+  it("打ち切られたスキャンは skipped として返し、掃除もレポートもしない", async () => {
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 0, fingerprint: "", request_key: "c:/ssp::", cancelled: true });
+
+    const result = await refreshGhostCatalog({
+      sspPath: "C:/SSP",
+      ghostFolders: [],
+      forceFullScan: true,
+      scanId: "scan-1",
+    });
+
+    expect(result).toEqual({ skipped: true, cancelled: true });
+    expect(reportScanComplete).not.toHaveBeenCalled();
+    expect(cleanupOldGhostCaches).not.toHaveBeenCalled();
+  });
+
   it("cache miss 後に古い request_key キャッシュを掃除する", async () => {
-    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 0, fingerprint: "fp3", request_key: "c:/ssp::" });
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 0, fingerprint: "fp3", request_key: "c:/ssp::", cancelled: false });
 
     await refreshGhostCatalog({
       sspPath: "C:/SSP",
       ghostFolders: [],
       forceFullScan: true,
+      scanId: "scan-1",
     });
 
     // fire-and-forget なので await 不要だが、呼ばれたことは確認
@@ -105,24 +127,26 @@ describe("refreshGhostCatalog", () => {
   it("cache_hit 時は cleanupOldGhostCaches を呼ばない", async () => {
     vi.mocked(getCachedFingerprint).mockResolvedValue("fp1");
     vi.mocked(hasGhosts).mockResolvedValue(true);
-    vi.mocked(invoke).mockResolvedValue({ cache_hit: true, total: 0, fingerprint: "fp1", request_key: "c:/ssp::" });
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: true, total: 0, fingerprint: "fp1", request_key: "c:/ssp::", cancelled: false });
 
     await refreshGhostCatalog({
       sspPath: "C:/SSP",
       ghostFolders: [],
       forceFullScan: false,
+      scanId: "scan-1",
     });
 
     expect(cleanupOldGhostCaches).not.toHaveBeenCalled();
   });
 
   it("requestKey に requestKeyFromSettings の出力を渡す", async () => {
-    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 0, fingerprint: "fp", request_key: "x" });
+    vi.mocked(invoke).mockResolvedValue({ cache_hit: false, total: 0, fingerprint: "fp", request_key: "x", cancelled: false });
 
     await refreshGhostCatalog({
       sspPath: "C:/SSP",
       ghostFolders: ["C:/Ghosts"],
       forceFullScan: true,
+      scanId: "scan-1",
     });
 
     expect(invoke).toHaveBeenCalledWith("scan_and_store", expect.objectContaining({
@@ -8,16 +8,21 @@ export interface RefreshGhostCatalogParams {
   sspPath: string;
   ghostFolders: string[];
   forceFullScan: boolean;
+  /// cancelGhostScan で打ち切るときの ID（createScanId で作る）
+  scanId: string;
 }
 
 export interface RefreshGhostCatalogResult {
   skipped: boolean;
+  /// スキャンが打ち切られた（DB は前の内容のまま）
+  cancelled: boolean;
 }
 
 export async function refreshGhostCatalog({
   sspPath,
   ghostFolders,
   forceFullScan,
+  scanId,
 }: RefreshGhostCatalogParams): Promise<RefreshGhostCatalogResult> {
   const additionalFolders = buildAdditionalFolders(ghostFolders);
   const requestKey = buildRequestKey(sspPath, additionalFolders);
@@ -38,11 +43,15 @@ export async function refreshGhostCatalog({
     additionalFolders,
     requestKey,
     cachedFingerprint,
+    scanId,
   });
   const scanDurationMs = Math.round(performance.now() - scanStart);
 
+  if (result.cancelled) {
+    return { skipped: true, cancelled: true };
+  }
   if (result.cache_hit) {
-    return { skipped: true };
+    return { skipped: true, cancelled: false };
   }
 
   reportScanComplete(result, scanDurationMs);
@@ -55,5 +64,5 @@ export async function refreshGhostCatalog({
     console.warn("[ghostCatalogService] キャッシュ寿命管理のクリーンアップに失敗しました", error);
   });
 
-  return { skipped: false };
+  return { skipped: false, cancelled: false };
 }
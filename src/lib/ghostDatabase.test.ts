@@ -221,12 +221,12 @@ describe("ghostDatabase - searchGhosts NFKC正規化", () => {
   it("全角英字クエリを NFKC 正規化してから小文字化した LIKE パターンで検索する", async () => {
     mockSelect.mockResolvedValue([{ count: 0 }]);
     const { searchGhosts } = await import("./ghostDatabase");
-    await searchGhosts("rk1", "Ａｌｉｃｅ", 50, 0);
+    await searchGhosts("rk1", "Ａｌ", 50, 0);
 
     const countCall = mockSelect.mock.calls.find((c) =>
       (c[0] as string).includes("COUNT(*)"));
     expect(countCall).toBeDefined();
-    expect(countCall![1][1]).toBe("%alice%");
+    expect(countCall![1][1]).toBe("%al%");
   });
 });
 
@@ -267,15 +267,95 @@ describe("ghostDatabase - countGhostsByQuery", () => {
   it("非空クエリ時は NFKC 正規化した LIKE で件数取得する", async () => {
     mockSelect.mockResolvedValue([{ count: 1 }]);
     const { countGhostsByQuery } = await import("./ghostDatabase");
-    const total = await countGhostsByQuery("rk1", "Ａｌｉｃｅ");
+    const total = await countGhostsByQuery("rk1", "Ａｌ");
 
     expect(total).toBe(1);
     const call = mockSelect.mock.calls.find((c) =>
       (c[0] as string).includes("COUNT(*)"));
     expect(call).toBeDefined();
-    expect(call![1][1]).toBe("%alice%");
+    expect(call![1][1]).toBe("%al%");
+  });
+
+  it("シェル名（ghost_shells.name_lower）も検索対象に含める", async () => {
+    mockSelect.mockResolvedValue([{ count: 1 }]);
+    const { countGhostsByQuery } = await import("./ghostDatabase");
+    await countGhostsByQuery("rk1", "冬服");
+
+    const call = mockSelect.mock.calls.find((c) =>
+      (c[0] as string).includes("COUNT(*)"));
+    expect(call).toBeDefined();
+    const sql = call![0] as string;
+    expect(sql).toContain("FROM ghost_shells s");
+    expect(sql).toContain("s.name_lower LIKE ?");
+    const placeholderCount = sql.split("?").length - 1;
+    expect(call![1]).toHaveLength(placeholderCount);
+    expect(call![1][placeholderCount - 1]).toBe("%冬服%");
+  });
+});
+describe("ghostDatabase - shiori_engine での絞り込み", () => {
+  it("shioriEngine を指定すると件数・一覧とも shiori_engine = ? を末尾に付ける", async () => {
+    mockSelect.mockResolvedValue([{ count: 1 }]);
+    const { searchGhosts } = await import("./ghostDatabase");
+    await searchGhosts("rk1", "al", 50, 0, "name", "satori");
+
+    const countCall = mockSelect.mock.calls.find((c) => (c[0] as string).includes("COUNT(*)"));
+    const rowsCall = mockSelect.mock.calls.find((c) => (c[0] as string).includes("OFFSET"));
+    expect(countCall![0]).toContain("AND ghosts.shiori_engine = ?");
+    expect(countCall![1][countCall![1].length - 1]).toBe("satori");
+    expect(rowsCall![0]).toContain("AND g.shiori_engine = ?");
+    expect(rowsCall![1].slice(-3)).toEqual(["satori", 50, 0]);
+  });
+
+  it("初期ページも shioriEngine で絞り込める", async () => {
+    mockSelect.mockResolvedValue([]);
+    const { searchGhostsInitialPage } = await import("./ghostDatabase");
+    await searchGhostsInitialPage("rk1", 50, "name", "yaya");
+
+    const call = mockSelect.mock.calls.find((c) => (c[0] as string).includes("SELECT g."));
+    expect(call![0]).toContain("WHERE g.request_key = ? AND g.shiori_engine = ?");
+    expect(call![1]).toEqual(["rk1", "yaya", 50]);
   });
 });
+
+describe("ghostDatabase - trigram 索引での検索", () => {
+  it("3 文字以上の検索語は search_ghosts で引き、返った順に行を並べる", async () => {
+    const { invoke: mockInvoke } = await import("@tauri-apps/api/core");
+    vi.mocked(mockInvoke).mockResolvedValueOnce({ ghost_identity_keys: ["k2", "k1"], total: 7 });
+    mockSelect.mockResolvedValue([
+      { ghost_identity_key: "k1", name: "Alice" },
+      { ghost_identity_key: "k2", name: "Alicia" },
+    ]);
+    const { searchGhosts } = await import("./ghostDatabase");
+    const result = await searchGhosts("rk1", "Ａｌｉ", 50, 10, "recent", "yaya");
+
+    expect(mockInvoke).toHaveBeenCalledWith("search_ghosts", {
+      requestKey: "rk1",
+      query: "Ａｌｉ",
+      limit: 50,
+      offset: 10,
+      sortOrder: "recent",
+      shioriEngine: "yaya",
+    });
+    expect(result.total).toBe(7);
+    expect(result.ghosts.map((g) => g.name)).toEqual(["Alicia", "Alice"]);
+    expect(mockSelect.mock.calls.some((c) => (c[0] as string).includes("LIKE"))).toBe(false);
+    const rowsCall = mockSelect.mock.calls.find((c) => (c[0] as string).includes("ghost_identity_key IN"));
+    expect(rowsCall![0]).toContain("g.ghost_identity_key IN (?, ?)");
+    expect(rowsCall![1]).toEqual(["rk1", "k2", "k1"]);
+  });
+
+  it("3 文字以上の件数取得も search_ghosts の total を使う", async () => {
+    const { invoke: mockInvoke } = await import("@tauri-apps/api/core");
+    vi.mocked(mockInvoke).mockResolvedValueOnce({ ghost_identity_keys: [], total: 3 });
+    const { countGhostsByQuery } = await import("./ghostDatabase");
+    const total = await countGhostsByQuery("rk1", "冬服セ");
+
+    expect(total).toBe(3);
+    expect(mockInvoke).toHaveBeenCalledWith("search_ghosts", expect.objectContaining({ query: "冬服セ", limit: 0 }));
+    expect(mockSelect.mock.calls.some((c) => (c[0] as string).includes("COUNT(*)"))).toBe(false);
+  });
+});
+
 describe("ghostDatabase - getCachedFingerprint", () => {
   it("request_key が存在する場合は fingerprint を返す", async () => {
     mockSelect.mockResolvedValue([{ fingerprint: "fp-abc" }]);
@@ -324,6 +404,18 @@ describe("ghostDatabase - cleanupOldGhostCaches", () => {
     );
     expect(fpDeleteCall).toBeDefined();
     expect(fpDeleteCall![1]).toEqual(["rk-old"]);
+
+    const shellsDeleteCall = mockExecute.mock.calls.find((c) =>
+      (c[0] as string).startsWith("DELETE FROM ghost_shells WHERE request_key IN")
+    );
+    expect(shellsDeleteCall).toBeDefined();
+    expect(shellsDeleteCall![1]).toEqual(["rk-old"]);
+
+    const skipsDeleteCall = mockExecute.mock.calls.find((c) =>
+      (c[0] as string).startsWith("DELETE FROM ghost_scan_skips WHERE request_key IN")
+    );
+    expect(skipsDeleteCall).toBeDefined();
+    expect(skipsDeleteCall![1]).toEqual(["rk-old"]);
   });
 
   it("currentRequestKey が DB に存在しない場合でも戻り値に含まれる", async () => {
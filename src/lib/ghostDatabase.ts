@@ -1,6 +1,7 @@
 import Database from "@tauri-apps/plugin-sql";
 import { invoke } from "@tauri-apps/api/core";
-import { GhostView, SortOrder } from "../types";
+import { GhostView, ShioriEngine, SortOrder } from "../types";
+import type { GhostSearchPage } from "../types/generated/GhostSearchPage";
 import { measureSearch, reportDbSize } from "./dbMonitor";
 
 let dbInitPromise: Promise<Database> | null = null;
@@ -123,7 +124,9 @@ export async function cleanupOldGhostCaches(
   if (deleteRequestKeys.length > 0) {
     const placeholders = buildInClausePlaceholders(deleteRequestKeys.length);
     await db.execute(`DELETE FROM ghosts WHERE request_key IN (${placeholders})`, deleteRequestKeys);
+    await db.execute(`DELETE FROM ghost_shells WHERE request_key IN (${placeholders})`, deleteRequestKeys);
     await db.execute(`DELETE FROM ghost_fingerprints WHERE request_key IN (${placeholders})`, deleteRequestKeys);
+    await db.execute(`DELETE FROM ghost_scan_skips WHERE request_key IN (${placeholders})`, deleteRequestKeys);
     console.log(`[ghostDatabase] Cleaned ${deleteRequestKeys.length} stale request_key caches`);
   }
 }
@@ -148,7 +151,7 @@ export async function hasGhosts(requestKey: string): Promise<boolean> {
 }
 
 const GHOST_SELECT_COLUMNS =
-  "name, sakura_name, kero_name, craftman, craftmanw, directory_name, path, source, name_lower, sakura_name_lower, kero_name_lower, craftman_lower, craftmanw_lower, directory_name_lower, thumbnail_path, thumbnail_use_self_alpha, thumbnail_kind, ghost_identity_key";
+  "name, sakura_name, kero_name, craftman, craftmanw, descript_id, ghost_type, shiori, homeurl, craftmanurl, readme, icon, balloon, sakura_default_surface, kero_default_surface, install_accept, directory_name, path, source, name_lower, sakura_name_lower, kero_name_lower, craftman_lower, craftmanw_lower, directory_name_lower, descript_id_lower, shiori_lower, balloon_lower, homeurl_lower, craftmanurl_lower, thumbnail_kind, shell_count, shiori_engine, ghost_identity_key";
 
 const GHOST_SEARCH_LOWER_COLUMNS = [
   "name_lower",
@@ -157,10 +160,56 @@ const GHOST_SEARCH_LOWER_COLUMNS = [
   "craftman_lower",
   "craftmanw_lower",
   "directory_name_lower",
+  "descript_id_lower",
+  "shiori_lower",
+  "balloon_lower",
+  "homeurl_lower",
+  "craftmanurl_lower",
+  "shiori_engine",
 ] as const;
 
-const GHOST_SEARCH_WHERE =
-  GHOST_SEARCH_LOWER_COLUMNS.map((col) => `${col} LIKE ?`).join(" OR ");
+// ghosts の lower カラムに加え、ghost_shells のシェル名でもヒットさせる
+function buildSearchWhere(table: string): string {
+  return [
+    ...GHOST_SEARCH_LOWER_COLUMNS.map((col) => `${table}.${col} LIKE ?`),
+    `EXISTS (SELECT 1 FROM ghost_shells s WHERE s.request_key = ${table}.request_key AND s.ghost_identity_key = ${table}.ghost_identity_key AND s.name_lower LIKE ?)`,
+  ].join(" OR ");
+}
+
+const GHOST_SEARCH_PARAM_COUNT = GHOST_SEARCH_LOWER_COLUMNS.length + 1;
+
+const GHOST_SEARCH_WHERE = buildSearchWhere("ghosts");
+
+function buildSearchParams(likePattern: string): string[] {
+  return new Array<string>(GHOST_SEARCH_PARAM_COUNT).fill(likePattern);
+}
+
+/// SHIORI での絞り込み条件（"" なら絞り込まない）。WHERE 句の末尾に付け、パラメータも末尾に足す
+function buildEngineFilter(table: string, shioriEngine: ShioriEngine | ""): { where: string; params: string[] } {
+  return shioriEngine === ""
+    ? { where: "", params: [] }
+    : { where: ` AND ${table}.shiori_engine = ?`, params: [shioriEngine] };
+}
+
+/// trigram 索引（ghosts_fts）で引ける最短の検索語（Rust の MIN_TRIGRAM_QUERY_CHARS と同じ）。
+/// これより短い検索語は LIKE で全件を照合する
+const MIN_TRIGRAM_QUERY_CHARS = 3;
+
+/// 正規化済みの検索語を Rust の search_ghosts（trigram 索引）で引けるか（文字数はコードポイントで数える）
+function usesTrigramIndex(normalizedQuery: string): boolean {
+  return [...normalizedQuery].length >= MIN_TRIGRAM_QUERY_CHARS;
+}
+
+function invokeSearchGhosts(
+  requestKey: string,
+  query: string,
+  limit: number,
+  offset: number,
+  sortOrder: SortOrder,
+  shioriEngine: ShioriEngine | "",
+): Promise<GhostSearchPage> {
+  return invoke<GhostSearchPage>("search_ghosts", { requestKey, query, limit, offset, sortOrder, shioriEngine });
+}
 
 const GHOST_SELECT_COLUMNS_PREFIXED =
   GHOST_SELECT_COLUMNS.split(", ").map((c) => `g.${c}`).join(", ");
@@ -182,16 +231,17 @@ function buildOrderBy(sortOrder: SortOrder): { orderBy: string; join: string } {
   }
 }
 
-export async function searchGhostsInitialPage(requestKey: string, limit: number, sortOrder: SortOrder = "name"): Promise<GhostView[]> {
+export async function searchGhostsInitialPage(requestKey: string, limit: number, sortOrder: SortOrder = "name", shioriEngine: ShioriEngine | "" = ""): Promise<GhostView[]> {
   return measureSearch("searchGhostsInitialPage", async () => {
     const db = await getDb();
     const { join, orderBy } = buildOrderBy(sortOrder);
     const from = join
       ? `ghosts g ${join}`
       : "ghosts g";
+    const engine = buildEngineFilter("g", shioriEngine);
     const rows = await db.select<GhostView[]>(
-      `SELECT ${GHOST_SELECT_COLUMNS_PREFIXED} FROM ${from} WHERE g.request_key = ? ORDER BY ${orderBy} LIMIT ?`,
-      [requestKey, limit]
+      `SELECT ${GHOST_SELECT_COLUMNS_PREFIXED} FROM ${from} WHERE g.request_key = ?${engine.where} ORDER BY ${orderBy} LIMIT ?`,
+      [requestKey, ...engine.params, limit]
     );
 
     console.log(`[ghostDatabase] searchGhostsInitialPage(requestKey=${requestKey}, limit=${limit}, sort=${sortOrder}) → rows=${rows.length}`);
@@ -199,42 +249,65 @@ export async function searchGhostsInitialPage(requestKey: string, limit: number,
   });
 }
 
-export async function countGhostsByQuery(requestKey: string, query: string): Promise<number> {
+export async function countGhostsByQuery(requestKey: string, query: string, shioriEngine: ShioriEngine | "" = ""): Promise<number> {
   const db = await getDb();
   const normalizedQuery = normalizeForKey(query);
+  const engine = buildEngineFilter("ghosts", shioriEngine);
 
   let countResult: { count: number }[];
   if (normalizedQuery === "") {
     countResult = await db.select<{ count: number }[]>(
-      "SELECT COUNT(*) as count FROM ghosts WHERE request_key = ?",
-      [requestKey]
+      `SELECT COUNT(*) as count FROM ghosts WHERE request_key = ?${engine.where}`,
+      [requestKey, ...engine.params]
     );
+  } else if (usesTrigramIndex(normalizedQuery)) {
+    const page = await invokeSearchGhosts(requestKey, query, 0, 0, "name", shioriEngine);
+    return page.total;
   } else {
     const likePattern = `%${normalizedQuery}%`;
     countResult = await db.select<{ count: number }[]>(
-      `SELECT COUNT(*) as count FROM ghosts WHERE request_key = ? AND (${GHOST_SEARCH_WHERE})`,
-      [requestKey, ...GHOST_SEARCH_LOWER_COLUMNS.map(() => likePattern)]
+      `SELECT COUNT(*) as count FROM ghosts WHERE request_key = ? AND (${GHOST_SEARCH_WHERE})${engine.where}`,
+      [requestKey, ...buildSearchParams(likePattern), ...engine.params]
     );
   }
 
   return countResult.length > 0 ? countResult[0].count : 0;
 }
 
-export async function searchGhosts(requestKey: string, query: string, limit: number, offset: number, sortOrder: SortOrder = "name"): Promise<{ ghosts: GhostView[], total: number }> {
+/// search_ghosts が返した ghost_identity_key の行を、渡された順に並べて取得する
+async function selectGhostsByIdentityKeys(db: Database, requestKey: string, identityKeys: string[]): Promise<GhostView[]> {
+  if (identityKeys.length === 0) return [];
+  const rows = await db.select<GhostView[]>(
+    `SELECT ${GHOST_SELECT_COLUMNS_PREFIXED} FROM ghosts g WHERE g.request_key = ? AND g.ghost_identity_key IN (${buildInClausePlaceholders(identityKeys.length)})`,
+    [requestKey, ...identityKeys]
+  );
+  const byKey = new Map(rows.map((row) => [row.ghost_identity_key, row]));
+  return identityKeys.flatMap((key) => byKey.get(key) ?? []);
+}
+
+export async function searchGhosts(requestKey: string, query: string, limit: number, offset: number, sortOrder: SortOrder = "name", shioriEngine: ShioriEngine | "" = ""): Promise<{ ghosts: GhostView[], total: number }> {
   return measureSearch("searchGhosts", async () => {
     const db = await getDb();
 
     const normalizedQuery = normalizeForKey(query);
+    if (usesTrigramIndex(normalizedQuery)) {
+      const page = await invokeSearchGhosts(requestKey, query, limit, offset, sortOrder, shioriEngine);
+      const ghosts = await selectGhostsByIdentityKeys(db, requestKey, page.ghost_identity_keys);
+      console.log(`[ghostDatabase] searchGhosts(requestKey=${requestKey}, query="${query}", limit=${limit}, offset=${offset}, sort=${sortOrder}) → total=${page.total} (trigram)`);
+      return { ghosts, total: page.total };
+    }
+
     const likePattern = `%${normalizedQuery}%`;
     const { join, orderBy } = buildOrderBy(sortOrder);
     const from = join ? `ghosts g ${join}` : "ghosts g";
-    const searchWhere = GHOST_SEARCH_LOWER_COLUMNS.map((col) => `g.${col} LIKE ?`).join(" OR ");
+    const searchWhere = buildSearchWhere("g");
+    const engine = buildEngineFilter("g", shioriEngine);
 
     const [total, rows] = await Promise.all([
-      countGhostsByQuery(requestKey, query),
+      countGhostsByQuery(requestKey, query, shioriEngine),
       db.select<GhostView[]>(
-        `SELECT ${GHOST_SELECT_COLUMNS_PREFIXED} FROM ${from} WHERE g.request_key = ? AND (${searchWhere}) ORDER BY ${orderBy} LIMIT ? OFFSET ?`,
-        [requestKey, ...GHOST_SEARCH_LOWER_COLUMNS.map(() => likePattern), limit, offset]
+        `SELECT ${GHOST_SELECT_COLUMNS_PREFIXED} FROM ${from} WHERE g.request_key = ? AND (${searchWhere})${engine.where} ORDER BY ${orderBy} LIMIT ? OFFSET ?`,
+        [requestKey, ...buildSearchParams(likePattern), ...engine.params, limit, offset]
       ),
     ]);
 
@@ -244,6 +317,15 @@ export async function searchGhosts(requestKey: string, query: string, limit: num
   });
 }
 
+/// request_key のゴーストを SHIORI ごとに数える（絞り込みの選択肢用。件数の多い順）
+export async function countGhostsByShioriEngine(requestKey: string): Promise<{ shiori_engine: ShioriEngine; count: number }[]> {
+  const db = await getDb();
+  return db.select<{ shiori_engine: ShioriEngine; count: number }[]>(
+    "SELECT shiori_engine, COUNT(*) as count FROM ghosts WHERE request_key = ? GROUP BY shiori_engine ORDER BY count DESC, shiori_engine ASC",
+    [requestKey]
+  );
+}
+
 export async function recordLaunch(ghostIdentityKey: string): Promise<void> {
   const db = await getDb();
   await db.execute(
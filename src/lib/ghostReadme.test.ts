@@ -0,0 +1,14 @@
+import { describe, it, expect, vi } from "vitest";
+import { readGhostReadme } from "./ghostReadme";
+import { invoke } from "@tauri-apps/api/core";
+
+describe("readGhostReadme", () => {
+  it("ghostPath を渡して read_ghost_readme を呼び、readme がなければ null を返す", async () => {
+    vi.mocked(invoke).mockResolvedValue(null);
+
+    const result = await readGhostReadme("C:/SSP/ghost/sakura");
+
+    expect(result).toBeNull();
+    expect(invoke).toHaveBeenCalledWith("read_ghost_readme", { ghostPath: "C:/SSP/ghost/sakura" });
+  });
+});
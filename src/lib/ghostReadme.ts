@@ -0,0 +1,7 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { GhostReadme } from "../types/generated/GhostReadme";
+
+/// ゴーストの readme を読み込む（起動前に利用規約を確認するため）。readme がなければ null
+export async function readGhostReadme(ghostPath: string): Promise<GhostReadme | null> {
+  return invoke<GhostReadme | null>("read_ghost_readme", { ghostPath });
+}
@@ -0,0 +1,35 @@
+import { describe, it, expect, vi } from "vitest";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { SCAN_PROGRESS_EVENT, cancelGhostScan, createScanId, onScanProgress } from "./ghostScanProgress";
+
+describe("createScanId", () => {
+  it("呼ぶたびに別の ID を返す", () => {
+    expect(createScanId()).not.toBe(createScanId());
+  });
+});
+
+describe("onScanProgress", () => {
+  it("同じ scanId の進捗だけを handler に渡す", async () => {
+    const handler = vi.fn();
+    await onScanProgress("scan-1", handler);
+
+    const [eventName, listener] = vi.mocked(listen).mock.calls.at(-1)!;
+    expect(eventName).toBe(SCAN_PROGRESS_EVENT);
+    const progress = { parents_done: 1, parents_total: 2, entries_processed: 10, ghosts_found: 3 };
+    listener({ payload: { scan_id: "scan-0", ...progress } });
+    listener({ payload: { scan_id: "scan-1", ...progress } });
+
+    expect(handler).toHaveBeenCalledTimes(1);
+    expect(handler).toHaveBeenCalledWith({ scan_id: "scan-1", ...progress });
+  });
+});
+
+describe("cancelGhostScan", () => {
+  it("scanId を渡して cancel_ghost_scan を呼ぶ", async () => {
+    vi.mocked(invoke).mockResolvedValue(true);
+
+    await expect(cancelGhostScan("scan-1")).resolves.toBe(true);
+    expect(invoke).toHaveBeenCalledWith("cancel_ghost_scan", { scanId: "scan-1" });
+  });
+});
@@ -0,0 +1,34 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { ScanProgressEvent } from "../types/generated/ScanProgressEvent";
+
+export type { ScanProgressEvent };
+
+/// Rust 側 progress::SCAN_PROGRESS_EVENT と同じ名前
+export const SCAN_PROGRESS_EVENT = "ghost-scan-progress";
+
+let scanSeq = 0;
+
+/// scan_and_store に渡すスキャン ID を作る（アプリの起動中に重複しなければよい）
+export function createScanId(): string {
+  scanSeq += 1;
+  return `scan-${Date.now()}-${scanSeq}`;
+}
+
+/// scanId のスキャンの進捗が届くたびに handler を呼ぶ。別のスキャンの進捗は無視する
+export function onScanProgress(
+  scanId: string,
+  handler: (event: ScanProgressEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<ScanProgressEvent>(SCAN_PROGRESS_EVENT, (event) => {
+    if (event.payload.scan_id === scanId) {
+      handler(event.payload);
+    }
+  });
+}
+
+/// 実行中のスキャンを打ち切る。打ち切られたスキャンは DB を書き換えずに cancelled: true を返す。
+/// 打ち切りを要求できたら true（もう終わっていれば false）
+export async function cancelGhostScan(scanId: string): Promise<boolean> {
+  return invoke<boolean>("cancel_ghost_scan", { scanId });
+}
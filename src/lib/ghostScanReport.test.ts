@@ -0,0 +1,22 @@
+import { describe, it, expect, vi } from "vitest";
+import { describeScanSkipReason, getScanReport } from "./ghostScanReport";
+import { invoke } from "@tauri-apps/api/core";
+
+describe("getScanReport", () => {
+  it("requestKey を渡して get_scan_report を呼ぶ", async () => {
+    const skips = [{ source: "ssp", path: "C:/SSP/ghost/broken", reason: "no_descript", detail: "" }];
+    vi.mocked(invoke).mockResolvedValue(skips);
+
+    const result = await getScanReport("c:/ssp::");
+
+    expect(result).toEqual(skips);
+    expect(invoke).toHaveBeenCalledWith("get_scan_report", { requestKey: "c:/ssp::" });
+  });
+});
+
+describe("describeScanSkipReason", () => {
+  it("既知の理由は文言に、未知の理由はそのまま返す", () => {
+    expect(describeScanSkipReason("permission_denied")).toBe("アクセス権がありません");
+    expect(describeScanSkipReason("something_new")).toBe("something_new");
+  });
+});
@@ -0,0 +1,28 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { GhostScanSkip } from "../types/generated/GhostScanSkip";
+
+/// GhostScanSkip.reason の値
+export type ScanSkipReason = "no_descript" | "unreadable" | "non_utf8_name" | "decode_failure" | "permission_denied";
+
+/// 直近のスキャンで一覧に載らなかった・壊れていたフォルダを返す（パス順）。スキャンはしない
+export async function getScanReport(requestKey: string): Promise<GhostScanSkip[]> {
+  return invoke<GhostScanSkip[]>("get_scan_report", { requestKey });
+}
+
+/// スキャンレポートの理由を表示用の文言にする
+export function describeScanSkipReason(reason: string): string {
+  switch (reason as ScanSkipReason) {
+    case "no_descript":
+      return "ghost/master/descript.txt がありません";
+    case "unreadable":
+      return "フォルダを読み取れません";
+    case "non_utf8_name":
+      return "フォルダ名に使えない文字が含まれています";
+    case "decode_failure":
+      return "descript.txt の文字コードが正しくありません";
+    case "permission_denied":
+      return "アクセス権がありません";
+    default:
+      return reason;
+  }
+}
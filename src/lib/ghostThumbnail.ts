@@ -0,0 +1,14 @@
+import { convertFileSrc } from "@tauri-apps/api/core";
+
+/** animated は元画像が APNG ならアニメーション、それ以外は preview と同じ静止画 */
+export type GhostThumbnailSize = "card" | "preview" | "animated";
+
+/** Rust の ghostthumb スキームでサムネイルを配信する URL（ファイルパスは WebView に渡さない） */
+export function ghostThumbnailUrl(ghostIdentityKey: string, size: GhostThumbnailSize): string {
+  return `${convertFileSrc(ghostIdentityKey, "ghostthumb")}?size=${size}`;
+}
+
+/** バルーンのプレビュー画像の URL（balloons テーブルの balloon_identity_key から引く） */
+export function balloonThumbnailUrl(balloonIdentityKey: string, size: Exclude<GhostThumbnailSize, "animated">): string {
+  return `${convertFileSrc(`balloon/${balloonIdentityKey}`, "ghostthumb")}?size=${size}`;
+}
@@ -0,0 +1,39 @@
+import { describe, it, expect, vi } from "vitest";
+import { applyGhostUpdate, checkGhostUpdates, hasIntegrityIssues, verifyGhost } from "./ghostUpdates";
+import { invoke } from "@tauri-apps/api/core";
+import type { GhostVerifyResult } from "../types/generated/GhostVerifyResult";
+
+const clean: GhostVerifyResult = { manifest: "updates2.dau", verified_count: 3, modified: [], missing: [], extra: [] };
+
+describe("verifyGhost", () => {
+  it("ghostPath を渡して verify_ghost を呼ぶ", async () => {
+    vi.mocked(invoke).mockResolvedValue(clean);
+
+    await verifyGhost("C:/SSP/ghost/sakura");
+
+    expect(invoke).toHaveBeenCalledWith("verify_ghost", { ghostPath: "C:/SSP/ghost/sakura" });
+  });
+});
+
+describe("hasIntegrityIssues", () => {
+  it("modified / missing があれば true、extra だけなら false", () => {
+    expect(hasIntegrityIssues(clean)).toBe(false);
+    expect(hasIntegrityIssues({ ...clean, extra: ["ghost/master/new.dic"] })).toBe(false);
+    expect(hasIntegrityIssues({ ...clean, modified: ["ghost/master/descript.txt"] })).toBe(true);
+    expect(hasIntegrityIssues({ ...clean, missing: ["readme.txt"] })).toBe(true);
+  });
+});
+
+describe("checkGhostUpdates / applyGhostUpdate", () => {
+  it("ゴーストのパスを渡して各コマンドを呼ぶ", async () => {
+    vi.mocked(invoke).mockResolvedValue([]);
+    await checkGhostUpdates(["C:/SSP/ghost/sakura", "D:/ghosts/kero"]);
+    expect(invoke).toHaveBeenCalledWith("check_ghost_updates", {
+      ghostPaths: ["C:/SSP/ghost/sakura", "D:/ghosts/kero"],
+    });
+
+    vi.mocked(invoke).mockResolvedValue({ ghost_path: "C:/SSP/ghost/sakura", updated_files: [], downloaded_bytes: 0 });
+    await applyGhostUpdate("C:/SSP/ghost/sakura");
+    expect(invoke).toHaveBeenCalledWith("apply_ghost_update", { ghostPath: "C:/SSP/ghost/sakura" });
+  });
+});
@@ -0,0 +1,25 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { GhostUpdateApplyResult } from "../types/generated/GhostUpdateApplyResult";
+import type { GhostUpdateStatus } from "../types/generated/GhostUpdateStatus";
+import type { GhostVerifyResult } from "../types/generated/GhostVerifyResult";
+
+/// インストール済みゴーストを updates2.dau / updates.txt と照合する。
+/// manifest が空文字列ならマニフェストのないゴースト（照合できない）。
+export async function verifyGhost(ghostPath: string): Promise<GhostVerifyResult> {
+  return invoke<GhostVerifyResult>("verify_ghost", { ghostPath });
+}
+
+/// 照合結果が手編集・不完全なインストールを示しているか
+export function hasIntegrityIssues(result: GhostVerifyResult): boolean {
+  return result.modified.length > 0 || result.missing.length > 0;
+}
+
+/// descript.txt の homeurl からサーバーのマニフェストを取得し、ゴーストごとの更新の有無を返す
+export async function checkGhostUpdates(ghostPaths: string[]): Promise<GhostUpdateStatus[]> {
+  return invoke<GhostUpdateStatus[]>("check_ghost_updates", { ghostPaths });
+}
+
+/// 変更のあったファイルをダウンロードしてゴーストフォルダに反映する（反映後は再スキャンが必要）
+export async function applyGhostUpdate(ghostPath: string): Promise<GhostUpdateApplyResult> {
+  return invoke<GhostUpdateApplyResult>("apply_ghost_update", { ghostPath });
+}
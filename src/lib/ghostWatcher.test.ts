@@ -0,0 +1,35 @@
+import { describe, it, expect, vi } from "vitest";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { GHOSTS_CHANGED_EVENT, onGhostsChanged, startGhostWatcher, stopGhostWatcher } from "./ghostWatcher";
+
+describe("startGhostWatcher / stopGhostWatcher", () => {
+  it("scan_and_store と同じ additionalFolders と requestKey で監視を始め、止める", async () => {
+    vi.mocked(invoke).mockResolvedValue(undefined);
+
+    await startGhostWatcher("C:/SSP", ["D:/Ghosts", "d:/ghosts/"]);
+    await stopGhostWatcher();
+
+    expect(invoke).toHaveBeenCalledWith("start_ghost_watcher", {
+      sspPath: "C:/SSP",
+      additionalFolders: ["D:/Ghosts"],
+      requestKey: "c:/ssp::d:/ghosts",
+    });
+    expect(invoke).toHaveBeenCalledWith("stop_ghost_watcher");
+  });
+});
+
+describe("onGhostsChanged", () => {
+  it("同じ requestKey の通知だけを handler に渡す", async () => {
+    const handler = vi.fn();
+    await onGhostsChanged("c:/ssp::", handler);
+
+    const [eventName, listener] = vi.mocked(listen).mock.calls.at(-1)!;
+    expect(eventName).toBe(GHOSTS_CHANGED_EVENT);
+    listener({ payload: { request_key: "other::", updated: 1, removed: 0 } });
+    listener({ payload: { request_key: "c:/ssp::", updated: 2, removed: 1 } });
+
+    expect(handler).toHaveBeenCalledTimes(1);
+    expect(handler).toHaveBeenCalledWith({ request_key: "c:/ssp::", updated: 2, removed: 1 });
+  });
+});
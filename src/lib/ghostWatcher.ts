@@ -0,0 +1,32 @@
+import { invoke } from "@tauri-apps/api/core";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { GhostsChangedEvent } from "../types/generated/GhostsChangedEvent";
+import { buildAdditionalFolders, buildRequestKey } from "./ghostScanUtils";
+
+/// Rust 側 watcher::GHOSTS_CHANGED_EVENT と同じ名前
+export const GHOSTS_CHANGED_EVENT = "ghosts-changed";
+
+/// SSP の ghost/ と追加フォルダの監視を始める（起動中の監視は置き換わる）。
+/// 変更のあったゴーストは Rust 側で ghosts テーブルに反映され、GHOSTS_CHANGED_EVENT が届く
+export async function startGhostWatcher(sspPath: string, ghostFolders: string[]): Promise<void> {
+  const additionalFolders = buildAdditionalFolders(ghostFolders);
+  const requestKey = buildRequestKey(sspPath, additionalFolders);
+  await invoke("start_ghost_watcher", { sspPath, additionalFolders, requestKey });
+}
+
+/// フォルダの監視を止める
+export async function stopGhostWatcher(): Promise<void> {
+  await invoke("stop_ghost_watcher");
+}
+
+/// 監視が requestKey の ghosts を書き換えたときに handler を呼ぶ。別の requestKey（設定変更前の監視）の通知は無視する
+export function onGhostsChanged(
+  requestKey: string,
+  handler: (event: GhostsChangedEvent) => void,
+): Promise<UnlistenFn> {
+  return listen<GhostsChangedEvent>(GHOSTS_CHANGED_EVENT, (event) => {
+    if (event.payload.request_key === requestKey) {
+      handler(event.payload);
+    }
+  });
+}
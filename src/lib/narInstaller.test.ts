@@ -0,0 +1,25 @@
+import { describe, it, expect, vi, beforeEach } from "vitest";
+import { installNar } from "./narInstaller";
+import { invoke } from "@tauri-apps/api/core";
+import { requestKeyFromSettings } from "./ghostScanUtils";
+
+describe("installNar", () => {
+  beforeEach(() => {
+    vi.clearAllMocks();
+  });
+
+  it("request_key を付けて install_nar を呼び、既定では上書きしない", async () => {
+    vi.mocked(invoke).mockResolvedValue({ installed: false, existing_paths: ["C:/SSP/ghost/sakura"] });
+
+    const result = await installNar("C:/tmp/sakura.nar", "C:/SSP", ["D:/Ghosts"], null);
+
+    expect(result.installed).toBe(false);
+    expect(invoke).toHaveBeenCalledWith("install_nar", {
+      narPath: "C:/tmp/sakura.nar",
+      sspPath: "C:/SSP",
+      targetFolder: null,
+      requestKey: requestKeyFromSettings("C:/SSP", ["D:/Ghosts"]),
+      overwrite: false,
+    });
+  });
+});
@@ -0,0 +1,22 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { InstallNarResult } from "../types/generated/InstallNarResult";
+import { requestKeyFromSettings } from "./ghostScanUtils";
+
+/// .nar をインストールする。targetFolder が null なら SSP/ghost、それ以外は追加フォルダに置く。
+/// 既存ディレクトリを上書きする場合は installed=false が返るので、確認後に overwrite=true で呼び直す。
+/// インストールしたゴーストだけが ghosts テーブルに書き込まれる（全体の再スキャンは不要）。
+export async function installNar(
+  narPath: string,
+  sspPath: string,
+  ghostFolders: string[],
+  targetFolder: string | null,
+  overwrite = false
+): Promise<InstallNarResult> {
+  return invoke<InstallNarResult>("install_nar", {
+    narPath,
+    sspPath,
+    targetFolder,
+    requestKey: requestKeyFromSettings(sspPath, ghostFolders),
+    overwrite,
+  });
+}
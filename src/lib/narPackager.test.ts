@@ -0,0 +1,18 @@
+import { describe, it, expect, vi } from "vitest";
+import { packageGhost } from "./narPackager";
+import { invoke } from "@tauri-apps/api/core";
+
+describe("packageGhost", () => {
+  it("既定では dryRun: false で package_ghost を呼ぶ", async () => {
+    vi.mocked(invoke).mockResolvedValue({ written: true, issues: [] });
+
+    const result = await packageGhost("C:/dev/sakura", "C:/dist/sakura.nar");
+
+    expect(result.written).toBe(true);
+    expect(invoke).toHaveBeenCalledWith("package_ghost", {
+      ghostPath: "C:/dev/sakura",
+      outputPath: "C:/dist/sakura.nar",
+      dryRun: false,
+    });
+  });
+});
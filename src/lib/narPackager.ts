@@ -0,0 +1,8 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { PackageGhostResult } from "../types/generated/PackageGhostResult";
+
+/// ゴーストフォルダから .nar を作る（開発者向け）。dryRun なら検査結果（issues）だけを返す。
+/// issues に severity "error" があると書き出さない（written: false）。
+export async function packageGhost(ghostPath: string, outputPath: string, dryRun = false): Promise<PackageGhostResult> {
+  return invoke<PackageGhostResult>("package_ghost", { ghostPath, outputPath, dryRun });
+}
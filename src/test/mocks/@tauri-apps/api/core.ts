@@ -1,5 +1,7 @@
 // invoke のモック: テストごとに vi.mocked(invoke).mockResolvedValue(...) で制御
 import { vi } from "vitest";
 export const invoke = vi.fn();
-// convertFileSrc: ファイルパスを Tauri アセット URL に変換するモック
-export const convertFileSrc = vi.fn((path: string) => `asset://localhost/${path}`);
+// convertFileSrc: パスを Tauri のカスタムプロトコル URL に変換するモック
+export const convertFileSrc = vi.fn(
+  (path: string, protocol = "asset") => `${protocol}://localhost/${encodeURIComponent(path)}`,
+);
@@ -0,0 +1,5 @@
+// listen のモック: 既定では何も届かず、解除関数だけを返す。
+// イベントを届けたいテストは vi.mocked(listen).mock.calls からハンドラを取り出して呼ぶ
+import { vi } from "vitest";
+export type UnlistenFn = () => void;
+export const listen = vi.fn(async (_event: string, _handler: (event: { payload: unknown }) => void): Promise<UnlistenFn> => () => {});
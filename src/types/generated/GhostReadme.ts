@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type GhostReadme = { 
+/**
+ * readme ファイルのフルパス
+ */
+path: string, 
+/**
+ * デコードしたテキスト
+ */
+text: string, 
+/**
+ * デコードに使った文字コード名（"Shift_JIS" / "UTF-8" など）
+ */
+encoding: string, 
+/**
+ * text に含まれる http / https の URL（出現順、重複なし）
+ */
+urls: Array<string>, };
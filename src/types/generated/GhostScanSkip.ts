@@ -0,0 +1,22 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * スキャンで一覧に載らなかった（または壊れていた）フォルダ 1 件（ghost_scan_skips テーブルの 1 行）
+ */
+export type GhostScanSkip = { 
+/**
+ * 走査した場所（"ssp" or 追加フォルダのパス。Ghost::source と同じ）
+ */
+source: string, 
+/**
+ * 対象フォルダのフルパス
+ */
+path: string, 
+/**
+ * "no_descript" / "unreadable" / "non_utf8_name" / "decode_failure" / "permission_denied"
+ */
+reason: string, 
+/**
+ * 元のエラーメッセージなどの補足（表示用）
+ */
+detail: string, };
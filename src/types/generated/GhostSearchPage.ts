@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * search_ghosts の結果（1 ページ分の ghost_identity_key と一致した総数）。行の中身はフロントエンドが SQL で引く
+ */
+export type GhostSearchPage = { 
+/**
+ * 並び順どおりの ghost_identity_key（limit / offset の範囲）
+ */
+ghost_identity_keys: Array<string>, 
+/**
+ * 一致したゴーストの総数
+ */
+total: number, };
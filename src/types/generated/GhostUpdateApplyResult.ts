@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type GhostUpdateApplyResult = { ghost_path: string, 
+/**
+ * 書き換えたファイル（ゴーストのルートからの相対パス）
+ */
+updated_files: Array<string>, downloaded_bytes: number, };
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type GhostUpdateStatus = { ghost_path: string, 
+/**
+ * descript.txt の homeurl。未設定の場合は空文字列
+ */
+homeurl: string, 
+/**
+ * サーバーのマニフェストと照合できたか（homeurl がない・通信に失敗した場合は false）
+ */
+checked: boolean, update_available: boolean, 
+/**
+ * 取得が必要なファイル（ゴーストのルートからの相対パス）
+ */
+changed_files: Array<string>, 
+/**
+ * 取得が必要なファイルの合計サイズ（バイト）。マニフェストに size がないファイルは数えない
+ */
+download_bytes: number, 
+/**
+ * 照合できなかった理由。checked が true なら空文字列
+ */
+error: string, };
@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type GhostVerifyResult = { 
+/**
+ * 照合に使ったマニフェスト（"updates2.dau" / "updates.txt"）。マニフェストがなければ空文字列
+ */
+manifest: string, 
+/**
+ * MD5 が一致したファイル数
+ */
+verified_count: number, 
+/**
+ * マニフェストと内容が異なるファイル（ゴーストのルートからの相対パス）
+ */
+modified: Array<string>, 
+/**
+ * マニフェストにあるが存在しないファイル
+ */
+missing: Array<string>, 
+/**
+ * マニフェストにないファイル（profile 配下など実行時に作られるファイルは除く）
+ */
+extra: Array<string>, };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * フォルダ監視が ghosts テーブルを書き換えたときに送るイベント（"ghosts-changed"）のペイロード
+ */
+export type GhostsChangedEvent = { 
+/**
+ * 書き換えた request_key（start_ghost_watcher に渡したもの）
+ */
+request_key: string, 
+/**
+ * 追加・更新したゴーストの数
+ */
+updated: number, 
+/**
+ * 削除したゴーストの数
+ */
+removed: number, };
@@ -0,0 +1,31 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type InstallNarResult = { 
+/**
+ * false なら何もインストールしていない（existing_paths の上書き確認が必要）
+ */
+installed: boolean, 
+/**
+ * install.txt の type（"ghost" / "shell" / "balloon" / "supplement"）
+ */
+kind: string, 
+/**
+ * install.txt の name。未設定の場合は空文字列
+ */
+name: string, 
+/**
+ * 展開先のフルパス
+ */
+target_path: string, 
+/**
+ * 上書きになる既存ディレクトリのフルパス
+ */
+existing_paths: Array<string>, 
+/**
+ * 展開したファイル数（installed が false なら 0）
+ */
+file_count: number, 
+/**
+ * 再スキャンしたゴーストの ghost_identity_key。ゴーストに影響しない場合（balloon）は空文字列
+ */
+ghost_identity_key: string, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * デフォルトバルーン（descript.txt の balloon フィールド）が未インストールのゴースト
+ */
+export type MissingBalloon = { ghost_identity_key: string, ghost_name: string, balloon: string, };
@@ -0,0 +1,14 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * .nar 作成前の検査で見つかった問題
+ */
+export type NarPackageIssue = { 
+/**
+ * "error"（.nar を書き出さない）または "warning"
+ */
+severity: string, 
+/**
+ * 問題のあるファイル（ゴーストのルートからの相対パス）。ゴースト全体に対する問題では空文字列
+ */
+path: string, message: string, };
@@ -0,0 +1,28 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { NarPackageIssue } from "./NarPackageIssue";
+
+export type PackageGhostResult = { 
+/**
+ * .nar を書き出したか（dry_run または error があれば false）
+ */
+written: boolean, 
+/**
+ * 出力先のフルパス
+ */
+output_path: string, 
+/**
+ * アーカイブに入れるファイル数（install.txt・マニフェストを含む）
+ */
+file_count: number, 
+/**
+ * developer_options.txt の nonar で除外したファイル（相対パス）
+ */
+excluded_files: Array<string>, 
+/**
+ * install.txt がなく生成したか
+ */
+install_txt_generated: boolean, 
+/**
+ * updates2.dau / updates.txt に載せたファイル数
+ */
+manifest_file_count: number, issues: Array<NarPackageIssue>, };
@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * スキャン中に送る進捗イベント（"ghost-scan-progress"）のペイロード
+ */
+export type ScanProgressEvent = { 
+/**
+ * scan_and_store に渡したスキャン ID
+ */
+scan_id: string, 
+/**
+ * 走査を終えた起点（SSP の ghost/ と追加フォルダ）の数
+ */
+parents_done: number, 
+/**
+ * 走査する起点の数
+ */
+parents_total: number, 
+/**
+ * 調べたフォルダの数（入れ子のサブフォルダを含む）
+ */
+entries_processed: number, 
+/**
+ * 見つかったゴーストの数（読み直さなかったものを含む）
+ */
+ghosts_found: number, };
@@ -1,3 +1,7 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type ScanStoreResult = { cache_hit: boolean, total: number, fingerprint: string, request_key: string, };
+export type ScanStoreResult = { cache_hit: boolean, total: number, fingerprint: string, request_key: string, 
+/**
+ * cancel_ghost_scan で打ち切られた。このとき DB は書き換えていない
+ */
+cancelled: boolean, };
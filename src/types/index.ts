@@ -1,9 +1,13 @@
 // TS 専用型（フロントエンド固有）
 
-export type ThumbnailKind = "surface" | "thumbnail" | "";
+/** "balloon" はバルーンの balloons0.png（BalloonView のみ） */
+export type ThumbnailKind = "surface" | "thumbnail" | "balloon" | "";
 
 export type SortOrder = "name" | "recent" | "frequency" | "random";
 
+/** ghosts.shiori_engine の値（shiori フィールドと ghost/master のファイルから判定） */
+export type ShioriEngine = "yaya" | "aya" | "satori" | "kawari" | "misaka" | "kagari" | "unknown";
+
 /** DB クエリ結果。_lower カラムを含み、diff_fingerprint は SELECT 対象外 */
 export interface GhostView {
   name: string;
@@ -11,11 +15,21 @@ export interface GhostView {
   kero_name: string;
   craftman: string;
   craftmanw: string;
+  descript_id: string;
+  ghost_type: string;
+  shiori: string;
+  homeurl: string;
+  craftmanurl: string;
+  readme: string;
+  icon: string;
+  balloon: string;
+  sakura_default_surface: number | null;
+  kero_default_surface: number | null;
+  install_accept: string;
   directory_name: string;
   path: string;
   source: string;
-  thumbnail_path: string;
-  thumbnail_use_self_alpha: boolean;
+  /** 画像本体は ghostthumb スキーム（ghostThumbnailUrl）で取得する。"" はサムネイルなし */
   thumbnail_kind: ThumbnailKind;
   name_lower: string;
   sakura_name_lower: string;
@@ -23,5 +37,28 @@ export interface GhostView {
   craftman_lower: string;
   craftmanw_lower: string;
   directory_name_lower: string;
+  descript_id_lower: string;
+  shiori_lower: string;
+  balloon_lower: string;
+  homeurl_lower: string;
+  craftmanurl_lower: string;
+  /** shell/ 配下のシェル数（シェル本体は ghost_shells テーブル） */
+  shell_count: number;
+  shiori_engine: ShioriEngine;
   ghost_identity_key: string;
 }
+
+/** balloons テーブルのクエリ結果（プレビュー画像のパス列は SELECT しない） */
+export interface BalloonView {
+  name: string;
+  descript_id: string;
+  craftman: string;
+  craftmanw: string;
+  craftmanurl: string;
+  homeurl: string;
+  directory_name: string;
+  path: string;
+  /** 画像本体は ghostthumb スキーム（balloonThumbnailUrl）で取得する。"" は画像なし */
+  thumbnail_kind: ThumbnailKind;
+  balloon_identity_key: string;
+}
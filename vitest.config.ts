@@ -11,6 +11,7 @@ export default defineConfig({
     alias: {
       // Tauri API のモック差し替え（テスト環境のみ有効）
       "@tauri-apps/api/core": resolve(__dirname, "src/test/mocks/@tauri-apps/api/core.ts"),
+      "@tauri-apps/api/event": resolve(__dirname, "src/test/mocks/@tauri-apps/api/event.ts"),
       "@tauri-apps/plugin-store": resolve(__dirname, "src/test/mocks/@tauri-apps/plugin-store.ts"),
       "@tauri-apps/plugin-dialog": resolve(__dirname, "src/test/mocks/@tauri-apps/plugin-dialog.ts"),
     },